@@ -0,0 +1,90 @@
+// animation.rs
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::framebuffer::{Framebuffer, save_buffer_image};
+
+const DEFAULT_FPS: f32 = 30.0;
+
+// Offline export for `--record <frames>`: runs without a window, the
+// simulation and the demo advance a fixed 1/fps seconds per frame however
+// long a frame takes to render, and every frame is written as a numbered PNG
+// that ffmpeg can turn into a video.
+pub struct AnimationExport {
+    pub directory: PathBuf,
+    pub fps: f32,
+    pub total_frames: u64,
+    pub size: Option<(usize, usize)>, // Tamaño de los frames, None para el de la ventana
+    written: u64,
+}
+
+impl AnimationExport {
+    // `--record <frames>`, with `--fps <n>` and `--size <w>x<h>` next to it
+    pub fn from_args(args: &[String]) -> Result<Option<Self>, String> {
+        let value = |flag: &str| args.iter().position(|arg| arg == flag).map(|index| args.get(index + 1));
+        let Some(frames) = value("--record") else {
+            return Ok(None);
+        };
+        let total_frames = frames.and_then(|frames| frames.parse().ok())
+            .filter(|frames| *frames > 0)
+            .ok_or("--record takes a number of frames")?;
+        let fps = match value("--fps") {
+            Some(fps) => fps.and_then(|fps| fps.parse().ok())
+                .filter(|fps: &f32| *fps > 0.0)
+                .ok_or("--fps takes a positive number")?,
+            None => DEFAULT_FPS,
+        };
+        let size = match value("--size") {
+            Some(size) => Some(size.and_then(|size| parse_size(size)).ok_or("--size takes <width>x<height>")?),
+            None => None,
+        };
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let directory = PathBuf::from(format!("captures/animation_{}", timestamp));
+        fs::create_dir_all(&directory).map_err(|e| format!("{}: {}", directory.display(), e))?;
+
+        Ok(Some(AnimationExport { directory, fps, total_frames, size, written: 0 }))
+    }
+
+    // Real seconds each frame stands for
+    pub fn frame_seconds(&self) -> f32 {
+        1.0 / self.fps
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.written >= self.total_frames
+    }
+
+    pub fn write_frame(&mut self, framebuffer: &Framebuffer) -> Result<PathBuf, String> {
+        let path = self.directory.join(format!("frame_{:06}.png", self.written));
+        save_buffer_image(&path, &framebuffer.buffer, framebuffer.width, framebuffer.height)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        self.written += 1;
+        Ok(path)
+    }
+
+    // Progress line, every second of footage and on the last frame
+    pub fn progress(&self) -> Option<String> {
+        let every = (self.fps.round() as u64).max(1);
+        (self.written.is_multiple_of(every) || self.is_finished())
+            .then(|| format!("Recorded {}/{} frames", self.written, self.total_frames))
+    }
+
+    pub fn ffmpeg_hint(&self) -> String {
+        format!(
+            "ffmpeg -framerate {} -i {}/frame_%06d.png -pix_fmt yuv420p animation.mp4",
+            self.fps,
+            self.directory.display(),
+        )
+    }
+}
+
+fn parse_size(text: &str) -> Option<(usize, usize)> {
+    let (width, height) = text.split_once('x')?;
+    let size = (width.trim().parse().ok()?, height.trim().parse().ok()?);
+    (size.0 > 0 && size.1 > 0).then_some(size)
+}
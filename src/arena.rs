@@ -0,0 +1,75 @@
+// arena.rs
+
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+use crate::fragment::FragmentBuffer;
+use crate::framebuffer::ShadedFragment;
+use crate::triangle::Clipper;
+use crate::vertex::Vertex;
+
+// Scratch memory of the pipeline. What a draw only needs while it runs
+// (transformed vertices, clipped polygons, rasterized and shaded fragments,
+// the coarse pass's grids and the order fragments are merged in) lives in
+// buffers taken from here and given back when the draw ends, emptied but
+// keeping their capacity. After the first frames every draw finds buffers as
+// big as it needs and drawing meshes stops allocating, but for the odd draw
+// rayon splits among more workers than ever before: memory stays at what the
+// busiest frame used instead of coming and going. Particles keep pools of
+// their own, sized when the scene loads.
+pub static FRAME_ARENA: Lazy<FrameArena> = Lazy::new(FrameArena::default);
+
+// One worker's share of a draw
+#[derive(Default)]
+pub struct Scratch {
+    pub vertices: Vec<Vertex>, // Vértices transformados de una instancia
+    pub clipper: Clipper,
+    pub fragments: FragmentBuffer, // Los de un triángulo, se vacía en cada uno
+    pub shaded: Vec<ShadedFragment>,
+    pub coarse: Vec<Option<ShadedFragment>>, // Pasada gruesa, un fragmento por pixel grueso
+    pub fine: Vec<Option<ShadedFragment>>,   // Lo mismo ya en la resolución completa
+    pub band_starts: Vec<usize>, // Dónde empieza cada franja en band_order al escribir
+    pub band_next: Vec<usize>,
+    pub band_order: Vec<u32>,    // Índices de los fragmentos ordenados por franja
+}
+
+impl Scratch {
+    // Moves the fragments `other` shaded after this one's, keeping the order
+    // of the triangles, and returns `other` to the arena
+    pub fn merge(mut self, mut other: Scratch) -> Scratch {
+        self.shaded.append(&mut other.shaded);
+        FRAME_ARENA.give(other);
+        self
+    }
+
+    fn clear(&mut self) {
+        self.vertices.clear();
+        self.clipper.clear();
+        self.fragments.clear();
+        self.shaded.clear();
+        self.coarse.clear();
+        self.fine.clear();
+        self.band_starts.clear();
+        self.band_next.clear();
+        self.band_order.clear();
+    }
+}
+
+#[derive(Default)]
+pub struct FrameArena {
+    free: Mutex<Vec<Scratch>>,
+}
+
+impl FrameArena {
+    // A scratch set left by an earlier draw, or a new one while the pipeline
+    // runs more workers at once than ever before
+    pub fn take(&self) -> Scratch {
+        self.free.lock().ok().and_then(|mut free| free.pop()).unwrap_or_default()
+    }
+
+    pub fn give(&self, mut scratch: Scratch) {
+        scratch.clear();
+        if let Ok(mut free) = self.free.lock() {
+            free.push(scratch);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+// assets.rs
+
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use crate::framebuffer::Framebuffer;
+use crate::font::draw_text;
+use crate::normal_map::NormalMap;
+use crate::obj::Obj;
+use crate::skybox::Skybox;
+use crate::texture::Texture;
+use crate::threads;
+
+pub enum Asset {
+    Model(Obj),
+    Skybox(Skybox),
+    Texture(Texture),
+    NormalMap(NormalMap),
+}
+
+// Loads assets on worker threads while the main loop keeps the window alive,
+// in deterministic mode one after the other as they are asked for
+pub struct AssetLoader {
+    sender: Sender<(String, Result<Asset, String>)>,
+    receiver: Receiver<(String, Result<Asset, String>)>,
+    total: usize,
+    loaded: HashMap<String, Result<Asset, String>>,
+    last_loaded: String,
+}
+
+impl Default for AssetLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AssetLoader {
+    pub fn new() -> Self {
+        let (sender, receiver) = channel();
+        AssetLoader {
+            sender,
+            receiver,
+            total: 0,
+            loaded: HashMap::new(),
+            last_loaded: String::new(),
+        }
+    }
+
+    pub fn spawn<F>(&mut self, name: &str, job: F)
+    where
+        F: FnOnce() -> Result<Asset, String> + Send + 'static,
+    {
+        let sender = self.sender.clone();
+        let name = name.to_string();
+        self.total += 1;
+
+        threads::spawn(move || {
+            // A panicking job must still report back or the loading screen never ends
+            let result = panic::catch_unwind(AssertUnwindSafe(job))
+                .unwrap_or_else(|_| Err("loader thread panicked".to_string()));
+            // The receiver only goes away if the app is closed while loading
+            let _ = sender.send((name, result));
+        });
+    }
+
+    // Collects finished jobs, returns the names loaded since the last call
+    pub fn poll(&mut self) -> Vec<String> {
+        let mut finished = Vec::new();
+        while let Ok((name, result)) = self.receiver.try_recv() {
+            self.last_loaded = name.clone();
+            self.loaded.insert(name.clone(), result);
+            finished.push(name);
+        }
+        finished
+    }
+
+    pub fn progress(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.loaded.len() as f32 / self.total as f32
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.loaded.len() == self.total
+    }
+
+    fn take(&mut self, name: &str) -> Asset {
+        match self.loaded.remove(name) {
+            Some(Ok(asset)) => asset,
+            Some(Err(error)) => panic!("Failed to load {}: {}", name, error),
+            None => panic!("Asset {} was never loaded", name),
+        }
+    }
+
+    pub fn take_model(&mut self, name: &str) -> Obj {
+        match self.take(name) {
+            Asset::Model(model) => model,
+            _ => panic!("Asset {} is not a model", name),
+        }
+    }
+
+    pub fn take_skybox(&mut self, name: &str) -> Skybox {
+        match self.take(name) {
+            Asset::Skybox(skybox) => skybox,
+            _ => panic!("Asset {} is not a skybox", name),
+        }
+    }
+
+    pub fn take_texture(&mut self, name: &str) -> Texture {
+        match self.take(name) {
+            Asset::Texture(texture) => texture,
+            _ => panic!("Asset {} is not a texture", name),
+        }
+    }
+
+    pub fn take_normal_map(&mut self, name: &str) -> NormalMap {
+        match self.take(name) {
+            Asset::NormalMap(normal_map) => normal_map,
+            _ => panic!("Asset {} is not a normal map", name),
+        }
+    }
+
+    pub fn render(&self, framebuffer: &mut Framebuffer) {
+        let bar_width = framebuffer.width / 2;
+        let bar_height = 12;
+        let left = (framebuffer.width - bar_width) / 2;
+        let top = framebuffer.height / 2;
+
+        draw_text(framebuffer, left, top - 30, "LOADING...", 0xFFFFFF, 3);
+
+        // Progress bar outline and fill
+        let filled = (bar_width as f32 * self.progress()) as usize;
+        for y in top..top + bar_height {
+            for x in left..left + bar_width {
+                let border = y == top || y == top + bar_height - 1 || x == left || x == left + bar_width - 1;
+                if border {
+                    framebuffer.set_current_color(0xFFFFFF);
+                    framebuffer.point_overlay(x, y);
+                } else if x - left < filled {
+                    framebuffer.set_current_color(0xFFDD55);
+                    framebuffer.point_overlay(x, y);
+                }
+            }
+        }
+
+        draw_text(framebuffer, left, top + bar_height + 8, &self.last_loaded, 0xAAAAAA, 2);
+    }
+}
@@ -0,0 +1,225 @@
+// asteroids.rs
+
+use nalgebra_glm::{Mat4, Vec2, Vec3, Vec4};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use std::f32::consts::PI;
+use crate::culling::Frustum;
+use crate::framebuffer::Framebuffer;
+use crate::obj::IndexedMesh;
+use crate::vertex::Vertex;
+use crate::Uniforms;
+use crate::thermal::{false_color, Thermal, ViewMode};
+
+// Asteroids smaller than this on screen are drawn as single points
+const SPRITE_PIXEL_SIZE: f32 = 1.5;
+const ORE_DENSITY: f32 = 1e6;      // Toneladas por unidad de radio al cubo
+const DEPLETED_SIZE: f32 = 0.005;  // Más chica la roca se deshace
+
+pub struct Asteroid {
+    orbit_radius: f32,
+    angle: f32,
+    orbit_speed: f32,
+    height: f32,
+    size: f32, // Radio aproximado en unidades de mundo
+    stretch: Vec3,
+    rotation: Vec3,
+    spin: Vec3,
+}
+
+impl Asteroid {
+    pub fn get_position(&self) -> Vec3 {
+        Vec3::new(
+            self.orbit_radius * self.angle.cos(),
+            self.height,
+            self.orbit_radius * self.angle.sin(),
+        )
+    }
+
+    pub fn get_model_matrix(&self) -> Mat4 {
+        let scale = self.stretch * self.size;
+        let translation = nalgebra_glm::translation(&self.get_position());
+        let rotation = nalgebra_glm::rotation(self.rotation.z, &Vec3::z_axis())
+            * nalgebra_glm::rotation(self.rotation.y, &Vec3::y_axis())
+            * nalgebra_glm::rotation(self.rotation.x, &Vec3::x_axis());
+        translation * rotation * nalgebra_glm::scaling(&scale)
+    }
+
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    // Tons of ore left, from the volume
+    pub fn ore(&self) -> f32 {
+        ORE_DENSITY * self.size.powi(3)
+    }
+}
+
+// A few thousand rocks scattered in an annulus around the origin, all drawn
+// from one shared low-poly mesh with per-instance transforms
+pub struct AsteroidBelt {
+    pub asteroids: Vec<Asteroid>,
+    pub mesh: IndexedMesh,
+    pub visible: bool,
+}
+
+impl AsteroidBelt {
+    // `orbit_speed` is the angular speed at the inner edge, outer rocks are
+    // slower following Kepler's third law (speed ~ r^-1.5)
+    pub fn new(
+        count: usize,
+        inner_radius: f32,
+        outer_radius: f32,
+        thickness: f32,
+        size_range: (f32, f32),
+        orbit_speed: f32,
+        seed: u64,
+    ) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let asteroids = (0..count).map(|_| {
+            let orbit_radius = rng.gen_range(inner_radius..=outer_radius);
+            Asteroid {
+                orbit_radius,
+                angle: rng.gen_range(0.0..2.0 * PI),
+                orbit_speed: orbit_speed * (inner_radius / orbit_radius).powf(1.5),
+                height: rng.gen_range(-0.5..=0.5) * thickness,
+                size: rng.gen_range(size_range.0..=size_range.1),
+                stretch: Vec3::new(rng.gen_range(0.7..1.3), rng.gen_range(0.6..1.0), rng.gen_range(0.7..1.3)),
+                rotation: Vec3::new(rng.gen_range(0.0..2.0 * PI), rng.gen_range(0.0..2.0 * PI), 0.0),
+                spin: Vec3::new(rng.gen_range(-0.05..0.05), rng.gen_range(-0.05..0.05), 0.0),
+            }
+        }).collect();
+
+        AsteroidBelt {
+            asteroids,
+            mesh: rock_mesh(&mut rng),
+            visible: true,
+        }
+    }
+
+    // Splits the asteroids inside the view into mesh instances and point
+    // sprites depending on their size on screen. `pixels_per_unit` is the
+    // height in pixels of one world unit seen at distance one.
+    pub fn visible_instances(&self, frustum: &Frustum, camera_position: Vec3, pixels_per_unit: f32) -> (Vec<Mat4>, Vec<Vec3>) {
+        let mut instances = Vec::new();
+        let mut sprites = Vec::new();
+        for asteroid in &self.asteroids {
+            let position = asteroid.get_position();
+            let radius = asteroid.size * 1.3;
+            if !frustum.contains_sphere(&position, radius) {
+                continue;
+            }
+            let distance = (position - camera_position).magnitude().max(1e-3);
+            if asteroid.size * 2.0 * pixels_per_unit / distance < SPRITE_PIXEL_SIZE {
+                sprites.push(position);
+            } else {
+                instances.push(asteroid.get_model_matrix());
+            }
+        }
+        (instances, sprites)
+    }
+
+    pub fn render_sprites(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, sprites: &[Vec3]) {
+        let transform = uniforms.viewport_matrix * uniforms.projection_matrix * uniforms.view_matrix;
+        framebuffer.set_current_color(0x6e665c);
+        for position in sprites {
+            if uniforms.view_mode == ViewMode::Thermal {
+                let distance = (position - uniforms.light_position).magnitude();
+                let kelvin = Thermal::default().body_temperature(false, distance, uniforms.sunlight.reference_distance);
+                framebuffer.set_current_color(false_color(kelvin).to_hex());
+            }
+            let projected = transform * Vec4::new(position.x, position.y, position.z, 1.0);
+            if projected.w <= 0.0 {
+                continue;
+            }
+            let x = projected.x / projected.w;
+            let y = projected.y / projected.w;
+            if x >= 0.0 && y >= 0.0 {
+                framebuffer.point(x as usize, y as usize, projected.z / projected.w);
+            }
+        }
+    }
+
+    // Every rock as a sphere, for fitting the depth range
+    pub fn bounding_spheres(&self) -> impl Iterator<Item = (Vec3, f32)> + '_ {
+        self.asteroids.iter().map(|asteroid| (asteroid.get_position(), asteroid.size))
+    }
+
+    // Positions of the rocks overlapping a sphere, used for debris hits
+    pub fn touching(&self, center: &Vec3, radius: f32) -> Vec<Vec3> {
+        self.asteroids.iter()
+            .map(|asteroid| (asteroid.get_position(), asteroid.size))
+            .filter(|(position, size)| (position - center).magnitude() < radius + size)
+            .map(|(position, _)| position)
+            .collect()
+    }
+
+    // The rock closest along a ray that passes within its radius, or within
+    // `aim` radians of its center, up to `range` away
+    pub fn pick(&self, origin: &Vec3, direction: &Vec3, range: f32, aim: f32) -> Option<usize> {
+        let direction = direction.try_normalize(1e-6)?;
+        self.asteroids.iter()
+            .enumerate()
+            .filter_map(|(index, asteroid)| {
+                let offset = asteroid.get_position() - origin;
+                let along = offset.dot(&direction);
+                if along <= 0.0 || along > range {
+                    return None;
+                }
+                let miss = (offset - direction * along).magnitude();
+                (miss < asteroid.size + along * aim.tan()).then_some((index, along))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index)
+    }
+
+    // Takes up to `amount` tons out of a rock, which shrinks with what is
+    // left. Returns the tons taken and whether the rock broke up; a broken
+    // rock is removed, so the last one takes its index.
+    pub fn mine(&mut self, index: usize, amount: f32) -> (f32, bool) {
+        let Some(asteroid) = self.asteroids.get_mut(index) else {
+            return (0.0, false);
+        };
+        let ore = asteroid.ore();
+        let taken = amount.clamp(0.0, ore);
+        asteroid.size = ((ore - taken) / ORE_DENSITY).cbrt();
+        if asteroid.size < DEPLETED_SIZE {
+            self.asteroids.swap_remove(index);
+            return (ore, true);
+        }
+        (taken, false)
+    }
+
+    pub fn update(&mut self, time_scale: f32) {
+        for asteroid in &mut self.asteroids {
+            asteroid.angle = (asteroid.angle + asteroid.orbit_speed * time_scale) % (2.0 * PI);
+            asteroid.rotation += asteroid.spin * time_scale;
+        }
+    }
+}
+
+// Icosahedron with its corners pushed in and out so it reads as a rock
+fn rock_mesh(rng: &mut StdRng) -> IndexedMesh {
+    let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+    let corners = [
+        (-1.0, t, 0.0), (1.0, t, 0.0), (-1.0, -t, 0.0), (1.0, -t, 0.0),
+        (0.0, -1.0, t), (0.0, 1.0, t), (0.0, -1.0, -t), (0.0, 1.0, -t),
+        (t, 0.0, -1.0), (t, 0.0, 1.0), (-t, 0.0, -1.0), (-t, 0.0, 1.0),
+    ];
+
+    let vertices = corners.iter().map(|&(x, y, z)| {
+        let direction = Vec3::new(x, y, z).normalize();
+        let position = direction * rng.gen_range(0.75..1.1);
+        Vertex::new(position, direction, Vec2::new(0.0, 0.0))
+    }).collect();
+
+    let indices = vec![
+        0, 11, 5, 0, 5, 1, 0, 1, 7, 0, 7, 10, 0, 10, 11,
+        1, 5, 9, 5, 11, 4, 11, 10, 2, 10, 7, 6, 7, 1, 8,
+        3, 9, 4, 3, 4, 2, 3, 2, 6, 3, 6, 8, 3, 8, 9,
+        4, 9, 5, 2, 4, 11, 6, 2, 10, 8, 6, 7, 9, 8, 1,
+    ];
+
+    IndexedMesh { vertices, indices }
+}
@@ -0,0 +1,103 @@
+// autopilot.rs
+
+use nalgebra_glm::Vec3;
+use crate::flight::{FlightControls, FlightModel, FlightState};
+use crate::font::{draw_text, GLYPH_WIDTH};
+use crate::framebuffer::Framebuffer;
+use crate::orientation::Orientation;
+
+const STEERING_GAIN: f32 = 2.0;   // Mando del stick por radián de error
+const LEVELING_GAIN: f32 = 1.5;
+const ALIGNED_COSINE: f32 = 0.9;  // Acelera a fondo con la nariz a menos de ~25° del rumbo
+const ARRIVAL_RADII: f32 = 5.0;   // Se detiene a esta distancia del centro, en radios del cuerpo
+const APPROACH_GAIN: f32 = 0.01;  // Velocidad pedida por unidad de distancia que falta
+const ARRIVAL_SPEED: f32 = 0.004; // Unidades por frame
+const MAX_LEAD_FRAMES: f32 = 600.0;
+
+const TEXT_SCALE: usize = 2;
+const TEXT_COLOR: u32 = 0x66CCFF;
+
+// Where the trip stands this frame, for the HUD
+pub struct NavReadout {
+    pub range: f32,          // Hasta la superficie
+    pub closing_speed: f32,  // Unidades por frame, positiva al acercarse
+    pub remaining: f32,      // Hasta el punto donde se detiene
+    pub arrived: bool,
+}
+
+// Flies the ship to a body and parks it a few radii away. Steering is
+// proportional: the stick is set by the angle between the nose and the point
+// where the body will be, the throttle by how far the closing speed is from
+// one that slows down on arrival.
+pub struct Autopilot {
+    pub body: usize, // Índice en planets
+    last_target: Option<Vec3>,
+}
+
+impl Autopilot {
+    pub fn new(body: usize) -> Self {
+        Autopilot { body, last_target: None }
+    }
+
+    // Controls for this frame given where the body is now and its radius
+    pub fn steer(&mut self, model: &FlightModel, flight: &FlightState, rotation: &Orientation, position: &Vec3, target: Vec3, radius: f32) -> (FlightControls, NavReadout) {
+        let target_velocity = self.last_target.map_or(Vec3::zeros(), |last| target - last);
+        self.last_target = Some(target);
+
+        let offset = target - position;
+        let distance = offset.magnitude().max(1e-6);
+        let direction = offset / distance;
+        let stop_distance = radius * ARRIVAL_RADII;
+        let relative_velocity = flight.velocity - target_velocity;
+        let closing_speed = relative_velocity.dot(&direction);
+
+        // Aim where the body will be by the time the ship gets there
+        let lead_frames = (distance / closing_speed.max(model.max_speed * 0.5)).min(MAX_LEAD_FRAMES);
+        let aim = target + target_velocity * lead_frames - position;
+        let local = rotation.inverse() * aim.try_normalize(1e-6).unwrap_or(direction);
+
+        // +X is the left wing, a left yaw swings the nose towards it
+        let yaw_error = local.x.atan2(local.z);
+        let pitch_error = local.y.atan2(local.x.hypot(local.z));
+        let wing = rotation * Vec3::x();
+
+        let remaining = distance - stop_distance;
+        let wanted_speed = (remaining * APPROACH_GAIN).clamp(0.0, model.max_speed);
+        let throttle = if model.acceleration > 0.0 { (wanted_speed - closing_speed) / model.acceleration } else { 0.0 };
+        let alignment = ((local.z - ALIGNED_COSINE) / (1.0 - ALIGNED_COSINE)).clamp(0.0, 1.0);
+
+        let controls = FlightControls {
+            throttle: throttle.clamp(-1.0, 1.0) * alignment,
+            pitch: (pitch_error * STEERING_GAIN).clamp(-1.0, 1.0),
+            yaw: (yaw_error * STEERING_GAIN).clamp(-1.0, 1.0),
+            roll: (-wing.y * LEVELING_GAIN).clamp(-1.0, 1.0),
+        };
+        let readout = NavReadout {
+            range: (distance - radius).max(0.0),
+            closing_speed,
+            remaining: remaining.max(0.0),
+            arrived: remaining <= radius && closing_speed.abs() < ARRIVAL_SPEED,
+        };
+        (controls, readout)
+    }
+}
+
+// Target, range and time to arrival centered at the top of the screen. The
+// ETA is in real seconds at the current frame time, blank while the ship
+// isn't closing in.
+pub fn render(framebuffer: &mut Framebuffer, name: &str, readout: &NavReadout, frame_seconds: f32) {
+    let eta = if readout.closing_speed > 1e-5 && frame_seconds > 0.0 {
+        format!("{:.0} S", readout.remaining / readout.closing_speed * frame_seconds)
+    } else {
+        "--".to_string()
+    };
+    let lines = [
+        format!("AUTOPILOT {}", name),
+        format!("RANGE {:.1}  ETA {}", readout.range, eta),
+    ];
+    for (i, line) in lines.iter().enumerate() {
+        let width = line.chars().count() * (GLYPH_WIDTH + 1) * TEXT_SCALE;
+        let x = framebuffer.width.saturating_sub(width) / 2;
+        draw_text(framebuffer, x, 4 + i * 14, line, TEXT_COLOR, TEXT_SCALE);
+    }
+}
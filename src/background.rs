@@ -0,0 +1,84 @@
+// background.rs
+
+use crate::color::Color;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BackgroundMode {
+    Black,    // Espacio puro
+    Solid,
+    Gradient, // De `top` arriba a `bottom` abajo
+}
+
+impl BackgroundMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "black" => Some(BackgroundMode::Black),
+            "solid" => Some(BackgroundMode::Solid),
+            "gradient" => Some(BackgroundMode::Gradient),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BackgroundMode::Black => "black",
+            BackgroundMode::Solid => "solid",
+            BackgroundMode::Gradient => "gradient",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            BackgroundMode::Black => BackgroundMode::Solid,
+            BackgroundMode::Solid => BackgroundMode::Gradient,
+            BackgroundMode::Gradient => BackgroundMode::Black,
+        }
+    }
+}
+
+// What the framebuffer is cleared to, seen wherever no sky map covers it.
+// Colors are linear like everything else before bloom, the gradient blends in
+// linear light. `ambient` lights the night sides with the background's
+// average color, so a bright backdrop doesn't leave planets pitch black.
+#[derive(Clone, Copy, Debug)]
+pub struct Background {
+    pub mode: BackgroundMode,
+    pub color: Color,
+    pub top: Color,
+    pub bottom: Color,
+    pub ambient: f32, // 0 = sin luz ambiente
+}
+
+impl Background {
+    pub fn black() -> Self {
+        Background {
+            mode: BackgroundMode::Black,
+            color: Color::black(),
+            top: Color::black(),
+            bottom: Color::black(),
+            ambient: 0.0,
+        }
+    }
+
+    // Clear color of row `y` out of `height`
+    pub fn row_color(&self, y: usize, height: usize) -> u32 {
+        match self.mode {
+            BackgroundMode::Black => 0x000000,
+            BackgroundMode::Solid => self.color.to_hex(),
+            BackgroundMode::Gradient => {
+                let t = if height > 1 { y as f32 / (height - 1) as f32 } else { 0.0 };
+                self.top.lerp(&self.bottom, t).to_hex()
+            }
+        }
+    }
+
+    // Light the background adds to every lit surface, None when there is none
+    pub fn ambient_light(&self) -> Option<Color> {
+        let average = match self.mode {
+            BackgroundMode::Black => return None,
+            BackgroundMode::Solid => self.color,
+            BackgroundMode::Gradient => self.top.lerp(&self.bottom, 0.5),
+        };
+        (self.ambient > 0.0 && !average.is_black()).then(|| average * self.ambient)
+    }
+}
@@ -1,13 +1,39 @@
 // camera.rs
 
-use nalgebra_glm::{Vec3, rotate_vec3};
+use nalgebra_glm::Vec3;
 use std::f32::consts::PI;
+use crate::orientation::{Orientation, about_axis, looking_along, yaw};
+
+// Limits for orbit and zoom, angles in radians. Pitch is the elevation over
+// the target (positive = camera above it), azimuth is atan2(z, x) of the eye
+// around the target in -PI..PI.
+#[derive(Clone, Copy, Debug)]
+pub struct CameraConstraints {
+	pub min_pitch: f32,
+	pub max_pitch: f32,
+	pub min_distance: f32,
+	pub max_distance: f32,
+	pub azimuth: Option<(f32, f32)>, // None gira libremente
+}
+
+impl Default for CameraConstraints {
+	fn default() -> Self {
+		CameraConstraints {
+			min_pitch: -PI / 2.0 + 0.1,
+			max_pitch: PI / 2.0 - 0.1,
+			min_distance: 1.0,
+			max_distance: 500.0,
+			azimuth: None,
+		}
+	}
+}
 
 pub struct Camera {
 	pub eye: Vec3,
 	pub center: Vec3,
 	pub up: Vec3,
-	pub has_changed: bool
+	pub has_changed: bool,
+	pub constraints: CameraConstraints,
 }
 
 impl Camera {
@@ -17,6 +43,7 @@ impl Camera {
 			center,
 			up,
 			has_changed: true,
+			constraints: CameraConstraints::default(),
 		}
 	}
 
@@ -35,29 +62,35 @@ impl Camera {
 
 	pub fn orbit(&mut self, delta_yaw: f32, delta_pitch: f32) {
 		let radius_vector = self.eye - self.center;
-		let radius = radius_vector.magnitude();
 
 		let current_yaw = radius_vector.z.atan2(radius_vector.x);
 
 		let radius_xz = (radius_vector.x * radius_vector.x + radius_vector.z * radius_vector.z).sqrt();
 		let current_pitch = (-radius_vector.y).atan2(radius_xz);
 
-		let new_yaw = (current_yaw + delta_yaw) % (2.0 * PI);
-		let new_pitch = (current_pitch + delta_pitch).clamp(-PI / 2.0 + 0.1, PI / 2.0 - 0.1);
-
-		let new_eye = self.center + Vec3::new(
-			radius * new_yaw.cos() * new_pitch.cos(),
-			-radius * new_pitch.sin(),
-			radius * new_yaw.sin() * new_pitch.cos()
-		);
-
-		self.eye = new_eye;
+		// current_pitch grows downwards, the limits are elevations
+		let limits = &self.constraints;
+		let new_pitch = (current_pitch + delta_pitch).clamp(-limits.max_pitch, -limits.min_pitch);
+		let new_yaw = match limits.azimuth {
+			Some((min, max)) => wrap_angle(current_yaw + delta_yaw).clamp(min, max),
+			None => current_yaw + delta_yaw,
+		};
+
+		// Tilt the eye around the horizontal axis to its side, then turn it
+		// around the vertical. The side axis comes from the yaw so it still
+		// exists with the eye straight above the target.
+		let side = Vec3::new(current_yaw.cos(), 0.0, current_yaw.sin()).cross(&Vec3::y());
+		let rotation = yaw(current_yaw - new_yaw) * about_axis(&side, current_pitch - new_pitch);
+
+		self.eye = self.center + rotation * radius_vector;
 		self.has_changed = true;
 	}
 
+	// Never closer than min_distance, so the eye can't cross the target
 	pub fn zoom(&mut self, delta: f32) {
-		let direction = (self.center - self.eye).normalize();
-		self.eye += direction * delta;
+		let offset = self.center - self.eye;
+		let distance = (offset.magnitude() - delta).clamp(self.constraints.min_distance, self.constraints.max_distance);
+		self.eye = self.center - offset.normalize() * distance;
 		self.has_changed = true;
 	}
 
@@ -68,15 +101,27 @@ impl Camera {
 		let angle_x = direction.x * 0.05; // Adjust this factor to control rotation speed
 		let angle_y = direction.y * 0.05;
 
-		let rotated = rotate_vec3(&radius_vector, angle_x, &Vec3::new(0.0, 1.0, 0.0));
+		let rotated = yaw(angle_x) * radius_vector;
 
-		let right = rotated.cross(&self.up).normalize();
-		let final_rotated = rotate_vec3(&rotated, angle_y, &right);
+		let right = rotated.cross(&self.up);
+		let final_rotated = about_axis(&right, angle_y) * rotated;
 
 		self.center = self.eye + final_rotated.normalize() * radius;
 		self.has_changed = true;
 	}
 
+	// Rotation that takes +Z to the view direction
+	pub fn orientation(&self) -> Orientation {
+		looking_along(&(self.center - self.eye), &self.up)
+	}
+
+	// Looks along `orientation` from `eye`, the target `distance` ahead
+	pub fn look_from(&mut self, eye: Vec3, orientation: &Orientation, distance: f32) {
+		self.eye = eye;
+		self.center = eye + orientation * Vec3::z() * distance;
+		self.has_changed = true;
+	}
+
 	pub fn check_if_changed(&mut self) -> bool {
 		if self.has_changed {
 			self.has_changed = false;
@@ -85,4 +130,9 @@ impl Camera {
 			false
 		}
 	}
-}
\ No newline at end of file
+}
+
+// Brings an angle into -PI..PI
+fn wrap_angle(angle: f32) -> f32 {
+	(angle + PI).rem_euclid(2.0 * PI) - PI
+}
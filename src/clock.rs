@@ -0,0 +1,82 @@
+// clock.rs
+
+use std::time::Instant;
+
+const SECONDS_PER_DAY: f32 = 86_400.0;
+const SECONDS_PER_YEAR: f32 = 365.25 * SECONDS_PER_DAY;
+
+// Named simulation rates, in simulated seconds per real second
+pub const TIME_PRESETS: &[(&str, f32)] = &[
+    ("real time", 1.0),
+    ("1 hr/s", 3_600.0),
+    ("1 day/s", SECONDS_PER_DAY),
+    ("1 yr/min", SECONDS_PER_YEAR / 60.0),
+];
+
+const MIN_RATE: f32 = 1.0;
+const MAX_RATE: f32 = 4.0 * SECONDS_PER_YEAR;
+// Longest frame the clock advances for, so a stall doesn't jump the orbits
+const MAX_FRAME_SECONDS: f32 = 0.25;
+
+// Turns real frame time into steps of the orbit update. One step moves each
+// body by its orbit_speed and stands for `days_per_step` simulated days.
+pub struct SimulationClock {
+    pub rate: f32,          // Segundos simulados por segundo real
+    pub days_per_step: f32,
+    pub elapsed_days: f64,
+    pub fixed_frame_seconds: Option<f32>, // Paso fijo al exportar una animación
+    pub frame_seconds: f32,               // Tiempo real del último tick
+    last_frame: Instant,
+}
+
+impl SimulationClock {
+    pub fn new(days_per_step: f32, rate: f32) -> Self {
+        SimulationClock {
+            rate,
+            days_per_step,
+            elapsed_days: 0.0,
+            fixed_frame_seconds: None,
+            frame_seconds: 0.0,
+            last_frame: Instant::now(),
+        }
+    }
+
+    // Orbit steps to apply this frame. `cap` limits them, for reduced motion.
+    // With a fixed frame time every frame advances the same, however long it took.
+    pub fn tick(&mut self, cap: impl Fn(f32) -> f32) -> f32 {
+        let now = Instant::now();
+        let seconds = self.fixed_frame_seconds
+            .unwrap_or_else(|| now.duration_since(self.last_frame).as_secs_f32().min(MAX_FRAME_SECONDS));
+        self.last_frame = now;
+        self.frame_seconds = seconds;
+
+        let steps = cap(self.rate * seconds / (self.days_per_step * SECONDS_PER_DAY));
+        self.elapsed_days += (steps * self.days_per_step) as f64;
+        steps
+    }
+
+    // Account for steps applied outside `tick`, like jumping to an event
+    pub fn advance(&mut self, steps: f32) {
+        self.elapsed_days += (steps * self.days_per_step) as f64;
+    }
+
+    pub fn set_preset(&mut self, index: usize) {
+        self.rate = TIME_PRESETS[index].1;
+    }
+
+    pub fn scale_rate(&mut self, factor: f32) {
+        self.rate = (self.rate * factor).clamp(MIN_RATE, MAX_RATE);
+    }
+
+    // Preset name when the rate is one, otherwise in the largest unit that fits
+    pub fn label(&self) -> String {
+        if let Some((name, _)) = TIME_PRESETS.iter().find(|(_, rate)| (rate - self.rate).abs() < rate * 1e-3) {
+            return name.to_string();
+        }
+        let units = [(SECONDS_PER_YEAR, "yr/s"), (SECONDS_PER_DAY, "days/s"), (3_600.0, "hr/s"), (60.0, "min/s")];
+        match units.iter().find(|(seconds, _)| self.rate >= *seconds) {
+            Some((seconds, unit)) => format!("{:.2} {}", self.rate / seconds, unit),
+            None => format!("{:.2} s/s", self.rate),
+        }
+    }
+}
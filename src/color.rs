@@ -1,59 +1,122 @@
 // colo.rs
 
 use std::fmt;
+use std::sync::OnceLock;
 
+// Stored in linear light so lighting, blending and filtering add up like real
+// light does. Bytes (hex colors, images, the framebuffer) are sRGB: they are
+// decoded when a color is built and encoded again in to_hex. Alpha is the
+// coverage of translucent surfaces, 1 unless a shader sets it.
 #[derive(Debug, Clone, Copy)]
 pub struct Color {
-	r: u8,
-	g: u8,
-	b: u8,
+	r: f32,
+	g: f32,
+	b: f32,
+	a: f32,
+}
+
+const ENCODE_STEPS: usize = 4096; // Entradas de la tabla lineal -> sRGB
+
+// sRGB byte to linear 0..1
+pub fn srgb_to_linear(value: u8) -> f32 {
+	static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+	TABLE.get_or_init(|| {
+		let mut table = [0.0; 256];
+		for (byte, entry) in table.iter_mut().enumerate() {
+			let value = byte as f32 / 255.0;
+			*entry = if value <= 0.04045 {
+				value / 12.92
+			} else {
+				((value + 0.055) / 1.055).powf(2.4)
+			};
+		}
+		table
+	})[value as usize]
+}
+
+// Linear value to sRGB byte, clamped to 0..1 first
+pub fn linear_to_srgb(value: f32) -> u8 {
+	static TABLE: OnceLock<Vec<u8>> = OnceLock::new();
+	let table = TABLE.get_or_init(|| {
+		(0..ENCODE_STEPS)
+			.map(|step| {
+				let value = step as f32 / (ENCODE_STEPS - 1) as f32;
+				let encoded = if value <= 0.0031308 {
+					value * 12.92
+				} else {
+					1.055 * value.powf(1.0 / 2.4) - 0.055
+				};
+				(encoded * 255.0).round() as u8
+			})
+			.collect()
+	});
+	let value = if value.is_nan() { 0.0 } else { value.clamp(0.0, 1.0) };
+	table[(value * (ENCODE_STEPS - 1) as f32).round() as usize]
 }
 
 impl Color {
-	// Constructor to initialize the color using r, g, b values as u8
+	// Constructor to initialize the color using sRGB r, g, b values as u8
 	pub fn new(r: u8, g: u8, b: u8) -> Self {
-		Color { r, g, b }
+		Color { r: srgb_to_linear(r), g: srgb_to_linear(g), b: srgb_to_linear(b), a: 1.0 }
 	}
 
 	// default color
 	pub fn black() -> Self {
-		Color { r: 0, g: 0, b: 0 }
+		Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 }
 	}
 
-	// New constructor to initialize the color using r, g, b values as f32 (0.0 to 1.0)
+	// New constructor to initialize the color using sRGB r, g, b values as f32 (0.0 to 1.0)
 	pub fn from_float(r: f32, g: f32, b: f32) -> Self {
-		Color {
-		r: (r.clamp(0.0, 1.0) * 255.0) as u8,
-		g: (g.clamp(0.0, 1.0) * 255.0) as u8,
-		b: (b.clamp(0.0, 1.0) * 255.0) as u8,
-		}
+		let channel = |value: f32| srgb_to_linear((value.clamp(0.0, 1.0) * 255.0).round() as u8);
+		Color { r: channel(r), g: channel(g), b: channel(b), a: 1.0 }
+	}
+
+	// Linear values as they are, no decoding
+	pub fn from_linear(r: f32, g: f32, b: f32) -> Self {
+		Color { r: r.max(0.0), g: g.max(0.0), b: b.max(0.0), a: 1.0 }
 	}
 
-	// Function to create a color from a hex value
+	// Function to create a color from a sRGB hex value
 	pub fn from_hex(hex: u32) -> Self {
-		let r = ((hex >> 16) & 0xFF) as u8;
-		let g = ((hex >> 8) & 0xFF) as u8;
-		let b = (hex & 0xFF) as u8;
-		Color { r, g, b }
+		Color::new(((hex >> 16) & 0xFF) as u8, ((hex >> 8) & 0xFF) as u8, (hex & 0xFF) as u8)
 	}
 
-	// Function to return the color as a hex value
+	// Function to return the color as a sRGB hex value, ready for the framebuffer
 	pub fn to_hex(&self) -> u32 {
-		((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
+		((linear_to_srgb(self.r) as u32) << 16) | ((linear_to_srgb(self.g) as u32) << 8) | (linear_to_srgb(self.b) as u32)
+	}
+
+	// sRGB with the alpha in the top byte, 0xAARRGGBB
+	pub fn to_argb(self) -> u32 {
+		(((self.a.clamp(0.0, 1.0) * 255.0).round() as u32) << 24) | self.to_hex()
+	}
+
+	// Linear channels, for code that accumulates light itself
+	pub fn to_linear(self) -> [f32; 3] {
+		[self.r, self.g, self.b]
+	}
+
+	pub fn alpha(&self) -> f32 {
+		self.a
+	}
+
+	pub fn with_alpha(self, a: f32) -> Self {
+		Color { a: a.clamp(0.0, 1.0), ..self }
 	}
 
 	// Linear interpolation between two colors
 	pub fn lerp(&self, other: &Color, t: f32) -> Self {
 		let t = t.clamp(0.0, 1.0);
 		Color {
-		r: (self.r as f32 + (other.r as f32 - self.r as f32) * t).round() as u8,
-		g: (self.g as f32 + (other.g as f32 - self.g as f32) * t).round() as u8,
-		b: (self.b as f32 + (other.b as f32 - self.b as f32) * t).round() as u8,
+		r: self.r + (other.r - self.r) * t,
+		g: self.g + (other.g - self.g) * t,
+		b: self.b + (other.b - self.b) * t,
+		a: self.a + (other.a - self.a) * t,
 		}
 	}
 
 	pub fn is_black(&self) -> bool {
-		self.r == 0 && self.g == 0 && self.b == 0 
+		self.r <= 0.0 && self.g <= 0.0 && self.b <= 0.0
 	}
 
 	// New blend mode methods
@@ -62,40 +125,41 @@ impl Color {
 	}
 
 	pub fn blend_multiply(&self, blend: &Color) -> Color {
-		Color::new(
-		((self.r as f32 * blend.r as f32) / 255.0) as u8,
-		((self.g as f32 * blend.g as f32) / 255.0) as u8,
-		((self.b as f32 * blend.b as f32) / 255.0) as u8
-		)
+		Color {
+		r: self.r * blend.r,
+		g: self.g * blend.g,
+		b: self.b * blend.b,
+		a: self.a,
+		}
 	}
 
 	pub fn blend_add(&self, blend: &Color) -> Color {
-		Color::new(
-		(self.r as u16 + blend.r as u16).min(255) as u8,
-		(self.g as u16 + blend.g as u16).min(255) as u8,
-		(self.b as u16 + blend.b as u16).min(255) as u8
-		)
+		*self + *blend
 	}
 
 	pub fn blend_subtract(&self, blend: &Color) -> Color {
-		let r = (self.r as i16 - blend.r as i16).max(0).min(255) as u8;
-		let g = (self.g as i16 - blend.g as i16).max(0).min(255) as u8;
-		let b = (self.b as i16 - blend.b as i16).max(0).min(255) as u8;
-
-		Color::new(r, g, b)
+		Color {
+		r: (self.r - blend.r).max(0.0),
+		g: (self.g - blend.g).max(0.0),
+		b: (self.b - blend.b).max(0.0),
+		a: self.a,
+		}
 	}
 
 	pub fn blend_screen(&self, blend: &Color) -> Color {
-		Color::new(
-		255 - ((255 - self.r as u16) * (255 - blend.r as u16) / 255) as u8,
-		255 - ((255 - self.g as u16) * (255 - blend.g as u16) / 255) as u8,
-		255 - ((255 - self.b as u16) * (255 - blend.b as u16) / 255) as u8
-		)
+		let screen = |a: f32, b: f32| 1.0 - (1.0 - a.min(1.0)) * (1.0 - b.min(1.0));
+		Color {
+		r: screen(self.r, blend.r),
+		g: screen(self.g, blend.g),
+		b: screen(self.b, blend.b),
+		a: self.a,
+		}
 	}
 
 }
 
-// Implement addition for Color
+// Implement addition for Color. Sums can go over 1, to_hex clamps them.
+// Light added to a surface keeps the surface's alpha.
 use std::ops::Add;
 
 impl Add for Color {
@@ -103,9 +167,10 @@ impl Add for Color {
 
 	fn add(self, other: Color) -> Color {
 		Color {
-		r: self.r.saturating_add(other.r),
-		g: self.g.saturating_add(other.g),
-		b: self.b.saturating_add(other.b),
+		r: self.r + other.r,
+		g: self.g + other.g,
+		b: self.b + other.b,
+		a: self.a,
 		}
 	}
 }
@@ -117,10 +182,12 @@ impl Mul<f32> for Color {
 	type Output = Color;
 
 	fn mul(self, scalar: f32) -> Color {
+		let scalar = scalar.max(0.0);
 		Color {
-		r: (self.r as f32 * scalar).clamp(0.0, 255.0) as u8,
-		g: (self.g as f32 * scalar).clamp(0.0, 255.0) as u8,
-		b: (self.b as f32 * scalar).clamp(0.0, 255.0) as u8,
+		r: self.r * scalar,
+		g: self.g * scalar,
+		b: self.b * scalar,
+		a: self.a,
 		}
 	}
 }
@@ -128,6 +195,6 @@ impl Mul<f32> for Color {
 // Implement display formatting for Color
 impl fmt::Display for Color {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		write!(f, "Color(r: {}, g: {}, b: {})", self.r, self.g, self.b)
+		write!(f, "Color(r: {:.3}, g: {:.3}, b: {:.3})", self.r, self.g, self.b)
 	}
 }
\ No newline at end of file
@@ -0,0 +1,221 @@
+// comet.rs
+
+use std::f32::consts::PI;
+use nalgebra_glm::{Vec2, Vec3, Vec4};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::obj::IndexedMesh;
+use crate::particles::{Emitter, ParticleSystem};
+use crate::planet::{Planet, SPHERE_RADIUS};
+use crate::vertex::Vertex;
+use crate::Uniforms;
+
+const MAX_ACTIVITY: f32 = 2.0;       // Tope del brillo de las colas cerca del sol
+const DUST_INHERITANCE: f32 = 0.9;   // Parte de la velocidad del núcleo que conserva el polvo
+const MAX_NUCLEUS_STEP: f32 = 2.0;   // Un salto mayor en un frame es un cambio de tiempo, no movimiento
+
+const NUCLEUS_SLICES: u32 = 24;      // Meridianos de la malla del núcleo
+const NUCLEUS_STACKS: u32 = 12;
+const NUCLEUS_LOBES: usize = 6;      // Bultos y hundimientos de la forma
+const NUCLEUS_ELONGATION: f32 = 1.6; // Largo respecto al ancho, los núcleos son alargados
+
+const COMA_TAIL_STRETCH: f32 = 4.0;  // La coma se alarga hacia la cola
+const COMA_BRIGHTNESS: f32 = 0.6;
+const MAX_COMA_RADIUS: f32 = 160.0;  // Pixeles, más cerca se recorta
+
+// A body on a long ellipse whose tails come out as it nears the sun. Both
+// point away from it: the ion tail is gas blown straight out by the solar
+// wind and moves along with the nucleus, the dust tail drifts out slowly and
+// keeps only part of the nucleus' motion, so it lags behind along the orbit
+// and curves. Near perihelion both grow longer and the nucleus wraps itself
+// in a glowing coma.
+pub struct Comet {
+    pub body: usize,          // Índice del núcleo en planets
+    pub active_distance: f32, // Distancia al sol con las colas a pleno
+    pub coma_size: f32,       // Radio de la coma a pleno, unidades
+    pub coma_color: u32,
+    dust: Emitter,
+    ion: Emitter,
+    dust_speed: f32,          // Velocidades de las colas a active_distance
+    ion_speed: f32,
+    activity: f32,            // La del último frame, 0 lejos del sol
+    away: Vec3,               // Dirección opuesta al sol en el último frame
+    last_position: Option<Vec3>,
+}
+
+impl Comet {
+    pub fn new(body: usize, active_distance: f32, dust: Emitter, ion: Emitter) -> Self {
+        Comet {
+            body,
+            active_distance,
+            coma_size: 0.0,
+            coma_color: dust.color,
+            dust_speed: dust.speed,
+            ion_speed: ion.speed,
+            dust,
+            ion,
+            activity: 0.0,
+            away: Vec3::zeros(),
+            last_position: None,
+        }
+    }
+
+    // Emits this frame's tail particles, `density` scales them for the
+    // quality tier. Tails fall off with 1/d² away from `active_distance` and
+    // reach out as 1/d, pushed harder by the sunlight.
+    pub fn update(&mut self, planets: &[Planet], sun_position: &Vec3, particles: &mut ParticleSystem, density: f32) {
+        let Some(nucleus) = planets.get(self.body) else {
+            return;
+        };
+        let position = nucleus.get_position();
+        let velocity = self.last_position
+            .map(|last| position - last)
+            .filter(|step| step.magnitude() < MAX_NUCLEUS_STEP)
+            .unwrap_or(Vec3::zeros());
+        self.last_position = Some(position);
+
+        let away = position - sun_position;
+        let distance = away.magnitude().max(1e-3);
+        self.activity = (self.active_distance / distance).powi(2).min(MAX_ACTIVITY);
+        self.away = away / distance;
+
+        let reach = self.activity.sqrt();
+        self.ion.speed = self.ion_speed * reach;
+        self.dust.speed = self.dust_speed * reach;
+        let activity = self.activity * density;
+        self.ion.emit(particles, &position, &away, &velocity, activity);
+        self.dust.emit(particles, &position, &away, &(velocity * DUST_INHERITANCE), activity);
+    }
+
+    // The coma, a haze of gas around the nucleus drawn like the particles:
+    // added to the scene and hidden behind what is in front of it. It is
+    // round towards the sun and stretched into the start of the tail away
+    // from it, foreshortened with the view.
+    pub fn render_coma(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, planets: &[Planet]) {
+        let Some(nucleus) = planets.get(self.body) else {
+            return;
+        };
+        let size = self.coma_size * self.activity.sqrt();
+        if size <= 0.0 {
+            return;
+        }
+        let center = nucleus.get_position();
+        let view = uniforms.view_matrix * Vec4::new(center.x, center.y, center.z, 1.0);
+        let distance = -view.z;
+        if distance <= uniforms.depth_range.planes().0 {
+            return;
+        }
+        let to_screen = |point: Vec3| {
+            let clip = uniforms.projection_matrix * uniforms.view_matrix * Vec4::new(point.x, point.y, point.z, 1.0);
+            let screen = uniforms.viewport_matrix * (clip / clip.w);
+            Vec2::new(screen.x, screen.y)
+        };
+        let pixels_per_unit = uniforms.projection_matrix[(1, 1)] * framebuffer.height as f32 * 0.5;
+        let radius = (size / distance * pixels_per_unit).min(MAX_COMA_RADIUS);
+        if radius < 1.0 {
+            return;
+        }
+
+        // Tail direction on screen, its length tells how much of it faces the camera
+        let origin = to_screen(center);
+        let tail = (to_screen(center + self.away * size) - origin) / radius;
+        let foreshortening = tail.magnitude().min(1.0);
+        let axis = tail.try_normalize(1e-6).unwrap_or(Vec2::new(1.0, 0.0));
+        let stretch = 1.0 + (COMA_TAIL_STRETCH - 1.0) * foreshortening;
+
+        let color = Color::from_hex(self.coma_color) * (COMA_BRIGHTNESS * self.activity.min(1.0));
+        let depth = uniforms.depth_range.depth_at(distance);
+        let reach = (radius * stretch).ceil() as i32;
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                let (x, y) = (origin.x + dx as f32, origin.y + dy as f32);
+                if x < 0.0 || y < 0.0 {
+                    continue;
+                }
+                let offset = Vec2::new(dx as f32, dy as f32) / radius;
+                let along = offset.dot(&axis);
+                let across = offset.dot(&Vec2::new(-axis.y, axis.x));
+                let along = if along > 0.0 { along / stretch } else { along };
+                let spread = along * along + across * across;
+                if spread >= 1.0 {
+                    continue;
+                }
+                // Bright core fading to nothing at the edge
+                let glow = (1.0 / (1.0 + spread * 16.0) - 1.0 / 17.0) * (17.0 / 16.0);
+                framebuffer.point_additive(x as usize, y as usize, depth, (color * glow).to_hex());
+            }
+        }
+    }
+}
+
+// Lumpy, elongated nucleus with the same size and texture layout as the
+// planet sphere, so the planet shaders and the culling treat it the same
+pub fn nucleus_mesh(seed: u64) -> IndexedMesh {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let lobes: Vec<(Vec3, f32)> = (0..NUCLEUS_LOBES)
+        .map(|_| {
+            let direction = Vec3::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0));
+            (direction.try_normalize(1e-6).unwrap_or(Vec3::y()), rng.gen_range(-0.3..=0.3))
+        })
+        .collect();
+    let shape = |direction: Vec3| {
+        let bumps: f32 = lobes.iter().map(|(lobe, amount)| amount * direction.dot(lobe).max(0.0).powi(4)).sum();
+        Vec3::new(direction.x * NUCLEUS_ELONGATION, direction.y, direction.z) * (1.0 + bumps)
+    };
+
+    let mut positions = Vec::with_capacity(((NUCLEUS_SLICES + 1) * (NUCLEUS_STACKS + 1)) as usize);
+    let mut tex_coords = Vec::with_capacity(positions.capacity());
+    for stack in 0..=NUCLEUS_STACKS {
+        let v = stack as f32 / NUCLEUS_STACKS as f32;
+        let (sin_lat, cos_lat) = (v * PI).sin_cos();
+        for slice in 0..=NUCLEUS_SLICES {
+            let u = slice as f32 / NUCLEUS_SLICES as f32;
+            let (sin_lon, cos_lon) = (u * 2.0 * PI).sin_cos();
+            positions.push(shape(Vec3::new(sin_lat * cos_lon, cos_lat, sin_lat * sin_lon)));
+            tex_coords.push(Vec2::new(u, v));
+        }
+    }
+
+    // Largest extent back to the sphere's radius
+    let extent = positions.iter().map(|position| position.magnitude()).fold(0.0, f32::max);
+    for position in positions.iter_mut() {
+        *position *= SPHERE_RADIUS / extent;
+    }
+
+    let row = NUCLEUS_SLICES + 1;
+    let mut indices = Vec::with_capacity((NUCLEUS_SLICES * NUCLEUS_STACKS * 6) as usize);
+    for stack in 0..NUCLEUS_STACKS {
+        for slice in 0..NUCLEUS_SLICES {
+            let a = stack * row + slice;
+            let b = a + row;
+            indices.extend_from_slice(&[a, a + 1, b, a + 1, b + 1, b]);
+        }
+    }
+
+    // Smooth normals from the faces around each vertex, the seam and the
+    // poles share positions but not vertices so they are averaged by position
+    let mut normals = vec![Vec3::zeros(); positions.len()];
+    for triangle in indices.chunks(3) {
+        let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|index| positions[index as usize]);
+        let face = (b - a).cross(&(c - a));
+        for &index in triangle {
+            normals[index as usize] += face;
+        }
+    }
+    let vertices = positions.iter().zip(&tex_coords).enumerate()
+        .map(|(index, (position, tex_coords))| {
+            let shared: Vec3 = positions.iter().zip(&normals)
+                .filter(|(other, _)| (*other - position).magnitude_squared() < 1e-10)
+                .map(|(_, normal)| normal)
+                .sum();
+            let normal = shared.try_normalize(1e-12).unwrap_or_else(|| positions[index].normalize());
+            Vertex::new(*position, normal, *tex_coords)
+        })
+        .collect();
+
+    let mut mesh = IndexedMesh { vertices, indices };
+    mesh.generate_tangents();
+    mesh
+}
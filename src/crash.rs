@@ -0,0 +1,73 @@
+// crash.rs
+
+use std::fs;
+use std::panic::PanicHookInfo;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use once_cell::sync::Lazy;
+use crate::recorder::FlightRecorder;
+
+// Last known scene state and log lines, refreshed every frame so the panic
+// hook can write them out without touching the (possibly broken) main loop
+struct CrashContext {
+    state: String,
+    log: Vec<String>,
+}
+
+static CRASH_CONTEXT: Lazy<Mutex<CrashContext>> = Lazy::new(|| {
+    Mutex::new(CrashContext { state: String::new(), log: Vec::new() })
+});
+
+pub fn update_context(state: String, log: Vec<String>) {
+    if let Ok(mut context) = CRASH_CONTEXT.lock() {
+        context.state = state;
+        context.log = log;
+    }
+}
+
+// Writes a crash report and dumps the flight recorder before the default hook runs
+pub fn install_panic_hook(recorder: Arc<Mutex<FlightRecorder>>) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        match write_report(info) {
+            Ok(path) => eprintln!("Crash report saved to {}", path.display()),
+            Err(error) => eprintln!("Crash report failed: {}", error),
+        }
+
+        if let Ok(recorder) = recorder.try_lock() {
+            match recorder.dump(&format!("panic: {}", info)) {
+                Ok(dir) => eprintln!("Flight recorder saved to {}", dir.display()),
+                Err(error) => eprintln!("Flight recorder dump failed: {}", error),
+            }
+        }
+
+        default_hook(info);
+    }));
+}
+
+fn write_report(info: &PanicHookInfo) -> std::io::Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    fs::create_dir_all("crash_reports")?;
+    let path = PathBuf::from(format!("crash_reports/crash_{}.txt", timestamp));
+
+    // try_lock: the panic may have happened while the context was being updated
+    let (state, log) = match CRASH_CONTEXT.try_lock() {
+        Ok(context) => (context.state.clone(), context.log.join("\n")),
+        Err(_) => ("<unavailable>".to_string(), "<unavailable>".to_string()),
+    };
+
+    let thread = std::thread::current();
+    let report = format!(
+        "Graficas_Proy3 crash report\n\npanic: {}\nthread: {}\n\n[scene state]\n{}\n[recent log]\n{}\n",
+        info,
+        thread.name().unwrap_or("<unnamed>"),
+        state,
+        log,
+    );
+    fs::write(&path, report)?;
+    Ok(path)
+}
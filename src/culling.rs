@@ -0,0 +1,49 @@
+// culling.rs
+
+use nalgebra_glm::{Vec3, Vec4, Mat4};
+
+// View frustum as six planes (ax + by + cz + d >= 0 inside), extracted
+// from the projection * view matrix. Reversed-Z keeps depth in 1..0.
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    pub fn from_matrix(view_projection: &Mat4, reversed_z: bool) -> Self {
+        let row = |i: usize| -> Vec4 { view_projection.row(i).transpose() };
+        let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+
+        let planes = [
+            r3 + r0, // left
+            r3 - r0, // right
+            r3 + r1, // bottom
+            r3 - r1, // top
+            if reversed_z { r3 - r2 } else { r3 + r2 }, // near
+            if reversed_z { r2 } else { r3 - r2 },      // far
+        ]
+        .map(|plane| {
+            let length = Vec3::new(plane.x, plane.y, plane.z).magnitude();
+            // An infinite far plane has no normal and keeps everything
+            if length < 1e-6 {
+                Vec4::new(0.0, 0.0, 0.0, 1.0)
+            } else {
+                plane / length
+            }
+        });
+
+        Frustum { planes }
+    }
+
+    pub fn contains_sphere(&self, center: &Vec3, radius: f32) -> bool {
+        self.planes.iter().all(|plane| {
+            plane.x * center.x + plane.y * center.y + plane.z * center.z + plane.w >= -radius
+        })
+    }
+
+    // Only left, right, bottom and top, which don't depend on near and far
+    pub fn contains_sphere_sides(&self, center: &Vec3, radius: f32) -> bool {
+        self.planes[..4].iter().all(|plane| {
+            plane.x * center.x + plane.y * center.y + plane.z * center.z + plane.w >= -radius
+        })
+    }
+}
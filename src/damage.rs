@@ -0,0 +1,75 @@
+// damage.rs
+
+use nalgebra_glm::Vec3;
+use crate::surface_cache::{direction_from_uv, uv_from_direction};
+
+// Per-ship scorch texture, equirectangular around the model's origin like the
+// surface cache. Values go from 0 (clean hull) to 1 (fully burnt).
+#[derive(Clone)]
+pub struct DamageMap {
+    width: usize,
+    height: usize,
+    data: Vec<f32>,
+}
+
+impl DamageMap {
+    pub fn new(width: usize, height: usize) -> Self {
+        DamageMap {
+            width,
+            height,
+            data: vec![0.0; width * height],
+        }
+    }
+
+    // Burns a mark centered on a model-space direction, `spread` is the
+    // angular radius in radians and `amount` the added scorch at its center
+    pub fn add_scorch(&mut self, direction: &Vec3, spread: f32, amount: f32) {
+        let direction = direction.normalize();
+        let min_cos = spread.cos();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let u = (x as f32 + 0.5) / self.width as f32;
+                let v = (y as f32 + 0.5) / self.height as f32;
+                let cos_angle = direction_from_uv(u, v).dot(&direction);
+                if cos_angle <= min_cos {
+                    continue;
+                }
+
+                // Ragged edge: fade out from the center with a little per-texel variation
+                let falloff = (cos_angle - min_cos) / (1.0 - min_cos);
+                let ragged = 0.7 + 0.3 * texel_hash(x, y);
+                let texel = &mut self.data[y * self.width + x];
+                *texel = (*texel + amount * falloff * ragged).min(1.0);
+            }
+        }
+    }
+
+    // Every texel row by row, for saving a session
+    pub fn texels(&self) -> &[f32] {
+        &self.data
+    }
+
+    // Texels saved with `texels`, ignored if they come from another size
+    pub fn restore(&mut self, texels: &[f32]) {
+        if texels.len() == self.data.len() {
+            self.data.copy_from_slice(texels);
+        }
+    }
+
+    pub fn sample(&self, position: &Vec3) -> f32 {
+        if position.magnitude_squared() == 0.0 {
+            return 0.0;
+        }
+        let (u, v) = uv_from_direction(&position.normalize());
+        let x = ((u * self.width as f32) as usize).min(self.width - 1);
+        let y = ((v * self.height as f32) as usize).min(self.height - 1);
+        self.data[y * self.width + x]
+    }
+}
+
+fn texel_hash(x: usize, y: usize) -> f32 {
+    let n = (x as u32).wrapping_mul(374761393) ^ (y as u32).wrapping_mul(668265263);
+    let n = (n ^ (n >> 13)).wrapping_mul(1274126177);
+    (n & 0xFFFF) as f32 / 65535.0
+}
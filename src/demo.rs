@@ -0,0 +1,203 @@
+// demo.rs
+
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+use std::fs;
+use std::time::Instant;
+use crate::camera::Camera;
+use crate::clock::{SimulationClock, TIME_PRESETS};
+use crate::easing::smoothstep;
+use crate::font::{draw_text, GLYPH_WIDTH};
+use crate::framebuffer::Framebuffer;
+use crate::scene::{EscortConfig, NebulaConfig};
+
+const CAPTION_SCALE: usize = 3;
+const CAPTION_MARGIN: usize = 60; // Pixeles sobre el borde inferior
+
+fn default_caption_duration() -> f32 {
+    3.0
+}
+
+// What a script line does. Camera moves chained one after another make a path.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ScriptAction {
+    Camera {
+        eye: [f32; 3],
+        center: [f32; 3],
+        #[serde(default)]
+        duration: f32, // Segundos, 0 salta directo
+    },
+    Timescale {
+        preset: Option<String>, // Nombre en clock::TIME_PRESETS
+        rate: Option<f32>,      // O segundos simulados por segundo real
+    },
+    Caption {
+        text: String,
+        #[serde(default = "default_caption_duration")]
+        duration: f32,
+    },
+    SpawnNebula(NebulaConfig),
+    SpawnEscort(EscortConfig),
+    End,  // Termina el guion y muestra el resumen de rendimiento
+    Quit, // Igual que End y además cierra el programa
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ScriptEvent {
+    pub time: f32, // Segundos reales desde que empieza el guion
+    #[serde(flatten)]
+    pub action: ScriptAction,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct DemoScript {
+    pub events: Vec<ScriptEvent>,
+}
+
+impl DemoScript {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut script: DemoScript = toml::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+
+        for (index, event) in script.events.iter().enumerate() {
+            if let ScriptAction::Timescale { preset, rate } = &event.action {
+                match (preset, rate) {
+                    (Some(name), None) if !TIME_PRESETS.iter().any(|(preset, _)| preset == name) => {
+                        return Err(format!("{}: event {}: unknown time preset '{}'", path, index, name));
+                    }
+                    (Some(_), None) | (None, Some(_)) => {}
+                    _ => return Err(format!("{}: event {}: timescale needs either preset or rate", path, index)),
+                }
+            }
+        }
+        // Events at the same time keep their order in the file
+        script.events.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Ok(script)
+    }
+}
+
+struct CameraMove {
+    from: (Vec3, Vec3),
+    to: (Vec3, Vec3),
+    start: f32,
+    duration: f32,
+}
+
+// Plays a script against the wall clock, so a benchmark run always shows
+// the same shots however fast frames are. Camera moves, captions and the
+// timescale are applied here; spawns, End and Quit are returned to the caller.
+// An exported animation plays it against a fixed frame time instead.
+pub struct DemoPlayer {
+    pub fixed_frame_seconds: Option<f32>,
+    script: DemoScript,
+    next_event: usize,
+    started: Instant,
+    camera_move: Option<CameraMove>,
+    caption: Option<(String, f32)>, // Texto y segundo del guion en que desaparece
+    last_frame: Instant,
+    frame_seconds: Vec<f32>,
+}
+
+impl DemoPlayer {
+    pub fn new(script: DemoScript) -> Self {
+        let now = Instant::now();
+        DemoPlayer {
+            fixed_frame_seconds: None,
+            script,
+            next_event: 0,
+            started: now,
+            camera_move: None,
+            caption: None,
+            last_frame: now,
+            frame_seconds: Vec::new(),
+        }
+    }
+
+    pub fn update(&mut self, camera: &mut Camera, clock: &mut SimulationClock) -> Vec<ScriptAction> {
+        let now = Instant::now();
+        self.frame_seconds.push(now.duration_since(self.last_frame).as_secs_f32());
+        self.last_frame = now;
+        let time = match self.fixed_frame_seconds {
+            Some(seconds) => (self.frame_seconds.len() - 1) as f32 * seconds,
+            None => now.duration_since(self.started).as_secs_f32(),
+        };
+
+        let mut returned = Vec::new();
+        while let Some(event) = self.script.events.get(self.next_event).filter(|event| event.time <= time) {
+            match &event.action {
+                ScriptAction::Camera { eye, center, duration } => {
+                    self.camera_move = Some(CameraMove {
+                        from: (camera.eye, camera.center),
+                        to: (Vec3::from(*eye), Vec3::from(*center)),
+                        start: event.time,
+                        duration: *duration,
+                    });
+                }
+                ScriptAction::Timescale { preset, rate } => {
+                    if let Some(index) = preset.as_ref().and_then(|name| TIME_PRESETS.iter().position(|(preset, _)| preset == name)) {
+                        clock.set_preset(index);
+                    } else if let Some(rate) = rate {
+                        clock.rate = *rate;
+                    }
+                }
+                ScriptAction::Caption { text, duration } => {
+                    self.caption = Some((text.clone(), event.time + duration));
+                }
+                action => returned.push(action.clone()),
+            }
+            self.next_event += 1;
+        }
+
+        if let Some(camera_move) = &self.camera_move {
+            let progress = if camera_move.duration > 0.0 {
+                ((time - camera_move.start) / camera_move.duration).clamp(0.0, 1.0)
+            } else {
+                1.0
+            };
+            // Eases in and out of every leg of a path
+            let t = smoothstep(progress);
+            camera.eye = camera_move.from.0 + (camera_move.to.0 - camera_move.from.0) * t;
+            camera.center = camera_move.from.1 + (camera_move.to.1 - camera_move.from.1) * t;
+            camera.has_changed = true;
+            if progress >= 1.0 {
+                self.camera_move = None;
+            }
+        }
+        if self.caption.as_ref().is_some_and(|(_, until)| time >= *until) {
+            self.caption = None;
+        }
+        returned
+    }
+
+    // Frames, average fps and the slowest frames since the script started
+    pub fn summary(&self) -> String {
+        let mut frames: Vec<f32> = self.frame_seconds.iter().skip(1).copied().collect();
+        if frames.is_empty() {
+            return "no frames".to_string();
+        }
+        frames.sort_by(|a, b| a.total_cmp(b));
+        let total: f32 = frames.iter().sum();
+        let average = total / frames.len() as f32;
+        let p99 = frames[((frames.len() - 1) as f32 * 0.99) as usize];
+        format!(
+            "{} frames, {:.1} fps, avg {:.1} ms, p99 {:.1} ms, worst {:.1} ms",
+            frames.len(),
+            1.0 / average,
+            average * 1000.0,
+            p99 * 1000.0,
+            frames[frames.len() - 1] * 1000.0,
+        )
+    }
+
+    // Caption centered over the bottom of the screen
+    pub fn render(&self, framebuffer: &mut Framebuffer) {
+        let Some((text, _)) = &self.caption else {
+            return;
+        };
+        let width = text.chars().count() * (GLYPH_WIDTH + 1) * CAPTION_SCALE;
+        let x = framebuffer.width.saturating_sub(width) / 2;
+        let y = framebuffer.height.saturating_sub(CAPTION_MARGIN);
+        draw_text(framebuffer, x, y, text, 0xFFFFFF, CAPTION_SCALE);
+    }
+}
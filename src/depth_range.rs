@@ -0,0 +1,99 @@
+// depth_range.rs
+
+use nalgebra_glm::{Vec3, Vec4};
+use crate::culling::Frustum;
+
+const FIT_MARGIN: f32 = 1.02;      // Holgura para que nada roce los planos
+const MAX_DEPTH_RATIO: f32 = 1e5;  // far / near como máximo al ajustar
+const INFINITE_EPSILON: f32 = 2.4e-7; // Deja el infinito justo dentro del rango sin reversed-Z
+
+// Near and far planes of the projection. With `auto_fit` they are fitted every
+// frame to the bounding spheres in view, `near` from the config stays as the
+// closest they can get. Reversed-Z maps near to 1 and far to 0, where floats
+// are densest; the viewport negates it so the depth buffer still keeps the
+// smallest value as the closest and clears to infinity in both modes.
+// An infinite far plane never culls anything for being too far; `far` then
+// only marks how deep the nebulae look for the far side of a pixel.
+#[derive(Clone, Copy, Debug)]
+pub struct DepthRange {
+    pub near: f32,
+    pub far: f32,
+    pub auto_fit: bool,
+    pub reversed: bool,
+    pub infinite_far: bool,
+    fitted: Option<(f32, f32)>, // Planos del último ajuste
+}
+
+impl DepthRange {
+    pub fn new(near: f32, far: f32, auto_fit: bool, reversed: bool, infinite_far: bool) -> Self {
+        let near = near.max(1e-4);
+        DepthRange { near, far: far.max(near * 2.0), auto_fit, reversed, infinite_far, fitted: None }
+    }
+
+    // Planes the projection uses this frame
+    pub fn planes(&self) -> (f32, f32) {
+        match self.fitted {
+            Some(planes) if self.auto_fit => planes,
+            _ => (self.near, self.far),
+        }
+    }
+
+    // Depth row of the projection as (a, b): clip z = a * view z + b * w
+    pub fn projection_depth(&self) -> (f32, f32) {
+        let (near, far) = self.planes();
+        match (self.reversed, self.infinite_far) {
+            (false, false) => (-(far + near) / (far - near), -2.0 * far * near / (far - near)),
+            (false, true) => (INFINITE_EPSILON - 1.0, (INFINITE_EPSILON - 2.0) * near),
+            (true, false) => (near / (far - near), near * far / (far - near)), // 1 en near, 0 en far
+            (true, true) => (0.0, near),                                         // near / distancia
+        }
+    }
+
+    // Depth buffer value of a point `distance` in front of the camera
+    pub fn depth_at(&self, distance: f32) -> f32 {
+        let (a, b) = self.projection_depth();
+        let ndc = (b - a * distance) / distance;
+        if self.reversed { -ndc } else { ndc }
+    }
+
+    // Depth buffer value of the far plane, or of `far` when it is infinite
+    pub fn far_depth(&self) -> f32 {
+        self.depth_at(self.planes().1)
+    }
+
+    // Homogeneous near clip plane, same convention as the triangle clip planes
+    pub fn near_clip_plane(&self) -> Vec4 {
+        if self.reversed {
+            Vec4::new(0.0, 0.0, -1.0, 1.0) // z <= w
+        } else {
+            Vec4::new(0.0, 0.0, 1.0, 1.0)  // z >= -w
+        }
+    }
+
+    // Closest and farthest extent along `forward` of the spheres inside the
+    // frustum's side planes. Nothing in view keeps the configured planes.
+    pub fn fit(&mut self, eye: &Vec3, forward: &Vec3, frustum: &Frustum, spheres: impl Iterator<Item = (Vec3, f32)>) {
+        if !self.auto_fit {
+            self.fitted = None;
+            return;
+        }
+        let mut closest = f32::INFINITY;
+        let mut farthest: f32 = 0.0;
+        for (center, radius) in spheres {
+            let distance = (center - eye).dot(forward);
+            if distance + radius <= 0.0 || !frustum.contains_sphere_sides(&center, radius) {
+                continue;
+            }
+            closest = closest.min(distance - radius);
+            farthest = farthest.max(distance + radius);
+        }
+        if farthest <= 0.0 {
+            self.fitted = None;
+            return;
+        }
+
+        let far = (farthest * FIT_MARGIN).max(self.near * 2.0);
+        let near = (closest / FIT_MARGIN).max(self.near).max(far / MAX_DEPTH_RATIO).min(far * 0.5);
+        self.fitted = Some((near, far));
+    }
+}
@@ -0,0 +1,157 @@
+// docking.rs
+
+use nalgebra_glm::{Vec2, Vec3, Vec4};
+use crate::font::draw_text;
+use crate::labels::faded;
+use crate::framebuffer::Framebuffer;
+use crate::station::Station;
+use crate::Uniforms;
+
+// Tolerances for a clean docking, world units and degrees
+const DOCK_DISTANCE: f32 = 0.4;
+const DOCK_LATERAL: f32 = 0.25;
+const DOCK_ALIGNMENT: f32 = 10.0;
+const DOCK_CLOSURE: f32 = 0.05;
+
+const GOOD_COLOR: u32 = 0x66FF66;
+const WARN_COLOR: u32 = 0xFFB030;
+
+// Ship state relative to the docking port. `lateral` is the offset from the
+// approach axis as seen by a pilot facing the port (right, up).
+pub struct DockingReadout {
+    pub range: f32,
+    pub lateral: Vec2,
+    pub closure: f32,         // Unidades por frame, positiva al acercarse
+    pub alignment_error: f32, // Grados entre la nariz y el eje del puerto
+}
+
+impl DockingReadout {
+    pub fn aligned(&self) -> bool {
+        self.lateral.magnitude() < DOCK_LATERAL && self.alignment_error < DOCK_ALIGNMENT
+    }
+
+    pub fn docked(&self) -> bool {
+        self.aligned() && self.range < DOCK_DISTANCE && self.closure.abs() < DOCK_CLOSURE
+    }
+}
+
+pub struct DockingComputer {
+    pub approach_range: f32, // El HUD aparece por debajo de esta distancia
+    previous_range: Option<f32>,
+}
+
+impl DockingComputer {
+    pub fn new(approach_range: f32) -> Self {
+        DockingComputer {
+            approach_range,
+            previous_range: None,
+        }
+    }
+
+    // None while the ship is too far from the port
+    pub fn update(&mut self, station: &Station, ship_position: &Vec3, ship_heading: f32) -> Option<DockingReadout> {
+        let axis = station.port_axis();
+        let offset = ship_position - station.port_position();
+        let range = offset.magnitude();
+        if range > self.approach_range {
+            self.previous_range = None;
+            return None;
+        }
+
+        // The pilot faces into the port, along -axis
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let right = (-axis).cross(&up).normalize();
+        let lateral = Vec2::new(offset.dot(&right), offset.dot(&up));
+
+        let forward = Vec3::new(ship_heading.sin(), 0.0, ship_heading.cos());
+        let alignment_error = forward.dot(&-axis).clamp(-1.0, 1.0).acos().to_degrees();
+
+        let closure = self.previous_range.map_or(0.0, |previous| previous - range);
+        self.previous_range = Some(range);
+
+        Some(DockingReadout { range, lateral, closure, alignment_error })
+    }
+}
+
+// Port marker around the projected port, dimmed when `port_visible` says
+// something is in front of it, lateral offset crosshair at the bottom center
+// and the numeric readouts next to it
+pub fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, station: &Station, readout: &DockingReadout, port_visible: f32) {
+    let status_color = if readout.aligned() { GOOD_COLOR } else { WARN_COLOR };
+
+    let port = station.port_position();
+    let projected = uniforms.viewport_matrix * uniforms.projection_matrix * uniforms.view_matrix
+        * Vec4::new(port.x, port.y, port.z, 1.0);
+    if projected.w > 0.0 {
+        let x = (projected.x / projected.w) as i32;
+        let y = (projected.y / projected.w) as i32;
+        framebuffer.set_current_color(faded(status_color, port_visible));
+        draw_brackets(framebuffer, x, y, 10, 4);
+    }
+
+    // Crosshair box: the dot is the ship's offset from the approach axis, it
+    // has to sit in the center ring to dock
+    let box_half = 40;
+    let cx = (framebuffer.width / 2) as i32;
+    let cy = framebuffer.height as i32 - box_half - 16;
+    framebuffer.set_current_color(0x1F5F3F);
+    for i in -box_half..=box_half {
+        plot(framebuffer, cx + i, cy - box_half);
+        plot(framebuffer, cx + i, cy + box_half);
+        plot(framebuffer, cx - box_half, cy + i);
+        plot(framebuffer, cx + box_half, cy + i);
+        plot(framebuffer, cx + i, cy);
+        plot(framebuffer, cx, cy + i);
+    }
+    let tolerance = (box_half as f32 * DOCK_LATERAL / 2.0) as i32;
+    draw_brackets(framebuffer, cx, cy, tolerance, 2);
+
+    // Two units of offset reach the edge of the box
+    let pixels_per_unit = box_half as f32 / 2.0;
+    let dot_x = cx + (readout.lateral.x * pixels_per_unit).clamp(-(box_half as f32), box_half as f32) as i32;
+    let dot_y = cy - (readout.lateral.y * pixels_per_unit).clamp(-(box_half as f32), box_half as f32) as i32;
+    framebuffer.set_current_color(status_color);
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            plot(framebuffer, dot_x + dx, dot_y + dy);
+        }
+    }
+
+    let lines = [
+        format!("DOCK {}", station.name),
+        format!("RANGE {:.2}", readout.range),
+        format!("CLOSURE {:+.3}", readout.closure),
+        format!("LAT {:+.2} {:+.2}", readout.lateral.x, readout.lateral.y),
+        format!("ALIGN {:.1}", readout.alignment_error),
+    ];
+    let text_x = (cx + box_half + 12) as usize;
+    let text_y = (cy - box_half) as usize;
+    for (i, line) in lines.iter().enumerate() {
+        let color = match i {
+            3 if readout.lateral.magnitude() >= DOCK_LATERAL => WARN_COLOR,
+            4 if readout.alignment_error >= DOCK_ALIGNMENT => WARN_COLOR,
+            _ => GOOD_COLOR,
+        };
+        draw_text(framebuffer, text_x, text_y + i * 14, line, color, 2);
+    }
+}
+
+// Four corner marks around (x, y)
+pub fn draw_brackets(framebuffer: &mut Framebuffer, x: i32, y: i32, half: i32, arm: i32) {
+    for sx in [-1, 1] {
+        for sy in [-1, 1] {
+            let corner_x = x + sx * half;
+            let corner_y = y + sy * half;
+            for i in 0..=arm {
+                plot(framebuffer, corner_x - sx * i, corner_y);
+                plot(framebuffer, corner_x, corner_y - sy * i);
+            }
+        }
+    }
+}
+
+fn plot(framebuffer: &mut Framebuffer, x: i32, y: i32) {
+    if x >= 0 && y >= 0 {
+        framebuffer.point_overlay(x as usize, y as usize);
+    }
+}
@@ -0,0 +1,20 @@
+// easing.rs
+
+// Curves that turn the linear progress of a transition (0..1) into an eased
+// one, so camera moves start and stop gently instead of snapping
+
+// Eases in and out, used by the demo's camera legs
+pub fn smoothstep(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Slower start and stop than smoothstep, for longer flights
+pub fn ease_in_out_cubic(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    if t < 0.5 {
+        4.0 * t * t * t
+    } else {
+        1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+    }
+}
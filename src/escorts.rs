@@ -0,0 +1,111 @@
+// escorts.rs
+
+use nalgebra_glm::Vec3;
+
+// AI wingman holding a slot relative to the player ship
+pub struct Escort {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub heading: f32,  // Giro en Y
+    pub offset: Vec3,  // Posición en formación, en el marco local del jugador
+    pub ship: usize,   // Índice en la flota (malla, escala y shader)
+}
+
+// Steering parameters shared by the whole formation
+pub struct EscortWing {
+    pub escorts: Vec<Escort>,
+    pub max_speed: f32,          // Unidades por frame
+    pub max_force: f32,          // Cambio de velocidad máximo por frame
+    pub slowing_radius: f32,     // Arrival: frena dentro de esta distancia al puesto
+    pub separation_radius: f32,  // Se aparta de naves más cercanas que esto
+}
+
+impl EscortWing {
+    pub fn new(escorts: Vec<Escort>) -> Self {
+        EscortWing {
+            escorts,
+            max_speed: 0.25,
+            max_force: 0.02,
+            slowing_radius: 2.0,
+            separation_radius: 0.9,
+        }
+    }
+
+    // World position of a formation slot, rotated with the player's heading
+    pub fn slot_position(leader_position: &Vec3, leader_heading: f32, offset: &Vec3) -> Vec3 {
+        let (sin, cos) = leader_heading.sin_cos();
+        leader_position + Vec3::new(
+            offset.x * cos + offset.z * sin,
+            offset.y,
+            -offset.x * sin + offset.z * cos,
+        )
+    }
+
+    // Arrival towards each slot plus separation from the leader and each other
+    pub fn update(&mut self, leader_position: &Vec3, leader_heading: f32) {
+        let positions: Vec<Vec3> = self.escorts.iter().map(|escort| escort.position).collect();
+
+        for (index, escort) in self.escorts.iter_mut().enumerate() {
+            let slot = Self::slot_position(leader_position, leader_heading, &escort.offset);
+            let mut steering = arrive(escort, &slot, self.max_speed, self.slowing_radius);
+
+            let neighbours = positions.iter()
+                .enumerate()
+                .filter(|(other, _)| *other != index)
+                .map(|(_, position)| position)
+                .chain(std::iter::once(leader_position));
+            steering += separate(&escort.position, neighbours, self.separation_radius) * self.max_speed;
+
+            escort.velocity += clamp_length(steering, self.max_force);
+            escort.velocity = clamp_length(escort.velocity, self.max_speed);
+            escort.position += escort.velocity;
+
+            // Far from the slot it faces where it flies, once in formation it
+            // matches the leader so strafing doesn't swing the whole wing around
+            let catching_up = (slot - escort.position).magnitude() > self.slowing_radius;
+            let moving = escort.velocity.x.abs() + escort.velocity.z.abs() > 1e-3;
+            escort.heading = if catching_up && moving {
+                escort.velocity.x.atan2(escort.velocity.z)
+            } else {
+                leader_heading
+            };
+        }
+    }
+}
+
+// Seek with a speed ramp inside the slowing radius so escorts settle on the slot
+fn arrive(escort: &Escort, target: &Vec3, max_speed: f32, slowing_radius: f32) -> Vec3 {
+    let to_target = target - escort.position;
+    let distance = to_target.magnitude();
+    if distance < 1e-4 {
+        return -escort.velocity;
+    }
+    let speed = if distance < slowing_radius {
+        max_speed * distance / slowing_radius
+    } else {
+        max_speed
+    };
+    to_target / distance * speed - escort.velocity
+}
+
+// Push away from nearby ships, stronger the closer they are
+fn separate<'a>(position: &Vec3, others: impl Iterator<Item = &'a Vec3>, radius: f32) -> Vec3 {
+    let mut push = Vec3::new(0.0, 0.0, 0.0);
+    for other in others {
+        let away = position - other;
+        let distance = away.magnitude();
+        if distance > 1e-4 && distance < radius {
+            push += away / distance * (1.0 - distance / radius);
+        }
+    }
+    push
+}
+
+fn clamp_length(vector: Vec3, max_length: f32) -> Vec3 {
+    let length = vector.magnitude();
+    if length > max_length {
+        vector * (max_length / length)
+    } else {
+        vector
+    }
+}
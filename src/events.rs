@@ -0,0 +1,243 @@
+// events.rs
+
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+use crate::font::draw_text;
+use crate::framebuffer::Framebuffer;
+use crate::planet::{Planet, SPHERE_RADIUS, positions_after};
+
+const HORIZON_DAYS: f32 = 365.25;
+const MAX_SAMPLE_ANGLE: f32 = 0.02; // Radianes que avanza el cuerpo más rápido entre muestras
+const CONJUNCTION_ANGLE: f32 = 2.0 * PI / 180.0;
+const MIN_PERIAPSIS_ECCENTRICITY: f32 = 0.01;
+const REFRESH_FRAMES: u32 = 30;
+const TIMELINE_ROWS: usize = 4;
+const TIMELINE_X: usize = 4;
+const TIMELINE_Y: usize = 20;
+const TIMELINE_WIDTH: usize = 240; // Pixeles para todo el horizonte
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EventKind {
+    SolarEclipse, // La luna tapa el sol visto desde su planeta
+    LunarEclipse, // La luna entra en la sombra de su planeta
+    Conjunction,
+    Periapsis,
+}
+
+impl EventKind {
+    fn label(&self) -> &'static str {
+        match self {
+            EventKind::SolarEclipse => "Solar eclipse",
+            EventKind::LunarEclipse => "Lunar eclipse",
+            EventKind::Conjunction => "Conjunction",
+            EventKind::Periapsis => "Periapsis",
+        }
+    }
+
+    fn color(&self) -> u32 {
+        match self {
+            EventKind::SolarEclipse => 0xFFCC44,
+            EventKind::LunarEclipse => 0xFF6644,
+            EventKind::Conjunction => 0x66CCFF,
+            EventKind::Periapsis => 0x66FF66,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Event {
+    pub kind: EventKind,
+    pub steps: f32, // Pasos de órbita desde ahora
+    pub bodies: String,
+}
+
+impl Event {
+    pub fn describe(&self) -> String {
+        format!("{} {}", self.kind.label(), self.bodies)
+    }
+}
+
+// Upcoming eclipses, conjunctions and periapsis passages over the next year,
+// found by stepping copies of the orbits forward. The player ship flies
+// freely instead of following an orbit, so it has no periapsis to predict.
+pub struct EventPredictor {
+    pub events: Vec<Event>, // El más próximo primero
+    pub visible: bool,
+    frames_until_refresh: u32,
+}
+
+impl EventPredictor {
+    pub fn new() -> Self {
+        EventPredictor {
+            events: Vec::new(),
+            visible: true,
+            frames_until_refresh: 0,
+        }
+    }
+
+    // Counts down the frames already advanced and re-predicts now and then
+    pub fn update(&mut self, planets: &[Planet], days_per_step: f32, steps: f32) {
+        for event in &mut self.events {
+            event.steps -= steps;
+        }
+        self.events.retain(|event| event.steps > 0.0);
+
+        if self.frames_until_refresh == 0 {
+            self.events = predict(planets, HORIZON_DAYS / days_per_step);
+            self.frames_until_refresh = REFRESH_FRAMES;
+        }
+        self.frames_until_refresh -= 1;
+    }
+
+    // Predict again on the next update, after the orbits jumped
+    pub fn refresh(&mut self) {
+        self.frames_until_refresh = 0;
+    }
+
+    // Skips an event that is already underway so repeated jumps move on
+    pub fn next_event(&self, min_steps: f32) -> Option<&Event> {
+        self.events.iter().find(|event| event.steps > min_steps)
+    }
+
+    pub fn render(&self, framebuffer: &mut Framebuffer, days_per_step: f32) {
+        if !self.visible {
+            return;
+        }
+
+        // Horizon bar with a tick per event
+        let horizon_steps = HORIZON_DAYS / days_per_step;
+        framebuffer.set_current_color(0x555555);
+        for x in TIMELINE_X..TIMELINE_X + TIMELINE_WIDTH {
+            framebuffer.point_overlay(x, TIMELINE_Y + 2);
+        }
+        for event in &self.events {
+            let x = TIMELINE_X + ((event.steps / horizon_steps).min(1.0) * (TIMELINE_WIDTH - 1) as f32) as usize;
+            framebuffer.set_current_color(event.kind.color());
+            for y in TIMELINE_Y..TIMELINE_Y + 5 {
+                framebuffer.point_overlay(x, y);
+            }
+        }
+
+        for (row, event) in self.events.iter().take(TIMELINE_ROWS).enumerate() {
+            let text = format!("{:6.1} d  {}", event.steps * days_per_step, event.describe());
+            draw_text(framebuffer, TIMELINE_X, TIMELINE_Y + 10 + row * 14, &text, event.kind.color(), 2);
+        }
+    }
+}
+
+// Local minimum of a sampled quantity, tracked one sample late
+struct MinimumTracker {
+    previous: [f32; 2],
+}
+
+impl MinimumTracker {
+    fn new() -> Self {
+        MinimumTracker { previous: [f32::INFINITY; 2] }
+    }
+
+    // When the last sample was a minimum below `threshold`, returns its offset
+    // from the current sample in samples, refined with a parabola
+    fn push(&mut self, value: f32, threshold: f32) -> Option<f32> {
+        let [before, middle] = self.previous;
+        self.previous = [middle, value];
+        if !(middle < before && middle <= value && middle < threshold) {
+            return None;
+        }
+        let curvature = before - 2.0 * middle + value;
+        let shift = if curvature > 0.0 { 0.5 * (before - value) / curvature } else { 0.0 };
+        Some(shift - 1.0)
+    }
+}
+
+fn angle_between(a: &Vec3, b: &Vec3) -> f32 {
+    let lengths = a.magnitude() * b.magnitude();
+    if lengths == 0.0 {
+        return PI;
+    }
+    (a.dot(b) / lengths).clamp(-1.0, 1.0).acos()
+}
+
+fn predict(planets: &[Planet], horizon_steps: f32) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    // Periapsis passages are periodic, so they're solved directly
+    for planet in planets.iter().filter(|planet| planet.eccentricity > MIN_PERIAPSIS_ECCENTRICITY && planet.orbit_speed > 0.0) {
+        let period = 2.0 * PI / planet.orbit_speed;
+        let mut steps = (2.0 * PI - planet.current_angle.rem_euclid(2.0 * PI)) / planet.orbit_speed;
+        while steps < horizon_steps {
+            events.push(Event { kind: EventKind::Periapsis, steps, bodies: planet.name.clone() });
+            steps += period;
+        }
+    }
+
+    if let Some(star) = planets.iter().position(|planet| planet.semi_major_axis == 0.0 && planet.parent.is_none()) {
+        events.extend(alignments(planets, star, horizon_steps));
+    }
+
+    events.retain(|event| event.steps > 0.0);
+    events.sort_by(|a, b| a.steps.total_cmp(&b.steps));
+    events
+}
+
+// Eclipses and conjunctions, sampled finely enough that the fastest body
+// moves MAX_SAMPLE_ANGLE between samples
+fn alignments(planets: &[Planet], star: usize, horizon_steps: f32) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    // Moons of bodies that orbit the star, and pairs of the star's planets
+    let moons: Vec<(usize, usize)> = planets.iter()
+        .enumerate()
+        .filter_map(|(index, planet)| planet.parent.map(|parent| (index, parent)))
+        .filter(|&(_, parent)| parent != star)
+        .collect();
+    let orbiting: Vec<usize> = (0..planets.len())
+        .filter(|&index| index != star && planets[index].parent.is_none())
+        .collect();
+    let pairs: Vec<(usize, usize)> = orbiting.iter()
+        .enumerate()
+        .flat_map(|(i, &a)| orbiting[i + 1..].iter().map(move |&b| (a, b)))
+        .collect();
+
+    let fastest = planets.iter().map(|planet| planet.orbit_speed.abs()).fold(0.0, f32::max);
+    if fastest == 0.0 {
+        return events;
+    }
+    let sample_steps = MAX_SAMPLE_ANGLE / fastest;
+    let samples = (horizon_steps / sample_steps).ceil() as usize;
+
+    let mut moon_trackers: Vec<MinimumTracker> = moons.iter().map(|_| MinimumTracker::new()).collect();
+    let mut pair_trackers: Vec<MinimumTracker> = pairs.iter().map(|_| MinimumTracker::new()).collect();
+    let mut positions = Vec::with_capacity(planets.len());
+
+    for sample in 0..=samples {
+        let steps = sample as f32 * sample_steps;
+        positions_after(planets, steps, &mut positions);
+        let star_position = positions[star];
+
+        // Eclipse when the moon crosses the line through its planet and the
+        // star closer than the two radii: in front it shades the planet,
+        // behind it falls into the planet's shadow
+        for (&(moon, parent), tracker) in moons.iter().zip(&mut moon_trackers) {
+            let sun_direction = (star_position - positions[parent]).normalize();
+            let offset = positions[moon] - positions[parent];
+            let along = offset.dot(&sun_direction);
+            let distance = (offset - sun_direction * along).magnitude();
+            let reach = (planets[moon].radius + planets[parent].radius) * SPHERE_RADIUS;
+            if let Some(shift) = tracker.push(distance, reach) {
+                let kind = if along > 0.0 { EventKind::SolarEclipse } else { EventKind::LunarEclipse };
+                let bodies = format!("{}/{}", planets[moon].name, planets[parent].name);
+                events.push(Event { kind, steps: steps + shift * sample_steps, bodies });
+            }
+        }
+
+        // Conjunction when two planets line up as seen from the star
+        for (&(a, b), tracker) in pairs.iter().zip(&mut pair_trackers) {
+            let separation = angle_between(&(positions[a] - star_position), &(positions[b] - star_position));
+            if let Some(shift) = tracker.push(separation, CONJUNCTION_ANGLE) {
+                let bodies = format!("{}-{}", planets[a].name, planets[b].name);
+                events.push(Event { kind: EventKind::Conjunction, steps: steps + shift * sample_steps, bodies });
+            }
+        }
+    }
+    events
+}
@@ -0,0 +1,191 @@
+// exporter.rs
+
+use nalgebra_glm::Vec3;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::planet::{Planet, positions_after};
+
+// Orbit steps on each side of now used to differentiate the orbits
+const VELOCITY_STEPS: f32 = 0.01;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            "json" => Some(ExportFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct BodyState {
+    name: String,
+    kind: &'static str, // "body" o "ship"
+    position: [f32; 3],
+    velocity: [f32; 3], // Unidades por día simulado
+}
+
+#[derive(Serialize)]
+struct Sample {
+    days: f64,
+    bodies: Vec<BodyState>,
+}
+
+enum Output {
+    Csv(BufWriter<File>),
+    Json(Vec<Sample>),
+}
+
+// Writes every body's position and velocity plus the ship's trajectory each
+// `interval_days` of simulated time. CSV is streamed row by row, JSON is kept
+// in memory and written when the export stops. Body velocities come from the
+// orbits themselves and ignore the star's exaggerated wobble; the ship's is
+// the difference since its last sample.
+pub struct StateExporter {
+    pub format: ExportFormat,
+    pub interval_days: f64,
+    pub directory: String,
+    output: Option<(PathBuf, Output)>,
+    next_sample_days: f64,
+    last_ship: Option<(f64, Vec3)>,
+}
+
+impl StateExporter {
+    pub fn new(format: ExportFormat, interval_days: f64, directory: &str) -> Self {
+        StateExporter {
+            format,
+            interval_days: interval_days.max(1e-3),
+            directory: directory.to_string(),
+            output: None,
+            next_sample_days: 0.0,
+            last_ship: None,
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.output.is_some()
+    }
+
+    // Opens a new file, the first sample is taken on the next update
+    pub fn start(&mut self, elapsed_days: f64) -> Result<PathBuf, String> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        fs::create_dir_all(&self.directory).map_err(|e| format!("{}: {}", self.directory, e))?;
+
+        let extension = match self.format {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        };
+        let path = PathBuf::from(&self.directory).join(format!("state_{}.{}", timestamp, extension));
+        let output = match self.format {
+            ExportFormat::Csv => {
+                let file = File::create(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+                let mut writer = BufWriter::new(file);
+                writeln!(writer, "days,kind,name,x,y,z,vx,vy,vz").map_err(|e| format!("{}: {}", path.display(), e))?;
+                Output::Csv(writer)
+            }
+            ExportFormat::Json => Output::Json(Vec::new()),
+        };
+
+        self.output = Some((path.clone(), output));
+        self.next_sample_days = elapsed_days;
+        self.last_ship = None;
+        Ok(path)
+    }
+
+    // Flushes the CSV or writes the JSON, returning the file written
+    pub fn stop(&mut self) -> Result<Option<PathBuf>, String> {
+        let Some((path, output)) = self.output.take() else {
+            return Ok(None);
+        };
+        let result = match output {
+            Output::Csv(mut writer) => writer.flush().map_err(|e| e.to_string()),
+            Output::Json(samples) => File::create(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|file| serde_json::to_writer_pretty(BufWriter::new(file), &samples).map_err(|e| e.to_string())),
+        };
+        result.map(|_| Some(path.clone())).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // Samples once `interval_days` have passed. A frame that skips several
+    // intervals at high time rates gives one sample at its own time.
+    pub fn update(&mut self, planets: &[Planet], ship_name: &str, ship_position: Vec3, elapsed_days: f64, days_per_step: f32) -> Result<(), String> {
+        let Some((path, output)) = self.output.as_mut() else {
+            return Ok(());
+        };
+        if elapsed_days < self.next_sample_days {
+            return Ok(());
+        }
+        while self.next_sample_days <= elapsed_days {
+            self.next_sample_days += self.interval_days;
+        }
+
+        let mut before = Vec::with_capacity(planets.len());
+        let mut after = Vec::with_capacity(planets.len());
+        positions_after(planets, -VELOCITY_STEPS, &mut before);
+        positions_after(planets, VELOCITY_STEPS, &mut after);
+        let mut bodies: Vec<BodyState> = planets.iter()
+            .enumerate()
+            .map(|(index, planet)| BodyState {
+                name: planet.name.clone(),
+                kind: "body",
+                position: planet.get_position().into(),
+                velocity: ((after[index] - before[index]) / (2.0 * VELOCITY_STEPS * days_per_step)).into(),
+            })
+            .collect();
+
+        let ship_velocity = match self.last_ship {
+            Some((days, position)) if elapsed_days > days => (ship_position - position) / (elapsed_days - days) as f32,
+            _ => Vec3::new(0.0, 0.0, 0.0),
+        };
+        self.last_ship = Some((elapsed_days, ship_position));
+        bodies.push(BodyState {
+            name: ship_name.to_string(),
+            kind: "ship",
+            position: ship_position.into(),
+            velocity: ship_velocity.into(),
+        });
+
+        match output {
+            Output::Csv(writer) => {
+                for body in &bodies {
+                    let [x, y, z] = body.position;
+                    let [vx, vy, vz] = body.velocity;
+                    writeln!(writer, "{:.4},{},{},{},{},{},{},{},{}", elapsed_days, body.kind, csv_field(&body.name), x, y, z, vx, vy, vz)
+                        .map_err(|e| format!("{}: {}", path.display(), e))?;
+                }
+            }
+            Output::Json(samples) => samples.push(Sample { days: elapsed_days, bodies }),
+        }
+        Ok(())
+    }
+}
+
+// Closing the window while exporting still writes the file
+impl Drop for StateExporter {
+    fn drop(&mut self) {
+        if let Err(error) = self.stop() {
+            eprintln!("State export failed: {}", error);
+        }
+    }
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
@@ -0,0 +1,124 @@
+// flight.rs
+
+use nalgebra_glm::Vec3;
+use crate::orientation::{Orientation, about_axis};
+use crate::planet::Planet;
+
+// Share of the gap to the requested turn rates closed every frame, the ship
+// takes a few frames to start and stop turning
+const ANGULAR_RESPONSE: f32 = 0.15;
+const BANK_RESPONSE: f32 = 0.1;
+
+// Units³ per Earth mass per frame²: around the sun at the Earth's distance a
+// circular orbit takes about 0.05 units per frame, within a ship's top speed
+const GRAVITATIONAL_CONSTANT: f32 = 6e-8;
+
+// Stick and throttle for one frame, each in -1..1. Pitch up, yaw left and
+// roll right are positive, throttle forward is positive.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct FlightControls {
+    pub throttle: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+    pub roll: f32,
+}
+
+// How a ship handles, from its roster entry. Speeds are per frame like the
+// rest of the simulation.
+#[derive(Clone, Copy, Debug)]
+pub struct FlightModel {
+    pub max_speed: f32,    // Unidades por frame
+    pub acceleration: f32, // Unidades por frame, cada frame con el acelerador a fondo
+    pub damping: f32,      // Fracción de la velocidad que se pierde cada frame
+    pub turn_rate: f32,    // Radianes por frame en cabeceo y guiñada
+    pub roll_rate: f32,
+    pub max_bank: f32,     // Alabeo al girar a tope, radianes
+}
+
+// Pull of the sun and planets on the player's ship. The scene keeps real
+// masses but squeezes the distances, so next to the sun a planet pulls too
+// little to bend a path; `planet_strength` exaggerates every body but the star.
+#[derive(Clone, Copy, Debug)]
+pub struct Gravity {
+    pub enabled: bool,
+    pub strength: f32,        // Escala la constante gravitatoria
+    pub planet_strength: f32, // Multiplica la masa de todo menos la estrella
+}
+
+impl Gravity {
+    // Acceleration at `position`, in units per frame². Inside a body the pull
+    // falls off to zero at its center as for a uniform sphere, so flying
+    // through one doesn't fling the ship away.
+    pub fn acceleration(&self, position: &Vec3, planets: &[Planet], bounding_radius: f32) -> Vec3 {
+        let mut acceleration = Vec3::zeros();
+        for planet in planets.iter().filter(|planet| planet.mass > 0.0) {
+            let mass = if planet.semi_major_axis == 0.0 { planet.mass } else { planet.mass * self.planet_strength };
+            let offset = planet.get_position() - position;
+            let distance = offset.magnitude();
+            let radius = (planet.radius * bounding_radius).max(1e-3); // La esfera, sin los anillos
+            if distance < 1e-6 {
+                continue;
+            }
+            let pull = if distance >= radius { 1.0 / (distance * distance) } else { distance / (radius * radius * radius) };
+            acceleration += offset / distance * (GRAVITATIONAL_CONSTANT * self.strength * mass * pull);
+        }
+        acceleration
+    }
+}
+
+// Motion carried over between frames. The nose is the model's +Z and its
+// left wing +X, as the escort offsets assume.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FlightState {
+    pub velocity: Vec3,
+    angular_velocity: Vec3, // Cabeceo, guiñada y alabeo, en los ejes de la nave
+    pub bank: f32,          // Inclinación visual hacia el giro, no cambia el rumbo
+}
+
+impl FlightState {
+    // Turns the ship, pushes it along its nose and moves it one frame. With a
+    // gravitational pull the ship coasts like a body in orbit: nothing damps
+    // it, and the top speed only stops the engine from adding more, a
+    // slingshot can still carry it past.
+    pub fn step(&mut self, model: &FlightModel, controls: &FlightControls, pull: Option<Vec3>, rotation: &mut Orientation, position: &mut Vec3) {
+        let requested = Vec3::new(
+            controls.pitch * model.turn_rate,
+            controls.yaw * model.turn_rate,
+            controls.roll * model.roll_rate,
+        );
+        self.angular_velocity += (requested - self.angular_velocity) * ANGULAR_RESPONSE;
+
+        // Rates are about the ship's own axes, so they apply after its rotation.
+        // Nose up turns +Z towards +Y, a negative angle around +X.
+        let turn = about_axis(&Vec3::x(), -self.angular_velocity.x)
+            * about_axis(&Vec3::y(), self.angular_velocity.y)
+            * about_axis(&Vec3::z(), self.angular_velocity.z);
+        *rotation *= turn;
+        rotation.renormalize_fast();
+
+        // Turning left dips the left wing
+        let bank = if model.turn_rate > 0.0 { -self.angular_velocity.y / model.turn_rate * model.max_bank } else { 0.0 };
+        self.bank += (bank - self.bank) * BANK_RESPONSE;
+
+        let forward = *rotation * Vec3::z();
+        let thrust = forward * (controls.throttle.clamp(-1.0, 1.0) * model.acceleration);
+        match pull {
+            None => {
+                self.velocity += thrust;
+                self.velocity *= 1.0 - model.damping.clamp(0.0, 1.0);
+                if self.velocity.magnitude() > model.max_speed {
+                    self.velocity = self.velocity.normalize() * model.max_speed;
+                }
+            }
+            Some(pull) => {
+                let limit = self.velocity.magnitude().max(model.max_speed);
+                self.velocity += thrust;
+                if self.velocity.magnitude() > limit {
+                    self.velocity = self.velocity.normalize() * limit;
+                }
+                self.velocity += pull;
+            }
+        }
+        *position += self.velocity;
+    }
+}
@@ -0,0 +1,105 @@
+// focus.rs
+
+use nalgebra_glm::Vec3;
+use crate::camera::Camera;
+use crate::easing::ease_in_out_cubic;
+use crate::orientation::{Orientation, looking_along, slerp};
+use crate::planet::Planet;
+
+const FOCUS_DISTANCE_RADII: f32 = 4.0; // Distancia de la cámara al cuerpo, en radios
+const APPROACH_LIFT: f32 = 0.3;        // Llega un poco por encima del plano de la órbita
+const FLIGHT_SPEED: f32 = 30.0;        // Unidades por segundo
+const MIN_FLIGHT_SECONDS: f32 = 0.75;
+const MAX_FLIGHT_SECONDS: f32 = 2.5;
+
+// Bodies a number key goes through: the nth planet around the star in scene
+// order first, then its moons on the following presses
+pub fn focus_cycle(planets: &[Planet], slot: u8) -> Vec<usize> {
+    let Some(planet) = planets.iter()
+        .enumerate()
+        .filter(|(_, planet)| planet.parent.is_none() && planet.semi_major_axis > 0.0)
+        .map(|(index, _)| index)
+        .nth(slot as usize - 1)
+    else {
+        return Vec::new();
+    };
+    std::iter::once(planet)
+        .chain(planets.iter().enumerate().filter(|(_, moon)| moon.parent == Some(planet)).map(|(index, _)| index))
+        .collect()
+}
+
+struct Flight {
+    from_eye: Vec3,
+    from_orientation: Orientation, // Hacia dónde miraba la cámara
+    from_distance: f32,            // Del ojo a lo que miraba
+    duration: f32,
+    elapsed: f32,
+}
+
+// Flies the camera to an offset from a body, then carries it along as the
+// body orbits. Once there, orbiting and zooming move the camera around the
+// body and the offset they leave is kept.
+pub struct CameraFocus {
+    pub body: usize, // Índice en planets
+    flight: Option<Flight>,
+    offset: Vec3,        // Ojo respecto al cuerpo al terminar el vuelo
+    last_position: Vec3,
+}
+
+impl CameraFocus {
+    // `instant` skips the flight, for reduced motion
+    pub fn new(camera: &Camera, body: usize, position: Vec3, radius: f32, instant: bool) -> Self {
+        // Arrive from the side the camera is already on
+        let approach = camera.eye - position;
+        let direction = if approach.magnitude() > 1e-4 { approach.normalize() } else { Vec3::new(0.0, 0.0, 1.0) };
+        let direction = (direction + Vec3::new(0.0, APPROACH_LIFT, 0.0)).normalize();
+        let distance = (radius * FOCUS_DISTANCE_RADII).max(camera.constraints.min_distance);
+        let offset = direction * distance;
+
+        let travel = (position + offset - camera.eye).magnitude();
+        let duration = if instant { 0.0 } else { (travel / FLIGHT_SPEED).clamp(MIN_FLIGHT_SECONDS, MAX_FLIGHT_SECONDS) };
+        CameraFocus {
+            body,
+            flight: Some(Flight {
+                from_eye: camera.eye,
+                from_orientation: camera.orientation(),
+                from_distance: (camera.center - camera.eye).magnitude(),
+                duration,
+                elapsed: 0.0,
+            }),
+            offset,
+            last_position: position,
+        }
+    }
+
+    // Call once per frame with where the body is now and the frame time
+    pub fn update(&mut self, camera: &mut Camera, position: Vec3, seconds: f32) {
+        match self.flight.as_mut() {
+            // The goal moves with the body, the flight still lands on it. The
+            // view turns along the shortest arc instead of sweeping its target
+            // across the scene.
+            Some(flight) => {
+                flight.elapsed += seconds;
+                let progress = if flight.duration > 0.0 { flight.elapsed / flight.duration } else { 1.0 };
+                let t = ease_in_out_cubic(progress);
+                let goal_eye = position + self.offset;
+                let goal_orientation = looking_along(&-self.offset, &camera.up);
+                camera.look_from(
+                    flight.from_eye.lerp(&goal_eye, t),
+                    &slerp(&flight.from_orientation, &goal_orientation, t),
+                    flight.from_distance + (self.offset.magnitude() - flight.from_distance) * t,
+                );
+                if progress >= 1.0 {
+                    self.flight = None;
+                }
+            }
+            None => {
+                let moved = position - self.last_position;
+                camera.eye += moved;
+                camera.center += moved;
+            }
+        }
+        self.last_position = position;
+        camera.has_changed = true;
+    }
+}
@@ -1,7 +1,8 @@
 // fragment.rs
 
-use nalgebra_glm::{Vec3, Vec2};
+use nalgebra_glm::{Vec2, Vec3, Vec4};
 use crate::color::Color;
+use crate::shaders::LightTerms;
 
 pub struct Fragment {
     pub position: Vec2,
@@ -11,6 +12,8 @@ pub struct Fragment {
     pub intensity: f32,
     pub vertex_position: Vec3,
     pub tex_coords: Vec2,
+    pub view_direction: Vec3, // Hacia la cámara en el mundo, cero sin cámara (texturas horneadas)
+    pub tangent: Vec4,        // En el mundo, w el signo de la bitangente; cero si la malla no tiene
 }
 
 impl Fragment {
@@ -31,6 +34,68 @@ impl Fragment {
             intensity,
             vertex_position,
             tex_coords,
+            view_direction: Vec3::zeros(),
+            tangent: Vec4::zeros(),
         }
     }
-}
\ No newline at end of file
+}
+
+// The fragments of one triangle as parallel arrays, one entry per covered
+// pixel. Each attribute is interpolated in its own loop over the barycentric
+// weights, and so are the lighting terms the shading shares, which the
+// compiler can vectorize; the buffer is reused from triangle to triangle so
+// rasterizing doesn't allocate.
+#[derive(Default)]
+pub struct FragmentBuffer {
+    pub positions: Vec<Vec2>,
+    pub weights: Vec<Vec3>, // Coordenadas baricéntricas
+    pub depths: Vec<f32>,
+    pub normals: Vec<Vec3>,
+    pub intensities: Vec<f32>,
+    pub vertex_positions: Vec<Vec3>,
+    pub tex_coords: Vec<Vec2>,
+    pub view_directions: Vec<Vec3>,
+    pub tangents: Vec<Vec4>,
+    pub colors: Vec<Color>,
+    pub dimming: Vec<f32>,    // Sombra de los anillos por la luz del sol a esa distancia
+    pub highlights: Vec<f32>, // Brillo especular del material
+    pub ambient: Vec<Color>,  // Luz del fondo y de las sondas por la normal
+}
+
+impl FragmentBuffer {
+    pub fn clear(&mut self) {
+        self.positions.clear();
+        self.weights.clear();
+        self.depths.clear();
+        self.normals.clear();
+        self.intensities.clear();
+        self.vertex_positions.clear();
+        self.tex_coords.clear();
+        self.view_directions.clear();
+        self.tangents.clear();
+        self.colors.clear();
+        self.dimming.clear();
+        self.highlights.clear();
+        self.ambient.clear();
+    }
+
+    // One fragment gathered back into the struct the shaders take
+    pub fn get(&self, index: usize) -> Fragment {
+        Fragment {
+            position: self.positions[index],
+            color: self.colors[index],
+            depth: self.depths[index],
+            normal: self.normals[index],
+            intensity: self.intensities[index],
+            vertex_position: self.vertex_positions[index],
+            tex_coords: self.tex_coords[index],
+            view_direction: self.view_directions[index],
+            tangent: self.tangents[index],
+        }
+    }
+
+    // Filled by shaders::light_fragments
+    pub fn light(&self, index: usize) -> LightTerms {
+        LightTerms { dimming: self.dimming[index], highlight: self.highlights[index], ambient: self.ambient[index] }
+    }
+}
@@ -0,0 +1,99 @@
+// frame_capture.rs
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::framebuffer::{Framebuffer, save_buffer_image};
+
+struct CapturedImage {
+    name: String,
+    width: usize,
+    height: usize,
+    pixels: Vec<u32>,
+}
+
+// Every intermediate buffer of one frame, written as numbered PNGs in one
+// folder so a rendering problem can be followed pass by pass: the color
+// buffer after each pass, the bloom's own buffers, and the depth and normal
+// of the surface that won each pixel.
+pub struct FrameCapture {
+    images: Vec<CapturedImage>,
+}
+
+impl FrameCapture {
+    // Call right after clearing, so the normal buffer starts empty
+    pub fn begin(framebuffer: &mut Framebuffer) -> Self {
+        framebuffer.normals = Some(vec![0; framebuffer.width * framebuffer.height]);
+        FrameCapture { images: Vec::new() }
+    }
+
+    // Color buffer as the last pass left it
+    pub fn stage(&mut self, name: &str, framebuffer: &Framebuffer) {
+        self.image(name, framebuffer.width, framebuffer.height, framebuffer.buffer.clone());
+    }
+
+    pub fn image(&mut self, name: &str, width: usize, height: usize, pixels: Vec<u32>) {
+        let name = format!("{:02}_{}", self.images.len(), name);
+        self.images.push(CapturedImage { name, width, height, pixels });
+    }
+
+    // Writes everything to captures/frame_<timestamp> and stops recording normals
+    pub fn finish(self, framebuffer: &mut Framebuffer) -> Result<PathBuf, String> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let dir = PathBuf::from(format!("captures/frame_{}", timestamp));
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+        let normals = framebuffer.normals.take().unwrap_or_default();
+        save(&dir, "depth", framebuffer.width, framebuffer.height, &depth_image(&framebuffer.zbuffer))?;
+        save(&dir, "normals", framebuffer.width, framebuffer.height, &normals)?;
+        for image in &self.images {
+            save(&dir, &image.name, image.width, image.height, &image.pixels)?;
+        }
+        Ok(dir)
+    }
+}
+
+fn save(dir: &Path, name: &str, width: usize, height: usize, pixels: &[u32]) -> Result<(), String> {
+    let path = dir.join(format!("{}.png", name));
+    save_buffer_image(&path, pixels, width, height).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+// The window's frame as it is now, as captures/screenshot_<timestamp>.png
+pub fn screenshot(framebuffer: &Framebuffer) -> Result<PathBuf, String> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    fs::create_dir_all("captures").map_err(|e| e.to_string())?;
+    let path = PathBuf::from(format!("captures/screenshot_{}.png", timestamp));
+    save_buffer_image(&path, &framebuffer.buffer, framebuffer.width, framebuffer.height)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+// Nearest drawn depth white, farthest dark gray, empty pixels black
+fn depth_image(zbuffer: &[f32]) -> Vec<u32> {
+    let (near, far) = zbuffer.iter()
+        .filter(|depth| depth.is_finite())
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(near, far), &depth| (near.min(depth), far.max(depth)));
+    let range = (far - near).max(1e-6);
+    zbuffer.iter()
+        .map(|&depth| {
+            if !depth.is_finite() {
+                return 0;
+            }
+            let value = (255.0 - (depth - near) / range * 223.0) as u32;
+            (value << 16) | (value << 8) | value
+        })
+        .collect()
+}
+
+// Normal packed as a color, each axis from -1..1 to 0..255
+pub fn encode_normal(normal: &nalgebra_glm::Vec3) -> u32 {
+    let normal = normal.try_normalize(1e-6).unwrap_or(*normal);
+    let channel = |value: f32| ((value * 0.5 + 0.5).clamp(0.0, 1.0) * 255.0) as u32;
+    (channel(normal.x) << 16) | (channel(normal.y) << 8) | channel(normal.z)
+}
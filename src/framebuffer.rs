@@ -1,13 +1,35 @@
 // framebuffer.rs
 
+use std::path::Path;
+use nalgebra_glm::Vec2;
+use rayon::prelude::*;
+use rayon::iter::Either;
+use crate::color::Color;
+use crate::background::Background;
+use crate::material::BlendMode;
+use crate::arena::{Scratch, FRAME_ARENA};
+
+// Rows written by one worker when merging shaded fragments
+const MERGE_BAND_ROWS: usize = 16;
+const OPAQUE: u32 = 0xFF000000;
+
+// A shaded fragment waiting for its depth test: x, y, depth, color as
+// 0xAARRGGBB, the encoded normal and the pixels it moved since the last
+// frame, None for objects that don't track it
+pub type ShadedFragment = (usize, usize, f32, u32, u32, Option<Vec2>);
+
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
     pub buffer: Vec<u32>,
     pub zbuffer: Vec<f32>,
-    background_color: u32,
+    pub normals: Option<Vec<u32>>, // Normal de cada pixel como color, solo al capturar un frame
+    pub motion: Option<Vec<Option<Vec2>>>, // Vector de movimiento de cada pixel, solo si un efecto lo usa
+    background: Background,
     current_color: u32,
     active_buffer: bool,
+    transparent: bool, // Capa del HUD, con alfa en el byte alto
+    translucent: Vec<(ShadedFragment, BlendMode)>, // Esperando el pase traslúcido del frame
 }
 
 impl Framebuffer {
@@ -17,19 +39,49 @@ impl Framebuffer {
             height,
             buffer: vec![0; width * height],
             zbuffer: vec![f32::INFINITY; width * height],
-            background_color: 0x000000,
+            normals: None,
+            motion: None,
+            background: Background::black(),
             current_color: 0xFFFFFF,
             active_buffer: true,
+            transparent: false,
+            translucent: Vec::new(),
         }
     }
 
+    // See-through target for the HUD, drawn apart from the 3D frame so it
+    // never touches the scene's depth buffer and can be left out of a
+    // screenshot. Pixels are 0xAARRGGBB with the color premultiplied by its
+    // alpha, cleared to fully transparent; `composite` lays it over the scene.
+    pub fn layer(width: usize, height: usize) -> Self {
+        Framebuffer { transparent: true, ..Framebuffer::new(width, height) }
+    }
+
     pub fn clear(&mut self) {
-        for pixel in self.buffer.iter_mut() {
-            *pixel = self.background_color;
+        if self.transparent {
+            self.buffer.fill(0);
+            return;
+        }
+        let height = self.height;
+        for (y, row) in self.buffer.chunks_mut(self.width.max(1)).enumerate() {
+            row.fill(self.background.row_color(y, height));
         }
         for depth in self.zbuffer.iter_mut() {
             *depth = f32::INFINITY;
         }
+        if let Some(motion) = self.motion.as_mut() {
+            motion.fill(None);
+        }
+    }
+
+    // Keeps a motion vector per pixel from the next frame on, or stops
+    pub fn track_motion(&mut self, enabled: bool) {
+        let size = self.width * self.height;
+        match (enabled, &self.motion) {
+            (true, Some(motion)) if motion.len() == size => {}
+            (true, _) => self.motion = Some(vec![None; size]),
+            (false, _) => self.motion = None,
+        }
     }
 
     pub fn point(&mut self, x: usize, y: usize, depth: f32) {
@@ -38,15 +90,196 @@ impl Framebuffer {
             if self.zbuffer[index] > depth {
                 self.buffer[index] = self.current_color;
                 self.zbuffer[index] = depth;
+                // Points and lines follow the camera when reprojected
+                if let Some(motion) = self.motion.as_mut() {
+                    motion[index] = None;
+                }
+            }
+        }
+    }
+
+    // Depth-tests and writes fragments shaded in parallel. Every band of rows
+    // belongs to one worker and keeps the fragments in the order they were
+    // submitted, so the frame is the same with any number of threads and at
+    // equal depth the first fragment wins, as with `point`. Normals are kept
+    // while a frame capture records them, motion vectors while an effect uses
+    // them.
+    pub fn write_fragments(&mut self, fragments: &[ShadedFragment]) {
+        let band_len = self.width * MERGE_BAND_ROWS;
+        let bands = self.height.div_ceil(MERGE_BAND_ROWS);
+        if band_len == 0 {
+            return;
+        }
+
+        // Counting sort by band, stable so the submission order survives
+        let mut merge = FRAME_ARENA.take();
+        let Scratch { band_starts: starts, band_next: next, band_order: sorted, .. } = &mut merge;
+        let visible = |fragment: &&ShadedFragment| fragment.0 < self.width && fragment.1 < self.height;
+        starts.resize(bands + 1, 0);
+        for fragment in fragments.iter().filter(visible) {
+            starts[fragment.1 / MERGE_BAND_ROWS + 1] += 1;
+        }
+        for band in 0..bands {
+            starts[band + 1] += starts[band];
+        }
+        next.extend_from_slice(starts);
+        sorted.resize(starts[bands], 0);
+        for (index, fragment) in fragments.iter().enumerate().filter(|(_, fragment)| visible(fragment)) {
+            let band = fragment.1 / MERGE_BAND_ROWS;
+            sorted[next[band]] = index as u32;
+            next[band] += 1;
+        }
+
+        // Bands of the normals and motion vectors, or nothing for each band when they aren't kept
+        let normal_bands = match self.normals.as_mut() {
+            Some(normals) => Either::Left(normals.par_chunks_mut(band_len).map(Some)),
+            None => Either::Right((0..bands).into_par_iter().map(|_| None)),
+        };
+        let motion_bands = match self.motion.as_mut() {
+            Some(motion) => Either::Left(motion.par_chunks_mut(band_len).map(Some)),
+            None => Either::Right((0..bands).into_par_iter().map(|_| None)),
+        };
+        let width = self.width;
+        self.buffer.par_chunks_mut(band_len)
+            .zip(self.zbuffer.par_chunks_mut(band_len))
+            .zip(normal_bands)
+            .zip(motion_bands)
+            .enumerate()
+            .for_each(|(band, (((colors, depths), mut normals), mut motion))| {
+                let first_row = band * MERGE_BAND_ROWS;
+                for &index in &sorted[starts[band]..starts[band + 1]] {
+                    let (x, y, depth, color, normal, moved) = fragments[index as usize];
+                    let local = (y - first_row) * width + x;
+                    if depths[local] > depth {
+                        colors[local] = color & 0xFFFFFF;
+                        depths[local] = depth;
+                        if let Some(normals) = normals.as_mut() {
+                            normals[local] = normal;
+                        }
+                        if let Some(motion) = motion.as_mut() {
+                            motion[local] = moved;
+                        }
+                    }
+                }
+            });
+        FRAME_ARENA.give(merge);
+    }
+
+    // Holds translucent fragments until `resolve_translucent`, they can only
+    // be mixed once everything opaque is in
+    pub fn queue_translucent(&mut self, fragments: &[ShadedFragment], blend: BlendMode) {
+        self.translucent.extend(fragments.iter().map(|fragment| (*fragment, blend)));
+    }
+
+    // The translucent pass: every queued fragment from the farthest to the
+    // nearest, depth-tested against the opaque scene and mixed over it in
+    // linear light. They leave no depth, so they never hide one another.
+    pub fn resolve_translucent(&mut self) {
+        let mut translucent = std::mem::take(&mut self.translucent);
+        translucent.sort_by(|(a, _), (b, _)| b.2.total_cmp(&a.2));
+        for &((x, y, depth, color, _, _), blend) in &translucent {
+            let alpha = (color >> 24) as f32 / 255.0;
+            if x >= self.width || y >= self.height || alpha <= 0.0 {
+                continue;
+            }
+            let index = y * self.width + x;
+            if self.zbuffer[index] <= depth {
+                continue;
+            }
+            let below = Color::from_hex(self.buffer[index]);
+            let above = Color::from_hex(color & 0xFFFFFF);
+            self.buffer[index] = match blend {
+                BlendMode::Additive => below + above * alpha,
+                _ => below.lerp(&above, alpha),
+            }.to_hex();
+        }
+        translucent.clear();
+        self.translucent = translucent;
+    }
+
+    // Writes the current color ignoring the depth buffer (used for overlays like text)
+    pub fn point_overlay(&mut self, x: usize, y: usize) {
+        if x < self.width && y < self.height {
+            let index = y * self.width + x;
+            self.buffer[index] = if self.transparent { self.current_color | OPAQUE } else { self.current_color };
+        }
+    }
+
+    // Dims a pixel to `factor` of its brightness, like a dark glass over it.
+    // On a layer an empty pixel turns into translucent black, so the scene
+    // behind it is dimmed once composited.
+    pub fn darken(&mut self, x: usize, y: usize, factor: f32) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let index = y * self.width + x;
+        let pixel = self.buffer[index];
+        let channel = |shift: u32| ((((pixel >> shift) & 0xFF) as f32 * factor) as u32).min(0xFF) << shift;
+        let alpha = if self.transparent {
+            let coverage = (pixel >> 24) as f32 / 255.0;
+            (((1.0 - (1.0 - coverage) * factor) * 255.0).round() as u32).min(0xFF) << 24
+        } else {
+            0
+        };
+        self.buffer[index] = alpha | channel(16) | channel(8) | channel(0);
+    }
+
+    // Blends this layer over `target` of the same size, "over" with
+    // premultiplied alpha
+    pub fn composite(&self, target: &mut Framebuffer) {
+        if self.width != target.width || self.height != target.height {
+            return;
+        }
+        target.buffer.par_iter_mut().zip(self.buffer.par_iter()).for_each(|(below, &above)| {
+            let alpha = above >> 24;
+            if alpha == 0xFF {
+                *below = above & 0xFFFFFF;
+            } else if above != 0 {
+                let channel = |shift: u32| {
+                    let under = (*below >> shift) & 0xFF;
+                    ((above >> shift) & 0xFF) + under * (0xFF - alpha) / 0xFF
+                };
+                *below = channel(16).min(0xFF) << 16 | channel(8).min(0xFF) << 8 | channel(0).min(0xFF);
+            }
+        });
+    }
+
+    // Background pass (stars and their glows): behind everything, so it only
+    // adds to pixels nothing has drawn to and leaves their depth at infinity.
+    // Light adds in linear space, so overlapping glows don't bloat.
+    pub fn point_background_additive(&mut self, x: usize, y: usize, color: u32) {
+        if x < self.width && y < self.height {
+            let index = y * self.width + x;
+            if self.zbuffer[index] == f32::INFINITY {
+                self.buffer[index] = (Color::from_hex(self.buffer[index]) + Color::from_hex(color)).to_hex();
             }
         }
     }
 
-    pub fn set_background_color(&mut self, color: u32) {
-        self.background_color = color;
+    // Glowing sprites (particles): depth-tested against the scene but they
+    // leave no depth, so overlapping sprites add up in linear light
+    pub fn point_additive(&mut self, x: usize, y: usize, depth: f32, color: u32) {
+        if x < self.width && y < self.height {
+            let index = y * self.width + x;
+            if self.zbuffer[index] > depth {
+                self.buffer[index] = (Color::from_hex(self.buffer[index]) + Color::from_hex(color)).to_hex();
+            }
+        }
+    }
+
+    pub fn set_background(&mut self, background: Background) {
+        self.background = background;
     }
 
     pub fn set_current_color(&mut self, color: u32) {
         self.current_color = color;
     }
-}
\ No newline at end of file
+}
+
+// Writes a 0xRRGGBB pixel buffer to an image file (format from the extension)
+pub fn save_buffer_image(path: &Path, buffer: &[u32], width: usize, height: usize) -> image::ImageResult<()> {
+    let bytes: Vec<u8> = buffer.iter()
+        .flat_map(|pixel| [(pixel >> 16) as u8, (pixel >> 8) as u8, *pixel as u8])
+        .collect();
+    image::save_buffer(path, &bytes, width as u32, height as u32, image::ColorType::Rgb8)
+}
@@ -0,0 +1,492 @@
+// galaxy.rs
+
+use std::f32::consts::PI;
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use minifb::Key;
+use nalgebra_glm::Vec2;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use crate::docking::draw_brackets;
+use crate::font::{draw_text, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::framebuffer::Framebuffer;
+use crate::input::Input;
+use crate::material::MaterialLibrary;
+use crate::planet::Planet;
+use crate::shaders::SUN_SHADER;
+use crate::threads;
+use crate::scene::{AsteroidBeltConfig, AtmosphereConfig, CloudsConfig, MagnetosphereConfig, NebulaConfig, NoiseConfig, PlanetConfig, RingsConfig, SceneConfig, ShaderRef, TailConfig};
+
+const SYLLABLES: [&str; 20] = [
+    "ka", "ri", "vel", "to", "an", "mi", "sor", "e", "lu", "den",
+    "ya", "zo", "ter", "ni", "ar", "qui", "bel", "os", "tra", "no",
+];
+const PLACEMENT_TRIES: usize = 30;
+const MIN_SEPARATION: f32 = 0.12;  // Fracción del radio de la galaxia entre dos estrellas
+const REFERENCE_ORBIT: (f32, f32) = (8.0, 0.02); // La Tierra: semieje y orbit_speed, el resto sigue a Kepler
+const FIRST_ORBIT: f32 = 5.0;
+const GIANT_ORBIT: f32 = 12.0;     // Más allá se forman gigantes gaseosos
+const ROCKY_SHADERS: [&str; 4] = ["lava", "gas", "rocky", "moon"];
+const GIANT_SHADERS: [&str; 4] = ["gas-giant", "ice", "waves", "atmospheric"];
+const MOON_SHADER: &str = "moon";
+const TEXT_SCALE: usize = 2;
+const ROW_HEIGHT: usize = (GLYPH_HEIGHT + 3) * TEXT_SCALE;
+const MAP_FILL: f32 = 0.7;         // Lado del mapa en fracción del lado corto de la pantalla
+const PANEL_PADDING: usize = 12;
+const PANEL_DIM: f32 = 0.25;
+const CURRENT_RING: f32 = 7.0;     // Pixeles
+const ROUTE_DASH: usize = 4;
+const TITLE_COLOR: u32 = 0xFFFFFF;
+const SELECTED_COLOR: u32 = 0x66FF66;
+const ROUTE_COLOR: u32 = 0x3C8C3C;
+const HINT_COLOR: u32 = 0x9AA8B8;
+
+// Star colors from hot to cool with the star's radius and mass in the scene,
+// cool dwarfs are the most common
+const STAR_CLASSES: [(u32, f32, f32, f32); 6] = [
+    // Color, radio, masa (terrestres), peso
+    (0x9BB0FF, 8.0, 900000.0, 0.03),
+    (0xCAD7FF, 7.0, 600000.0, 0.07),
+    (0xF8F7FF, 6.5, 420000.0, 0.12),
+    (0xFFF4EA, 6.0, 333000.0, 0.18),
+    (0xFFD2A1, 5.0, 250000.0, 0.25),
+    (0xFFCC6F, 4.0, 160000.0, 0.35),
+];
+
+// A star on the galaxy map. Its system is generated from `seed` when the
+// ship jumps there, the same every time.
+#[derive(Clone, Debug)]
+pub struct StarSystem {
+    pub name: String,
+    pub position: Vec2,  // Años luz desde el sistema de origen
+    pub seed: u64,
+    pub class: usize,    // Índice en STAR_CLASSES
+    pub planets: usize,
+}
+
+impl StarSystem {
+    pub fn color(&self) -> u32 {
+        STAR_CLASSES[self.class].0
+    }
+}
+
+// A seeded set of star systems around the one of the scene file, which is
+// always the first
+pub struct Galaxy {
+    pub systems: Vec<StarSystem>,
+    pub current: usize,
+    pub radius: f32, // Años luz
+}
+
+impl Galaxy {
+    pub fn new(seed: u64, count: usize, radius: f32, home: &str) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut systems = vec![StarSystem {
+            name: home.to_string(),
+            position: Vec2::zeros(),
+            seed,
+            class: 3,
+            planets: 0,
+        }];
+        for _ in 1..count.max(1) {
+            // Spread them out, the last try is kept even if it is crowded
+            let mut position = Vec2::zeros();
+            for _ in 0..PLACEMENT_TRIES {
+                let angle = rng.gen_range(0.0..2.0 * PI);
+                let distance = rng.gen_range(0.0f32..1.0).sqrt() * radius;
+                position = Vec2::new(angle.cos(), angle.sin()) * distance;
+                if systems.iter().all(|system| (system.position - position).magnitude() > MIN_SEPARATION * radius) {
+                    break;
+                }
+            }
+            let name = loop {
+                let name = star_name(&mut rng);
+                if systems.iter().all(|system| system.name != name) {
+                    break name;
+                }
+            };
+            let mut pick = rng.gen_range(0.0..1.0);
+            let class = STAR_CLASSES.iter().position(|(_, _, _, weight)| {
+                pick -= weight;
+                pick < 0.0
+            }).unwrap_or(STAR_CLASSES.len() - 1);
+            systems.push(StarSystem {
+                name,
+                position,
+                seed: rng.gen(),
+                class,
+                planets: rng.gen_range(3..=7),
+            });
+        }
+        Galaxy { systems, current: 0, radius }
+    }
+
+    pub fn at_home(&self) -> bool {
+        self.current == 0
+    }
+
+    pub fn distance(&self, from: usize, to: usize) -> f32 {
+        (self.systems[to].position - self.systems[from].position).magnitude()
+    }
+
+    // Starts building the scene of `target` on a worker: the scene file for
+    // home, a generated system from `template` otherwise
+    pub fn jump(&self, target: usize, template: &SceneConfig, home_path: &str, materials: &MaterialLibrary, max_octaves: i32) -> Jump {
+        let system = self.systems[target].clone();
+        let template = template.clone();
+        let home_path = home_path.to_string();
+        let mut materials = materials.clone();
+        let (sender, receiver) = channel();
+        threads::spawn(move || {
+            let scene = if target == 0 {
+                SceneConfig::load(&home_path)
+            } else {
+                Ok(generate_scene(&system, &template))
+            };
+            let arrival = scene.and_then(|scene| {
+                let planets = scene.build_planets(&mut materials, max_octaves)?;
+                Ok((scene, planets))
+            });
+            // The receiver only goes away if the app is closed mid-jump
+            let _ = sender.send(arrival);
+        });
+        Jump { target, receiver }
+    }
+}
+
+// A jump under way, the old system keeps running until the new one is ready
+pub struct Jump {
+    pub target: usize,
+    receiver: Receiver<Result<(SceneConfig, Vec<Planet>), String>>,
+}
+
+impl Jump {
+    pub fn poll(&self) -> Option<Result<(SceneConfig, Vec<Planet>), String>> {
+        match self.receiver.try_recv() {
+            Ok(arrival) => Some(arrival),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err("the worker building the system stopped".to_string())),
+        }
+    }
+}
+
+fn star_name(rng: &mut StdRng) -> String {
+    let syllables = rng.gen_range(2..=3);
+    let name: String = (0..syllables).map(|_| SYLLABLES[rng.gen_range(0..SYLLABLES.len())]).collect();
+    let mut chars = name.chars();
+    chars.next().map_or(String::new(), |first| first.to_uppercase().chain(chars).collect())
+}
+
+// Orbit speed at `semi_major_axis` that keeps Kepler's third law with the
+// scene's Earth
+fn kepler_speed(semi_major_axis: f32) -> f32 {
+    REFERENCE_ORBIT.1 * (REFERENCE_ORBIT.0 / semi_major_axis).powf(1.5)
+}
+
+fn body(name: String, radius: f32, semi_major_axis: f32, orbit_speed: f32, color: u32, shader: &str) -> PlanetConfig {
+    PlanetConfig {
+        name,
+        radius,
+        semi_major_axis,
+        eccentricity: 0.0,
+        inclination: 0.0,
+        argument_of_periapsis: 0.0,
+        longitude_of_node: 0.0,
+        orbit_speed,
+        rotation_speed: 0.0,
+        axial_tilt: None,
+        color,
+        shader: ShaderRef::Name(shader.to_string()),
+        shading: None,
+        cull_back_faces: None,
+        mass: None,
+        temperature: None,
+        resonance: None,
+        rings: None,
+        atmosphere: None,
+        clouds: None,
+        tail: None,
+        magnetosphere: None,
+        subdivisions: None,
+        parent: None,
+        noise: None,
+        relief: None,
+        texture: None,
+        normal_map: None,
+        specular: None,
+        pbr: None,
+        filter: None,
+        wrap: None,
+    }
+}
+
+fn random_color(rng: &mut StdRng) -> u32 {
+    let channel = |rng: &mut StdRng| rng.gen_range(0x40u32..=0xF0);
+    (channel(rng) << 16) | (channel(rng) << 8) | channel(rng)
+}
+
+// Halfway to white, for the air over a planet of that color
+fn lighten(color: u32) -> u32 {
+    let channel = |shift: u32| ((((color >> shift) & 0xFF) + 0xFF) / 2) << shift;
+    channel(16) | channel(8) | channel(0)
+}
+
+// The system of a star: the star, rocky planets close in and gas giants
+// past GIANT_ORBIT with their moons and rings, maybe an asteroid belt in a
+// wide gap, a comet and a nebula. Everything that is not a body, the ships
+// and the settings, comes from `template`; there is no station out here.
+pub fn generate_scene(system: &StarSystem, template: &SceneConfig) -> SceneConfig {
+    let mut rng = StdRng::seed_from_u64(system.seed);
+    let (star_color, star_radius, star_mass, _) = STAR_CLASSES[system.class];
+    let mut scene = template.clone();
+    scene.station = None;
+    scene.trade.contracts.clear();
+    scene.nebulae.clear();
+    scene.asteroid_belt = None;
+
+    let mut star = body(system.name.clone(), star_radius, 0.0, 0.0, star_color, SUN_SHADER);
+    star.mass = Some(star_mass);
+    let mut planets = vec![star];
+
+    let mut semi_major_axis = FIRST_ORBIT + rng.gen_range(0.0..1.5);
+    let mut widest_gap = (0.0, 0.0);
+    for index in 0..system.planets {
+        let giant = semi_major_axis > GIANT_ORBIT;
+        let name = format!("{} {}", system.name, (b'b' + index as u8) as char);
+        let (radius, shader) = if giant {
+            (rng.gen_range(2.5..5.0), GIANT_SHADERS[rng.gen_range(0..GIANT_SHADERS.len())])
+        } else {
+            (rng.gen_range(0.5..1.3), ROCKY_SHADERS[rng.gen_range(0..ROCKY_SHADERS.len())])
+        };
+        let mut planet = body(name.clone(), radius, semi_major_axis, kepler_speed(semi_major_axis), random_color(&mut rng), shader);
+        planet.eccentricity = rng.gen_range(0.0..0.1);
+        planet.inclination = rng.gen_range(0.0..4.0);
+        planet.argument_of_periapsis = rng.gen_range(0.0..360.0);
+        planet.longitude_of_node = rng.gen_range(0.0..360.0);
+        planet.rotation_speed = rng.gen_range(0.01..0.1);
+        planet.noise = Some(NoiseConfig {
+            seed: rng.gen(),
+            noise_type: "open-simplex2".to_string(),
+            frequency: None,
+            fractal: None,
+            octaves: None,
+            lacunarity: None,
+            gain: None,
+        });
+        if giant {
+            planet.mass = Some(rng.gen_range(10.0..320.0));
+            planet.subdivisions = Some(16);
+            planet.atmosphere = Some(AtmosphereConfig {
+                thickness: 0.04,
+                color: lighten(planet.color),
+                density: 0.6,
+                falloff: 1.8,
+                ..AtmosphereConfig::default()
+            });
+            if rng.gen_bool(0.4) {
+                let inner_radius = rng.gen_range(1.2..1.6);
+                planet.rings = Some(RingsConfig {
+                    inner_radius,
+                    outer_radius: inner_radius + rng.gen_range(0.4..1.1),
+                    opacity: rng.gen_range(0.2..0.6),
+                    tilt: rng.gen_range(0.0..30.0),
+                });
+            }
+            if rng.gen_bool(0.5) {
+                planet.magnetosphere = Some(MagnetosphereConfig { tilt: rng.gen_range(0.0..15.0), ..MagnetosphereConfig::default() });
+            }
+        } else {
+            planet.mass = Some(rng.gen_range(0.05..2.0));
+            if rng.gen_bool(0.4) {
+                planet.atmosphere = Some(AtmosphereConfig { color: lighten(planet.color), ..AtmosphereConfig::default() });
+                planet.clouds = Some(CloudsConfig::default());
+            }
+        }
+
+        // Moons just outside the planet, further out around the giants
+        let moons = if giant { rng.gen_range(0..=3) } else { rng.gen_range(0..=1) };
+        let mut moon_orbit = radius * 0.5 + rng.gen_range(0.6..1.0);
+        planets.push(planet);
+        for moon in 0..moons {
+            let numeral = ["I", "II", "III"][moon];
+            let mut satellite = body(format!("{} {}", name, numeral), rng.gen_range(0.15..0.4), moon_orbit, rng.gen_range(0.03..0.15), random_color(&mut rng), MOON_SHADER);
+            satellite.parent = Some(name.clone());
+            satellite.inclination = rng.gen_range(0.0..6.0);
+            satellite.rotation_speed = satellite.orbit_speed;
+            planets.push(satellite);
+            moon_orbit += rng.gen_range(0.7..1.2);
+        }
+
+        let gap = rng.gen_range(1.5..5.0) + if giant { radius * 2.0 } else { 0.0 };
+        if gap > widest_gap.1 {
+            widest_gap = (semi_major_axis + gap * 0.5, gap);
+        }
+        semi_major_axis += gap;
+    }
+
+    if rng.gen_bool(0.5) && widest_gap.1 > 3.0 {
+        let (middle, width) = widest_gap;
+        scene.asteroid_belt = Some(AsteroidBeltConfig {
+            count: rng.gen_range(1500..3000),
+            inner_radius: middle - width * 0.2,
+            outer_radius: middle + width * 0.2,
+            thickness: 0.6,
+            min_size: 0.02,
+            max_size: 0.09,
+            orbit_speed: kepler_speed(middle),
+            seed: rng.gen(),
+        });
+    }
+    if rng.gen_bool(0.3) {
+        let mut comet = body(format!("{} comet", system.name), 0.25, semi_major_axis * 0.8, 0.0, 0x9A9080, MOON_SHADER);
+        comet.orbit_speed = kepler_speed(comet.semi_major_axis);
+        comet.eccentricity = rng.gen_range(0.5..0.8);
+        comet.inclination = rng.gen_range(0.0..180.0);
+        comet.argument_of_periapsis = rng.gen_range(0.0..360.0);
+        comet.rotation_speed = 0.02;
+        comet.tail = Some(TailConfig::default());
+        planets.push(comet);
+    }
+    if rng.gen_bool(0.3) {
+        let angle = rng.gen_range(0.0..2.0 * PI);
+        let distance = rng.gen_range(10.0..semi_major_axis.max(11.0));
+        scene.nebulae.push(NebulaConfig {
+            center: [angle.cos() * distance, rng.gen_range(-2.0..3.0), angle.sin() * distance],
+            radii: [rng.gen_range(4.0..7.0), rng.gen_range(2.0..4.0), rng.gen_range(3.0..6.0)],
+            core_color: random_color(&mut rng),
+            edge_color: random_color(&mut rng),
+            density: rng.gen_range(0.3..0.7),
+            noise_scale: 0.3,
+            seed: rng.gen(),
+            steps: None,
+        });
+    }
+    scene.planets = planets;
+    scene
+}
+
+// Full screen map of the galaxy, modal like the session menu: the arrows
+// move the selection to the nearest star that way, Enter jumps there and
+// Backspace or the chord that opened it closes it
+pub struct GalaxyMap {
+    pub open: bool,
+    selected: usize,
+}
+
+impl GalaxyMap {
+    pub fn new() -> Self {
+        GalaxyMap { open: false, selected: 0 }
+    }
+
+    pub fn toggle(&mut self, galaxy: &Galaxy) {
+        self.open = !self.open;
+        self.selected = galaxy.current;
+    }
+
+    // Nearest star in a direction, stars off to the side count as further
+    fn step(&self, galaxy: &Galaxy, direction: Vec2) -> usize {
+        let from = galaxy.systems[self.selected].position;
+        galaxy.systems.iter()
+            .enumerate()
+            .filter_map(|(index, system)| {
+                let offset = system.position - from;
+                let distance = offset.magnitude();
+                let facing = offset.dot(&direction) / distance.max(1e-6);
+                (index != self.selected && facing > 0.3).then_some((index, distance * (2.0 - facing)))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or(self.selected, |(index, _)| index)
+    }
+
+    // The system to jump to when Enter is pressed on another one
+    pub fn update(&mut self, input: &Input, galaxy: &Galaxy) -> Option<usize> {
+        if !self.open {
+            return None;
+        }
+        if input.is_pressed(Key::Backspace) {
+            self.open = false;
+            return None;
+        }
+        // The map has y up and the screen y down
+        for (key, direction) in [(Key::Left, Vec2::new(-1.0, 0.0)), (Key::Right, Vec2::new(1.0, 0.0)), (Key::Up, Vec2::new(0.0, 1.0)), (Key::Down, Vec2::new(0.0, -1.0))] {
+            if input.is_pressed(key) {
+                self.selected = self.step(galaxy, direction);
+            }
+        }
+        if input.is_pressed(Key::Enter) && self.selected != galaxy.current {
+            self.open = false;
+            return Some(self.selected);
+        }
+        None
+    }
+
+    // Every star in its color, a ring around the current one, brackets and a
+    // dashed route to the selected one, and its distance and planets below
+    pub fn render(&self, framebuffer: &mut Framebuffer, galaxy: &Galaxy) {
+        if !self.open {
+            return;
+        }
+        let side = (framebuffer.width.min(framebuffer.height) as f32 * MAP_FILL) as usize;
+        let width = side + 2 * PANEL_PADDING;
+        let height = side + 2 * PANEL_PADDING + 3 * ROW_HEIGHT;
+        let left = framebuffer.width.saturating_sub(width) / 2;
+        let top = framebuffer.height.saturating_sub(height) / 2;
+        for y in top..top + height {
+            for x in left..left + width {
+                framebuffer.darken(x, y, PANEL_DIM);
+            }
+        }
+
+        let current = &galaxy.systems[galaxy.current];
+        draw_text(framebuffer, left + PANEL_PADDING, top + PANEL_PADDING, &format!("GALAXY  {}", current.name), TITLE_COLOR, TEXT_SCALE);
+        let map_top = top + PANEL_PADDING + ROW_HEIGHT;
+        let center = Vec2::new((left + width / 2) as f32, (map_top + side / 2) as f32);
+        let scale = side as f32 * 0.5 / galaxy.radius.max(1e-3);
+        let to_screen = |position: &Vec2| Vec2::new(center.x + position.x * scale, center.y - position.y * scale);
+
+        let (from, to) = (to_screen(&current.position), to_screen(&galaxy.systems[self.selected].position));
+        let dashes = ((to - from).magnitude() as usize / ROUTE_DASH).max(1);
+        framebuffer.set_current_color(ROUTE_COLOR);
+        for dash in (0..dashes).step_by(2) {
+            for step in 0..ROUTE_DASH {
+                let point = from + (to - from) * ((dash * ROUTE_DASH + step) as f32 / (dashes * ROUTE_DASH) as f32);
+                framebuffer.point_overlay(point.x as usize, point.y as usize);
+            }
+        }
+
+        for system in &galaxy.systems {
+            let point = to_screen(&system.position);
+            framebuffer.set_current_color(system.color());
+            for dy in 0..2 {
+                for dx in 0..2 {
+                    framebuffer.point_overlay(point.x as usize + dx, point.y as usize + dy);
+                }
+            }
+        }
+
+        let ring = to_screen(&current.position);
+        framebuffer.set_current_color(TITLE_COLOR);
+        for step in 0..32 {
+            let (sin, cos) = (step as f32 / 32.0 * 2.0 * PI).sin_cos();
+            framebuffer.point_overlay((ring.x + cos * CURRENT_RING) as usize, (ring.y + sin * CURRENT_RING) as usize);
+        }
+
+        let selected = &galaxy.systems[self.selected];
+        framebuffer.set_current_color(SELECTED_COLOR);
+        draw_brackets(framebuffer, to.x as i32, to.y as i32, 6, 2);
+        draw_text(framebuffer, to.x as usize + 10, to.y as usize + 6, &selected.name, SELECTED_COLOR, TEXT_SCALE);
+
+        let info = if self.selected == galaxy.current {
+            format!("{}  HERE", selected.name)
+        } else if self.selected == 0 {
+            format!("{}  {:.1} LY  HOME", selected.name, galaxy.distance(galaxy.current, self.selected))
+        } else {
+            format!("{}  {:.1} LY  {} PLANETS", selected.name, galaxy.distance(galaxy.current, self.selected), selected.planets)
+        };
+        let bottom = map_top + side + ROW_HEIGHT / 2;
+        draw_text(framebuffer, left + PANEL_PADDING, bottom, &info, SELECTED_COLOR, TEXT_SCALE);
+        let hint = "ARROWS SELECT  ENTER JUMP  BACKSPACE CLOSE";
+        if (hint.len() * (GLYPH_WIDTH + 1) * TEXT_SCALE) < side {
+            draw_text(framebuffer, left + PANEL_PADDING, bottom + ROW_HEIGHT, hint, HINT_COLOR, TEXT_SCALE);
+        }
+    }
+}
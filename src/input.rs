@@ -0,0 +1,193 @@
+// input.rs
+
+use minifb::{InputCallback, Key, Window};
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+// Names accepted in keybinding files, also used to print chords back
+const KEY_NAMES: &[(&str, Key)] = &[
+    ("A", Key::A), ("B", Key::B), ("C", Key::C), ("D", Key::D), ("E", Key::E), ("F", Key::F),
+    ("G", Key::G), ("H", Key::H), ("I", Key::I), ("J", Key::J), ("K", Key::K), ("L", Key::L),
+    ("M", Key::M), ("N", Key::N), ("O", Key::O), ("P", Key::P), ("Q", Key::Q), ("R", Key::R),
+    ("S", Key::S), ("T", Key::T), ("U", Key::U), ("V", Key::V), ("W", Key::W), ("X", Key::X),
+    ("Y", Key::Y), ("Z", Key::Z),
+    ("0", Key::Key0), ("1", Key::Key1), ("2", Key::Key2), ("3", Key::Key3), ("4", Key::Key4),
+    ("5", Key::Key5), ("6", Key::Key6), ("7", Key::Key7), ("8", Key::Key8), ("9", Key::Key9),
+    ("F1", Key::F1), ("F2", Key::F2), ("F3", Key::F3), ("F4", Key::F4), ("F5", Key::F5), ("F6", Key::F6),
+    ("F7", Key::F7), ("F8", Key::F8), ("F9", Key::F9), ("F10", Key::F10), ("F11", Key::F11), ("F12", Key::F12),
+    ("Left", Key::Left), ("Right", Key::Right), ("Up", Key::Up), ("Down", Key::Down),
+    ("Tab", Key::Tab), ("Space", Key::Space), ("Enter", Key::Enter), ("Backspace", Key::Backspace),
+    ("=", Key::Equal), ("-", Key::Minus), ("[", Key::LeftBracket), ("]", Key::RightBracket),
+    (",", Key::Comma), (".", Key::Period), ("/", Key::Slash),
+];
+
+// A key plus the exact set of modifiers that must be held with it, written
+// like "Ctrl+Shift+S"
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Chord {
+    pub key: Key,
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+}
+
+impl Chord {
+    pub fn new(key: Key) -> Self {
+        Chord { key, shift: false, ctrl: false, alt: false }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let (key_name, modifiers) = parts.split_last().ok_or_else(|| format!("empty chord '{}'", text))?;
+
+        let mut chord = Chord::new(key_from_name(key_name).ok_or_else(|| format!("unknown key '{}' in '{}'", key_name, text))?);
+        for modifier in modifiers {
+            let flag = match modifier.to_ascii_lowercase().as_str() {
+                "shift" => &mut chord.shift,
+                "ctrl" | "control" => &mut chord.ctrl,
+                "alt" => &mut chord.alt,
+                _ => return Err(format!("unknown modifier '{}' in '{}'", modifier, text)),
+            };
+            if *flag {
+                return Err(format!("repeated modifier '{}' in '{}'", modifier, text));
+            }
+            *flag = true;
+        }
+        Ok(chord)
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.alt {
+            write!(f, "Alt+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        match KEY_NAMES.iter().find(|(_, key)| *key == self.key) {
+            Some((name, _)) => write!(f, "{}", name),
+            None => write!(f, "{:?}", self.key),
+        }
+    }
+}
+
+fn key_from_name(name: &str) -> Option<Key> {
+    KEY_NAMES.iter()
+        .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name))
+        .map(|(_, key)| *key)
+}
+
+#[derive(Clone, Copy)]
+struct KeyEvent {
+    key: Key,
+    down: bool,
+}
+
+// Receives every key transition as the window pumps its OS events, so a tap
+// that goes down and up between two frames is still queued
+struct EventQueue {
+    events: Arc<Mutex<VecDeque<KeyEvent>>>,
+}
+
+impl InputCallback for EventQueue {
+    fn add_char(&mut self, _uni_char: u32) {}
+
+    fn set_key_state(&mut self, key: Key, down: bool) {
+        if let Ok(mut events) = self.events.lock() {
+            events.push_back(KeyEvent { key, down });
+        }
+    }
+}
+
+// Keyboard state for one frame, rebuilt from the queued events by `update`
+pub struct Input {
+    events: Arc<Mutex<VecDeque<KeyEvent>>>,
+    held: HashSet<Key>,
+    just_pressed: HashSet<Key>,
+    released: HashSet<Key>,
+}
+
+impl Input {
+    pub fn attach(window: &mut Window) -> Self {
+        let input = Input::detached();
+        window.set_input_callback(Box::new(EventQueue { events: Arc::clone(&input.events) }));
+        input
+    }
+
+    // No window to listen to, no key is ever pressed
+    pub fn detached() -> Self {
+        Input {
+            events: Arc::new(Mutex::new(VecDeque::new())),
+            held: HashSet::new(),
+            just_pressed: HashSet::new(),
+            released: HashSet::new(),
+        }
+    }
+
+    // Drain everything queued since the last frame. Key repeats arrive as
+    // extra downs and are ignored while the key is held.
+    pub fn update(&mut self) {
+        self.just_pressed.clear();
+        self.released.clear();
+        let events: Vec<KeyEvent> = match self.events.lock() {
+            Ok(mut events) => events.drain(..).collect(),
+            Err(_) => return,
+        };
+        for event in events {
+            if event.down {
+                if self.held.insert(event.key) {
+                    self.just_pressed.insert(event.key);
+                }
+            } else if self.held.remove(&event.key) {
+                self.released.insert(event.key);
+            }
+        }
+    }
+
+    // Forget this frame's keys, for a menu that took them. A key still held
+    // stays forgotten until it is pressed again.
+    pub fn clear(&mut self) {
+        self.held.clear();
+        self.just_pressed.clear();
+        self.released.clear();
+    }
+
+    // Held now, or tapped and let go during the last frame so it still
+    // counts for one frame of movement
+    pub fn is_down(&self, key: Key) -> bool {
+        self.held.contains(&key) || self.just_pressed.contains(&key)
+    }
+
+    pub fn is_pressed(&self, key: Key) -> bool {
+        self.just_pressed.contains(&key)
+    }
+
+    pub fn is_released(&self, key: Key) -> bool {
+        self.released.contains(&key)
+    }
+
+    pub fn ctrl_down(&self) -> bool {
+        self.is_down(Key::LeftCtrl) || self.is_down(Key::RightCtrl)
+    }
+
+    // Modifiers must match exactly, so Shift+Left doesn't also fire Left
+    fn modifiers_match(&self, chord: &Chord) -> bool {
+        let shift = self.is_down(Key::LeftShift) || self.is_down(Key::RightShift);
+        let ctrl = self.ctrl_down();
+        let alt = self.is_down(Key::LeftAlt) || self.is_down(Key::RightAlt);
+        shift == chord.shift && ctrl == chord.ctrl && alt == chord.alt
+    }
+
+    pub fn chord_down(&self, chord: &Chord) -> bool {
+        self.is_down(chord.key) && self.modifiers_match(chord)
+    }
+
+    pub fn chord_pressed(&self, chord: &Chord) -> bool {
+        self.is_pressed(chord.key) && self.modifiers_match(chord)
+    }
+}
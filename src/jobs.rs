@@ -0,0 +1,70 @@
+// jobs.rs
+
+use rayon::prelude::*;
+
+type Work<'a> = Box<dyn FnOnce() + Send + 'a>;
+
+struct Job<'a> {
+    name: &'static str,
+    after: Vec<&'static str>, // Trabajos que tienen que terminar antes
+    work: Work<'a>,
+}
+
+// One frame's work split into named jobs, each listing the ones it has to
+// wait for. run() goes in stages: every job whose dependencies are done runs
+// at once on rayon's pool, and the next stage starts when they all finish.
+// Jobs borrow what they work on, so only one can hold something mutably:
+// work that takes turns on the same thing goes in one job, and what needs
+// the results of a job that writes goes in a graph run after it.
+#[derive(Default)]
+pub struct JobGraph<'a> {
+    jobs: Vec<Job<'a>>,
+}
+
+impl<'a> JobGraph<'a> {
+    pub fn add(&mut self, name: &'static str, after: &[&'static str], work: impl FnOnce() + Send + 'a) -> &mut Self {
+        self.jobs.push(Job { name, after: after.to_vec(), work: Box::new(work) });
+        self
+    }
+
+    // Fails, running nothing, when a job waits for one that isn't there or
+    // the dependencies go in a circle
+    pub fn run(self) -> Result<(), String> {
+        let stages = self.stages()?;
+        let mut jobs: Vec<Option<Work<'a>>> = self.jobs.into_iter().map(|job| Some(job.work)).collect();
+        for stage in stages {
+            let work: Vec<Work<'a>> = stage.iter().filter_map(|&index| jobs[index].take()).collect();
+            work.into_par_iter().for_each(|work| work());
+        }
+        Ok(())
+    }
+
+    // Indices of the jobs in each stage, in the order they were added
+    fn stages(&self) -> Result<Vec<Vec<usize>>, String> {
+        for job in &self.jobs {
+            if let Some(missing) = job.after.iter().find(|name| !self.jobs.iter().any(|other| other.name == **name)) {
+                return Err(format!("job '{}' waits for '{}', which doesn't exist", job.name, missing));
+            }
+        }
+
+        let mut done = vec![false; self.jobs.len()];
+        let mut stages = Vec::new();
+        while done.iter().any(|finished| !finished) {
+            let ready: Vec<usize> = (0..self.jobs.len())
+                .filter(|&index| !done[index])
+                .filter(|&index| self.jobs[index].after.iter().all(|name| {
+                    self.jobs.iter().zip(&done).all(|(other, finished)| other.name != *name || *finished)
+                }))
+                .collect();
+            if ready.is_empty() {
+                let waiting: Vec<&str> = (0..self.jobs.len()).filter(|&index| !done[index]).map(|index| self.jobs[index].name).collect();
+                return Err(format!("jobs wait for each other: {}", waiting.join(", ")));
+            }
+            for &index in &ready {
+                done[index] = true;
+            }
+            stages.push(ready);
+        }
+        Ok(stages)
+    }
+}
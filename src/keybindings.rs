@@ -0,0 +1,245 @@
+// keybindings.rs
+
+use std::collections::HashMap;
+use std::fs;
+use crate::input::{Chord, Input};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    OrbitLeft,
+    OrbitRight,
+    OrbitUp,
+    OrbitDown,
+    FastOrbitLeft,
+    FastOrbitRight,
+    FastOrbitUp,
+    FastOrbitDown,
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    ZoomIn,
+    ZoomOut,
+    ThrustForward,
+    ThrustBack,
+    PitchUp,
+    PitchDown,
+    YawLeft,
+    YawRight,
+    RollLeft,
+    RollRight,
+    TargetAsteroid,
+    MiningBeam,
+    BirdEyeView,
+    ToggleConsole,
+    ReducedMotion,
+    PhotosensitiveMode,
+    NextShadingMode,
+    TextureSpaceShading,
+    BackFaceCulling,
+    SpeedUp,
+    SlowDown,
+    TimePreset(u8), // Índice en clock::TIME_PRESETS
+    NextShip,
+    ToggleAsteroids,
+    DiffractionSpikes,
+    ToggleRadar,
+    RadarRangeDown,
+    RadarRangeUp,
+    ToggleTimeline,
+    ToggleLabels,
+    ToggleGravity,
+    ToggleSolarWind,
+    ToggleHud,
+    NextQuality,
+    NextViewMode,
+    ToggleBloom,
+    NextBackground,
+    NextAntialiasing,
+    JumpToEvent,
+    Capture,
+    DumpFrame,
+    Screenshot,
+    SaveScene,
+    SessionMenu,
+    GalaxyMap,
+    ShaderTuning,
+    ToggleExport,
+    ToggleDemo,
+    SaveBookmark(u8),
+    RecallBookmark(u8),
+    FocusPlanet(u8),
+    ReleaseFocus,
+    Autopilot,
+}
+
+pub const BOOKMARK_COUNT: u8 = 9;
+
+// Name used in the keybinding file and the default chord of every action
+const DEFAULT_BINDINGS: &[(Action, &str, &str)] = &[
+    (Action::OrbitLeft, "orbit_left", "Left"),
+    (Action::OrbitRight, "orbit_right", "Right"),
+    (Action::OrbitUp, "orbit_up", "W"),
+    (Action::OrbitDown, "orbit_down", "S"),
+    (Action::FastOrbitLeft, "fast_orbit_left", "Shift+Left"),
+    (Action::FastOrbitRight, "fast_orbit_right", "Shift+Right"),
+    (Action::FastOrbitUp, "fast_orbit_up", "Shift+Up"),
+    (Action::FastOrbitDown, "fast_orbit_down", "Shift+Down"),
+    (Action::PanLeft, "pan_left", "A"),
+    (Action::PanRight, "pan_right", "D"),
+    (Action::PanUp, "pan_up", "Q"),
+    (Action::PanDown, "pan_down", "E"),
+    (Action::ZoomIn, "zoom_in", "Up"),
+    (Action::ZoomOut, "zoom_out", "Down"),
+    (Action::ThrustForward, "thrust_forward", "I"),
+    (Action::ThrustBack, "thrust_back", "K"),
+    (Action::PitchUp, "pitch_up", "H"),
+    (Action::PitchDown, "pitch_down", "Y"),
+    (Action::YawLeft, "yaw_left", "U"),
+    (Action::YawRight, "yaw_right", "O"),
+    (Action::RollLeft, "roll_left", "J"),
+    (Action::RollRight, "roll_right", "L"),
+    (Action::TargetAsteroid, "target_asteroid", "T"),
+    (Action::MiningBeam, "mining_beam", "M"),
+    (Action::BirdEyeView, "bird_eye_view", "B"),
+    (Action::ToggleConsole, "toggle_console", "Tab"),
+    (Action::ReducedMotion, "reduced_motion", "F1"),
+    (Action::PhotosensitiveMode, "photosensitive_mode", "F2"),
+    (Action::NextShadingMode, "next_shading_mode", "F3"),
+    (Action::TextureSpaceShading, "texture_space_shading", "F4"),
+    (Action::BackFaceCulling, "back_face_culling", "F5"),
+    (Action::DiffractionSpikes, "diffraction_spikes", "F6"),
+    (Action::ToggleAsteroids, "toggle_asteroids", "F7"),
+    (Action::NextShip, "next_ship", "F8"),
+    (Action::ToggleRadar, "toggle_radar", "F9"),
+    (Action::ToggleTimeline, "toggle_timeline", "F10"),
+    (Action::ToggleLabels, "toggle_labels", "Ctrl+L"),
+    (Action::ToggleGravity, "toggle_gravity", "Ctrl+G"),
+    (Action::ToggleSolarWind, "toggle_solar_wind", "Ctrl+W"),
+    (Action::ToggleHud, "toggle_hud", "Ctrl+H"),
+    (Action::NextQuality, "next_quality", "Ctrl+Q"),
+    (Action::NextViewMode, "next_view_mode", "Ctrl+T"),
+    (Action::ToggleBloom, "toggle_bloom", "F11"),
+    (Action::NextBackground, "next_background", "Ctrl+B"),
+    (Action::NextAntialiasing, "next_antialiasing", "Ctrl+A"),
+    (Action::Capture, "capture", "F12"),
+    (Action::DumpFrame, "dump_frame", "Shift+F12"),
+    (Action::Screenshot, "screenshot", "Ctrl+F12"),
+    (Action::SpeedUp, "speed_up", "="),
+    (Action::SlowDown, "slow_down", "-"),
+    (Action::TimePreset(0), "time_real_time", "Shift+1"),
+    (Action::TimePreset(1), "time_hour_per_second", "Shift+2"),
+    (Action::TimePreset(2), "time_day_per_second", "Shift+3"),
+    (Action::TimePreset(3), "time_year_per_minute", "Shift+4"),
+    (Action::RadarRangeDown, "radar_range_down", "["),
+    (Action::RadarRangeUp, "radar_range_up", "]"),
+    (Action::JumpToEvent, "jump_to_event", "N"),
+    (Action::SaveScene, "save_scene", "Ctrl+S"),
+    (Action::SessionMenu, "session_menu", "Ctrl+O"),
+    (Action::GalaxyMap, "galaxy_map", "G"),
+    (Action::ShaderTuning, "shader_tuning", "Ctrl+U"),
+    (Action::ToggleExport, "toggle_export", "Ctrl+E"),
+    (Action::ToggleDemo, "toggle_demo", "Ctrl+D"),
+    (Action::SaveBookmark(1), "save_bookmark_1", "Ctrl+1"),
+    (Action::SaveBookmark(2), "save_bookmark_2", "Ctrl+2"),
+    (Action::SaveBookmark(3), "save_bookmark_3", "Ctrl+3"),
+    (Action::SaveBookmark(4), "save_bookmark_4", "Ctrl+4"),
+    (Action::SaveBookmark(5), "save_bookmark_5", "Ctrl+5"),
+    (Action::SaveBookmark(6), "save_bookmark_6", "Ctrl+6"),
+    (Action::SaveBookmark(7), "save_bookmark_7", "Ctrl+7"),
+    (Action::SaveBookmark(8), "save_bookmark_8", "Ctrl+8"),
+    (Action::SaveBookmark(9), "save_bookmark_9", "Ctrl+9"),
+    (Action::RecallBookmark(1), "recall_bookmark_1", "Alt+1"),
+    (Action::RecallBookmark(2), "recall_bookmark_2", "Alt+2"),
+    (Action::RecallBookmark(3), "recall_bookmark_3", "Alt+3"),
+    (Action::RecallBookmark(4), "recall_bookmark_4", "Alt+4"),
+    (Action::RecallBookmark(5), "recall_bookmark_5", "Alt+5"),
+    (Action::RecallBookmark(6), "recall_bookmark_6", "Alt+6"),
+    (Action::RecallBookmark(7), "recall_bookmark_7", "Alt+7"),
+    (Action::RecallBookmark(8), "recall_bookmark_8", "Alt+8"),
+    (Action::RecallBookmark(9), "recall_bookmark_9", "Alt+9"),
+    (Action::FocusPlanet(1), "focus_planet_1", "1"),
+    (Action::FocusPlanet(2), "focus_planet_2", "2"),
+    (Action::FocusPlanet(3), "focus_planet_3", "3"),
+    (Action::FocusPlanet(4), "focus_planet_4", "4"),
+    (Action::FocusPlanet(5), "focus_planet_5", "5"),
+    (Action::FocusPlanet(6), "focus_planet_6", "6"),
+    (Action::FocusPlanet(7), "focus_planet_7", "7"),
+    (Action::FocusPlanet(8), "focus_planet_8", "8"),
+    (Action::FocusPlanet(9), "focus_planet_9", "9"),
+    (Action::ReleaseFocus, "release_focus", "Backspace"),
+    (Action::Autopilot, "autopilot", "Ctrl+P"),
+];
+
+// Chord bound to each action. Actions without one never fire.
+pub struct KeyBindings {
+    chords: HashMap<Action, Chord>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let chords = DEFAULT_BINDINGS.iter()
+            .map(|(action, _, chord)| (*action, Chord::parse(chord).expect("invalid default binding")))
+            .collect();
+        KeyBindings { chords }
+    }
+}
+
+impl KeyBindings {
+    // TOML table of `action = "Chord"`, actions left out keep their default
+    // and "none" unbinds one. Two actions on the same chord is an error.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let table: HashMap<String, String> = toml::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+
+        let mut bindings = KeyBindings::default();
+        for (name, chord) in &table {
+            let action = DEFAULT_BINDINGS.iter()
+                .find(|(_, action_name, _)| action_name == name)
+                .map(|(action, _, _)| *action)
+                .ok_or_else(|| format!("{}: unknown action '{}'", path, name))?;
+            if chord.eq_ignore_ascii_case("none") {
+                bindings.chords.remove(&action);
+            } else {
+                let chord = Chord::parse(chord).map_err(|e| format!("{}: {}: {}", path, name, e))?;
+                bindings.chords.insert(action, chord);
+            }
+        }
+
+        let conflicts = bindings.conflicts();
+        if !conflicts.is_empty() {
+            return Err(format!("{}: {}", path, conflicts.join("; ")));
+        }
+        Ok(bindings)
+    }
+
+    // One message per chord that more than one action uses
+    fn conflicts(&self) -> Vec<String> {
+        let mut by_chord: Vec<(Chord, Vec<&str>)> = Vec::new();
+        for (action, name, _) in DEFAULT_BINDINGS {
+            let Some(chord) = self.chords.get(action) else {
+                continue;
+            };
+            match by_chord.iter_mut().find(|(other, _)| other == chord) {
+                Some((_, names)) => names.push(name),
+                None => by_chord.push((*chord, vec![name])),
+            }
+        }
+        by_chord.into_iter()
+            .filter(|(_, names)| names.len() > 1)
+            .map(|(chord, names)| format!("{} is bound to {}", chord, names.join(", ")))
+            .collect()
+    }
+
+    pub fn down(&self, input: &Input, action: Action) -> bool {
+        self.chords.get(&action).is_some_and(|chord| input.chord_down(chord))
+    }
+
+    pub fn pressed(&self, input: &Input, action: Action) -> bool {
+        self.chords.get(&action).is_some_and(|chord| input.chord_pressed(chord))
+    }
+
+    pub fn released(&self, input: &Input, action: Action) -> bool {
+        self.chords.get(&action).is_some_and(|chord| input.is_released(chord.key))
+    }
+}
@@ -0,0 +1,105 @@
+// labels.rs
+
+use nalgebra_glm::{Vec3, Vec4};
+use std::f32::consts::{FRAC_1_SQRT_2, PI};
+use crate::color::Color;
+use crate::font::{draw_text, GLYPH_HEIGHT};
+use crate::framebuffer::Framebuffer;
+use crate::Uniforms;
+
+const SAMPLE_RING: usize = 8;        // Puntos del disco además del centro
+const SAMPLE_SPREAD: f32 = 0.6;      // Radio del anillo de muestras, en radios del disco
+const DEPTH_TOLERANCE: f32 = 1e-5;
+const HIDDEN_ALPHA: f32 = 0.3;       // Brillo de la etiqueta de un cuerpo tapado
+const LABEL_COLOR: u32 = 0xCCDDFF;
+const MIN_RING: f32 = 6.0;           // Pixeles de pantalla
+const MAX_RING: f32 = 160.0;         // Más grande ya no marca nada, el cuerpo llena la vista
+const DASH_STEPS: usize = 3;
+
+// A sphere as the 3D pass drew it: its center and radius in pixels of that
+// framebuffer, and the share of points on its disc that nothing closer
+// covers in the depth buffer
+pub struct Sighting {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    pub visible: f32, // 0 tapado del todo, 1 a la vista
+}
+
+// None when the center is behind the camera or off the framebuffer
+pub fn sight(framebuffer: &Framebuffer, uniforms: &Uniforms, center: &Vec3, radius: f32) -> Option<Sighting> {
+    let view = uniforms.view_matrix * Vec4::new(center.x, center.y, center.z, 1.0);
+    let distance = -view.z;
+    if distance <= radius {
+        return None;
+    }
+    let clip = uniforms.projection_matrix * view;
+    let ndc = clip / clip.w;
+    let screen = uniforms.viewport_matrix * Vec4::new(ndc.x, ndc.y, ndc.z, 1.0);
+    let (x, y) = (screen.x, screen.y);
+    let (width, height) = (framebuffer.width as f32, framebuffer.height as f32);
+    if x < 0.0 || y < 0.0 || x >= width || y >= height {
+        return None;
+    }
+    let pixel_radius = radius / distance * uniforms.projection_matrix[(1, 1)] * height * 0.5;
+
+    // Anything in the depth buffer in front of the sphere's nearest point
+    // hides that sample, the sphere's own pixels are all behind it
+    let front = uniforms.depth_range.depth_at(distance - radius);
+    let samples = std::iter::once((x, y)).chain((0..SAMPLE_RING).map(|i| {
+        let angle = i as f32 / SAMPLE_RING as f32 * 2.0 * PI;
+        (x + angle.cos() * pixel_radius * SAMPLE_SPREAD, y + angle.sin() * pixel_radius * SAMPLE_SPREAD)
+    }));
+    let (mut seen, mut total) = (0, 0);
+    for (sample_x, sample_y) in samples {
+        if sample_x < 0.0 || sample_y < 0.0 || sample_x >= width || sample_y >= height {
+            continue;
+        }
+        total += 1;
+        let depth = framebuffer.zbuffer[sample_y as usize * framebuffer.width + sample_x as usize];
+        if depth + DEPTH_TOLERANCE >= front {
+            seen += 1;
+        }
+    }
+
+    Some(Sighting { x, y, radius: pixel_radius, visible: seen as f32 / total as f32 })
+}
+
+// HUD color dimmed for something mostly out of sight
+pub fn faded(color: u32, visible: f32) -> u32 {
+    (Color::from_hex(color) * (HIDDEN_ALPHA + (1.0 - HIDDEN_ALPHA) * visible.clamp(0.0, 1.0))).to_hex()
+}
+
+// Ring and name over every body in view, drawn on the display after the
+// scene was resolved into it. A body behind another one gets a dimmed label
+// and a dashed ring, so the overlay never claims it can be seen.
+pub fn render<'a>(display: &mut Framebuffer, scene: &Framebuffer, uniforms: &Uniforms, bodies: impl Iterator<Item = (&'a str, Vec3, f32)>) {
+    let scale = display.width as f32 / scene.width.max(1) as f32;
+    for (name, center, radius) in bodies {
+        let Some(sighting) = sight(scene, uniforms, &center, radius) else {
+            continue;
+        };
+        let (x, y) = (sighting.x * scale, sighting.y * scale);
+        let ring = (sighting.radius * scale).max(MIN_RING) + 2.0;
+        let color = faded(LABEL_COLOR, sighting.visible);
+        let hidden = sighting.visible < 0.5;
+
+        if ring < MAX_RING {
+            display.set_current_color(color);
+            let steps = ((2.0 * PI * ring) as usize).max(16);
+            for step in (0..steps).filter(|step| !hidden || (step / DASH_STEPS).is_multiple_of(2)) {
+                let angle = step as f32 / steps as f32 * 2.0 * PI;
+                let (px, py) = (x + angle.cos() * ring, y + angle.sin() * ring);
+                if px >= 0.0 && py >= 0.0 {
+                    display.point_overlay(px as usize, py as usize);
+                }
+            }
+        }
+
+        let text_x = x + ring.min(MAX_RING) * FRAC_1_SQRT_2 + 2.0;
+        let text_y = y - ring.min(MAX_RING) * FRAC_1_SQRT_2 - GLYPH_HEIGHT as f32 * 2.0;
+        if text_x >= 0.0 && text_y >= 0.0 {
+            draw_text(display, text_x as usize, text_y as usize, name, color, 2);
+        }
+    }
+}
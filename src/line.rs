@@ -0,0 +1,121 @@
+// line.rs
+
+use nalgebra_glm::{Vec3, Vec4};
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::Uniforms;
+
+const MAX_LINE_PIXELS: usize = 4096; // Tope de pasos por segmento
+const MIN_BEAM_HALF_WIDTH: f32 = 0.5; // Pixeles, más fino se dibuja más tenue
+
+// A segment as seen on screen: its ends in pixels and 1/distance at each,
+// which is what stays linear on screen. None when it is all behind the near
+// plane or too long to walk.
+struct ScreenSegment {
+    a: Vec4,
+    b: Vec4,
+    inverse_a: f32,
+    inverse_b: f32,
+    steps: usize,
+}
+
+impl ScreenSegment {
+    fn project(uniforms: &Uniforms, from: &Vec3, to: &Vec3) -> Option<Self> {
+        let to_view = |point: &Vec3| uniforms.view_matrix * Vec4::new(point.x, point.y, point.z, 1.0);
+        let (mut start, mut end) = (to_view(from), to_view(to));
+
+        // Distances in front of the camera, a segment crossing the near plane keeps its visible part
+        let near = uniforms.depth_range.planes().0;
+        let (start_distance, end_distance) = (-start.z, -end.z);
+        if start_distance <= near && end_distance <= near {
+            return None;
+        }
+        if start_distance <= near {
+            start += (end - start) * ((near - start_distance) / (end_distance - start_distance));
+        } else if end_distance <= near {
+            end += (start - end) * ((near - end_distance) / (start_distance - end_distance));
+        }
+
+        let to_screen = |view: &Vec4| {
+            let clip = uniforms.projection_matrix * view;
+            uniforms.viewport_matrix * (clip / clip.w)
+        };
+        let (a, b) = (to_screen(&start), to_screen(&end));
+        let steps = (b.x - a.x).abs().max((b.y - a.y).abs()).ceil() as usize;
+        if steps > MAX_LINE_PIXELS {
+            return None;
+        }
+        Some(ScreenSegment { a, b, inverse_a: 1.0 / -start.z, inverse_b: 1.0 / -end.z, steps })
+    }
+
+    // Pixel position and distance at step `step`, with the fraction walked
+    fn at(&self, step: usize) -> (f32, f32, f32, f32) {
+        let t = if self.steps == 0 { 0.0 } else { step as f32 / self.steps as f32 };
+        let x = self.a.x + (self.b.x - self.a.x) * t;
+        let y = self.a.y + (self.b.y - self.a.y) * t;
+        (x, y, 1.0 / (self.inverse_a + (self.inverse_b - self.inverse_a) * t), t)
+    }
+}
+
+// One pixel wide segment between two world points. Like the particles it is
+// depth-tested against the scene, leaves no depth and adds its light to
+// what is behind, so crossing lines brighten. Cut at the near plane.
+pub fn line(framebuffer: &mut Framebuffer, uniforms: &Uniforms, from: &Vec3, to: &Vec3, color: Color) {
+    let Some(segment) = ScreenSegment::project(uniforms, from, to) else {
+        return;
+    };
+    let hex = color.to_hex();
+    for step in 0..=segment.steps {
+        let (x, y, distance, _) = segment.at(step);
+        if x < 0.0 || y < 0.0 {
+            continue;
+        }
+        framebuffer.point_additive(x as usize, y as usize, uniforms.depth_range.depth_at(distance), hex);
+    }
+}
+
+// Connected segments through `points`
+pub fn polyline(framebuffer: &mut Framebuffer, uniforms: &Uniforms, points: &[Vec3], color: Color) {
+    for pair in points.windows(2) {
+        line(framebuffer, uniforms, &pair[0], &pair[1], color);
+    }
+}
+
+// A line with a width in world units that goes from `from_width` to
+// `to_width`, so it narrows with perspective and along its length. Bright in
+// the middle and fading to the edges, added like `line`. Each step fills the
+// column (or row) across it, so steep and shallow beams cover the same.
+pub fn beam(framebuffer: &mut Framebuffer, uniforms: &Uniforms, from: &Vec3, to: &Vec3, from_width: f32, to_width: f32, color: Color) {
+    let Some(segment) = ScreenSegment::project(uniforms, from, to) else {
+        return;
+    };
+    let (dx, dy) = (segment.b.x - segment.a.x, segment.b.y - segment.a.y);
+    let length = (dx * dx + dy * dy).sqrt().max(1e-6);
+    let steep = dy.abs() > dx.abs();
+    // Pixels across the column for one pixel across the beam
+    let slant = length / dx.abs().max(dy.abs()).max(1e-6);
+    let pixels_per_unit = uniforms.projection_matrix[(1, 1)] * framebuffer.height as f32 * 0.5;
+
+    for step in 0..=segment.steps {
+        let (x, y, distance, t) = segment.at(step);
+        let width = from_width + (to_width - from_width) * t;
+        let half = width * 0.5 / distance * pixels_per_unit;
+        // Thinner than a pixel: the core alone, as bright as the width it covers
+        let coverage = (half / MIN_BEAM_HALF_WIDTH).min(1.0);
+        let half = half.max(MIN_BEAM_HALF_WIDTH);
+        let depth = uniforms.depth_range.depth_at(distance);
+        let reach = (half * slant).ceil() as i32;
+        for offset in -reach..=reach {
+            let across = offset as f32 / (half * slant);
+            let falloff = 1.0 - across * across;
+            if falloff <= 0.0 {
+                continue;
+            }
+            let (px, py) = if steep { (x + offset as f32, y) } else { (x, y + offset as f32) };
+            if px < 0.0 || py < 0.0 {
+                continue;
+            }
+            framebuffer.point_additive(px as usize, py as usize, depth, (color * (falloff * falloff * coverage)).to_hex());
+        }
+    }
+}
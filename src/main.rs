@@ -1,14 +1,14 @@
 // main.rs
 
-use nalgebra_glm::{Vec3, Mat4, look_at, perspective};
+use nalgebra_glm::{Vec2, Vec3, Mat4, look_at, perspective};
 use minifb::{Key, Window, WindowOptions};
 use core::num;
 use std::time::Duration;
 use std::f32::consts::PI;
-use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 use winit::{
     event::{Event, WindowEvent, DeviceEvent, ElementState, MouseButton, MouseScrollDelta, VirtualKeyCode},
-    event_loop::{ControlFlow, EventLoop},
+    event_loop::ControlFlow,
     dpi::PhysicalPosition,
     window::WindowBuilder,
 };
@@ -25,60 +25,179 @@ mod texture;
 mod normal_map;
 mod skybox;
 mod planet;
-
-use framebuffer::Framebuffer;
+mod particles;
+mod comet;
+mod quality;
+mod font;
+mod text_log;
+mod settings;
+mod surface_cache;
+mod assets;
+mod autopilot;
+mod culling;
+mod scene;
+mod recorder;
+mod nebula;
+mod rings;
+mod asteroids;
+mod damage;
+mod crash;
+mod escorts;
+mod radar;
+mod station;
+mod docking;
+mod material;
+mod input;
+mod keybindings;
+mod touchpad;
+mod clock;
+mod events;
+mod postprocess;
+mod exporter;
+mod demo;
+mod frame_capture;
+mod depth_range;
+mod animation;
+mod easing;
+mod focus;
+mod orientation;
+mod background;
+mod flight;
+mod labels;
+mod thermal;
+mod line;
+mod solar_wind;
+mod mining;
+mod trade;
+mod sphere;
+mod session;
+mod galaxy;
+mod probes;
+mod tuning;
+mod motion;
+mod arena;
+mod jobs;
+mod threads;
+mod simplify;
+
+use framebuffer::{Framebuffer, ShadedFragment};
 use vertex::Vertex;
-use obj::Obj;
-use triangle::triangle;
-use shaders::{vertex_shader, fragment_shader};
+use obj::{Obj, IndexedMesh};
+use triangle::{triangle, is_back_facing};
+use fragment::FragmentBuffer;
+use arena::{Scratch, FRAME_ARENA};
+use jobs::JobGraph;
+use threads::ThreadSettings;
+use simplify::MeshLod;
+use shaders::{vertex_shader, light_fragments, shade_lit, shade_vertex, builtin_shader, PlanetShader, Shader, ShadingMode, SunlightFalloff, Planetshine, RING_SHADER, ASTEROID_SHADER, ATMOSPHERE_SHADER, CLOUD_SHADER};
 use camera::Camera;
-use fastnoise_lite::{FastNoiseLite, NoiseType, FractalType};
-use texture::init_texture;
-use normal_map::init_normal_map;
-use skybox::Skybox;
-use planet::Planet;
-
+use fastnoise_lite::{FastNoiseLite, NoiseType};
+use rayon::prelude::*;
+use texture::Texture;
+use color::Color;
+use normal_map::NormalMap;
+use skybox::{Skybox, SkyMap, DEFAULT_PARALLAX_LAYERS};
+use probes::AmbientCube;
+use text_log::TextLog;
+use settings::AccessibilitySettings;
+use quality::{QualitySettings, QualityTier};
+use thermal::ViewMode;
+use surface_cache::SurfaceCache;
+use assets::{Asset, AssetLoader};
+use culling::Frustum;
+use planet::{default_noise, Atmosphere, CloudLayer, Planet, Rings, SPHERE_RADIUS, update_barycenter, update_hierarchy};
+use sphere::{SphereLod, DEFAULT_SUBDIVISIONS};
+use scene::{SceneConfig, ShipConfig, FileWatcher};
+use recorder::FlightRecorder;
+use damage::DamageMap;
+use radar::{Radar, BlipKind};
+use docking::DockingComputer;
+use escorts::EscortWing;
+use material::{BlendMode, Material, MaterialLibrary};
+use input::Input;
+use touchpad::Touchpad;
+use clock::{SimulationClock, TIME_PRESETS};
+use font::draw_text;
+use events::EventPredictor;
+use demo::{DemoPlayer, DemoScript, ScriptAction};
+use frame_capture::{FrameCapture, encode_normal, screenshot};
+use postprocess::{resolve_supersampled, texel_span, PostChain, SceneDepth};
+use depth_range::DepthRange;
+use animation::AnimationExport;
+use focus::{CameraFocus, focus_cycle};
+use autopilot::Autopilot;
+use particles::{Emitter, ParticleSystem};
+use orientation::{Orientation, about_axis, from_euler, to_euler, heading, yaw};
+use background::Background;
+use flight::{FlightControls, FlightModel, FlightState};
+use keybindings::{Action, KeyBindings, BOOKMARK_COUNT};
+use galaxy::{GalaxyMap, Jump};
+use trade::Trade;
+use session::{MenuCommand, Session, SessionMenu, ShipState, TradeState};
+use tuning::{ShaderParams, TuningCommand, TuningPanel};
+use motion::{MotionTracker, MotionTransform};
+
+const SCENE_PATH: &str = "assets/scene/solar_system.toml";
+const SAVED_SCENE_PATH: &str = "assets/scene/saved_scene.toml";
+const SESSION_DIRECTORY: &str = "saves";
+const SESSION_SLOTS: usize = 5; // Ctrl+O abre el menú de partidas
+const KEYBINDINGS_PATH: &str = "assets/keybindings.toml";
+const SHADER_PARAMS_PATH: &str = "assets/shader_params.toml"; // Ctrl+U, S los guarda
+const DEMO_PATH: &str = "assets/demo/tour.toml"; // Ctrl+D, o --demo <guion> al arrancar
+const MIN_EVENT_JUMP_STEPS: f32 = 0.5; // Saltar a un evento que ya está ocurriendo no avanza nada
+const PARTICLE_CAPACITY: usize = 6000;
+const SOLAR_WIND_CAPACITY: usize = 3000;
+const NOZZLE_OFFSET: f32 = 0.9; // Radios del modelo detrás del centro de la nave
+const IDLE_EXHAUST: f32 = 0.2;
+const PORT_CLEARANCE: f32 = 0.1; // Radio alrededor del puerto que no cuenta como tapado
+const SSAA_FACTORS: [usize; 3] = [1, 2, 4]; // Pixeles por lado de cada pixel de pantalla, Ctrl+A o --ssaa <factor>
+
+#[derive(Clone)]
 pub struct Uniforms {
     model_matrix: Mat4,
     view_matrix: Mat4,
+    camera_position: Vec3, // Ojo de la cámara en el mundo, el de view_matrix
     projection_matrix: Mat4,
     viewport_matrix: Mat4,
     time: u32,
-    noise: Rc<FastNoiseLite>,
+    noise: Arc<FastNoiseLite>,
+    accessibility: AccessibilitySettings,
+    quality: QualitySettings,
+    view_mode: ViewMode,
+    light_position: Vec3,
+    rings: Option<Rings>,
+    atmosphere: Option<Atmosphere>,
+    clouds: Option<CloudLayer>,
+    damage: Option<Arc<DamageMap>>,
+    material: Material,
+    depth_range: DepthRange,
+    sunlight: SunlightFalloff,
+    planetshine: Option<Planetshine>,
+    ambient: Option<AmbientCube>, // Luz del fondo y de las sondas, None sin ella
+    shading_scale: usize, // Pixeles por lado de cada fragmento sombreado, 1 = todos
+    shader_params: ShaderParams, // Ctrl+U los ajusta en vivo
+    motion: Option<MotionTransform>, // Dónde estaba el objeto en pantalla el frame anterior, None sin vectores de movimiento
 }
 
 pub struct Spaceship {
+    pub name: String,
     pub position: Vec3,
     pub scale: f32,
-    pub rotation: Vec3,
+    pub rotation: Orientation,
     pub model: Obj, // El modelo .obj cargado
-    pub shader_index: u32, // Shader que usará la nave
-}
-
-
-fn create_noise_for_planet(index: usize) -> FastNoiseLite {
-    match index {
-        0 => create_generic_noise(),
-        1 => create_gas_giant_noise(),
-        2 => create_lava_noise(),
-        3 => create_ground_noise(),
-        4 => create_cloud_noise(),
-        5 => create_icy_noise(),
-        6 => create_generic_noise(),
-        7 => create_generic_noise(),
-        8 => create_generic_noise(),
-        9 => create_generic_noise(),
-        10 => create_noise(),
-        _ => create_generic_noise(),
-    }
-}
-
-fn create_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(1337);
-    noise.set_noise_type(Some(NoiseType::OpenSimplex2));
-    noise
+    pub mesh: MeshLod, // El modelo y versiones simplificadas para verlo de lejos
+    pub shader: Shader, // Shader que usará la nave
+    pub shading_mode: Option<ShadingMode>, // None usa el modo global
+    pub cull_back_faces: bool, // El tie-fighter no es una malla cerrada
+    pub damage: Arc<DamageMap>, // Quemaduras acumuladas, las lee el shader de la nave
+    pub material: Material,
+    pub handling: FlightModel,
+    pub flight: FlightState,
+    pub controls: FlightControls, // Lo que pide el jugador este frame
+    pub exhaust: Emitter,
 }
 
+// Noise of the scene-wide uniforms, the asteroid belt draws with it
 fn create_generic_noise() -> FastNoiseLite {
     let mut noise = FastNoiseLite::with_seed(1337);
     noise.set_noise_type(Some(NoiseType::Perlin));  // Usar Perlin por defecto
@@ -86,83 +205,8 @@ fn create_generic_noise() -> FastNoiseLite {
     noise
 }
 
-fn create_icy_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(7890);
-    noise.set_noise_type(Some(NoiseType::OpenSimplex2)); // Simplex para suaves transiciones
-    noise.set_frequency(Some(0.08));                    // Frecuencia más alta
-    noise.set_fractal_type(Some(FractalType::FBm));     // Más octavas para textura
-    noise
-}
-
-fn create_gas_giant_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(4242);
-    noise.set_noise_type(Some(NoiseType::OpenSimplex2)); // Efecto de bandas suaves
-    noise.set_frequency(Some(0.02));                    // Características grandes
-    noise
-}
-
-fn create_cloud_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(1337);
-    noise.set_noise_type(Some(NoiseType::OpenSimplex2));
-    noise
-}
-
-
-fn create_ground_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(1337);
-    
-    // Use FBm fractal type to layer multiple octaves of noise
-    noise.set_noise_type(Some(NoiseType::Cellular)); // Cellular noise for cracks
-    noise.set_fractal_type(Some(FractalType::FBm));  // Fractal Brownian Motion
-    noise.set_fractal_octaves(Some(5));              // More octaves = more detail
-    noise.set_fractal_lacunarity(Some(2.0));         // Lacunarity controls frequency scaling
-    noise.set_fractal_gain(Some(0.5));               // Gain controls amplitude scaling
-    noise.set_frequency(Some(0.05));                 // Lower frequency for larger features
-
-    noise
-}
-
-fn create_lava_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(42);
-    
-    // Use FBm for multi-layered noise, giving a "turbulent" feel
-    noise.set_noise_type(Some(NoiseType::Perlin));  // Perlin noise for smooth, natural texture
-    noise.set_fractal_type(Some(FractalType::FBm)); // FBm for layered detail
-    noise.set_fractal_octaves(Some(6));             // High octaves for rich detail
-    noise.set_fractal_lacunarity(Some(2.0));        // Higher lacunarity = more contrast between layers
-    noise.set_fractal_gain(Some(0.5));              // Higher gain = more influence of smaller details
-    noise.set_frequency(Some(0.002));                // Low frequency = large features
-    
-    noise
-}
-
-fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat4 {
-    let (sin_x, cos_x) = rotation.x.sin_cos();
-    let (sin_y, cos_y) = rotation.y.sin_cos();
-    let (sin_z, cos_z) = rotation.z.sin_cos();
-
-    let rotation_matrix_x = Mat4::new(
-        1.0,  0.0,    0.0,   0.0,
-        0.0,  cos_x, -sin_x, 0.0,
-        0.0,  sin_x,  cos_x, 0.0,
-        0.0,  0.0,    0.0,   1.0,
-    );
-
-    let rotation_matrix_y = Mat4::new(
-        cos_y,  0.0,  sin_y, 0.0,
-        0.0,    1.0,  0.0,   0.0,
-        -sin_y, 0.0,  cos_y, 0.0,
-        0.0,    0.0,  0.0,   1.0,
-    );
-
-    let rotation_matrix_z = Mat4::new(
-        cos_z, -sin_z, 0.0, 0.0,
-        sin_z,  cos_z, 0.0, 0.0,
-        0.0,    0.0,  1.0, 0.0,
-        0.0,    0.0,  0.0, 1.0,
-    );
-
-    let rotation_matrix = rotation_matrix_z * rotation_matrix_y * rotation_matrix_x;
+fn create_model_matrix(translation: Vec3, scale: f32, rotation: Orientation) -> Mat4 {
+    let rotation_matrix = rotation.to_homogeneous();
 
     let transform_matrix = Mat4::new(
         scale, 0.0,   0.0,   translation.x,
@@ -179,119 +223,539 @@ fn create_view_matrix(eye: Vec3, center: Vec3, up: Vec3) -> Mat4 {
     look_at(&eye, &center, &up)
 }
 
-fn create_perspective_matrix(window_width: f32, window_height: f32) -> Mat4 {
+fn create_perspective_matrix(window_width: f32, window_height: f32, depth_range: &DepthRange) -> Mat4 {
     let fov = 60.0 * PI / 180.0;
     let aspect_ratio = window_width / window_height;
-    let near = 0.1;
-    let far = 1000.0;
+    let (near, far) = depth_range.planes();
+
+    // Reversed-Z and the infinite far plane only change the depth row
+    let mut projection = perspective(fov, aspect_ratio, near, far);
+    (projection[(2, 2)], projection[(2, 3)]) = depth_range.projection_depth();
+    projection
+}
 
-    perspective(fov, aspect_ratio, near, far)
+// The 3D passes render into this one, `factor` times the window per side,
+// and are averaged down before bloom and the HUD
+fn create_scene_framebuffer(width: usize, height: usize, factor: usize, background: Background) -> Framebuffer {
+    let mut framebuffer = Framebuffer::new(width * factor, height * factor);
+    framebuffer.set_background(background);
+    framebuffer
 }
 
-fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
+// Reversed-Z is negated so the depth buffer keeps the closest value smallest
+fn create_viewport_matrix(width: f32, height: f32, depth_range: &DepthRange) -> Mat4 {
+    let depth_scale = if depth_range.reversed { -1.0 } else { 1.0 };
     Mat4::new(
         width / 2.0, 0.0, 0.0, width / 2.0,
         0.0, -height / 2.0, 0.0, height / 2.0,
-        0.0, 0.0, 1.0, 0.0,
+        0.0, 0.0, depth_scale, 0.0,
         0.0, 0.0, 0.0, 1.0
     )
 }
 
 impl Spaceship {
-    pub fn new(model_path: &str, position: Vec3, scale: f32, rotation: Vec3, shader_index: u32) -> Self {
+    pub fn new(model: Obj, position: Vec3, scale: f32, rotation: Orientation, shader: Shader) -> Self {
         Spaceship {
+            name: String::new(),
             position,
             scale,
             rotation,
-            model: Obj::load("assets/model/tie-fighter.obj").expect("Failed to load spaceship model"),
-            shader_index,
+            mesh: MeshLod::new(model.get_indexed_mesh()),
+            model,
+            shader,
+            shading_mode: None,
+            cull_back_faces: false,
+            damage: Arc::new(DamageMap::new(64, 32)),
+            material: Material::default(),
+            handling: FlightModel {
+                max_speed: 0.1,
+                acceleration: 0.006,
+                damping: 0.02,
+                turn_rate: 0.05,
+                roll_rate: 0.06,
+                max_bank: 0.5,
+            },
+            flight: FlightState::default(),
+            controls: FlightControls::default(),
+            exhaust: {
+                let mut exhaust = Emitter::new(8.0, 0.04, 0.12, 24.0, 0.15 * scale, 0xFFD080, 0xFF3010);
+                exhaust.growth = 2.0;
+                exhaust.intensity = 0.6;
+                exhaust
+            },
         }
     }
 
-    pub fn update_position(&mut self, direction: Vec3) {
-        self.position += direction;
+    // Ship from a roster entry of the scene file
+    pub fn from_config(model: Obj, config: &ShipConfig, position: Vec3, rotation: Orientation, materials: &mut MaterialLibrary) -> Result<Self, String> {
+        let mut spaceship = Spaceship::new(model, position, config.scale, rotation, config.shader.resolve().map_err(|error| format!("{}: {}", config.name, error))?);
+        spaceship.name = config.name.clone();
+        spaceship.shading_mode = config.shading_mode()?;
+        spaceship.cull_back_faces = config.cull_back_faces;
+        spaceship.handling = config.flight_model();
+        spaceship.material = config.material(materials)?;
+        spaceship.material.thermal.engine_length = spaceship.model.bounding_radius();
+        spaceship.material.thermal.engine_heat = IDLE_EXHAUST;
+        Ok(spaceship)
+    }
+
+    // Scorches the hull on the side facing a world-space point
+    pub fn take_hit(&mut self, point: &Vec3, spread: f32, amount: f32) {
+        let world_direction = point - self.position;
+        if world_direction.magnitude_squared() == 0.0 {
+            return;
+        }
+        // The model matrix is a rotation times a uniform scale
+        let rotation = nalgebra_glm::mat4_to_mat3(&self.get_model_matrix());
+        let direction = rotation.transpose() * world_direction;
+        Arc::make_mut(&mut self.damage).add_scorch(&direction, spread, amount);
+    }
+
+    // One frame of flight with this frame's controls, `pull` is the gravity
+    // at the ship when it is on
+    pub fn fly(&mut self, pull: Option<Vec3>) {
+        self.flight.step(&self.handling, &self.controls, pull, &mut self.rotation, &mut self.position);
+        self.material.thermal.engine_heat = self.controls.throttle.max(IDLE_EXHAUST);
+    }
+
+    // Engine glow out of the tail, as strong as the forward throttle with a
+    // faint idle flame. `density` scales it for the quality tier.
+    pub fn emit_exhaust(&mut self, particles: &mut ParticleSystem, density: f32) {
+        let forward = self.rotation * Vec3::z();
+        let nozzle = self.position - forward * (self.model.bounding_radius() * self.scale * NOZZLE_OFFSET);
+        let strength = self.controls.throttle.max(IDLE_EXHAUST) * density;
+        self.exhaust.emit(particles, &nozzle, &-forward, &self.flight.velocity, strength);
+    }
+
+    // Front of the hull, where the mining beam comes out
+    pub fn nose(&self) -> Vec3 {
+        let forward = self.rotation * Vec3::z();
+        self.position + forward * (self.model.bounding_radius() * self.scale * NOZZLE_OFFSET)
+    }
+
+    // Compass direction of the nose around Y
+    pub fn heading(&self) -> f32 {
+        heading(&self.rotation)
     }
 
     pub fn get_model_matrix(&self) -> Mat4 {
-        create_model_matrix(self.position, self.scale, self.rotation)
+        create_model_matrix(self.position, self.scale, self.rotation * about_axis(&Vec3::z(), self.flight.bank))
+    }
+}
+
+// Text snapshot of the simulation for captures and reports
+fn describe_state(time: u32, camera: &Camera, spaceship: &Spaceship, planets: &[Planet]) -> String {
+    let mut state = format!(
+        "time: {}\ncamera eye: {:?}\ncamera center: {:?}\nspaceship {}: {:?}\n",
+        time, camera.eye, camera.center, spaceship.name, spaceship.position,
+    );
+    for planet in planets {
+        state.push_str(&format!(
+            "planet {}: angle {:.4}, position {:?}\n",
+            planet.name, planet.current_angle, planet.get_position(),
+        ));
     }
+    state
 }
 
 fn render(
     framebuffer: &mut Framebuffer,
     uniforms: &Uniforms, 
-    vertex_array: &[Vertex], 
-    current_shader: u32
+    mesh: &IndexedMesh, 
+    shader: &dyn PlanetShader,
+    shading_mode: ShadingMode,
+    surface_cache: Option<&SurfaceCache>,
+    cull_back_faces: bool,
 ) {
-    let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
+    // A coarse pass rasterizes and shades at 1/scale of the resolution
+    let scale = uniforms.shading_scale.max(1);
+    let coarse_uniforms;
+    let uniforms = if scale > 1 {
+        let downscale = nalgebra_glm::scaling(&Vec3::new(1.0 / scale as f32, 1.0 / scale as f32, 1.0));
+        coarse_uniforms = Uniforms { viewport_matrix: downscale * uniforms.viewport_matrix, ..uniforms.clone() };
+        &coarse_uniforms
+    } else {
+        uniforms
+    };
 
-    for vertex in vertex_array {
-        let transformed = vertex_shader(vertex, uniforms);
-        transformed_vertices.push(transformed);
+    // Every unique vertex is shaded once, this array is the post-transform
+    // cache the triangles are assembled from
+    let mut draw = FRAME_ARENA.take();
+    mesh.vertices
+        .par_iter()
+        .map(|vertex| vertex_shader(vertex, uniforms))
+        .collect_into_vec(&mut draw.vertices);
+
+    // Gouraud: evaluate the shader once per vertex
+    if shading_mode == ShadingMode::Gouraud && surface_cache.is_none() {
+        draw.vertices.par_iter_mut().for_each(|vertex| {
+            vertex.color = shade_vertex(vertex, uniforms, shader);
+        });
     }
 
-    let mut triangles = Vec::new();
-    for i in (0..transformed_vertices.len()).step_by(3) {
-        if i + 2 < transformed_vertices.len() {
-            triangles.push([
-                transformed_vertices[i].clone(),
-                transformed_vertices[i + 1].clone(),
-                transformed_vertices[i + 2].clone(),
-            ]);
-        }
+    // Clip, cull, rasterize and shade the triangles in parallel, each worker fills
+    // its own scratch from the arena and they are merged back in triangle order
+    let width = framebuffer.width.div_ceil(scale);
+    let height = framebuffer.height.div_ceil(scale);
+    let transformed_vertices = &draw.vertices;
+    let near_plane = uniforms.depth_range.near_clip_plane();
+    let shaded = mesh.indices
+        .par_chunks_exact(3)
+        .fold(|| FRAME_ARENA.take(), |mut scratch, tri| {
+            let Scratch { clipper, fragments, shaded, .. } = &mut scratch;
+            let clipped = clipper.clip(
+                &transformed_vertices[tri[0] as usize],
+                &transformed_vertices[tri[1] as usize],
+                &transformed_vertices[tri[2] as usize],
+                &uniforms.viewport_matrix,
+                &near_plane,
+            );
+            for tri in clipped.iter().filter(|tri| !(cull_back_faces && is_back_facing(&tri[0], &tri[1], &tri[2]))) {
+                shade_triangle(tri, uniforms, shader, shading_mode, surface_cache, (width, height), (fragments, shaded));
+            }
+            scratch
+        })
+        .reduce_with(Scratch::merge);
+    let Some(shaded) = shaded else {
+        FRAME_ARENA.give(draw);
+        return;
+    };
+
+    if scale > 1 {
+        // The draw's own scratch holds the grids and the upsampled fragments
+        let Scratch { coarse, fine, shaded: upsampled, .. } = &mut draw;
+        upsample_fragments(&shaded.shaded, scale, (framebuffer.width, framebuffer.height), (coarse, fine, upsampled));
+        write_shaded(framebuffer, upsampled, uniforms.material.blend);
+    } else {
+        write_shaded(framebuffer, &shaded.shaded, uniforms.material.blend);
     }
+    FRAME_ARENA.give(draw);
+    FRAME_ARENA.give(shaded);
+}
 
-    let mut fragments = Vec::new();
-    for tri in &triangles {
-        fragments.extend(triangle(&tri[0], &tri[1], &tri[2]));
+fn write_shaded(framebuffer: &mut Framebuffer, fragments: &[ShadedFragment], blend: BlendMode) {
+    match blend {
+        BlendMode::Opaque => framebuffer.write_fragments(fragments),
+        blend => framebuffer.queue_translucent(fragments, blend),
     }
+}
 
-    for fragment in fragments {
-        let x = fragment.position.x as usize;
-        let y = fragment.position.y as usize;
+// Coarse fragments spread back over the full resolution. Every pixel blends
+// the four coarse ones around it bilinearly by their alpha, the missing ones
+// counting as see-through, so the edges fade across a block instead of
+// stepping. The depth test against the scene still runs per pixel. `coarse`
+// and `fine` are the grids at both resolutions, the result goes to `upsampled`.
+fn upsample_fragments(
+    fragments: &[ShadedFragment],
+    scale: usize,
+    (width, height): (usize, usize),
+    (coarse, fine, upsampled): (&mut Vec<Option<ShadedFragment>>, &mut Vec<Option<ShadedFragment>>, &mut Vec<ShadedFragment>),
+) {
+    let (low_width, low_height) = (width.div_ceil(scale), height.div_ceil(scale));
+    coarse.clear();
+    coarse.resize(low_width * low_height, None);
+    let (mut low, mut high) = ((usize::MAX, usize::MAX), (0, 0));
+    for fragment in fragments.iter().filter(|fragment| fragment.0 < low_width && fragment.1 < low_height) {
+        let slot = &mut coarse[fragment.1 * low_width + fragment.0];
+        if slot.is_none_or(|kept| kept.2 > fragment.2) {
+            *slot = Some(*fragment);
+        }
+        low = (low.0.min(fragment.0), low.1.min(fragment.1));
+        high = (high.0.max(fragment.0), high.1.max(fragment.1));
+    }
+    upsampled.clear();
+    if low.0 > high.0 {
+        return;
+    }
 
-        if x < framebuffer.width && y < framebuffer.height {
-            let shaded_color = fragment_shader(&fragment, &uniforms, current_shader);
-            let color = shaded_color.to_hex();
-            framebuffer.set_current_color(color);
-            framebuffer.point(x, y, fragment.depth);
+    let coarse = &*coarse;
+    let blend = |x: usize, y: usize| -> Option<ShadedFragment> {
+        let (y0, y1, fy) = texel_span(y, low_height, scale);
+        let (x0, x1, fx) = texel_span(x, low_width, scale);
+        let corners = [
+            (y0 * low_width + x0, (1.0 - fx) * (1.0 - fy)),
+            (y0 * low_width + x1, fx * (1.0 - fy)),
+            (y1 * low_width + x0, (1.0 - fx) * fy),
+            (y1 * low_width + x1, fx * fy),
+        ];
+        let (mut alpha, mut color, mut depth, mut coverage) = (0.0, [0.0; 3], 0.0, 0.0);
+        let mut nearest: Option<(f32, u32, Option<Vec2>)> = None;
+        for (index, weight) in corners {
+            let Some((_, _, fragment_depth, fragment_color, normal, motion)) = coarse[index] else {
+                continue;
+            };
+            let weighted_alpha = (fragment_color >> 24) as f32 / 255.0 * weight;
+            for (channel, value) in color.iter_mut().enumerate() {
+                *value += ((fragment_color >> (16 - channel * 8)) & 0xFF) as f32 * weighted_alpha;
+            }
+            alpha += weighted_alpha;
+            depth += fragment_depth * weight;
+            coverage += weight;
+            if nearest.is_none_or(|(kept, _, _)| weight > kept) {
+                nearest = Some((weight, normal, motion));
+            }
+        }
+        if alpha <= 0.0 {
+            return None;
         }
+        let channel = |value: f32| ((value / alpha).round() as u32).min(0xFF);
+        let argb = ((alpha * 255.0).round() as u32).min(0xFF) << 24 | channel(color[0]) << 16 | channel(color[1]) << 8 | channel(color[2]);
+        let (normal, motion) = nearest.map_or((0, None), |(_, normal, motion)| (normal, motion));
+        Some((x, y, depth / coverage, argb, normal, motion))
+    };
+
+    // Only the pixels within half a block of a covered one can get anything
+    let columns = (low.0 * scale).saturating_sub(scale / 2)..((high.0 + 1) * scale + scale / 2).min(width);
+    let rows = (low.1 * scale).saturating_sub(scale / 2)..((high.1 + 1) * scale + scale / 2).min(height);
+    fine.clear();
+    fine.resize(columns.len() * rows.len(), None);
+    fine.par_chunks_mut(columns.len())
+        .zip(rows.into_par_iter())
+        .for_each(|(row, y)| {
+            for (slot, x) in row.iter_mut().zip(columns.clone()) {
+                *slot = blend(x, y);
+            }
+        });
+    upsampled.extend(fine.iter().flatten());
+}
+
+// Rasterizes one clipped triangle into `fragments` and adds them, shaded, to `shaded`
+fn shade_triangle(
+    tri: &[Vertex; 3],
+    uniforms: &Uniforms,
+    shader: &dyn PlanetShader,
+    shading_mode: ShadingMode,
+    surface_cache: Option<&SurfaceCache>,
+    (width, height): (usize, usize),
+    (fragments, shaded): (&mut FragmentBuffer, &mut Vec<ShadedFragment>),
+) {
+    let positions = (&uniforms.light_position, &uniforms.camera_position);
+    triangle(&tri[0], &tri[1], &tri[2], shading_mode, positions, (width, height), fragments);
+    // The cache and Gouraud carry their lighting already
+    if surface_cache.is_none() && shading_mode != ShadingMode::Gouraud {
+        light_fragments(fragments, uniforms, shader);
+    }
+    shaded.extend(fragments.positions.iter()
+        .enumerate()
+        .map(|(index, position)| {
+            let (x, y) = (position.x as usize, position.y as usize);
+            let fragment = fragments.get(index);
+            let shaded_color = match (surface_cache, shading_mode) {
+                (Some(cache), _) => cache.shade(&fragment),
+                (None, ShadingMode::Gouraud) => fragment.color,
+                _ => shade_lit(&fragment, uniforms, shader, &fragments.light(index)),
+            };
+            let motion = uniforms.motion.and_then(|motion| motion.motion(&fragment.vertex_position));
+            (x, y, fragment.depth, shaded_color.to_argb(), encode_normal(&fragment.normal), motion)
+        }));
+}
+
+// Draws the same mesh once per model matrix. The work is split by instance
+// instead of by triangle, which suits many small meshes like asteroids.
+fn render_instances(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    mesh: &IndexedMesh,
+    model_matrices: &[Mat4],
+    shader: &dyn PlanetShader,
+    shading_mode: ShadingMode,
+    cull_back_faces: bool,
+) {
+    let width = framebuffer.width;
+    let height = framebuffer.height;
+    let shaded = model_matrices
+        .par_iter()
+        .fold(|| FRAME_ARENA.take(), |mut scratch, model_matrix| {
+            let instance_uniforms = Uniforms { model_matrix: *model_matrix, ..uniforms.clone() };
+            let Scratch { vertices, clipper, fragments, shaded, .. } = &mut scratch;
+            vertices.clear();
+            vertices.extend(mesh.vertices.iter().map(|vertex| vertex_shader(vertex, &instance_uniforms)));
+            if shading_mode == ShadingMode::Gouraud {
+                for vertex in vertices.iter_mut() {
+                    vertex.color = shade_vertex(vertex, &instance_uniforms, shader);
+                }
+            }
+
+            let near_plane = instance_uniforms.depth_range.near_clip_plane();
+            for tri in mesh.indices.chunks_exact(3) {
+                let clipped = clipper.clip(
+                    &vertices[tri[0] as usize],
+                    &vertices[tri[1] as usize],
+                    &vertices[tri[2] as usize],
+                    &instance_uniforms.viewport_matrix,
+                    &near_plane,
+                );
+                for tri in clipped {
+                    if cull_back_faces && is_back_facing(&tri[0], &tri[1], &tri[2]) {
+                        continue;
+                    }
+                    shade_triangle(tri, &instance_uniforms, shader, shading_mode, None, (width, height), (fragments, shaded));
+                }
+            }
+            scratch
+        })
+        .reduce_with(Scratch::merge);
+
+    if let Some(shaded) = shaded {
+        framebuffer.write_fragments(&shaded.shaded);
+        FRAME_ARENA.give(shaded);
     }
 }
 
 
 fn main() {
 
-    let window_width = 800;
-    let window_height = 600;
-    let framebuffer_width = 800;
-    let framebuffer_height = 600;
+    let mut window_width = 800;
+    let mut window_height = 600;
+    let mut framebuffer_width = 800;
+    let mut framebuffer_height = 600;
     let frame_delay = Duration::from_millis(16);
-    let event_loop = EventLoop::new();
+
+    // `--record` exports an animation without opening a window
+    let args: Vec<String> = std::env::args().collect();
+    let mut animation = match AnimationExport::from_args(&args) {
+        Ok(Some(animation)) => {
+            if let Some(size) = animation.size {
+                (window_width, window_height) = size;
+                (framebuffer_width, framebuffer_height) = size;
+            }
+            Some(animation)
+        }
+        Ok(None) => None,
+        Err(error) => panic!("Failed to start recording {}", error),
+    };
+    // `--quality low|medium|high` also sets bloom and supersampling, without it
+    // the scene keeps its own and the shaders run at full detail
+    let quality_flag = args.iter().position(|arg| arg == "--quality").and_then(|index| args.get(index + 1))
+        .map(|name| QualityTier::from_name(name).unwrap_or_else(|| panic!("--quality takes low, medium or high")));
+    let mut quality = quality_flag.unwrap_or(QualityTier::High).settings();
+    // `--threads <n>` and `--deterministic`, before any parallel work starts
+    let threads = ThreadSettings::from_args(&args)
+        .and_then(|threads| threads.apply().map(|_| threads))
+        .unwrap_or_else(|error| panic!("Failed to set up threads {}", error));
 
     let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
-    let mut window = Window::new(
+    let mut window = animation.is_none().then(|| Window::new(
         "Graficas por Computadora - Solar System",
         window_width,
         window_height,
-        WindowOptions::default(),
+        WindowOptions {
+            resize: true,
+            ..WindowOptions::default()
+        },
     )
-    .unwrap();
+    .unwrap());
 
+    // Key events are queued as the window receives them and read once per frame
+    let mut input = match window.as_mut() {
+        Some(window) => Input::attach(window),
+        None => Input::detached(),
+    };
 
-    framebuffer.set_background_color(0x333355);
+    // Keep the last frames and dump them on F12, on panic also write a crash report
+    let recorder = Arc::new(Mutex::new(FlightRecorder::new(30, framebuffer_width, framebuffer_height)));
+    crash::install_panic_hook(Arc::clone(&recorder));
+
+    let mut text_log = TextLog::new(6);
+
+    // Bad keybinding files fall back to the defaults instead of stopping the program
+    let bindings = KeyBindings::load(KEYBINDINGS_PATH).unwrap_or_else(|error| {
+        text_log.log(format!("Keybindings: {}", error));
+        KeyBindings::default()
+    });
+
+    // Shader parameters tuned and saved in an earlier run, the defaults without the file
+    let shader_params = if std::path::Path::new(SHADER_PARAMS_PATH).exists() {
+        ShaderParams::load(SHADER_PARAMS_PATH).unwrap_or_else(|error| {
+            text_log.log(format!("Shader parameters: {}", error));
+            ShaderParams::default()
+        })
+    } else {
+        ShaderParams::default()
+    };
+
+    // Bodies, spaceship, camera and noise settings come from the scene file
+    let mut scene = SceneConfig::load(SCENE_PATH)
+        .unwrap_or_else(|error| panic!("Failed to load scene {}", error));
+    let mut background = scene.background.background()
+        .unwrap_or_else(|error| panic!("Failed to load scene {}", error));
+    framebuffer.set_background(background);
+
+    // Load models and textures on worker threads while showing a loading screen
+    let mut loader = AssetLoader::new();
+    for ship in &scene.ships {
+        let model = ship.model.clone();
+        loader.spawn(&format!("ship {}", ship.name), move || Obj::load(&model).map(Asset::Model).map_err(|e| e.to_string()));
+    }
+    for path in scene.texture_paths() {
+        let path = path.to_string();
+        loader.spawn(&format!("texture {}", path), move || Texture::new(&path).map(Asset::Texture).map_err(|e| e.to_string()));
+    }
+    for path in scene.normal_map_paths() {
+        let path = path.to_string();
+        loader.spawn(&format!("normal map {}", path), move || NormalMap::new(&path).map(Asset::NormalMap).map_err(|e| e.to_string()));
+    }
+    let sky = scene.sky.clone();
+    loader.spawn("skybox", move || {
+        let catalog = sky.as_ref().and_then(|sky| sky.catalog.as_deref());
+        let parallax_layers = sky.as_ref().map_or(DEFAULT_PARALLAX_LAYERS, |sky| sky.parallax_layers);
+        let mut skybox = Skybox::new(50000, catalog, parallax_layers)?;
+        if let Some(sky) = sky {
+            let paths = sky.map_paths()?;
+            if !paths.is_empty() {
+                skybox.map = Some(SkyMap::load(&paths)?);
+            }
+            skybox.map_brightness = sky.brightness;
+            skybox.show_stars = sky.stars;
+            skybox.parallax = sky.parallax;
+        }
+        Ok(Asset::Skybox(skybox))
+    });
+
+    while !loader.is_done() {
+        input.update();
+        if window.as_ref().is_some_and(|window| !window.is_open()) || input.is_down(Key::Escape) {
+            return;
+        }
+        for name in loader.poll() {
+            text_log.log(format!("Loaded {}", name));
+        }
+
+        match window.as_mut() {
+            Some(window) => {
+                framebuffer.clear();
+                loader.render(&mut framebuffer);
+                window
+                    .update_with_buffer(&framebuffer.buffer, framebuffer_width, framebuffer_height)
+                    .unwrap();
+            }
+            None => std::thread::sleep(frame_delay),
+        }
+    }
+
+    // Per-object images, shared between the bodies that use the same file
+    let mut materials = MaterialLibrary::default();
+    for path in scene.texture_paths() {
+        materials.insert_texture(path, loader.take_texture(&format!("texture {}", path)));
+    }
+    for path in scene.normal_map_paths() {
+        materials.insert_normal_map(path, loader.take_normal_map(&format!("normal map {}", path)));
+    }
 
 	// model position
 	let translation = Vec3::new(0.0, 0.0, 0.0);
-	let rotation = Vec3::new(0.0, 0.0, 0.0);
+	let rotation = Orientation::identity();
 	let scale = 1.0f32;
 
 	// camera parameters
 	let mut camera = Camera::new(
-        Vec3::new(0.0, 10.0, 30.0),  
-        Vec3::new(0.0, 0.0, 0.0),    
-        Vec3::new(0.0, 1.0, 0.0),    
-    );  
+        Vec3::from(scene.camera.eye),
+        Vec3::from(scene.camera.center),
+        Vec3::from(scene.camera.up),
+    );
+    camera.constraints = scene.camera.constraints()
+        .unwrap_or_else(|error| panic!("Failed to load scene {}", error));
+    let touchpad = scene.touchpad.touchpad();
 
     let mut last_mouse_position = PhysicalPosition::new(0.0, 0.0);
     let mut mouse_pressed = false;
@@ -299,183 +763,1400 @@ fn main() {
     let mut zoom_speed = 2.0;
 
     let mut bird_eye_view_active = false; // Estado de la vista de pájaro
+    let mut show_labels = false; // Nombres sobre los cuerpos, Ctrl+L
+    let mut show_hud = true;     // Ctrl+H deja solo la escena
     let default_camera_eye = camera.eye; // Guardar la posición inicial de la cámara
     let default_camera_center = camera.center; // Guardar el centro inicial de la cámara
 
 
 
-    let mut planets = vec![
-        Planet::new("Sol", 6.0, 0.0, 0.0, 0.0, 0xFFFF00, 2),
-        Planet::new("Mercurio", 0.7, 5.0, 0.04, 0.1, 0xffc300, 1),
-        Planet::new("Venus", 1.0, 6.5, 0.03, 0.08, 0xe24e42, 0),
-        Planet::new("Tierra", 1.2, 8.0, 0.02, 0.07, 0x0077be, 10),
-        Planet::new("Luna", 0.3, 8.2, 0.1, 0.1, 0xaaaaaa, 7),
-        Planet::new("Marte", 0.8, 9.8, 0.01, 0.05, 0xd95d39, 3),
-        Planet::new("Júpiter", 5.0, 14.0, 0.005, 0.03, 0xfff9a6, 5),
-        Planet::new("Saturno", 4.0, 20.0, 0.004, 0.02, 0xc49c48, 6),
-        Planet::new("Urano", 3.0, 25.0, 0.003, 0.01, 0x7ec8f7, 9),
-        Planet::new("Neptuno", 3.0, 29.0, 0.002, 0.009, 0x4a6dcd, 8),
-    ];
+    let mut planets = scene.build_planets(&mut materials, quality.max_octaves)
+        .unwrap_or_else(|error| panic!("Failed to load scene {}", error));
+    let mut barycenter_scale = scene.barycenter_scale;
+    let mut nebulae = scene.build_nebulae();
+    let mut asteroid_belt = scene.build_asteroid_belt(quality.particles);
+    let mut comets = scene.build_comets();
+    let mut particles = ParticleSystem::new(PARTICLE_CAPACITY, 7);
+    let mut solar_wind = scene.build_solar_wind(SOLAR_WIND_CAPACITY);
+    let mut mining_laser = scene.build_mining_laser();
+    let mut trade = scene.build_trade()
+        .unwrap_or_else(|error| panic!("Failed to load scene {}", error));
+    let mut scene_watcher = FileWatcher::new(SCENE_PATH);
 
-    let planet_obj = Obj::load("assets/model/sphere.obj").expect("Failed to load obj");
+    // Shared by every planet without a tessellation of its own
+    let planet_bounding_radius = SPHERE_RADIUS;
+    let planet_sphere = SphereLod::new(DEFAULT_SUBDIVISIONS);
 
     let mut current_shader = 0; // Shader inicial
 
-    let mut spaceship = Spaceship::new(
-        "assets/models/tie-fighter.obj", // Ruta de tu modelo de nave
-        Vec3::new(5.5, 1.5, 0.0),      // Cerca de la Tierra, en su órbita
-        0.5,                           // Escala pequeña
-        Vec3::new(0.0, 0.0, 0.0),      // Rotación inicial
-        7,                             // Shader para la nave
-    );
-
-	let mut time = 0;
-    let skybox = Skybox::new(50000);
-
-    let mut noises: Vec<Rc<FastNoiseLite>> = Vec::new();
-    for i in 0..7 {
-        noises.push(Rc::new(create_noise_for_planet(i)));
+    // Every ship of the roster is ready to swap in, each keeps its own damage
+    let mut fleet: Vec<Spaceship> = scene.ships.iter()
+        .map(|ship| Spaceship::from_config(
+            loader.take_model(&format!("ship {}", ship.name)),
+            ship,
+            Vec3::from(scene.spaceship.position),
+            from_euler(&Vec3::from(scene.spaceship.rotation)),
+            &mut materials,
+        ))
+        .collect::<Result<_, _>>()
+        .unwrap_or_else(|error| panic!("Failed to load scene {}", error));
+    let ship_radii: Vec<f32> = fleet.iter().map(|ship| ship.model.bounding_radius() * ship.scale).collect();
+    let ship_scales: Vec<f32> = fleet.iter().map(|ship| ship.scale).collect();
+    let mut active_ship = scene.starting_ship()
+        .unwrap_or_else(|error| panic!("Failed to load scene {}", error));
+    let mut escort_wing = scene.build_escorts()
+        .unwrap_or_else(|error| panic!("Failed to load scene {}", error));
+    let mut radar = Radar::new(30.0);
+    let mut event_predictor = EventPredictor::new();
+    let mut post_chain = PostChain::default();
+    scene.configure_post_chain(&mut post_chain);
+    if quality_flag.is_some() {
+        post_chain.set_enabled("bloom", quality.bloom);
+    }
+    for name in post_chain.missing() {
+        text_log.log(format!("No post effect named {}", name));
     }
-    
-    let generic_noise = Rc::new(create_generic_noise());
-    let projection_matrix = create_perspective_matrix(window_width as f32, window_height as f32);
-    let viewport_matrix = create_viewport_matrix(framebuffer_width as f32, framebuffer_height as f32);
+    let mut gravity = scene.gravity.gravity();
+    let mut exporter = scene.export.exporter()
+        .unwrap_or_else(|error| panic!("Failed to load scene {}", error));
+    // Recordings and deterministic runs advance the same time every frame, however long it took
+    let fixed_frame_seconds = animation.as_ref().map(AnimationExport::frame_seconds)
+        .or(threads.deterministic.then_some(frame_delay.as_secs_f32()));
+    let mut demo: Option<DemoPlayer> = None;
+    if let Some(path) = args.iter().position(|arg| arg == "--demo").and_then(|index| args.get(index + 1)) {
+        let mut player = DemoPlayer::new(DemoScript::load(path)
+            .unwrap_or_else(|error| panic!("Failed to load demo {}", error)));
+        player.fixed_frame_seconds = fixed_frame_seconds;
+        demo = Some(player);
+    }
+    let mut ssaa_factor = match args.iter().position(|arg| arg == "--ssaa").and_then(|index| args.get(index + 1)) {
+        Some(value) => value.parse().ok()
+            .filter(|factor| SSAA_FACTORS.contains(factor))
+            .unwrap_or_else(|| panic!("--ssaa takes one of {:?}", SSAA_FACTORS)),
+        None => quality_flag.map_or(1, |_| quality.ssaa_factor),
+    };
+    // `framebuffer` holds the 3D scene, `display` is what the window shows and
+    // `hud` the overlays composited over it
+    let mut display = Framebuffer::new(framebuffer_width, framebuffer_height);
+    let mut hud = Framebuffer::layer(framebuffer_width, framebuffer_height);
+    framebuffer = create_scene_framebuffer(framebuffer_width, framebuffer_height, ssaa_factor, background);
+    let mut station = scene.build_station()
+        .unwrap_or_else(|error| panic!("Failed to load scene {}", error));
+    let mut docking_computer = DockingComputer::new(scene.station.as_ref().map_or(0.0, |config| config.approach_range));
+    let mut docking_state: Option<bool> = None; // None lejos, Some(atracado) en aproximación
+    let mut camera_bookmarks: [Option<(Vec3, Vec3)>; BOOKMARK_COUNT as usize] = [None; BOOKMARK_COUNT as usize];
+    let mut focus: Option<CameraFocus> = None; // Planeta que sigue la cámara
+    let mut session_menu = SessionMenu::new(SESSION_DIRECTORY, SESSION_SLOTS);
+    let mut pending_session: Option<(usize, Session)> = None; // Se aplica al terminar el salto a su sistema
+    let mut galaxy = scene.build_galaxy();
+    let mut galaxy_map = GalaxyMap::new();
+    let mut motion_tracker = MotionTracker::default();
+    let mut tuning_panel = TuningPanel::new();
+    let mut jump: Option<Jump> = None;
+    let mut home_trade: Option<Trade> = None; // Los contratos de casa mientras la nave está en otro sistema
+    let mut autopilot: Option<Autopilot> = None;
+
+	let mut time: u32 = 0;
+    let mut colliding_planet: Option<String> = None;
+    let mut accessibility = AccessibilitySettings::default();
+    let mut clock = SimulationClock::new(scene.days_per_step, TIME_PRESETS[3].1); // Empieza a 1 año por minuto
+    clock.fixed_frame_seconds = fixed_frame_seconds;
+    let mut shading_mode = ShadingMode::PerFragment;
+    let mut texture_space_shading = false;
+    let mut back_face_culling = true;
+    let mut skybox = loader.take_skybox("skybox");
+    skybox.pixel_scale = ssaa_factor as f32;
+    let mut light_probes = scene.probes.light_probes(&skybox);
+
+    let default_noise = Arc::new(default_noise());
+    // Rings, air shells and belt rocks always draw with their own shader
+    let ring_shader = builtin_shader(RING_SHADER);
+    let atmosphere_shader = builtin_shader(ATMOSPHERE_SHADER);
+    let cloud_shader = builtin_shader(CLOUD_SHADER);
+    let asteroid_shader = builtin_shader(ASTEROID_SHADER);
+    let mut depth_range = scene.depth.depth_range();
+    let mut projection_matrix = create_perspective_matrix(window_width as f32, window_height as f32, &depth_range);
+    let mut viewport_matrix = create_viewport_matrix(framebuffer.width as f32, framebuffer.height as f32, &depth_range);
     let mut uniforms = Uniforms { 
         model_matrix: Mat4::identity(), 
         view_matrix: Mat4::identity(), 
+        camera_position: Vec3::zeros(),
         projection_matrix, 
         viewport_matrix, 
         time: 0, 
-        noise: create_generic_noise().into(),
+        noise: Arc::new(create_generic_noise()),
+        accessibility,
+        quality,
+        view_mode: ViewMode::Visible,
+        light_position: Vec3::new(0.0, 0.0, 0.0),
+        rings: None,
+        atmosphere: None,
+        clouds: None,
+        damage: None,
+        material: Material::default(),
+        depth_range,
+        sunlight: scene.sunlight.falloff(),
+        planetshine: None,
+        ambient: background.ambient_light().map(AmbientCube::uniform),
+        shading_scale: 1,
+        shader_params,
+        motion: None,
     };
+    let mut planetshine_settings = scene.planetshine.settings();
+    let mut pass_scale = scene.pass_scale.clone();
 
-    while window.is_open() {
-        if window.is_key_down(Key::Escape) {
+    while window.as_ref().is_none_or(|window| window.is_open()) {
+        input.update();
+        if input.is_down(Key::Escape) {
             break;
         }
+
+        // Saved sessions and the galaxy map: while one is open it keeps the keys for itself
+        if !galaxy_map.open && bindings.pressed(&input, Action::SessionMenu) {
+            session_menu.toggle();
+        }
+        if !session_menu.open && bindings.pressed(&input, Action::GalaxyMap) {
+            galaxy_map.toggle(&galaxy);
+        }
+        if !session_menu.open && !galaxy_map.open && bindings.pressed(&input, Action::ShaderTuning) {
+            tuning_panel.toggle();
+        }
+        let session_command = session_menu.update(&input);
+        let jump_target = galaxy_map.update(&input, &galaxy);
+        let tuning_command = tuning_panel.update(&input, &mut uniforms.shader_params);
+        if session_menu.open || galaxy_map.open || tuning_panel.open {
+            input.clear();
+        }
+        match tuning_command {
+            Some(TuningCommand::Save) => match uniforms.shader_params.save(SHADER_PARAMS_PATH) {
+                Ok(()) => text_log.log(format!("Saved shader parameters to {}", SHADER_PARAMS_PATH)),
+                Err(error) => text_log.log(format!("Shader parameters: {}", error)),
+            },
+            Some(TuningCommand::Load) => match ShaderParams::load(SHADER_PARAMS_PATH) {
+                Ok(params) => {
+                    uniforms.shader_params = params;
+                    text_log.log(format!("Loaded shader parameters from {}", SHADER_PARAMS_PATH));
+                }
+                Err(error) => text_log.log(format!("Shader parameters: {}", error)),
+            },
+            None => {}
+        }
+        match session_command {
+            Some(MenuCommand::Save(slot)) => {
+                // Away from home the contracts wait there, their progress is what gets saved
+                let (next, hold) = home_trade.as_ref().unwrap_or(&trade).progress();
+                let session = Session {
+                    saved_at: session::unix_now(),
+                    system: galaxy.current,
+                    elapsed_days: clock.elapsed_days,
+                    rate: clock.rate,
+                    bodies: Session::capture_bodies(&planets),
+                    ship: ShipState::capture(&fleet[active_ship]),
+                    camera: [camera.eye.into(), camera.center.into(), camera.up.into()],
+                    bookmarks: camera_bookmarks.iter()
+                        .map(|bookmark| bookmark.map(|(eye, center)| [eye.into(), center.into()]))
+                        .collect(),
+                    cargo: mining_laser.cargo,
+                    trade: TradeState { credits: trade.credits, next, hold },
+                };
+                let path = session_menu.path(slot);
+                match session.save(&path) {
+                    Ok(()) => text_log.log(format!("Saved session to {}", path.display())),
+                    Err(error) => text_log.log(format!("Session save failed: {}", error)),
+                }
+                session_menu.refresh();
+            }
+            Some(MenuCommand::Load(_)) if jump.is_some() => text_log.log("Wait for the jump to finish"),
+            Some(MenuCommand::Load(slot)) => match Session::load(&session_menu.path(slot)) {
+                Ok(session) => {
+                    // A session saved in another system waits for the jump there
+                    if session.system != galaxy.current && session.system < galaxy.systems.len() {
+                        jump = Some(galaxy.jump(session.system, &scene, SCENE_PATH, &materials, quality.max_octaves));
+                        text_log.log(format!("Jumping to {}", galaxy.systems[session.system].name));
+                    }
+                    session_menu.open = false;
+                    pending_session = Some((slot, session));
+                }
+                Err(error) => text_log.log(format!("Session load failed: {}", error)),
+            },
+            None => {}
+        }
+        if let Some(target) = jump_target.filter(|_| jump.is_none()) {
+            jump = Some(galaxy.jump(target, &scene, SCENE_PATH, &materials, quality.max_octaves));
+            text_log.log(format!("Jumping to {}", galaxy.systems[target].name));
+        }
+
+        if let Some((slot, session)) = pending_session.take_if(|_| jump.is_none()) {
+            clock.elapsed_days = session.elapsed_days;
+            clock.rate = session.rate;
+            let missing = session.restore_bodies(&mut planets);
+            update_hierarchy(&mut planets);
+            update_barycenter(&mut planets, barycenter_scale);
+            event_predictor.refresh();
+
+            // The saved ship if the roster still has it, the escorts back in formation around it
+            if let Some(index) = fleet.iter().position(|ship| ship.name == session.ship.name) {
+                active_ship = index;
+            }
+            session.ship.apply(&mut fleet[active_ship]);
+            let leader = &fleet[active_ship];
+            for escort in escort_wing.escorts.iter_mut() {
+                escort.position = EscortWing::slot_position(&leader.position, leader.heading(), &escort.offset);
+                escort.velocity = Vec3::zeros();
+            }
+
+            let [eye, center, up] = session.camera;
+            camera.eye = eye.into();
+            camera.center = center.into();
+            camera.up = up.into();
+            camera.has_changed = true;
+            for (bookmark, saved) in camera_bookmarks.iter_mut().zip(&session.bookmarks) {
+                *bookmark = saved.map(|[eye, center]| (eye.into(), center.into()));
+            }
+            focus = None;
+            autopilot = None;
+
+            mining_laser.cargo = session.cargo;
+            mining_laser.target = None;
+            trade.credits = session.trade.credits;
+            home_trade.as_mut().unwrap_or(&mut trade).resume(session.trade.next, session.trade.hold);
+            text_log.log(format!("Loaded session {}", slot + 1));
+            if missing > 0 {
+                text_log.log(format!("{} saved bodies are not in the scene", missing));
+            }
+        }
+
+        // Buffers follow the window size, the projection is rebuilt below with its aspect ratio
+        let (width, height) = window.as_ref().map_or((framebuffer_width, framebuffer_height), |window| window.get_size());
+        if (width, height) != (framebuffer_width, framebuffer_height) && width > 0 && height > 0 {
+            (window_width, window_height) = (width, height);
+            (framebuffer_width, framebuffer_height) = (width, height);
+            display = Framebuffer::new(width, height);
+            hud = Framebuffer::layer(width, height);
+            framebuffer = create_scene_framebuffer(width, height, ssaa_factor, background);
+            if let Ok(mut recorder) = recorder.lock() {
+                recorder.resize(width, height);
+            }
+        }
+
+        // Supersampling: a bigger scene buffer averaged down to the window
+        // A quality tier sets everything it covers at once
+        let mut next_ssaa = None;
+        if bindings.pressed(&input, Action::NextQuality) {
+            quality = quality.tier.next().settings();
+            uniforms.quality = quality;
+            post_chain.set_enabled("bloom", quality.bloom);
+            next_ssaa = Some(quality.ssaa_factor);
+            if let Err(error) = scene.rebuild_noise(&mut planets, quality.max_octaves) {
+                text_log.log(error);
+            }
+            let belt_visible = asteroid_belt.as_ref().is_none_or(|belt| belt.visible);
+            asteroid_belt = scene.build_asteroid_belt(quality.particles);
+            mining_laser.target = None;
+            if let Some(belt) = asteroid_belt.as_mut() {
+                belt.visible = belt_visible;
+            }
+            text_log.log(format!("Quality {}", quality.tier.name()));
+        }
+        if bindings.pressed(&input, Action::NextAntialiasing) {
+            let index = SSAA_FACTORS.iter().position(|factor| *factor == ssaa_factor).unwrap_or(0);
+            next_ssaa = Some(SSAA_FACTORS[(index + 1) % SSAA_FACTORS.len()]);
+        }
+        if let Some(factor) = next_ssaa.filter(|factor| *factor != ssaa_factor) {
+            ssaa_factor = factor;
+            framebuffer = create_scene_framebuffer(framebuffer_width, framebuffer_height, ssaa_factor, background);
+            skybox.pixel_scale = ssaa_factor as f32;
+            text_log.log(if ssaa_factor == 1 {
+                "Anti-aliasing off".to_string()
+            } else {
+                format!("Anti-aliasing {}x{} SSAA", ssaa_factor, ssaa_factor)
+            });
+        }
         framebuffer.clear();
 
-        let current_mouse_position = window.get_mouse_pos(minifb::MouseMode::Discard).unwrap_or((0.0, 0.0));
-        let is_mouse_pressed = window.get_mouse_down(minifb::MouseButton::Left);
-        let simulated_scroll = 0.0; 
+        // Debug dump of every intermediate buffer of this frame
+        let mut frame_capture = bindings.pressed(&input, Action::DumpFrame)
+            .then(|| FrameCapture::begin(&mut framebuffer));
+
+        let current_mouse_position = window.as_ref()
+            .and_then(|window| window.get_mouse_pos(minifb::MouseMode::Discard))
+            .unwrap_or((0.0, 0.0));
+        let is_mouse_pressed = window.as_ref().is_some_and(|window| window.get_mouse_down(minifb::MouseButton::Left));
+        let scroll = window.as_ref().and_then(|window| window.get_scroll_wheel()).unwrap_or((0.0, 0.0));
+        // Ctrl as the window has it while scrolling: some platforms send a pinch
+        // as Ctrl + scroll without any key event for it
+        let scroll_ctrl = window.as_ref().is_some_and(|window| window.is_key_down(Key::LeftCtrl) || window.is_key_down(Key::RightCtrl));
 
         
-        handle_input(
-            &window,
-            &mut camera,
-            &mut spaceship,
-            is_mouse_pressed,
-            &mut last_mouse_position,
-            PhysicalPosition::new(current_mouse_position.0.into(), current_mouse_position.1.into()),
-            simulated_scroll,
-            &mut bird_eye_view_active,
-            default_camera_eye,
-            default_camera_center,
-        );
+        // Swap to the next ship of the roster where the current one is
+        if bindings.pressed(&input, Action::NextShip) && fleet.len() > 1 {
+            let next = (active_ship + 1) % fleet.len();
+            fleet[next].position = fleet[active_ship].position;
+            fleet[next].rotation = fleet[active_ship].rotation;
+            fleet[next].flight = fleet[active_ship].flight;
+            active_ship = next;
+            text_log.log(format!("Switched to ship {}", fleet[active_ship].name));
+        }
+        let spaceship = &mut fleet[active_ship];
+
+        // Bird eye view takes the camera away from the body it follows
+        if bindings.pressed(&input, Action::BirdEyeView) {
+            focus = None;
+        }
+
+        let controls = Controls {
+            input: &input,
+            bindings: &bindings,
+            mouse_pressed: is_mouse_pressed,
+            last_mouse_position: &mut last_mouse_position,
+            current_mouse_position: PhysicalPosition::new(current_mouse_position.0.into(), current_mouse_position.1.into()),
+            scroll,
+            scroll_ctrl,
+            touchpad: &touchpad,
+        };
+        let bird_eye_view = BirdEyeView { active: &mut bird_eye_view_active, eye: default_camera_eye, center: default_camera_center };
+        let switches = Switches {
+            accessibility: &mut accessibility,
+            shading_mode: &mut shading_mode,
+            texture_space_shading: &mut texture_space_shading,
+            back_face_culling: &mut back_face_culling,
+        };
+        handle_input(controls, &mut camera, spaceship, bird_eye_view, &mut text_log, &mut clock, switches);
+        // The autopilot flies until the player touches the controls or it arrives
+        let mut navigation = None;
+        if let Some(pilot) = autopilot.as_mut() {
+            if spaceship.controls != FlightControls::default() {
+                autopilot = None;
+                text_log.log("Autopilot off");
+            } else if let Some(planet) = planets.get(pilot.body) {
+                let (controls, readout) = pilot.steer(
+                    &spaceship.handling,
+                    &spaceship.flight,
+                    &spaceship.rotation,
+                    &spaceship.position,
+                    planet.get_position(),
+                    planet.radius * planet_bounding_radius,
+                );
+                spaceship.controls = controls;
+                if readout.arrived {
+                    text_log.log(format!("Arrived at {}", planet.name));
+                    autopilot = None;
+                } else {
+                    navigation = Some((planet.name.clone(), readout));
+                }
+            }
+        }
+        let pull = gravity.enabled.then(|| gravity.acceleration(&spaceship.position, &planets, planet_bounding_radius));
+        spaceship.fly(pull);
+        spaceship.emit_exhaust(&mut particles, quality.particles);
+
+        if bindings.pressed(&input, Action::ToggleDemo) {
+            if let Some(player) = demo.take() {
+                text_log.log(format!("Demo stopped: {}", player.summary()));
+            } else {
+                match DemoScript::load(DEMO_PATH) {
+                    Ok(script) => {
+                        demo = Some(DemoPlayer::new(script));
+                        focus = None;
+                        text_log.log(format!("Playing demo {}", DEMO_PATH));
+                    }
+                    Err(error) => text_log.log(format!("Demo failed: {}", error)),
+                }
+            }
+        }
+
+        // Scripted demo: camera path, captions and timescale, plus spawns here
+        let mut demo_finished = None;
+        if let Some(player) = demo.as_mut() {
+            for action in player.update(&mut camera, &mut clock) {
+                match action {
+                    ScriptAction::SpawnNebula(config) => nebulae.push(config.build()),
+                    ScriptAction::SpawnEscort(config) => {
+                        match scene.build_escort(&config, &spaceship.position, spaceship.heading()) {
+                            Ok(escort) => escort_wing.escorts.push(escort),
+                            Err(error) => text_log.log(format!("Demo escort: {}", error)),
+                        }
+                    }
+                    ScriptAction::End => demo_finished = Some(false),
+                    ScriptAction::Quit => demo_finished = Some(true),
+                    _ => {}
+                }
+            }
+        }
+        if let Some(quit) = demo_finished {
+            if let Some(player) = demo.take() {
+                let summary = player.summary();
+                println!("Demo finished: {}", summary);
+                text_log.log(format!("Demo finished: {}", summary));
+            }
+            if quit {
+                break;
+            }
+        }
+
+        // Los escoltas siguen a la nave activa
+        escort_wing.update(&spaceship.position, spaceship.heading());
+
+        if bindings.pressed(&input, Action::ToggleAsteroids) {
+            if let Some(belt) = asteroid_belt.as_mut() {
+                belt.visible = !belt.visible;
+                text_log.log(if belt.visible { "Asteroid belt on" } else { "Asteroid belt off" });
+            }
+        }
+        if bindings.pressed(&input, Action::DiffractionSpikes) {
+            skybox.diffraction_spikes = !skybox.diffraction_spikes;
+            text_log.log(if skybox.diffraction_spikes { "Diffraction spikes on" } else { "Diffraction spikes off" });
+        }
+        if bindings.pressed(&input, Action::ToggleRadar) {
+            radar.visible = !radar.visible;
+            text_log.log(if radar.visible { "Radar on" } else { "Radar off" });
+        }
+        if bindings.pressed(&input, Action::RadarRangeDown) {
+            radar.zoom(0.5);
+            text_log.log(format!("Radar range {}", radar.range));
+        }
+        if bindings.pressed(&input, Action::RadarRangeUp) {
+            radar.zoom(2.0);
+            text_log.log(format!("Radar range {}", radar.range));
+        }
+        if bindings.pressed(&input, Action::ToggleTimeline) {
+            event_predictor.visible = !event_predictor.visible;
+            text_log.log(if event_predictor.visible { "Event timeline on" } else { "Event timeline off" });
+        }
+        if bindings.pressed(&input, Action::ToggleHud) {
+            show_hud = !show_hud;
+            text_log.log(if show_hud { "HUD on" } else { "HUD off" });
+        }
+        if bindings.pressed(&input, Action::NextViewMode) {
+            uniforms.view_mode = uniforms.view_mode.next();
+            text_log.log(format!("View {}", uniforms.view_mode.name()));
+        }
+        if bindings.pressed(&input, Action::ToggleLabels) {
+            show_labels = !show_labels;
+            text_log.log(if show_labels { "Labels on" } else { "Labels off" });
+        }
+        if bindings.pressed(&input, Action::ToggleSolarWind) {
+            solar_wind.enabled = !solar_wind.enabled;
+            text_log.log(if solar_wind.enabled { "Solar wind on" } else { "Solar wind off" });
+        }
+        if bindings.pressed(&input, Action::TargetAsteroid) {
+            let forward = spaceship.rotation * Vec3::z();
+            let target = asteroid_belt.as_ref()
+                .filter(|belt| belt.visible)
+                .and_then(|belt| mining_laser.acquire(belt, &spaceship.nose(), &forward).map(|index| belt.asteroids[index].ore()));
+            match target {
+                Some(ore) => text_log.log(format!("Asteroid targeted, {:.0} t of ore", ore)),
+                None => text_log.log("No asteroid ahead"),
+            }
+        }
+        if bindings.pressed(&input, Action::ToggleGravity) {
+            gravity.enabled = !gravity.enabled;
+            text_log.log(if gravity.enabled { "Gravity on" } else { "Gravity off" });
+        }
+        if bindings.pressed(&input, Action::ToggleBloom) {
+            if let Some(enabled) = post_chain.toggle("bloom") {
+                text_log.log(if enabled { "Bloom on" } else { "Bloom off" });
+            }
+        }
+
+        // Black space, the solid color or the gradient, with the scene's colors
+        if bindings.pressed(&input, Action::NextBackground) {
+            background.mode = background.mode.next();
+            framebuffer.set_background(background);
+            uniforms.ambient = background.ambient_light().map(AmbientCube::uniform);
+            text_log.log(format!("Background {}", background.mode.name()));
+        }
+
+        // Fast-forward every orbit to the next predicted event
+        if bindings.pressed(&input, Action::JumpToEvent) {
+            match event_predictor.next_event(MIN_EVENT_JUMP_STEPS).cloned() {
+                Some(event) => {
+                    for planet in &mut planets {
+                        planet.update_position(event.steps);
+                    }
+                    if let Some(belt) = asteroid_belt.as_mut() {
+                        belt.update(event.steps);
+                    }
+                    clock.advance(event.steps);
+                    event_predictor.refresh();
+                    text_log.log(format!("Jumped {:.1} d to {}", event.steps * clock.days_per_step, event.describe()));
+                }
+                None => text_log.log("No upcoming events"),
+            }
+        }
+
+        // Camera bookmarks: one chord stores the current view, another goes back to it
+        for slot in 1..=BOOKMARK_COUNT {
+            let index = slot as usize - 1;
+            if bindings.pressed(&input, Action::SaveBookmark(slot)) {
+                camera_bookmarks[index] = Some((camera.eye, camera.center));
+                text_log.log(format!("Saved bookmark {}", slot));
+            }
+            if bindings.pressed(&input, Action::RecallBookmark(slot)) {
+                match camera_bookmarks[index] {
+                    Some((eye, center)) => {
+                        camera.eye = eye;
+                        camera.center = center;
+                        camera.has_changed = true;
+                        focus = None;
+                        text_log.log(format!("Bookmark {}", slot));
+                    }
+                    None => text_log.log(format!("Bookmark {} is empty", slot)),
+                }
+            }
+
+            // Fly to the nth planet, pressed again to the next of its moons
+            if bindings.pressed(&input, Action::FocusPlanet(slot)) {
+                let cycle = focus_cycle(&planets, slot);
+                let next = match focus.as_ref().and_then(|tracker| cycle.iter().position(|body| *body == tracker.body)) {
+                    Some(current) => cycle.get((current + 1) % cycle.len()),
+                    None => cycle.first(),
+                };
+                match next.map(|body| (*body, &planets[*body])) {
+                    Some((body, planet)) => {
+                        focus = Some(CameraFocus::new(
+                            &camera,
+                            body,
+                            planet.get_position(),
+                            planet.radius * planet_bounding_radius,
+                            accessibility.motion_amount() == 0.0,
+                        ));
+                        text_log.log(format!("Following {}", planet.name));
+                    }
+                    None => text_log.log(format!("No planet {}", slot)),
+                }
+            }
+        }
+        if bindings.pressed(&input, Action::ReleaseFocus) && focus.take().is_some() {
+            text_log.log("Camera released");
+        }
+        if bindings.pressed(&input, Action::Autopilot) {
+            if autopilot.take().is_some() {
+                text_log.log("Autopilot off");
+            } else {
+                match focus.as_ref().and_then(|tracker| planets.get(tracker.body).map(|planet| (tracker.body, planet))) {
+                    Some((body, planet)) => {
+                        autopilot = Some(Autopilot::new(body));
+                        text_log.log(format!("Autopilot to {}", planet.name));
+                    }
+                    None => text_log.log("Follow a planet first to pick the destination"),
+                }
+            }
+        }
+
+        // Write the scene as it is now, with the current view and ship, to a
+        // separate file so the commented original stays intact
+        if bindings.pressed(&input, Action::SaveScene) {
+            let mut snapshot = scene.clone();
+            snapshot.camera.eye = camera.eye.into();
+            snapshot.camera.center = camera.center.into();
+            snapshot.camera.up = camera.up.into();
+            snapshot.spaceship.ship = Some(spaceship.name.clone());
+            snapshot.spaceship.position = spaceship.position.into();
+            snapshot.spaceship.rotation = to_euler(&spaceship.rotation).into();
+            match snapshot.save(SAVED_SCENE_PATH) {
+                Ok(()) => text_log.log(format!("Saved scene to {}", SAVED_SCENE_PATH)),
+                Err(error) => text_log.log(format!("Scene save failed: {}", error)),
+            }
+        }
+
+        // Start or stop writing the system state for external analysis
+        if bindings.pressed(&input, Action::ToggleExport) {
+            if exporter.is_recording() {
+                match exporter.stop() {
+                    Ok(Some(path)) => text_log.log(format!("Exported state to {}", path.display())),
+                    Ok(None) => {}
+                    Err(error) => text_log.log(format!("State export failed: {}", error)),
+                }
+            } else {
+                match exporter.start(clock.elapsed_days) {
+                    Ok(path) => text_log.log(format!("Exporting state to {}", path.display())),
+                    Err(error) => text_log.log(format!("State export failed: {}", error)),
+                }
+            }
+        }
+
+        // A finished jump swaps the whole system in. Otherwise, at home, hot
+        // reload the scene file, camera and spaceship are left untouched
+        let arrival = match jump.as_ref().and_then(Jump::poll) {
+            Some(arrived) => jump.take().map(|jump| (jump.target, arrived)),
+            None => None,
+        };
+        let jumped = arrival.is_some();
+        let reloaded = match arrival {
+            Some((target, Ok((arrived, arrived_planets)))) => {
+                planets = arrived_planets;
+                galaxy.current = target;
+                Some(Ok(arrived))
+            }
+            Some((target, Err(error))) => {
+                text_log.log(format!("Jump to {} failed: {}", galaxy.systems[target].name, error));
+                pending_session = None;
+                None
+            }
+            None if galaxy.at_home() && scene_watcher.changed() => Some(
+                SceneConfig::load(scene_watcher.path())
+                    .and_then(|scene| scene.apply(&mut planets, &mut materials, quality.max_octaves).map(|_| scene))
+            ),
+            None => None,
+        };
+        if let Some(reloaded) = reloaded {
+            match reloaded {
+                Ok(reloaded) => {
+                    barycenter_scale = reloaded.barycenter_scale;
+                    clock.days_per_step = reloaded.days_per_step;
+                    nebulae = reloaded.build_nebulae();
+                    comets = reloaded.build_comets();
+                    solar_wind = reloaded.build_solar_wind(SOLAR_WIND_CAPACITY);
+                    let cargo = mining_laser.cargo;
+                    mining_laser = reloaded.build_mining_laser();
+                    mining_laser.cargo = cargo;
+                    match reloaded.build_trade() {
+                        Ok(reloaded_trade) => {
+                            // The home contracts wait there while the ship is away
+                            let credits = trade.credits;
+                            let left = std::mem::replace(&mut trade, reloaded_trade);
+                            match (jumped, galaxy.at_home()) {
+                                (true, true) => trade = home_trade.take().unwrap_or(trade),
+                                (true, false) => home_trade = home_trade.take().or(Some(left)),
+                                _ => {}
+                            }
+                            trade.credits = credits;
+                        }
+                        Err(error) => text_log.log(error),
+                    }
+                    let belt_visible = asteroid_belt.as_ref().is_none_or(|belt| belt.visible);
+                    asteroid_belt = reloaded.build_asteroid_belt(quality.particles);
+                    reloaded.configure_post_chain(&mut post_chain);
+                    if quality_flag.is_some() {
+                        post_chain.set_enabled("bloom", quality.bloom);
+                    }
+                    for name in post_chain.missing() {
+                        text_log.log(format!("No post effect named {}", name));
+                    }
+                    gravity = reloaded.gravity.gravity();
+                    depth_range = reloaded.depth.depth_range();
+                    uniforms.sunlight = reloaded.sunlight.falloff();
+                    planetshine_settings = reloaded.planetshine.settings();
+                    pass_scale = reloaded.pass_scale.clone();
+                    light_probes = reloaded.probes.light_probes(&skybox);
+                    match reloaded.background.background() {
+                        Ok(reloaded_background) => {
+                            background = reloaded_background;
+                            framebuffer.set_background(background);
+                            uniforms.ambient = background.ambient_light().map(AmbientCube::uniform);
+                        }
+                        Err(error) => text_log.log(error),
+                    }
+                    if let Some(belt) = asteroid_belt.as_mut() {
+                        belt.visible = belt_visible;
+                    }
+                    if jumped {
+                        // Arriving at a new system: the ship where the scene starts it, the view reset
+                        station = reloaded.build_station().unwrap_or_else(|error| {
+                            text_log.log(error);
+                            None
+                        });
+                        docking_computer = DockingComputer::new(reloaded.station.as_ref().map_or(0.0, |config| config.approach_range));
+                        docking_state = None;
+                        spaceship.position = Vec3::from(reloaded.spaceship.position);
+                        spaceship.rotation = from_euler(&Vec3::from(reloaded.spaceship.rotation));
+                        spaceship.flight = Default::default();
+                        for escort in escort_wing.escorts.iter_mut() {
+                            escort.position = EscortWing::slot_position(&spaceship.position, spaceship.heading(), &escort.offset);
+                            escort.velocity = Vec3::zeros();
+                        }
+                        camera.eye = Vec3::from(reloaded.camera.eye);
+                        camera.center = Vec3::from(reloaded.camera.center);
+                        camera.up = Vec3::from(reloaded.camera.up);
+                        camera.has_changed = true;
+                        focus = None;
+                        autopilot = None;
+                        mining_laser.target = None;
+                        update_hierarchy(&mut planets);
+                        update_barycenter(&mut planets, barycenter_scale);
+                        event_predictor.refresh();
+                        text_log.log(format!("Arrived at {}", galaxy.systems[galaxy.current].name));
+                    } else {
+                        text_log.log(format!("Reloaded {}", scene_watcher.path()));
+                    }
+                    scene = reloaded;
+                }
+                Err(error) => text_log.log(format!("Scene reload failed: {}", error)),
+            }
+        }
+
+        // Avanzar las órbitas, colocar las lunas y mover el sol alrededor del baricentro.
+        // Antes de la vista, así la cámara que sigue a un planeta lo ve donde se dibuja
+        // Los planetas y las rocas del cinturón no dependen unos de otros
+        let orbit_steps = clock.tick(|steps| accessibility.effective_time_scale(steps));
+        let mut update_jobs = JobGraph::default();
+        update_jobs
+            .add("orbits", &[], || {
+                for planet in planets.iter_mut() {
+                    planet.update_position(orbit_steps);
+                }
+                update_hierarchy(&mut planets);
+                update_barycenter(&mut planets, barycenter_scale);
+            })
+            .add("belt", &[], || {
+                if let Some(belt) = asteroid_belt.as_mut() {
+                    belt.update(orbit_steps);
+                }
+            });
+        if let Err(error) = update_jobs.run() {
+            text_log.log(&error);
+        }
+
+        // Mining beam on the rocks where they are this frame
+        match asteroid_belt.as_mut().filter(|belt| belt.visible) {
+            Some(belt) => {
+                let firing = bindings.down(&input, Action::MiningBeam);
+                if let Some(tons) = mining_laser.update(belt, &spaceship.nose(), firing, &mut particles, quality.particles) {
+                    text_log.log(format!("Asteroid mined out, {:.0} t", tons));
+                }
+            }
+            None => mining_laser.target = None,
+        }
+        event_predictor.update(&planets, clock.days_per_step, orbit_steps);
+        if let Err(error) = exporter.update(&planets, &spaceship.name, spaceship.position, clock.elapsed_days, clock.days_per_step) {
+            text_log.log(format!("State export failed: {}", error));
+            let _ = exporter.stop();
+        }
+
+        // A focused body keeps the camera on it as it orbits
+        if let Some(tracker) = focus.as_mut() {
+            if let Some(planet) = planets.get(tracker.body) {
+                tracker.update(&mut camera, planet.get_position(), clock.frame_seconds);
+            }
+        }
 
         //print camera position
         //println!("Camera position: {:?}", camera.eye);
         //println!("Camera center: {:?}", camera.center);
         
         let view_matrix = create_view_matrix(camera.eye, camera.center, camera.up);
-        
-        skybox.render(&mut framebuffer, &uniforms, camera.eye);
+        uniforms.view_matrix = view_matrix;
+        uniforms.camera_position = camera.eye;
+
+        // Empty space reads as near absolute zero in the thermal view
+        if uniforms.view_mode == ViewMode::Visible {
+            skybox.render(&mut framebuffer, &uniforms, camera.eye);
+        }
+        if let Some(capture) = frame_capture.as_mut() {
+            capture.stage("sky", &framebuffer);
+        }
 
         uniforms.model_matrix = create_model_matrix(translation, scale, rotation);
-        uniforms.view_matrix = create_view_matrix(camera.eye, camera.center, camera.up);
+        time = time.wrapping_add(1);
         uniforms.time = time;
+        uniforms.accessibility = accessibility;
         framebuffer.set_current_color(0xFFDDDD);
 
-         // Renderizar los planetas
-         for planet in &mut planets {
-            planet.update_position();
-            let model_matrix = create_model_matrix(planet.get_position(), planet.radius, rotation);
+        let sun_position = planets.iter()
+            .find(|planet| planet.semi_major_axis == 0.0)
+            .map(|planet| planet.get_position())
+            .unwrap_or(Vec3::new(0.0, 0.0, 0.0));
+
+        // Ambient probes ride on every body but the star, and on the station
+        let sun_color = planets.iter()
+            .find(|planet| planet.shader.is_star())
+            .map_or(Color::new(255, 255, 255), |planet| Color::from_hex(planet.color));
+        let probe_positions: Vec<Vec3> = planets.iter()
+            .filter(|planet| !planet.shader.is_star())
+            .map(|planet| planet.get_position())
+            .chain(station.as_ref().map(|station| station.position))
+            .collect();
+
+        // The frame's updates, the depth range and planetshine, as jobs: the
+        // ones that don't wait for each other run side by side
+        let side_frustum = Frustum::from_matrix(&(projection_matrix * view_matrix), depth_range.reversed);
+        let forward = (camera.center - camera.eye).normalize();
+        let spaceship_radius = ship_radii[active_ship];
+        let mut planetshine: Vec<Option<Planetshine>> = Vec::new();
+        let mut frame_jobs = JobGraph::default();
+        frame_jobs
+            .add("probes", &[], || light_probes.update(&probe_positions, &sun_position, sun_color, &uniforms.sunlight))
+            // Comet tails and engine exhaust, moved one frame and the comets' new ones added
+            .add("particles", &[], || {
+                particles.update();
+                for comet in comets.iter_mut() {
+                    comet.update(&planets, &sun_position, &mut particles, quality.particles);
+                }
+            })
+            .add("solar wind", &[], || solar_wind.update(&planets, &sun_position, planet_bounding_radius, quality.particles))
+            // Near and far around what is in view, the side planes don't depend on them
+            .add("depth range", &[], || {
+                let bounds = planets.iter()
+                    .map(|planet| (planet.get_position(), planet.bounding_radius(planet_bounding_radius)))
+                    .chain(std::iter::once((spaceship.position, spaceship_radius)))
+                    .chain(escort_wing.escorts.iter().map(|escort| (escort.position, ship_radii[escort.ship])))
+                    .chain(station.as_ref().map(|station| (station.position, station.bounding_radius())))
+                    .chain(nebulae.iter().map(|nebula| (nebula.center, nebula.radii.max())))
+                    .chain(asteroid_belt.iter().filter(|belt| belt.visible).flat_map(|belt| belt.bounding_spheres()));
+                depth_range.fit(&camera.eye, &forward, &side_frustum, bounds);
+            })
+            // Brightest neighbour of each body, the sun lights them directly instead
+            .add("planetshine", &[], || {
+                planetshine = planets.iter()
+                    .enumerate()
+                    .map(|(index, receiver)| {
+                        let sources = planets.iter()
+                            .enumerate()
+                            .filter(|(other, body)| *other != index && !body.shader.is_star())
+                            .map(|(_, body)| (body.get_position(), body.radius * planet_bounding_radius, body.color));
+                        planetshine_settings.brightest(receiver.get_position(), sources, sun_position, &uniforms.sunlight)
+                    })
+                    .collect();
+            });
+        if let Err(error) = frame_jobs.run() {
+            text_log.log(&error);
+        }
+        projection_matrix = create_perspective_matrix(window_width as f32, window_height as f32, &depth_range);
+        viewport_matrix = create_viewport_matrix(framebuffer.width as f32, framebuffer.height as f32, &depth_range);
+        // Objects remember where they were drawn while an effect reads motion vectors
+        motion_tracker.enabled = post_chain.uses_motion();
+        motion_tracker.begin_frame(viewport_matrix * projection_matrix * view_matrix);
+        framebuffer.track_motion(motion_tracker.enabled);
+        // Temporal AA renders the scene a fraction of a pixel off every frame
+        let jitter = post_chain.jitter() * ssaa_factor as f32;
+        viewport_matrix[(0, 3)] += jitter.x;
+        viewport_matrix[(1, 3)] += jitter.y;
+        uniforms.projection_matrix = projection_matrix;
+        uniforms.viewport_matrix = viewport_matrix;
+        uniforms.depth_range = depth_range;
+
+        // Skip objects whose bounding sphere is outside the view volume
+        let frustum = Frustum::from_matrix(&(projection_matrix * view_matrix), depth_range.reversed);
+
+        // Culling and placement of what gets drawn, one job per kind of object:
+        // the model matrix of each one in view, None for the rest
+        let mut planet_draws: Vec<Option<Mat4>> = Vec::new();
+        let mut ship_draws: (Option<Mat4>, Vec<Option<Mat4>>) = (None, Vec::new());
+        let mut station_draw: Option<Mat4> = None;
+        let mut draw_jobs = JobGraph::default();
+        draw_jobs
+            .add("planets", &[], || {
+                planet_draws = planets.iter()
+                    .map(|planet| {
+                        frustum.contains_sphere(&planet.get_position(), planet.bounding_radius(planet_bounding_radius))
+                            .then(|| create_model_matrix(planet.get_position(), planet.radius, rotation * planet.get_rotation()))
+                    })
+                    .collect();
+            })
+            .add("ships", &[], || {
+                ship_draws.0 = frustum.contains_sphere(&spaceship.position, spaceship_radius)
+                    .then(|| spaceship.get_model_matrix());
+                ship_draws.1 = escort_wing.escorts.iter()
+                    .map(|escort| {
+                        frustum.contains_sphere(&escort.position, ship_radii[escort.ship])
+                            .then(|| create_model_matrix(escort.position, ship_scales[escort.ship], yaw(escort.heading)))
+                    })
+                    .collect();
+            })
+            .add("station", &[], || {
+                station_draw = station.as_ref()
+                    .filter(|station| frustum.contains_sphere(&station.position, station.bounding_radius()))
+                    .map(|station| station.get_model_matrix());
+            });
+        if let Err(error) = draw_jobs.run() {
+            text_log.log(&error);
+        }
+        let (spaceship_draw, escort_draws) = ship_draws;
+
+         // Renderizar los planetas, cada esfera con el detalle que pide su tamaño en pantalla
+         let pixels_per_unit = framebuffer.height as f32 / 2.0 * projection_matrix[(1, 1)];
+         for ((planet, planetshine), model_matrix) in planets.iter_mut().zip(planetshine).zip(planet_draws) {
+            let Some(model_matrix) = model_matrix else {
+                continue;
+            };
 
             let uniforms = Uniforms {
                 model_matrix,
                 view_matrix,
+                camera_position: uniforms.camera_position,
                 projection_matrix,
                 viewport_matrix,
                 time,
-                noise: create_noise().into(),
+                noise: Arc::clone(&planet.noise),
+                accessibility,
+                quality,
+                view_mode: uniforms.view_mode,
+                light_position: sun_position,
+                rings: planet.rings,
+                atmosphere: planet.atmosphere,
+                clouds: planet.clouds,
+                damage: None,
+                material: planet.material.clone(),
+                depth_range,
+                sunlight: uniforms.sunlight,
+                planetshine,
+                ambient: light_probes.ambient(&planet.get_position(), uniforms.ambient),
+                shading_scale: 1,
+                shader_params: uniforms.shader_params,
+                motion: motion_tracker.transform(&planet.name, &model_matrix),
             };
 
+            if texture_space_shading {
+                planet.surface_cache
+                    .get_or_insert_with(|| SurfaceCache::new(256, 128, 4))
+                    .update(&uniforms, planet.shader.as_ref());
+            } else {
+                planet.surface_cache = None;
+            }
+
+            let distance = (planet.get_position() - camera.eye).magnitude();
+            let mesh = planet.mesh_for(&planet_sphere, pixels_per_unit, distance, quality.mesh_error);
             render(
                 &mut framebuffer,
                 &uniforms,
-                &planet_obj.get_vertex_array(),
-                planet.shader_index,
+                mesh,
+                planet.shader.as_ref(),
+                planet.shading_mode.unwrap_or(shading_mode),
+                planet.surface_cache.as_ref(),
+                back_face_culling && planet.cull_back_faces,
             );
+
+            // Rings after the planet, both faces visible and see-through by their density
+            if let Some(ring_mesh) = &planet.ring_mesh {
+                let ring_uniforms = Uniforms {
+                    material: Material { blend: BlendMode::Alpha, ..uniforms.material.clone() },
+                    ..uniforms.clone()
+                };
+                render(
+                    &mut framebuffer,
+                    &ring_uniforms,
+                    ring_mesh,
+                    ring_shader.as_ref(),
+                    ShadingMode::PerFragment,
+                    None,
+                    false,
+                );
+            }
+
+            // Clouds on their own sphere between the surface and the air,
+            // turning at their own speed
+            if let Some(clouds) = planet.clouds {
+                let cloud_matrix = create_model_matrix(planet.get_position(), planet.radius * (1.0 + clouds.height), rotation * planet.cloud_rotation(&clouds));
+                let cloud_uniforms = Uniforms {
+                    model_matrix: cloud_matrix,
+                    material: Material { blend: BlendMode::Alpha, ..Material::default() },
+                    motion: motion_tracker.transform(&format!("{} clouds", planet.name), &cloud_matrix),
+                    ..uniforms.clone()
+                };
+                render(
+                    &mut framebuffer,
+                    &cloud_uniforms,
+                    mesh,
+                    cloud_shader.as_ref(),
+                    ShadingMode::PerFragment,
+                    None,
+                    true,
+                );
+            }
+
+            // Air as a slightly bigger sphere over the surface, only its near side
+            if let Some(atmosphere) = planet.atmosphere {
+                let shell_uniforms = Uniforms {
+                    model_matrix: create_model_matrix(planet.get_position(), planet.radius * (1.0 + atmosphere.thickness), rotation * planet.get_rotation()),
+                    material: Material { blend: atmosphere.blend, ..Material::default() },
+                    shading_scale: pass_scale.atmospheres,
+                    ..uniforms.clone()
+                };
+                render(
+                    &mut framebuffer,
+                    &shell_uniforms,
+                    mesh,
+                    atmosphere_shader.as_ref(),
+                    ShadingMode::PerFragment,
+                    None,
+                    true,
+                );
+            }
+        }
+
+        if let Some(capture) = frame_capture.as_mut() {
+            capture.stage("planets", &framebuffer);
+        }
+
+        // Nearby rocks are mesh instances, distant ones single points
+        if let Some(belt) = asteroid_belt.as_ref().filter(|belt| belt.visible) {
+            let (instances, sprites) = belt.visible_instances(&frustum, camera.eye, pixels_per_unit);
+            // The rocks drawn as meshes are the ones near the camera, they take its ambient
+            let belt_uniforms = Uniforms {
+                light_position: sun_position,
+                rings: None,
+                damage: None,
+                ambient: light_probes.ambient(&camera.eye, uniforms.ambient),
+                motion: None, // Las rocas siguen a la cámara al reproyectar
+                ..uniforms.clone()
+            };
+            render_instances(&mut framebuffer, &belt_uniforms, &belt.mesh, &instances, asteroid_shader.as_ref(), ShadingMode::Flat, back_face_culling);
+            belt.render_sprites(&mut framebuffer, &belt_uniforms, &sprites);
+            if let Some(capture) = frame_capture.as_mut() {
+                capture.stage("asteroids", &framebuffer);
+            }
+        }
+
+        // Report when the spaceship enters or leaves a planet
+        let collision = planets.iter()
+            .find(|planet| nalgebra_glm::distance(&planet.get_position(), &spaceship.position) < planet.radius * 0.5)
+            .map(|planet| planet.name.clone());
+        if collision != colliding_planet {
+            if let Some(planet) = collision.as_ref().and_then(|name| planets.iter().find(|planet| &planet.name == name)) {
+                spaceship.take_hit(&planet.get_position(), 0.9, 0.8);
+            }
+            match &collision {
+                Some(name) => text_log.log(format!("Collision with {}", name)),
+                None => text_log.log("Spaceship clear"),
+            }
+            colliding_planet = collision;
         }
 
         // Renderizar la nave espacial
-        let spaceship_uniforms = Uniforms {
-            model_matrix: spaceship.get_model_matrix(),
-            view_matrix,
-            projection_matrix,
-            viewport_matrix,
-            time,
-            noise: create_noise().into(),
-        };
+        // Rocks grazing the hull leave small marks that build up over time
+        if let Some(belt) = asteroid_belt.as_ref() {
+            for rock in belt.touching(&spaceship.position, spaceship_radius) {
+                spaceship.take_hit(&rock, 0.35, 0.05);
+            }
+        }
+        if let Some(model_matrix) = spaceship_draw {
+            let spaceship_uniforms = Uniforms {
+                model_matrix,
+                view_matrix,
+                camera_position: uniforms.camera_position,
+                projection_matrix,
+                viewport_matrix,
+                time,
+                noise: Arc::clone(&default_noise),
+                accessibility,
+                quality,
+                view_mode: uniforms.view_mode,
+                light_position: sun_position,
+                rings: None,
+                atmosphere: None,
+                clouds: None,
+                damage: Some(Arc::clone(&spaceship.damage)),
+                material: spaceship.material.clone(),
+                depth_range,
+                sunlight: uniforms.sunlight,
+                planetshine: None,
+                ambient: light_probes.ambient(&spaceship.position, uniforms.ambient),
+                shading_scale: 1,
+                shader_params: uniforms.shader_params,
+                motion: motion_tracker.transform(&spaceship.name, &model_matrix),
+            };
+
+            render(
+                &mut framebuffer,
+                &spaceship_uniforms,
+                spaceship.mesh.select(pixels_per_unit * spaceship.scale / (spaceship.position - camera.eye).magnitude().max(1e-3), quality.mesh_error),
+                spaceship.shader.as_ref(),
+                spaceship.shading_mode.unwrap_or(shading_mode),
+                None,
+                back_face_culling && spaceship.cull_back_faces,
+            );
+        }
+
+        // Wingmen reuse the roster meshes, without damage of their own
+        let spaceship = &fleet[active_ship];
+        for ((index, escort), model_matrix) in escort_wing.escorts.iter().enumerate().zip(escort_draws) {
+            let Some(model_matrix) = model_matrix else {
+                continue;
+            };
+            let ship = &fleet[escort.ship];
+            let escort_uniforms = Uniforms {
+                model_matrix,
+                noise: Arc::clone(&default_noise),
+                light_position: sun_position,
+                rings: None,
+                damage: None,
+                material: ship.material.clone(),
+                ambient: light_probes.ambient(&escort.position, uniforms.ambient),
+                motion: motion_tracker.transform(&format!("escort {}", index), &model_matrix),
+                ..uniforms.clone()
+            };
+
+            render(
+                &mut framebuffer,
+                &escort_uniforms,
+                ship.mesh.select(pixels_per_unit * ship.scale / (escort.position - camera.eye).magnitude().max(1e-3), quality.mesh_error),
+                ship.shader.as_ref(),
+                ship.shading_mode.unwrap_or(shading_mode),
+                None,
+                back_face_culling && ship.cull_back_faces,
+            );
+        }
+
+        if let Some(station) = station.as_ref() {
+            if let Some(model_matrix) = station_draw {
+                let station_uniforms = Uniforms {
+                    model_matrix,
+                    noise: Arc::clone(&default_noise),
+                    light_position: sun_position,
+                    rings: None,
+                    damage: None,
+                    ambient: light_probes.ambient(&station.position, uniforms.ambient),
+                    motion: motion_tracker.transform("station", &model_matrix),
+                    ..uniforms.clone()
+                };
+                let mesh = station.mesh.select(pixels_per_unit * station.scale / (station.position - camera.eye).magnitude().max(1e-3), quality.mesh_error);
+                render(&mut framebuffer, &station_uniforms, mesh, station.shader.as_ref(), shading_mode, None, back_face_culling);
+            }
+        }
 
-        render(
-            &mut framebuffer,
-            &spaceship_uniforms,
-            &spaceship.model.get_vertex_array(),
-            spaceship.shader_index,
-        );
+        if let Some(capture) = frame_capture.as_mut() {
+            capture.stage("ships", &framebuffer);
+        }
+
+        // Rings and atmospheres of the whole frame, sorted back to front
+        framebuffer.resolve_translucent();
+        if let Some(capture) = frame_capture.as_mut() {
+            capture.stage("translucent", &framebuffer);
+        }
+
+        // Glowing sprites over the opaque geometry, hidden behind it
+        particles.render(&mut framebuffer, &uniforms);
+        for comet in &comets {
+            comet.render_coma(&mut framebuffer, &uniforms, &planets);
+        }
+        solar_wind.render(&mut framebuffer, &uniforms, &planets, &sun_position, planet_bounding_radius);
+        mining_laser.render_beam(&mut framebuffer, &uniforms, &spaceship.nose());
+        trade.render_zone(&mut framebuffer, &uniforms, &planets, planet_bounding_radius);
+        if let Some(capture) = frame_capture.as_mut() {
+            capture.stage("particles", &framebuffer);
+        }
+
+        // Fog goes over the opaque geometry, limited by the depth buffer
+        for nebula in &nebulae {
+            nebula.render(&mut framebuffer, &uniforms, camera.eye, pass_scale.nebulae);
+        }
+        if let Some(capture) = frame_capture.as_mut() {
+            capture.stage("nebulae", &framebuffer);
+        }
 
-        window
-            .update_with_buffer(&framebuffer.buffer, framebuffer_width, framebuffer_height)
-            .unwrap();
+        resolve_supersampled(&framebuffer, &mut display);
+
+        // Glow and the other full-screen effects over the finished scene, the HUD stays sharp on top
+        let display_camera = create_viewport_matrix(display.width as f32, display.height as f32, &depth_range) * projection_matrix * view_matrix;
+        let depth = SceneDepth::new(&framebuffer, display.width, display_camera, depth_range.far_depth());
+        post_chain.apply(&mut display, &depth, frame_capture.as_mut());
+
+        // The HUD projects onto a still screen
+        uniforms.viewport_matrix = create_viewport_matrix(framebuffer.width as f32, framebuffer.height as f32, &depth_range);
+
+        // The scene alone, before the HUD goes over it
+        if bindings.pressed(&input, Action::Screenshot) {
+            match screenshot(&display) {
+                Ok(path) => text_log.log(format!("Saved screenshot to {}", path.display())),
+                Err(error) => text_log.log(format!("Screenshot failed: {}", error)),
+            }
+        }
+
+        // Everything below draws on its own layer, composited at the end
+        hud.clear();
+
+        // Body names, dimmed for the ones hidden behind others in the depth buffer
+        if show_labels {
+            let bodies = planets.iter().map(|planet| (planet.name.as_str(), planet.get_position(), planet.radius * planet_bounding_radius));
+            labels::render(&mut hud, &framebuffer, &uniforms, bodies);
+        }
+
+        // Radar blips: the sun, planets and moons, the escorts and the station
+        radar.update(accessibility.motion_amount());
+        let blips: Vec<(Vec3, BlipKind)> = planets.iter()
+            .map(|planet| {
+                let kind = if planet.semi_major_axis == 0.0 { BlipKind::Star } else { BlipKind::Planet };
+                (planet.get_position(), kind)
+            })
+            .chain(escort_wing.escorts.iter().map(|escort| (escort.position, BlipKind::Ship)))
+            .chain(station.as_ref().map(|station| (station.position, BlipKind::Station)))
+            .collect();
+        radar.render(&mut hud, &spaceship.position, spaceship.heading(), &blips, accessibility.motion_amount() > 0.0);
+
+        // Docking guidance while close to the station's port
+        if let Some(station) = station.as_ref() {
+            let readout = docking_computer.update(station, &spaceship.position, spaceship.heading());
+            let state = readout.as_ref().map(|readout| readout.docked());
+            if state != docking_state {
+                match state {
+                    Some(true) => text_log.log(format!("Docked at {}", station.name)),
+                    Some(false) if docking_state.is_none() => text_log.log(format!("Docking approach to {}", station.name)),
+                    Some(false) => text_log.log(format!("Undocked from {}", station.name)),
+                    None => text_log.log(format!("Left {} approach", station.name)),
+                }
+                docking_state = state;
+            }
+            if let Some(readout) = readout {
+                let display_uniforms = Uniforms {
+                    viewport_matrix: create_viewport_matrix(hud.width as f32, hud.height as f32, &depth_range),
+                    ..uniforms.clone()
+                };
+                let port_visible = labels::sight(&framebuffer, &uniforms, &station.port_position(), PORT_CLEARANCE)
+                    .map_or(1.0, |sighting| sighting.visible);
+                docking::render(&mut hud, &display_uniforms, station, &readout, port_visible);
+            }
+        }
+
+        // Selling and loading while docked, deliveries on reaching a zone
+        for message in trade.update(docking_state == Some(true), &mut mining_laser.cargo, &spaceship.position, &planets, planet_bounding_radius) {
+            text_log.log(message);
+        }
+        trade.render(&mut hud, &spaceship.position, &planets, planet_bounding_radius);
+
+        if let Some((name, readout)) = navigation.as_ref() {
+            autopilot::render(&mut hud, name, readout, clock.frame_seconds);
+        }
+        if let Some(belt) = asteroid_belt.as_ref().filter(|belt| belt.visible) {
+            let display_uniforms = Uniforms {
+                viewport_matrix: create_viewport_matrix(hud.width as f32, hud.height as f32, &depth_range),
+                ..uniforms.clone()
+            };
+            mining_laser.render(&mut hud, &display_uniforms, belt, &spaceship.nose());
+        }
+
+        // Simulation speed and elapsed simulated time
+        draw_text(&mut hud, 4, 4, &format!("{}  T+{:.1} d", clock.label(), clock.elapsed_days), 0xDDDDDD, 2);
+        event_predictor.render(&mut hud, clock.days_per_step);
+        if let Some(player) = demo.as_ref() {
+            player.render(&mut hud);
+        }
+        text_log.render(&mut hud);
+        session_menu.render(&mut hud);
+        galaxy_map.render(&mut hud, &galaxy);
+        tuning_panel.render(&mut hud, &uniforms.shader_params);
+
+        if show_hud {
+            hud.composite(&mut display);
+        }
+        if let Some(mut capture) = frame_capture.take() {
+            capture.stage("hud_layer", &hud);
+            capture.stage("hud", &display);
+            match capture.finish(&mut framebuffer) {
+                Ok(dir) => text_log.log(format!("Saved frame buffers to {}", dir.display())),
+                Err(error) => text_log.log(format!("Frame dump failed: {}", error)),
+            }
+        }
+
+        let state = describe_state(time, &camera, spaceship, &planets);
+        crash::update_context(state.clone(), text_log.entries().cloned().collect());
+        if let Ok(mut recorder) = recorder.lock() {
+            recorder.record(&display.buffer, state);
+            if bindings.pressed(&input, Action::Capture) {
+                match recorder.dump("manual capture") {
+                    Ok(dir) => text_log.log(format!("Saved capture to {}", dir.display())),
+                    Err(error) => text_log.log(format!("Capture failed: {}", error)),
+                }
+            }
+        }
+
+        if let Some(window) = window.as_mut() {
+            window
+                .update_with_buffer(&display.buffer, framebuffer_width, framebuffer_height)
+                .unwrap();
+        }
+
+        if let Some(export) = animation.as_mut() {
+            if let Err(error) = export.write_frame(&display) {
+                panic!("Failed to record frame {}", error);
+            }
+            if let Some(progress) = export.progress() {
+                println!("{}", progress);
+            }
+            if export.is_finished() {
+                println!("Animation saved to {}", export.directory.display());
+                println!("{}", export.ffmpeg_hint());
+                break;
+            }
+        }
     }
 }
 
 
+// Keys, mouse buttons and wheel as they stand this frame
+struct Controls<'a> {
+    input: &'a Input,
+    bindings: &'a KeyBindings,
+    mouse_pressed: bool,
+    last_mouse_position: &'a mut PhysicalPosition<f64>,
+    current_mouse_position: PhysicalPosition<f64>,
+    scroll: (f32, f32),
+    scroll_ctrl: bool, // Ctrl según la ventana al girar la rueda, el pellizco del touchpad
+    touchpad: &'a Touchpad,
+}
+
+// Looking down on the whole system from where the camera started
+struct BirdEyeView<'a> {
+    active: &'a mut bool,
+    eye: Vec3,    // Posición inicial de la cámara
+    center: Vec3, // Centro inicial de la cámara
+}
+
+// What the keyboard switches on and off: accessibility and how everything is drawn
+struct Switches<'a> {
+    accessibility: &'a mut AccessibilitySettings,
+    shading_mode: &'a mut ShadingMode,
+    texture_space_shading: &'a mut bool,
+    back_face_culling: &'a mut bool,
+}
+
 fn handle_input(
-    window: &Window, 
+    controls: Controls,
     camera: &mut Camera, 
     spaceship: &mut Spaceship,
-    mouse_pressed: bool,
-    last_mouse_position: &mut PhysicalPosition<f64>,
-    current_mouse_position: PhysicalPosition<f64>,
-    scroll_delta: f32,
-    bird_eye_view_active: &mut bool, // Nuevo parámetro para saber si la vista de pájaro está activa
-    default_camera_eye: Vec3,       // Posición inicial de la cámara
-    default_camera_center: Vec3,   // Centro inicial de la cámara
+    bird_eye_view: BirdEyeView,
+    text_log: &mut TextLog,
+    clock: &mut SimulationClock,
+    switches: Switches,
 ) {
+    let Controls { input, bindings, mouse_pressed, last_mouse_position, current_mouse_position, scroll, scroll_ctrl, touchpad } = controls;
+    let BirdEyeView { active: bird_eye_view_active, eye: default_camera_eye, center: default_camera_center } = bird_eye_view;
+    let Switches { accessibility, shading_mode, texture_space_shading, back_face_culling } = switches;
 
     let movement_speed = 0.90;
     let rotation_speed = PI/60.0;
+    let fast_orbit_factor = 4.0;
     let zoom_speed = 0.1;
     let mouse_sensitivity = 0.005; 
 
     //  camera orbit controls
-    if window.is_key_down(Key::Left) {
+    if bindings.down(input, Action::OrbitLeft) {
         camera.orbit(rotation_speed, 0.0);
     }
-    if window.is_key_down(Key::Right) {
+    if bindings.down(input, Action::OrbitRight) {
         camera.orbit(-rotation_speed, 0.0);
     }
-    if window.is_key_down(Key::W) {
+    if bindings.down(input, Action::OrbitUp) {
         camera.orbit(0.0, -rotation_speed);
     }
-    if window.is_key_down(Key::S) {
+    if bindings.down(input, Action::OrbitDown) {
         camera.orbit(0.0, rotation_speed);
     }
+    if bindings.down(input, Action::FastOrbitLeft) {
+        camera.orbit(rotation_speed * fast_orbit_factor, 0.0);
+    }
+    if bindings.down(input, Action::FastOrbitRight) {
+        camera.orbit(-rotation_speed * fast_orbit_factor, 0.0);
+    }
+    if bindings.down(input, Action::FastOrbitUp) {
+        camera.orbit(0.0, -rotation_speed * fast_orbit_factor);
+    }
+    if bindings.down(input, Action::FastOrbitDown) {
+        camera.orbit(0.0, rotation_speed * fast_orbit_factor);
+    }
 
     // Camera movement controls
     let mut movement = Vec3::new(0.0, 0.0, 0.0);
-    if window.is_key_down(Key::A) {
+    if bindings.down(input, Action::PanLeft) {
         movement.x -= movement_speed;
     }
-    if window.is_key_down(Key::D) {
+    if bindings.down(input, Action::PanRight) {
         movement.x += movement_speed;
     }
-    if window.is_key_down(Key::Q) {
+    if bindings.down(input, Action::PanUp) {
         movement.y += movement_speed;
     }
-    if window.is_key_down(Key::E) {
+    if bindings.down(input, Action::PanDown) {
         movement.y -= movement_speed;
     }
     if movement.magnitude() > 0.0 {
@@ -483,29 +2164,29 @@ fn handle_input(
     }
 
     // Camera zoom controls
-    if window.is_key_down(Key::Up) {
+    if bindings.down(input, Action::ZoomIn) {
         camera.zoom(zoom_speed);
     }
-    if window.is_key_down(Key::Down) {
+    if bindings.down(input, Action::ZoomOut) {
         camera.zoom(-zoom_speed);
     }
 
-    // Control of the spaceship
-    if window.is_key_down(Key::J){
-        spaceship.update_position(Vec3::new(-0.1, 0.0, 0.0));
-    }
-    if window.is_key_down(Key::L) {
-        spaceship.update_position(Vec3::new(0.1, 0.0, 0.0));
-    }
-    if window.is_key_down(Key::I) {
-        spaceship.update_position(Vec3::new(0.0, 0.1, 0.0));
-    }
-    if window.is_key_down(Key::K) {
-        spaceship.update_position(Vec3::new(0.0, -0.1, 0.0));
+    // Stick and throttle of the spaceship, how it responds comes from its stats
+    let axis = |positive: Action, negative: Action| {
+        bindings.down(input, positive) as i32 as f32 - bindings.down(input, negative) as i32 as f32
+    };
+    spaceship.controls = FlightControls {
+        throttle: axis(Action::ThrustForward, Action::ThrustBack),
+        pitch: axis(Action::PitchUp, Action::PitchDown),
+        yaw: axis(Action::YawLeft, Action::YawRight),
+        roll: axis(Action::RollRight, Action::RollLeft),
+    };
+    if bindings.released(input, Action::YawLeft) || bindings.released(input, Action::YawRight) {
+        text_log.log(format!("Heading {:.0}", spaceship.heading().to_degrees().rem_euclid(360.0)));
     }
-    // --- Zoom of the camera with the mouse scroll ---
-    if scroll_delta != 0.0 {
-        camera.zoom(scroll_delta * zoom_speed);
+    // --- Scroll wheel zoom, or touchpad orbit and pinch zoom ---
+    if scroll != (0.0, 0.0) {
+        touchpad.apply(camera, scroll, scroll_ctrl);
     }
 
     // --- Movement of the camera with the mouse ---
@@ -522,7 +2203,7 @@ fn handle_input(
     *last_mouse_position = current_mouse_position;
 
     // Activate bird eye view
-    if window.is_key_pressed(Key::B, minifb::KeyRepeat::No) {
+    if bindings.pressed(input, Action::BirdEyeView) {
         if *bird_eye_view_active {
             // return to the default camera position
             camera.eye = default_camera_eye;
@@ -535,8 +2216,58 @@ fn handle_input(
 
         // Change the state of the bird eye view
         *bird_eye_view_active = !*bird_eye_view_active;
+        text_log.log(if *bird_eye_view_active { "Bird eye view on" } else { "Bird eye view off" });
 
         // make sure the camera has changed
         camera.has_changed = true;
     }
+
+    // Toggle the on-screen log console
+    if bindings.pressed(input, Action::ToggleConsole) {
+        text_log.toggle_visible();
+        text_log.log(if text_log.visible { "Console shown" } else { "Console hidden" });
+    }
+
+    // Accessibility toggles
+    if bindings.pressed(input, Action::ReducedMotion) {
+        accessibility.reduced_motion = !accessibility.reduced_motion;
+        text_log.log(if accessibility.reduced_motion { "Reduced motion on" } else { "Reduced motion off" });
+    }
+    if bindings.pressed(input, Action::PhotosensitiveMode) {
+        accessibility.photosensitive_safe = !accessibility.photosensitive_safe;
+        text_log.log(if accessibility.photosensitive_safe { "Photosensitive mode on" } else { "Photosensitive mode off" });
+    }
+
+    // Cycle the global shading mode
+    if bindings.pressed(input, Action::NextShadingMode) {
+        *shading_mode = shading_mode.next();
+        text_log.log(format!("Shading mode: {}", shading_mode.name()));
+    }
+
+    // Shade planets into cached surface textures
+    if bindings.pressed(input, Action::TextureSpaceShading) {
+        *texture_space_shading = !*texture_space_shading;
+        text_log.log(if *texture_space_shading { "Texture-space shading on" } else { "Texture-space shading off" });
+    }
+
+    if bindings.pressed(input, Action::BackFaceCulling) {
+        *back_face_culling = !*back_face_culling;
+        text_log.log(if *back_face_culling { "Back-face culling on" } else { "Back-face culling off" });
+    }
+
+    // Simulation speed, doubled and halved or jumped to a named preset
+    if bindings.pressed(input, Action::SpeedUp) {
+        clock.scale_rate(2.0);
+        text_log.log(format!("Simulation speed {}", clock.label()));
+    }
+    if bindings.pressed(input, Action::SlowDown) {
+        clock.scale_rate(0.5);
+        text_log.log(format!("Simulation speed {}", clock.label()));
+    }
+    for preset in 0..TIME_PRESETS.len() {
+        if bindings.pressed(input, Action::TimePreset(preset as u8)) {
+            clock.set_preset(preset);
+            text_log.log(format!("Simulation speed {}", clock.label()));
+        }
+    }
 }
\ No newline at end of file
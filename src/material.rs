@@ -0,0 +1,111 @@
+// material.rs
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::color::Color;
+use crate::normal_map::NormalMap;
+use crate::texture::{Sampler, Texture};
+use crate::thermal::Thermal;
+
+// Images bound to one object, passed to the shaders through the uniforms.
+// Missing maps fall back to the fragment color and the geometric normal.
+#[derive(Clone, Default)]
+pub struct Material {
+    pub texture: Option<Arc<Texture>>,
+    pub normal_map: Option<Arc<NormalMap>>,
+    pub sampler: Sampler, // Filtro y modo de repetición de las dos imágenes
+    pub thermal: Thermal, // Lo que muestra la vista térmica
+    pub blend: BlendMode,
+    pub specular: Specular,
+    pub pbr: Option<Pbr>, // Reemplaza la luz del shader y el brillo de specular
+}
+
+// Blinn-Phong highlight of the sun on top of what the shader returns.
+// Black, the default, has none.
+#[derive(Clone, Copy, Debug)]
+pub struct Specular {
+    pub color: Color,
+    pub shininess: f32, // Exponente de (N·H), más alto es un brillo más chico
+}
+
+impl Default for Specular {
+    fn default() -> Self {
+        Specular { color: Color::black(), shininess: 32.0 }
+    }
+}
+
+// Metallic/roughness surface lit with Cook-Torrance instead of the shader's
+// own lighting. The shader's color under full light is the base color.
+#[derive(Clone, Copy, Debug)]
+pub struct Pbr {
+    pub albedo: Color,   // Tiñe el color del shader, blanco lo deja igual
+    pub metallic: f32,   // 0 dieléctrico, 1 metal sin difuso y con el brillo del color
+    pub roughness: f32,  // 0 espejo, 1 mate
+    pub emissive: Color, // Luz propia, no la atenúa la distancia al sol
+}
+
+// How an object's fragments meet what is already drawn. Anything but opaque
+// is deferred to the translucent pass, which sorts the fragments of the whole
+// frame back to front and mixes them by the alpha of their color.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BlendMode {
+    #[default]
+    Opaque,   // Escribe el color y la profundidad
+    Alpha,    // Mezcla sobre lo de atrás según la cobertura
+    Additive, // Suma su luz, como un brillo
+}
+
+impl BlendMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "opaque" => Some(BlendMode::Opaque),
+            "alpha" => Some(BlendMode::Alpha),
+            "additive" => Some(BlendMode::Additive),
+            _ => None,
+        }
+    }
+}
+
+// Every image loaded so far, keyed by path, so objects sharing a file share
+// one copy and a scene reload only reads the new ones
+#[derive(Default, Clone)]
+pub struct MaterialLibrary {
+    textures: HashMap<String, Arc<Texture>>,
+    normal_maps: HashMap<String, Arc<NormalMap>>,
+}
+
+impl MaterialLibrary {
+    pub fn insert_texture(&mut self, path: &str, texture: Texture) {
+        self.textures.insert(path.to_string(), Arc::new(texture));
+    }
+
+    pub fn insert_normal_map(&mut self, path: &str, normal_map: NormalMap) {
+        self.normal_maps.insert(path.to_string(), Arc::new(normal_map));
+    }
+
+    pub fn material(&mut self, texture: Option<&str>, normal_map: Option<&str>) -> Result<Material, String> {
+        let texture = match texture {
+            Some(path) => Some(match self.textures.get(path) {
+                Some(texture) => Arc::clone(texture),
+                None => {
+                    let texture = Arc::new(Texture::new(path).map_err(|e| format!("texture '{}': {}", path, e))?);
+                    self.textures.insert(path.to_string(), Arc::clone(&texture));
+                    texture
+                }
+            }),
+            None => None,
+        };
+        let normal_map = match normal_map {
+            Some(path) => Some(match self.normal_maps.get(path) {
+                Some(normal_map) => Arc::clone(normal_map),
+                None => {
+                    let normal_map = Arc::new(NormalMap::new(path).map_err(|e| format!("normal map '{}': {}", path, e))?);
+                    self.normal_maps.insert(path.to_string(), Arc::clone(&normal_map));
+                    normal_map
+                }
+            }),
+            None => None,
+        };
+        Ok(Material { texture, normal_map, sampler: Sampler::default(), thermal: Thermal::default(), blend: BlendMode::Opaque, specular: Specular::default(), pbr: None })
+    }
+}
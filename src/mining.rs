@@ -0,0 +1,126 @@
+// mining.rs
+
+use nalgebra_glm::{Vec3, Vec4};
+use crate::asteroids::AsteroidBelt;
+use crate::color::Color;
+use crate::docking::draw_brackets;
+use crate::font::{draw_text, GLYPH_WIDTH};
+use crate::framebuffer::Framebuffer;
+use crate::line::beam;
+use crate::particles::{Emitter, ParticleSystem};
+use crate::Uniforms;
+
+const TARGET_REACH: f32 = 4.0;     // Se apunta hasta a tantos alcances del rayo
+const AIM_CONE: f32 = 0.08;        // Radianes alrededor de la nariz que cuentan como apuntar
+const BEAM_WIDTH: (f32, f32) = (0.03, 0.008); // En la nave y en la roca, unidades
+const CORE_WIDTH: (f32, f32) = (0.01, 0.003);
+const CORE_BRIGHTNESS: f32 = 0.8;
+const TEXT_SCALE: usize = 2;
+const READOUT_TOP: usize = 152;   // Pixeles, debajo del radar
+const READOUT_MARGIN: usize = 12;
+const GOOD_COLOR: u32 = 0x66FF66;
+const WARN_COLOR: u32 = 0xFFB030;
+
+// The ship's mining laser. A rock is picked by pointing the nose at it; while
+// the beam is held on a rock within range it cuts ore out of it, throwing
+// sparks off the contact point, until the rock breaks up. What it takes goes
+// into the cargo hold.
+pub struct MiningLaser {
+    pub range: f32,            // Alcance del rayo, unidades
+    pub rate: f32,             // Toneladas por frame
+    pub color: u32,
+    pub target: Option<usize>, // Índice en belt.asteroids
+    pub cargo: f32,            // Toneladas recogidas
+    contact: Option<Vec3>,     // Donde pega el rayo este frame, None sin disparar
+    sparks: Emitter,
+}
+
+impl MiningLaser {
+    pub fn new(range: f32, rate: f32, color: u32) -> Self {
+        let mut sparks = Emitter::new(4.0, 0.01, 0.9, 18.0, 0.012, 0xFFF0C0, color);
+        sparks.intensity = 0.8;
+        MiningLaser {
+            range,
+            rate,
+            color,
+            target: None,
+            cargo: 0.0,
+            contact: None,
+            sparks,
+        }
+    }
+
+    // Targets the rock the nose points at, None when there is none in sight
+    pub fn acquire(&mut self, belt: &AsteroidBelt, nose: &Vec3, forward: &Vec3) -> Option<usize> {
+        self.target = belt.pick(nose, forward, self.range * TARGET_REACH, AIM_CONE);
+        self.target
+    }
+
+    // One frame of the beam, held while `firing`. Returns the tons of the
+    // target when it breaks up, the target is dropped then. `density` scales
+    // the sparks for the quality tier.
+    pub fn update(&mut self, belt: &mut AsteroidBelt, nose: &Vec3, firing: bool, particles: &mut ParticleSystem, density: f32) -> Option<f32> {
+        self.contact = None;
+        let index = self.target?;
+        let Some(asteroid) = belt.asteroids.get(index) else {
+            self.target = None;
+            return None;
+        };
+        let center = asteroid.get_position();
+        let direction = (center - nose).try_normalize(1e-6)?;
+        let contact = center - direction * asteroid.size();
+        if !firing || (contact - nose).magnitude() > self.range {
+            return None;
+        }
+
+        self.contact = Some(contact);
+        self.sparks.emit(particles, &contact, &-direction, &Vec3::zeros(), density);
+        let (taken, broken) = belt.mine(index, self.rate);
+        self.cargo += taken;
+        if broken {
+            self.target = None;
+            return Some(taken);
+        }
+        None
+    }
+
+    // The beam from the nose to the rock, a wide glow around a white core,
+    // both narrowing towards the rock
+    pub fn render_beam(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, nose: &Vec3) {
+        let Some(contact) = self.contact else {
+            return;
+        };
+        beam(framebuffer, uniforms, nose, &contact, BEAM_WIDTH.0, BEAM_WIDTH.1, Color::from_hex(self.color));
+        beam(framebuffer, uniforms, nose, &contact, CORE_WIDTH.0, CORE_WIDTH.1, Color::from_hex(0xFFFFFF) * CORE_BRIGHTNESS);
+    }
+
+    // Brackets around the target and the readouts under the radar:
+    // the ore left in the target and how far it is, in warning color while
+    // out of the beam's range, and the cargo once there is any
+    pub fn render(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, belt: &AsteroidBelt, nose: &Vec3) {
+        let mut lines = Vec::new();
+        if let Some(asteroid) = self.target.and_then(|index| belt.asteroids.get(index)) {
+            let position = asteroid.get_position();
+            let range = (position - nose).magnitude() - asteroid.size();
+            let color = if range <= self.range { GOOD_COLOR } else { WARN_COLOR };
+
+            let projected = uniforms.viewport_matrix * uniforms.projection_matrix * uniforms.view_matrix
+                * Vec4::new(position.x, position.y, position.z, 1.0);
+            if projected.w > 0.0 {
+                let pixels_per_unit = uniforms.projection_matrix[(1, 1)] * framebuffer.height as f32 * 0.5;
+                let half = (asteroid.size() / projected.w * pixels_per_unit).clamp(6.0, 60.0) as i32 + 2;
+                framebuffer.set_current_color(color);
+                draw_brackets(framebuffer, (projected.x / projected.w) as i32, (projected.y / projected.w) as i32, half, 3);
+            }
+            lines.push((format!("TARGET {:.0} T  RANGE {:.2}", asteroid.ore(), range), color));
+        }
+        if self.cargo > 0.0 {
+            lines.push((format!("CARGO {:.0} T", self.cargo), GOOD_COLOR));
+        }
+        for (i, (line, color)) in lines.iter().enumerate() {
+            let width = line.chars().count() * (GLYPH_WIDTH + 1) * TEXT_SCALE;
+            let x = framebuffer.width.saturating_sub(width + READOUT_MARGIN);
+            draw_text(framebuffer, x, READOUT_TOP + i * 14, line, *color, TEXT_SCALE);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+// motion.rs
+
+use std::collections::HashMap;
+use nalgebra_glm::{Mat4, Vec2, Vec3, Vec4};
+
+// Takes a model-space point to its scene pixel this frame and on the last
+// one, both without the temporal AA jitter
+#[derive(Clone, Copy, Debug)]
+pub struct MotionTransform {
+    pub current: Mat4,
+    pub previous: Mat4,
+}
+
+impl MotionTransform {
+    // Scene pixels the point moved since the last frame, None when it is
+    // behind the camera in either
+    pub fn motion(&self, position: &Vec3) -> Option<Vec2> {
+        let point = Vec4::new(position.x, position.y, position.z, 1.0);
+        let (now, before) = (self.current * point, self.previous * point);
+        if now.w <= 0.0 || before.w <= 0.0 {
+            return None;
+        }
+        Some(Vec2::new(now.x / now.w - before.x / before.w, now.y / now.w - before.y / before.w))
+    }
+}
+
+// The camera and every object's model matrix on the last frame, so each
+// object's fragments know how far they moved and not only how the camera
+// did: orbiting planets and the ship reproject on their own. Objects are
+// keyed by name; one seen for the first time, or after a frame away, counts
+// as if it had only moved with the camera.
+#[derive(Default)]
+pub struct MotionTracker {
+    pub enabled: bool,
+    camera: Mat4,
+    previous_camera: Option<Mat4>,
+    models: HashMap<String, Mat4>,
+    previous_models: HashMap<String, Mat4>,
+}
+
+impl MotionTracker {
+    // `camera` takes a world point to its scene pixel this frame
+    pub fn begin_frame(&mut self, camera: Mat4) {
+        if !self.enabled {
+            self.previous_camera = None;
+            self.models.clear();
+            self.previous_models.clear();
+            return;
+        }
+        // The first frame has nothing before it and moved nowhere
+        self.previous_camera = Some(if self.previous_camera.is_some() { self.camera } else { camera });
+        self.camera = camera;
+        std::mem::swap(&mut self.models, &mut self.previous_models);
+        self.models.clear();
+    }
+
+    // Remembers where `name` is drawn this frame, None while no effect wants
+    // motion vectors
+    pub fn transform(&mut self, name: &str, model: &Mat4) -> Option<MotionTransform> {
+        if !self.enabled {
+            return None;
+        }
+        self.models.insert(name.to_string(), *model);
+        let previous_model = self.previous_models.get(name).unwrap_or(model);
+        let previous_camera = self.previous_camera.unwrap_or(self.camera);
+        Some(MotionTransform { current: self.camera * model, previous: previous_camera * previous_model })
+    }
+}
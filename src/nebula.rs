@@ -0,0 +1,226 @@
+// nebula.rs
+
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+use fastnoise_lite::{FastNoiseLite, FractalType, NoiseType};
+use rayon::prelude::*;
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::postprocess::texel_span;
+use crate::Uniforms;
+
+const MIN_STEPS: usize = 4; // Con menos la niebla se ve en capas
+const DEPTH_TOLERANCE: f32 = 0.05; // Diferencia relativa de distancia que aún mezcla dos muestras
+
+// Ellipsoidal fog volume whose density comes from 3D noise. It is ray-marched
+// after the opaque geometry, stopping at the depth buffer, once per pixel or
+// once per block of pixels at a reduced resolution.
+pub struct Nebula {
+    pub center: Vec3,
+    pub radii: Vec3,
+    pub core_color: Vec3, // RGB 0..1, color en el centro
+    pub edge_color: Vec3, // Color hacia el borde
+    pub density: f32,     // Extinción por unidad de distancia
+    pub noise_scale: f32,
+    pub steps: usize,
+    noise: FastNoiseLite,
+}
+
+// The fog in front of one pixel and how far its ray went
+#[derive(Clone, Copy)]
+struct FogSample {
+    color: Vec3,
+    transmittance: f32,
+    distance: f32, // Infinito donde no hay nada detrás
+}
+
+impl Nebula {
+    pub fn new(center: Vec3, radii: Vec3, core_color: u32, edge_color: u32, density: f32, noise_scale: f32, seed: i32) -> Self {
+        let mut noise = FastNoiseLite::with_seed(seed);
+        noise.set_noise_type(Some(NoiseType::OpenSimplex2));
+        noise.set_fractal_type(Some(FractalType::FBm));
+        noise.set_fractal_octaves(Some(3));
+        noise.set_frequency(Some(1.0));
+
+        Nebula {
+            center,
+            radii,
+            core_color: hex_to_vec(core_color),
+            edge_color: hex_to_vec(edge_color),
+            density,
+            noise_scale,
+            steps: 24,
+            noise,
+        }
+    }
+
+    // `scale` is how many pixels per side one ray stands for. The rays are
+    // spread over the pixels by how close their distances are to the
+    // pixel's, so fog in front of a planet doesn't bleed onto the sky beside it.
+    pub fn render(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, camera_position: Vec3, scale: usize) {
+        let screen_to_world = match (uniforms.viewport_matrix * uniforms.projection_matrix * uniforms.view_matrix).try_inverse() {
+            Some(matrix) => matrix,
+            None => return,
+        };
+        // Drift the noise slowly so the gas churns, frozen with reduced motion
+        let drift = uniforms.time as f32 * 0.002 * uniforms.accessibility.motion_amount();
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        let zbuffer = &framebuffer.zbuffer;
+        let steps = ((self.steps as f32 * uniforms.quality.scattering).round() as usize).max(MIN_STEPS);
+        let far_depth = uniforms.depth_range.far_depth();
+
+        // Opaque surfaces end the ray, empty pixels and stars (which leave
+        // no depth) let it run to the far side
+        let distance = |x: usize, y: usize| {
+            let depth = zbuffer[y * width + x];
+            if depth.is_finite() {
+                (unproject(&screen_to_world, x as f32 + 0.5, y as f32 + 0.5, depth) - camera_position).magnitude()
+            } else {
+                f32::INFINITY
+            }
+        };
+        let sample = |x: usize, y: usize| {
+            let far = unproject(&screen_to_world, x as f32 + 0.5, y as f32 + 0.5, far_depth);
+            let direction = (far - camera_position).normalize();
+            let distance = distance(x, y);
+            let (color, transmittance) = self.march(camera_position, direction, distance, drift, steps)
+                .unwrap_or((Vec3::zeros(), 1.0));
+            FogSample { color, transmittance, distance }
+        };
+        let composite = |pixel: &mut u32, fog: FogSample| {
+            if fog.transmittance < 1.0 {
+                *pixel = vec_to_hex(hex_to_vec(*pixel) * fog.transmittance + fog.color);
+            }
+        };
+
+        let scale = scale.max(1);
+        if scale == 1 {
+            framebuffer.buffer.par_iter_mut().enumerate().for_each(|(index, pixel)| {
+                composite(pixel, sample(index % width, index / width));
+            });
+            return;
+        }
+
+        // One ray from the middle of each block
+        let (low_width, low_height) = (width.div_ceil(scale), height.div_ceil(scale));
+        let samples: Vec<FogSample> = (0..low_width * low_height).into_par_iter()
+            .map(|index| {
+                let x = ((index % low_width) * scale + scale / 2).min(width - 1);
+                let y = ((index / low_width) * scale + scale / 2).min(height - 1);
+                sample(x, y)
+            })
+            .collect();
+
+        framebuffer.buffer.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+            let (y0, y1, fy) = texel_span(y, low_height, scale);
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let (x0, x1, fx) = texel_span(x, low_width, scale);
+                let corners = [
+                    (samples[y0 * low_width + x0], (1.0 - fx) * (1.0 - fy)),
+                    (samples[y0 * low_width + x1], fx * (1.0 - fy)),
+                    (samples[y1 * low_width + x0], (1.0 - fx) * fy),
+                    (samples[y1 * low_width + x1], fx * fy),
+                ];
+                if corners.iter().all(|(fog, _)| fog.transmittance >= 1.0) {
+                    continue;
+                }
+                composite(pixel, upsample(&corners, distance(x, y)));
+            }
+        });
+    }
+
+    // Front-to-back accumulation, returns the in-scattered color and the
+    // fraction of the background that still shows through
+    fn march(&self, origin: Vec3, direction: Vec3, max_distance: f32, drift: f32, steps: usize) -> Option<(Vec3, f32)> {
+        let (enter, exit) = self.intersect(origin, direction)?;
+        let start = enter.max(0.0);
+        let end = exit.min(max_distance);
+        if end <= start {
+            return None;
+        }
+
+        let step = (end - start) / steps as f32;
+        let mut color = Vec3::new(0.0, 0.0, 0.0);
+        let mut transmittance = 1.0;
+
+        for i in 0..steps {
+            let point = origin + direction * (start + step * (i as f32 + 0.5));
+            let local = (point - self.center).component_div(&self.radii);
+            let falloff = (1.0 - local.magnitude_squared()).max(0.0);
+
+            let sample = point * self.noise_scale;
+            let noise = self.noise.get_noise_3d(sample.x + drift, sample.y, sample.z - drift);
+            let density = ((noise + 0.3) * falloff * self.density).max(0.0);
+            if density <= 0.0 {
+                continue;
+            }
+
+            let absorbed = 1.0 - (-density * step).exp();
+            let tint = self.edge_color.lerp(&self.core_color, falloff);
+            color += tint * absorbed * transmittance;
+            transmittance *= 1.0 - absorbed;
+            if transmittance < 0.01 {
+                break;
+            }
+        }
+
+        Some((color, transmittance))
+    }
+
+    // Ray/ellipsoid distances, solved as a unit sphere in scaled space
+    fn intersect(&self, origin: Vec3, direction: Vec3) -> Option<(f32, f32)> {
+        let o = (origin - self.center).component_div(&self.radii);
+        let d = direction.component_div(&self.radii);
+        let a = d.dot(&d);
+        let b = o.dot(&d);
+        let c = o.dot(&o) - 1.0;
+        let discriminant = b * b - a * c;
+        if discriminant <= 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        Some(((-b - root) / a, (-b + root) / a))
+    }
+}
+
+// The four rays around a pixel blended bilinearly, each weighed down the
+// more its distance differs from the pixel's. Where none is close enough, as
+// along a thin silhouette, the closest one is taken as it is.
+fn upsample(corners: &[(FogSample, f32); 4], distance: f32) -> FogSample {
+    let difference = |fog: &FogSample| match (fog.distance.is_finite(), distance.is_finite()) {
+        (false, false) => 0.0,
+        (true, true) => (fog.distance - distance).abs() / distance.max(1e-4),
+        _ => f32::INFINITY,
+    };
+
+    let mut color = Vec3::zeros();
+    let mut transmittance = 0.0;
+    let mut total = 0.0;
+    for (fog, bilinear) in corners {
+        let relative = difference(fog) / DEPTH_TOLERANCE;
+        let weight = bilinear / (1.0 + relative * relative);
+        color += fog.color * weight;
+        transmittance += fog.transmittance * weight;
+        total += weight;
+    }
+    if total > 1e-4 {
+        return FogSample { color: color / total, transmittance: transmittance / total, distance };
+    }
+    corners.iter()
+        .map(|(fog, _)| *fog)
+        .min_by(|a, b| difference(a).total_cmp(&difference(b)))
+        .unwrap_or(FogSample { color: Vec3::zeros(), transmittance: 1.0, distance })
+}
+
+fn unproject(screen_to_world: &Mat4, x: f32, y: f32, depth: f32) -> Vec3 {
+    let world = screen_to_world * Vec4::new(x, y, depth, 1.0);
+    world.xyz() / world.w
+}
+
+// sRGB pixel to linear light, the fog is mixed in linear space
+fn hex_to_vec(hex: u32) -> Vec3 {
+    Vec3::from(Color::from_hex(hex).to_linear())
+}
+
+fn vec_to_hex(color: Vec3) -> u32 {
+    Color::from_linear(color.x, color.y, color.z).to_hex()
+}
@@ -1,9 +1,6 @@
 // normal_map.rs
-use std::sync::Arc;
-use once_cell::sync::OnceCell;
 use nalgebra_glm::Vec3;
-
-static NORMAL_MAP: OnceCell<Arc<NormalMap>> = OnceCell::new();
+use crate::texture::Sampler;
 
 #[derive(Clone, Debug)]
 pub struct NormalMap {
@@ -31,27 +28,11 @@ impl NormalMap {
         Ok(NormalMap { width, height, data })
     }
 
-    pub fn sample(&self, u: f32, v: f32) -> Vec3 {
-        let u = u.fract().abs();
-        let v = v.fract().abs();
-        
-        let x = (u * (self.width as f32)) as u32;
-        let y = (v * (self.height as f32)) as u32;
-        
-        let index = (y * self.width + x) as usize;
-        self.data[index]
+    pub fn sample(&self, u: f32, v: f32, sampler: &Sampler) -> Vec3 {
+        let (texels, tx, ty) = sampler.footprint(u, v, self.width, self.height);
+        let top = self.data[texels[0]].lerp(&self.data[texels[1]], tx);
+        let bottom = self.data[texels[2]].lerp(&self.data[texels[3]], tx);
+        // Blending shortens the vectors, bring them back to unit length
+        top.lerp(&bottom, ty).normalize()
     }
 }
-
-pub fn init_normal_map(path: &str) -> Result<(), image::ImageError> {
-    let normal_map = NormalMap::new(path)?;
-    NORMAL_MAP.set(Arc::new(normal_map))
-        .expect("Normal map already initialized");
-    Ok(())
-}
-
-pub fn with_normal_map(f: impl FnOnce(&NormalMap) -> Vec3) -> Vec3 {
-    let normal_map = NORMAL_MAP.get()
-        .expect("Normal map not initialized");
-    f(normal_map)
-}
\ No newline at end of file
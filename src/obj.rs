@@ -1,13 +1,61 @@
 // obj.rs
 
 use tobj;
-use nalgebra_glm::{Vec2, Vec3};
+use nalgebra_glm::{Vec2, Vec3, Vec4};
 use crate::vertex::Vertex;
 
 pub struct Obj {
     meshes: Vec<Mesh>,
 }
 
+// Mesh ready for rendering: each unique vertex is shaded once and the
+// triangles are assembled from the index buffer
+pub struct IndexedMesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+impl IndexedMesh {
+    // Per-vertex tangents from the texture coordinates: each triangle adds the
+    // directions along it in which u grows and v shrinks to its corners, then
+    // every tangent is made perpendicular to its normal. w is the sign that
+    // turns normal × tangent into the bitangent, -1 where the texture is
+    // mirrored. Vertices whose triangles have no texture keep a zero tangent.
+    pub fn generate_tangents(&mut self) {
+        let mut tangents = vec![Vec3::zeros(); self.vertices.len()];
+        let mut bitangents = vec![Vec3::zeros(); self.vertices.len()];
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+            let (v1, v2, v3) = (&self.vertices[a], &self.vertices[b], &self.vertices[c]);
+            let (edge1, edge2) = (v2.position - v1.position, v3.position - v1.position);
+            let (uv1, uv2) = (v2.tex_coords - v1.tex_coords, v3.tex_coords - v1.tex_coords);
+            let determinant = uv1.x * uv2.y - uv2.x * uv1.y;
+            if determinant.abs() < 1e-12 {
+                continue;
+            }
+            let tangent = (edge1 * uv2.y - edge2 * uv1.y) / determinant;
+            // Towards smaller v: images go down as v grows, a normal map's green points up
+            let bitangent = (edge1 * uv2.x - edge2 * uv1.x) / determinant;
+            for index in [a, b, c] {
+                tangents[index] += tangent;
+                bitangents[index] += bitangent;
+            }
+        }
+
+        for ((vertex, tangent), bitangent) in self.vertices.iter_mut().zip(tangents).zip(bitangents) {
+            let normal = vertex.normal;
+            let tangent = tangent - normal * normal.dot(&tangent);
+            vertex.tangent = if tangent.magnitude() < 1e-12 {
+                Vec4::zeros()
+            } else {
+                let tangent = tangent.normalize();
+                let handedness = if normal.cross(&tangent).dot(&bitangent) < 0.0 { -1.0 } else { 1.0 };
+                Vec4::new(tangent.x, tangent.y, tangent.z, handedness)
+            };
+        }
+    }
+}
+
 struct Mesh {
     vertices: Vec<Vec3>,
     normals: Vec<Vec3>,
@@ -42,23 +90,38 @@ impl Obj {
         Ok(Obj { meshes })
     }
 
-    pub fn get_vertex_array(&self) -> Vec<Vertex> {
+    // Distance from the model origin to its farthest vertex
+    pub fn bounding_radius(&self) -> f32 {
+        self.meshes.iter()
+            .flat_map(|mesh| mesh.vertices.iter())
+            .map(|vertex| vertex.magnitude())
+            .fold(0.0, f32::max)
+    }
+
+    // Unique vertices plus an index buffer, all meshes merged
+    pub fn get_indexed_mesh(&self) -> IndexedMesh {
         let mut vertices = Vec::new();
+        let mut indices = Vec::new();
 
         for mesh in &self.meshes {
-            for &index in &mesh.indices {
-                let position = mesh.vertices[index as usize];
-                let normal = mesh.normals.get(index as usize)
+            let base = vertices.len() as u32;
+
+            for (i, &position) in mesh.vertices.iter().enumerate() {
+                let normal = mesh.normals.get(i)
                     .cloned()
                     .unwrap_or(Vec3::new(0.0, 1.0, 0.0));
-                let tex_coords = mesh.texcoords.get(index as usize)
+                let tex_coords = mesh.texcoords.get(i)
                     .cloned()
                     .unwrap_or(Vec2::new(0.0, 0.0));
 
                 vertices.push(Vertex::new(position, normal, tex_coords));
             }
+
+            indices.extend(mesh.indices.iter().map(|&index| base + index));
         }
 
-        vertices
+        let mut mesh = IndexedMesh { vertices, indices };
+        mesh.generate_tangents();
+        mesh
     }
 }
@@ -0,0 +1,51 @@
+// orientation.rs
+
+use nalgebra::{Unit, UnitQuaternion};
+use nalgebra_glm::Vec3;
+
+// Rotations are unit quaternions: they compose without gimbal lock and can
+// be blended along the shortest arc, which Euler angles can't
+pub type Orientation = UnitQuaternion<f32>;
+
+const SLERP_EPSILON: f32 = 1e-6;
+
+// Euler angles in radians applied X first, then Y, then Z, the order the
+// scene file and the old model matrix use
+pub fn from_euler(angles: &Vec3) -> Orientation {
+    Orientation::from_euler_angles(angles.x, angles.y, angles.z)
+}
+
+// Back to X, Y, Z angles for the scene file
+pub fn to_euler(orientation: &Orientation) -> Vec3 {
+    let (x, y, z) = orientation.euler_angles();
+    Vec3::new(x, y, z)
+}
+
+pub fn about_axis(axis: &Vec3, angle: f32) -> Orientation {
+    Orientation::from_axis_angle(&Unit::new_normalize(*axis), angle)
+}
+
+// Turn around the world's up axis
+pub fn yaw(angle: f32) -> Orientation {
+    about_axis(&Vec3::y(), angle)
+}
+
+// Angle around Y of where the orientation points the model's +Z, the nose of
+// the ships: the compass heading, whatever the pitch and roll
+pub fn heading(orientation: &Orientation) -> f32 {
+    let forward = orientation * Vec3::z();
+    forward.x.atan2(forward.z)
+}
+
+// Orientation that sends +Z along `forward`, with `up` kept above it
+pub fn looking_along(forward: &Vec3, up: &Vec3) -> Orientation {
+    Orientation::face_towards(forward, up)
+}
+
+// Shortest-arc blend, t in 0..1. Two exactly opposite orientations have no
+// single arc, the nearest end is kept.
+pub fn slerp(from: &Orientation, to: &Orientation, t: f32) -> Orientation {
+    let t = t.clamp(0.0, 1.0);
+    from.try_slerp(to, t, SLERP_EPSILON)
+        .unwrap_or(if t < 0.5 { *from } else { *to })
+}
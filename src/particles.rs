@@ -0,0 +1,173 @@
+// particles.rs
+
+use std::collections::VecDeque;
+use nalgebra_glm::{Vec3, Vec4};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::Uniforms;
+
+const MAX_SPRITE_RADIUS: f32 = 24.0; // Pixeles, más cerca se recorta
+const MIN_SPRITE_RADIUS: f32 = 0.5;
+
+struct Particle {
+    position: Vec3,
+    velocity: Vec3,
+    age: f32,      // Frames
+    lifetime: f32,
+    size: f32,     // Radio en unidades del mundo al nacer
+    growth: f32,   // Tamaño al morir respecto al inicial
+    color: Color,
+    fade_to: Color,
+    intensity: f32,
+}
+
+// What an emitter throws out. Speeds are per frame like the rest of the
+// simulation; the particles move in a straight line and fade from `color`
+// to `fade_to` and to nothing over their lifetime.
+#[derive(Clone, Copy, Debug)]
+pub struct Emitter {
+    pub rate: f32,      // Partículas por frame a pleno
+    pub speed: f32,
+    pub spread: f32,    // Desvío máximo de la dirección, en fracción de la velocidad
+    pub lifetime: f32,  // Frames
+    pub size: f32,
+    pub growth: f32,
+    pub color: u32,
+    pub fade_to: u32,
+    pub intensity: f32, // Brillo que suma cada partícula
+    carry: f32,         // Fracción de partícula pendiente del frame anterior
+}
+
+impl Emitter {
+    pub fn new(rate: f32, speed: f32, spread: f32, lifetime: f32, size: f32, color: u32, fade_to: u32) -> Self {
+        Emitter {
+            rate,
+            speed,
+            spread,
+            lifetime,
+            size,
+            growth: 1.0,
+            color,
+            fade_to,
+            intensity: 1.0,
+            carry: 0.0,
+        }
+    }
+
+    // Spawns this frame's share of `rate * strength` particles at `origin`,
+    // thrown along `direction` on top of `inherited` (the velocity of what
+    // carries the emitter)
+    pub fn emit(&mut self, system: &mut ParticleSystem, origin: &Vec3, direction: &Vec3, inherited: &Vec3, strength: f32) {
+        self.carry += self.rate * strength.max(0.0);
+        let count = self.carry.floor();
+        self.carry -= count;
+        let direction = direction.try_normalize(1e-6).unwrap_or(Vec3::zeros());
+        for _ in 0..count as usize {
+            let jitter = Vec3::new(
+                system.rng.gen_range(-1.0..=1.0),
+                system.rng.gen_range(-1.0..=1.0),
+                system.rng.gen_range(-1.0..=1.0),
+            ) * self.spread;
+            let speed = self.speed * system.rng.gen_range(0.7..=1.3);
+            // Spread the births along the frame so a fast emitter leaves a
+            // continuous trail instead of clumps
+            let lag = system.rng.gen_range(0.0..1.0);
+            let velocity = inherited + (direction + jitter) * speed;
+            let lifetime = self.lifetime * system.rng.gen_range(0.8..=1.2);
+            system.spawn(Particle {
+                position: origin - inherited * lag,
+                velocity,
+                age: lag,
+                lifetime,
+                size: self.size,
+                growth: self.growth,
+                color: Color::from_hex(self.color),
+                fade_to: Color::from_hex(self.fade_to),
+                intensity: self.intensity,
+            });
+        }
+    }
+}
+
+// Every live particle in the scene, drawn as round glowing sprites that add
+// their light to what is behind them. The oldest particles are dropped when
+// the pool is full.
+pub struct ParticleSystem {
+    particles: VecDeque<Particle>,
+    capacity: usize,
+    rng: StdRng,
+}
+
+impl ParticleSystem {
+    pub fn new(capacity: usize, seed: u64) -> Self {
+        ParticleSystem {
+            particles: VecDeque::with_capacity(capacity),
+            capacity,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    fn spawn(&mut self, particle: Particle) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.particles.len() >= self.capacity {
+            self.particles.pop_front();
+        }
+        self.particles.push_back(particle);
+    }
+
+    // One frame older, dead ones removed
+    pub fn update(&mut self) {
+        for particle in self.particles.iter_mut() {
+            particle.position += particle.velocity;
+            particle.age += 1.0;
+        }
+        self.particles.retain(|particle| particle.age < particle.lifetime);
+    }
+
+    // After the opaque geometry: each sprite is depth-tested against it but
+    // leaves no depth of its own, so overlapping particles all add up
+    pub fn render(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms) {
+        let pixels_per_unit = uniforms.projection_matrix[(1, 1)] * framebuffer.height as f32 * 0.5;
+        for particle in &self.particles {
+            let view = uniforms.view_matrix * Vec4::new(particle.position.x, particle.position.y, particle.position.z, 1.0);
+            let distance = -view.z;
+            if distance <= uniforms.depth_range.planes().0 {
+                continue;
+            }
+            let clip = uniforms.projection_matrix * view;
+            let ndc = clip / clip.w;
+            let screen = uniforms.viewport_matrix * Vec4::new(ndc.x, ndc.y, ndc.z, 1.0);
+
+            let life = (particle.age / particle.lifetime).clamp(0.0, 1.0);
+            let size = particle.size * (1.0 + (particle.growth - 1.0) * life);
+            let radius = (size / distance * pixels_per_unit).min(MAX_SPRITE_RADIUS);
+            let color = particle.color.lerp(&particle.fade_to, life) * (particle.intensity * (1.0 - life));
+            let depth = uniforms.depth_range.depth_at(distance);
+
+            // Smaller than a pixel: one point with the light its disc would cover
+            if radius < MIN_SPRITE_RADIUS {
+                if screen.x >= 0.0 && screen.y >= 0.0 {
+                    let coverage = (radius / MIN_SPRITE_RADIUS).powi(2);
+                    framebuffer.point_additive(screen.x as usize, screen.y as usize, depth, (color * coverage).to_hex());
+                }
+                continue;
+            }
+
+            let reach = radius.ceil() as i32;
+            for dy in -reach..=reach {
+                for dx in -reach..=reach {
+                    let (x, y) = (screen.x + dx as f32, screen.y + dy as f32);
+                    let falloff = 1.0 - (dx * dx + dy * dy) as f32 / (radius * radius);
+                    if falloff <= 0.0 || x < 0.0 || y < 0.0 {
+                        continue;
+                    }
+                    framebuffer.point_additive(x as usize, y as usize, depth, (color * (falloff * falloff)).to_hex());
+                }
+            }
+        }
+    }
+}
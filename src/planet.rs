@@ -1,52 +1,259 @@
 // planet.rs
 
 use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+use std::sync::Arc;
+use fastnoise_lite::{FastNoiseLite, NoiseType};
+use crate::shaders::{Shader, ShadingMode};
+use crate::surface_cache::SurfaceCache;
+use crate::obj::IndexedMesh;
+use crate::sphere::SphereLod;
+use crate::material::{BlendMode, Material};
+use crate::orientation::{Orientation, about_axis, yaw};
+
+// Radius of the planet sphere (sphere.rs) before scaling
+pub const SPHERE_RADIUS: f32 = 0.5;
+
+// Noise the shaders read on bodies without a noise of their own in the scene
+// file, also the one ships and the station use
+pub fn default_noise() -> FastNoiseLite {
+    let mut noise = FastNoiseLite::with_seed(1337);
+    noise.set_noise_type(Some(NoiseType::OpenSimplex2));
+    noise
+}
+
+// Ring annulus in the planet's equatorial plane, radii in planet radii
+#[derive(Clone, Copy, Debug)]
+pub struct Rings {
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    pub opacity: f32,
+}
+
+// Thin shell of air around a planet, drawn translucent over its surface
+#[derive(Clone, Copy, Debug)]
+pub struct Atmosphere {
+    pub thickness: f32, // Fracción del radio del planeta
+    pub color: u32,
+    pub density: f32,   // Cobertura en el borde, 0..1
+    pub falloff: f32,   // Exponente del brillo hacia el borde, más alto = borde más fino
+    pub sunset: Option<u32>, // Color del borde cerca del terminador
+    pub blend: BlendMode,
+}
+
+// Clouds on a sphere of their own just above the surface, turning at their
+// own pace and drawn see-through over it
+#[derive(Clone, Copy, Debug)]
+pub struct CloudLayer {
+    pub height: f32,         // Fracción del radio sobre la superficie
+    pub rotation_speed: f32, // Giro por frame, como el del planeta
+    pub spin: f32,           // Ángulo girado, radianes
+    pub color: u32,
+    pub coverage: f32,       // Fracción del cielo cubierta, 0..1
+    pub opacity: f32,        // Alfa de las nubes más densas
+    pub zoom: f32,           // Escala del ruido, más alto = nubes más chicas
+}
 
 pub struct Planet {
     pub name: String,
     pub radius: f32,
-    pub orbit_radius: f32,
-    pub orbit_speed: f32,
-    pub rotation_speed: f32,
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,
+    pub inclination: f32,            // Radianes, respecto al plano XZ
+    pub argument_of_periapsis: f32,  // Radianes
+    pub longitude_of_node: f32,      // Radianes
+    pub orbit_speed: f32, // Avance de la anomalía media por frame
+    pub rotation_speed: f32, // Giro sobre su eje por frame
+    pub spin: f32,           // Ángulo girado, radianes
+    pub axial_tilt: f32,     // Radianes, inclina el eje de giro junto con los anillos y las nubes
     pub color: u32,
-    pub current_angle: f32,
-    pub shader_index: u32, // Nuevo campo para el índice del shader
+    pub current_angle: f32, // Anomalía media
+    pub shader: Shader, // Shader de la superficie, elegido por nombre en la escena
+    pub shading_mode: Option<ShadingMode>, // None usa el modo global
+    pub surface_cache: Option<SurfaceCache>, // Solo con texture-space shading activo
+    pub cull_back_faces: bool, // Desactivar para mallas abiertas
+    pub mass: f32, // En masas terrestres, 0 = no afecta al baricentro
+    pub barycenter_offset: Vec3, // Bamboleo de la estrella alrededor del baricentro
+    pub rings: Option<Rings>,
+    pub ring_mesh: Option<IndexedMesh>, // Generado a partir de rings
+    pub atmosphere: Option<Atmosphere>,
+    pub clouds: Option<CloudLayer>,
+    pub mesh: Option<IndexedMesh>, // Forma propia, None usa una esfera
+    pub sphere: Option<SphereLod>, // Esfera con subdivisiones propias, None usa la compartida
+    pub parent: Option<usize>, // Índice del cuerpo al que orbita, None = el origen
+    pub parent_position: Vec3, // Posición del padre, actualizada por update_hierarchy
+    pub noise: Arc<FastNoiseLite>, // Creado una vez, el de la escena o default_noise
+    pub material: Material,
 }
 
 impl Planet {
     pub fn new(
         name: &str,
         radius: f32,
-        orbit_radius: f32,
+        semi_major_axis: f32,
         orbit_speed: f32,
         rotation_speed: f32,
         color: u32,
-        shader_index: u32, // Nuevo parámetro
+        shader: Shader,
     ) -> Self {
         Planet {
             name: name.to_string(),
             radius,
-            orbit_radius,
+            semi_major_axis,
+            eccentricity: 0.0,
+            inclination: 0.0,
+            argument_of_periapsis: 0.0,
+            longitude_of_node: 0.0,
             orbit_speed,
             rotation_speed,
+            spin: 0.0,
+            axial_tilt: 0.0,
             color,
             current_angle: 0.0,
-            shader_index, // Inicializa el índice del shader
+            shader,
+            shading_mode: None,
+            surface_cache: None,
+            cull_back_faces: true,
+            mass: 0.0,
+            barycenter_offset: Vec3::new(0.0, 0.0, 0.0),
+            rings: None,
+            ring_mesh: None,
+            atmosphere: None,
+            clouds: None,
+            mesh: None,
+            sphere: None,
+            parent: None,
+            parent_position: Vec3::new(0.0, 0.0, 0.0),
+            noise: Arc::new(default_noise()),
+            material: Material::default(),
         }
     }
 
-    pub fn update_position(&mut self) {
-        self.current_angle += self.orbit_speed;
-        if self.current_angle > 2.0 * std::f32::consts::PI {
-            self.current_angle -= 2.0 * std::f32::consts::PI;
+    pub fn update_position(&mut self, time_scale: f32) {
+        // rem_euclid so a jump of several orbits still lands in [0, 2π)
+        self.current_angle = (self.current_angle + self.orbit_speed * time_scale).rem_euclid(2.0 * PI);
+        self.spin = (self.spin + self.rotation_speed * time_scale).rem_euclid(2.0 * PI);
+        if let Some(clouds) = &mut self.clouds {
+            clouds.spin = (clouds.spin + clouds.rotation_speed * time_scale).rem_euclid(2.0 * PI);
         }
     }
 
+    // Spin around the axis, leaned by the axial tilt. The planet and its
+    // rings share the model matrix, so the rings stay on the equator.
+    pub fn get_rotation(&self) -> Orientation {
+        about_axis(&Vec3::z(), self.axial_tilt) * yaw(self.spin)
+    }
+
+    // The clouds turn around the same leaned axis
+    pub fn cloud_rotation(&self, clouds: &CloudLayer) -> Orientation {
+        about_axis(&Vec3::z(), self.axial_tilt) * yaw(clouds.spin)
+    }
+
+    // Radius of the bounding sphere around the planet, its relief, clouds and rings
+    pub fn bounding_radius(&self, model_radius: f32) -> f32 {
+        let rings_radius = self.rings.map_or(0.0, |rings| rings.outer_radius * SPHERE_RADIUS);
+        let relief = self.sphere.as_ref().map_or(1.0, |sphere| sphere.extent)
+            .max(self.clouds.map_or(0.0, |clouds| 1.0 + clouds.height));
+        self.radius * (model_radius * relief).max(rings_radius)
+    }
+
+    // Its own shape, or the level of its sphere (the shared one without
+    // subdivisions of its own) for `pixels_per_unit` seen from `distance`
+    pub fn mesh_for<'a>(&'a self, shared: &'a SphereLod, pixels_per_unit: f32, distance: f32, error: f32) -> &'a IndexedMesh {
+        if let Some(mesh) = &self.mesh {
+            return mesh;
+        }
+        let screen_radius = self.radius * SPHERE_RADIUS * pixels_per_unit / distance.max(1e-3);
+        self.sphere.as_ref().unwrap_or(shared).select(screen_radius, error)
+    }
+
     pub fn get_position(&self) -> Vec3 {
+        self.heliocentric_position() + self.barycenter_offset
+    }
+
+    // Position around the origin, moons included, ignoring the star's wobble
+    fn heliocentric_position(&self) -> Vec3 {
+        self.parent_position + self.orbital_position_at(self.current_angle)
+    }
+
+    // Keplerian orbit: solve for the eccentric anomaly, place the body in its
+    // orbital plane and rotate by periapsis, inclination and ascending node.
+    // Relative to the parent, at any mean anomaly.
+    pub fn orbital_position_at(&self, mean_anomaly: f32) -> Vec3 {
+        let a = self.semi_major_axis;
+        let e = self.eccentricity.clamp(0.0, 0.99);
+        let eccentric_anomaly = solve_kepler(mean_anomaly, e);
+
+        // Position in the orbital plane, periapsis along +x
+        let x = a * (eccentric_anomaly.cos() - e);
+        let y = a * (1.0 - e * e).sqrt() * eccentric_anomaly.sin();
+
+        let (sin_w, cos_w) = self.argument_of_periapsis.sin_cos();
+        let (sin_i, cos_i) = self.inclination.sin_cos();
+        let (sin_o, cos_o) = self.longitude_of_node.sin_cos();
+
+        // Reference plane is XZ with Y up
         Vec3::new(
-            self.orbit_radius * self.current_angle.cos(),
-            0.0,
-            self.orbit_radius * self.current_angle.sin(),
+            (cos_o * cos_w - sin_o * sin_w * cos_i) * x + (-cos_o * sin_w - sin_o * cos_w * cos_i) * y,
+            (sin_w * sin_i) * x + (cos_w * sin_i) * y,
+            (sin_o * cos_w + cos_o * sin_w * cos_i) * x + (-sin_o * sin_w + cos_o * cos_w * cos_i) * y,
         )
     }
+}
+
+// Kepler's equation M = E - e sin(E), solved with Newton's method
+fn solve_kepler(mean_anomaly: f32, eccentricity: f32) -> f32 {
+    let mut eccentric_anomaly = if eccentricity > 0.8 { PI } else { mean_anomaly };
+    for _ in 0..8 {
+        let f = eccentric_anomaly - eccentricity * eccentric_anomaly.sin() - mean_anomaly;
+        let derivative = 1.0 - eccentricity * eccentric_anomaly.cos();
+        let step = f / derivative;
+        eccentric_anomaly -= step;
+        if step.abs() < 1e-6 {
+            break;
+        }
+    }
+    eccentric_anomaly
+}
+
+// Places satellites around their parent. Parents always come before their
+// children in the list, so a single pass resolves moons of moons too.
+pub fn update_hierarchy(planets: &mut [Planet]) {
+    for index in 0..planets.len() {
+        if let Some(parent) = planets[index].parent {
+            planets[index].parent_position = planets[parent].get_position();
+        }
+    }
+}
+
+// Where every body will be `steps` orbit steps from now, without moving
+// them. Ignores the star's wobble.
+pub fn positions_after(planets: &[Planet], steps: f32, positions: &mut Vec<Vec3>) {
+    positions.clear();
+    for planet in planets {
+        let local = planet.orbital_position_at(planet.current_angle + planet.orbit_speed * steps);
+        let origin = planet.parent.map_or(Vec3::new(0.0, 0.0, 0.0), |parent| positions[parent]);
+        positions.push(origin + local);
+    }
+}
+
+// Moves the central star (the heaviest body without an orbit) so that the
+// system barycenter stays at the origin. `scale` exaggerates the wobble.
+pub fn update_barycenter(planets: &mut [Planet], scale: f32) {
+    let star = planets.iter()
+        .enumerate()
+        .filter(|(_, planet)| planet.semi_major_axis == 0.0 && planet.mass > 0.0)
+        .max_by(|(_, a), (_, b)| a.mass.total_cmp(&b.mass))
+        .map(|(index, _)| index);
+
+    let Some(star) = star else { return };
+
+    let mut moment = Vec3::new(0.0, 0.0, 0.0);
+    for (index, planet) in planets.iter().enumerate() {
+        if index != star {
+            moment += planet.heliocentric_position() * planet.mass;
+        }
+    }
+
+    planets[star].barycenter_offset = -moment / planets[star].mass * scale;
 }
\ No newline at end of file
@@ -0,0 +1,675 @@
+// postprocess.rs
+
+use nalgebra_glm::{Mat4, Vec2, Vec4};
+use rayon::prelude::*;
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::frame_capture::FrameCapture;
+
+const JITTER_SAMPLES: usize = 8; // Posiciones de la secuencia de Halton antes de repetirla
+
+// A full-screen pass over the resolved frame, after the 3D passes and before
+// the HUD. Built-in effects and the ones registered by other code run the
+// same way, in the order the scene file lists their names.
+pub trait PostEffect {
+    fn name(&self) -> &str;
+    fn enabled(&self) -> bool;
+    fn set_enabled(&mut self, enabled: bool);
+    // `frame` is the window's color buffer, `depth` the scene's behind it.
+    // A frame capture gets whatever intermediate images the effect wants.
+    fn apply(&mut self, frame: &mut Framebuffer, depth: &SceneDepth, capture: Option<&mut FrameCapture>);
+
+    // How far the next frame's projection should move, in frame pixels
+    fn jitter(&self) -> Vec2 {
+        Vec2::zeros()
+    }
+
+    // Whether `apply` reads the motion vectors, which are only kept while
+    // some effect that runs does
+    fn uses_motion(&self) -> bool {
+        false
+    }
+}
+
+// The depth buffer of the scene framebuffer, which is `factor` times the
+// frame's size on each axis when supersampling. Infinity where nothing was drawn.
+// `camera` takes a world point to its frame pixel and depth buffer value.
+pub struct SceneDepth<'a> {
+    pub values: &'a [f32],
+    pub motion: Option<&'a [Option<Vec2>]>, // Pixeles de la escena que se movió cada uno, ver MotionTracker
+    pub width: usize,
+    pub factor: usize,
+    pub camera: Mat4,
+    pub far_depth: f32, // Lo que vale el cielo, para los pixeles sin nada
+}
+
+impl<'a> SceneDepth<'a> {
+    pub fn new(scene: &'a Framebuffer, frame_width: usize, camera: Mat4, far_depth: f32) -> Self {
+        SceneDepth {
+            values: &scene.zbuffer,
+            motion: scene.motion.as_deref(),
+            width: scene.width,
+            factor: (scene.width / frame_width.max(1)).max(1),
+            camera,
+            far_depth,
+        }
+    }
+
+    // The factor x factor depths under pixel (x, y) of the frame
+    pub fn samples(&self, x: usize, y: usize) -> impl Iterator<Item = f32> + '_ {
+        let factor = self.factor;
+        (y * factor..(y + 1) * factor).flat_map(move |sample_y| {
+            let start = sample_y * self.width + x * factor;
+            self.values[start..start + factor].iter().copied()
+        })
+    }
+
+    // Frame pixels the nearest surface under pixel (x, y) moved since the
+    // last frame, None where it has no motion vector and only the camera counts
+    pub fn motion(&self, x: usize, y: usize) -> Option<Vec2> {
+        let motion = self.motion?;
+        let factor = self.factor;
+        let nearest = (y * factor..(y + 1) * factor)
+            .flat_map(|sample_y| (x * factor..(x + 1) * factor).map(move |sample_x| sample_y * self.width + sample_x))
+            .min_by(|&a, &b| self.values[a].total_cmp(&self.values[b]))?;
+        motion[nearest].map(|moved| moved / factor as f32)
+    }
+}
+
+// Every effect that can run, and the names of the ones that do in order.
+// Registering under a name already taken replaces that effect, which is how
+// a reloaded scene swaps in its new settings.
+#[derive(Default)]
+pub struct PostChain {
+    effects: Vec<Box<dyn PostEffect>>,
+    order: Vec<String>,
+}
+
+impl PostChain {
+    pub fn register(&mut self, effect: Box<dyn PostEffect>) {
+        match self.effects.iter().position(|registered| registered.name() == effect.name()) {
+            Some(index) => self.effects[index] = effect,
+            None => self.effects.push(effect),
+        }
+    }
+
+    pub fn set_order(&mut self, names: &[String]) {
+        self.order = names.to_vec();
+    }
+
+    // Names in the order that no effect is registered under
+    pub fn missing(&self) -> Vec<&str> {
+        self.order.iter()
+            .filter(|name| self.get(name).is_none())
+            .map(String::as_str)
+            .collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn PostEffect> {
+        self.effects.iter().find(|effect| effect.name() == name).map(|effect| effect.as_ref())
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Box<dyn PostEffect>> {
+        self.effects.iter_mut().find(|effect| effect.name() == name)
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) {
+        if let Some(effect) = self.get_mut(name) {
+            effect.set_enabled(enabled);
+        }
+    }
+
+    // Switches the effect on or off, None if there is none with that name
+    pub fn toggle(&mut self, name: &str) -> Option<bool> {
+        let effect = self.get_mut(name)?;
+        let enabled = !effect.enabled();
+        effect.set_enabled(enabled);
+        Some(enabled)
+    }
+
+    // Projection offset for the next frame from the effects that run
+    pub fn jitter(&self) -> Vec2 {
+        self.order.iter()
+            .filter_map(|name| self.get(name))
+            .filter(|effect| effect.enabled())
+            .map(|effect| effect.jitter())
+            .sum()
+    }
+
+    pub fn uses_motion(&self) -> bool {
+        self.order.iter()
+            .filter_map(|name| self.get(name))
+            .any(|effect| effect.enabled() && effect.uses_motion())
+    }
+
+    // A frame capture gets the frame after each effect that ran
+    pub fn apply(&mut self, frame: &mut Framebuffer, depth: &SceneDepth, mut capture: Option<&mut FrameCapture>) {
+        for name in &self.order {
+            let Some(effect) = self.effects.iter_mut().find(|effect| effect.name() == name) else {
+                continue;
+            };
+            if !effect.enabled() {
+                continue;
+            }
+            effect.apply(frame, depth, capture.as_deref_mut());
+            if let Some(capture) = capture.as_mut() {
+                capture.stage(name, frame);
+            }
+        }
+    }
+}
+
+// Glow around the brightest pixels (the sun, lava): keep what is over the
+// threshold, blur it at quarter resolution with a separable Gaussian and add it
+// back over the frame. Runs after the 3D passes and before the HUD.
+pub struct Bloom {
+    pub enabled: bool,
+    pub threshold: f32, // Luminancia lineal 0..1 a partir de la cual un pixel brilla
+    pub strength: f32,
+    kernel: Vec<f32>,   // Mitad del kernel, el centro primero
+    downsample: usize,  // Pixeles por lado de cada texel del brillo
+    width: usize,       // Tamaño de los buffers reducidos
+    height: usize,
+    bright: Vec<[f32; 3]>,
+    blurred: Vec<[f32; 3]>,
+}
+
+impl Bloom {
+    // `radius` is how far the glow reaches, in framebuffer pixels. The bright
+    // pass and the blur run at 1/`downsample` of the frame's size per side.
+    pub fn new(threshold: f32, strength: f32, radius: f32, downsample: usize) -> Self {
+        let downsample = downsample.max(1);
+        Bloom {
+            enabled: true,
+            threshold: threshold.clamp(0.0, 0.99),
+            strength,
+            kernel: gaussian_kernel(radius / downsample as f32),
+            downsample,
+            width: 0,
+            height: 0,
+            bright: Vec::new(),
+            blurred: Vec::new(),
+        }
+    }
+
+    // Average each downsample² block, keeping only the part of every pixel
+    // above the threshold so bright pixels keep their hue
+    fn bright_pass(&mut self, framebuffer: &Framebuffer) {
+        let threshold = self.threshold;
+        let downsample = self.downsample;
+        let width = self.width;
+        let (full_width, full_height) = (framebuffer.width, framebuffer.height);
+        let buffer = &framebuffer.buffer;
+
+        self.bright.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+            for (x, texel) in row.iter_mut().enumerate() {
+                let mut sum = [0.0; 3];
+                for py in (y * downsample..(y + 1) * downsample).map(|py| py.min(full_height - 1)) {
+                    for px in (x * downsample..(x + 1) * downsample).map(|px| px.min(full_width - 1)) {
+                        let color = unpack(buffer[py * full_width + px]);
+                        let luminance = 0.2126 * color[0] + 0.7152 * color[1] + 0.0722 * color[2];
+                        if luminance > threshold {
+                            let weight = (luminance - threshold) / (1.0 - threshold);
+                            for channel in 0..3 {
+                                sum[channel] += color[channel] * weight;
+                            }
+                        }
+                    }
+                }
+                *texel = sum.map(|value| value / (downsample * downsample) as f32);
+            }
+        });
+    }
+
+    // Horizontal pass into `blurred`, vertical pass back into `bright`
+    fn blur(&mut self) {
+        let (width, height) = (self.width, self.height);
+        let kernel = &self.kernel;
+
+        let bright = &self.bright;
+        self.blurred.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+            let source = &bright[y * width..(y + 1) * width];
+            for (x, texel) in row.iter_mut().enumerate() {
+                *texel = convolve(kernel, |offset| source[clamp_index(x as isize + offset, width)]);
+            }
+        });
+
+        let blurred = &self.blurred;
+        self.bright.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+            for (x, texel) in row.iter_mut().enumerate() {
+                *texel = convolve(kernel, |offset| blurred[clamp_index(y as isize + offset, height) * width + x]);
+            }
+        });
+    }
+
+    // Bilinear upsample of the blurred glow added on top of the frame: each
+    // row is blended vertically at low resolution first, then stretched
+    fn composite(&self, framebuffer: &mut Framebuffer) {
+        let (width, height) = (self.width, self.height);
+        let full_width = framebuffer.width;
+        let glow = &self.bright;
+        let strength = self.strength;
+        let downsample = self.downsample;
+        let columns: Vec<(usize, usize, f32)> = (0..full_width).map(|x| texel_span(x, width, downsample)).collect();
+
+        framebuffer.buffer.par_chunks_mut(full_width).enumerate().for_each(|(y, row)| {
+            let (y0, y1, fy) = texel_span(y, height, downsample);
+            let line: Vec<[f32; 3]> = (0..width)
+                .map(|x| {
+                    let (top, bottom) = (glow[y0 * width + x], glow[y1 * width + x]);
+                    [0, 1, 2].map(|channel| (top[channel] * (1.0 - fy) + bottom[channel] * fy) * strength)
+                })
+                .collect();
+
+            // Most rows get no glow at all
+            if line.iter().all(|texel| texel.iter().all(|value| *value < 0.5 / 255.0)) {
+                return;
+            }
+
+            for (pixel, &(x0, x1, fx)) in row.iter_mut().zip(&columns) {
+                let (left, right) = (line[x0], line[x1]);
+                let added = [0, 1, 2].map(|channel| left[channel] * (1.0 - fx) + right[channel] * fx);
+                if added.iter().any(|value| *value >= 0.5 / 255.0) {
+                    let color = unpack(*pixel);
+                    *pixel = pack([0, 1, 2].map(|channel| color[channel] + added[channel]));
+                }
+            }
+        });
+    }
+}
+
+impl PostEffect for Bloom {
+    fn name(&self) -> &str {
+        "bloom"
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    // A frame capture also gets the low resolution buffers of each step
+    fn apply(&mut self, framebuffer: &mut Framebuffer, _depth: &SceneDepth, mut capture: Option<&mut FrameCapture>) {
+        if !self.enabled || self.strength <= 0.0 || framebuffer.width == 0 || framebuffer.height == 0 {
+            return;
+        }
+
+        let width = framebuffer.width.div_ceil(self.downsample);
+        let height = framebuffer.height.div_ceil(self.downsample);
+        if width != self.width || height != self.height {
+            self.width = width;
+            self.height = height;
+            self.bright = vec![[0.0; 3]; width * height];
+            self.blurred = vec![[0.0; 3]; width * height];
+        }
+
+        self.bright_pass(framebuffer);
+        if let Some(capture) = capture.as_mut() {
+            capture.image("bloom_bright_pass", width, height, self.bright.iter().map(|texel| pack(*texel)).collect());
+        }
+        self.blur();
+        if let Some(capture) = capture.as_mut() {
+            capture.image("bloom_blur_horizontal", width, height, self.blurred.iter().map(|texel| pack(*texel)).collect());
+            capture.image("bloom_blur", width, height, self.bright.iter().map(|texel| pack(*texel)).collect());
+        }
+        self.composite(framebuffer);
+    }
+}
+
+// Ink line along the silhouettes of everything drawn, where how much of a
+// pixel the scene covers differs from its neighbors. It reads only the depth
+// buffer, so the sky and the empty space behind the rings count as nothing.
+pub struct Outline {
+    pub enabled: bool,
+    pub color: Color,
+    pub strength: f32,   // 0..1, cuánto tapa la línea al pixel
+    coverage: Vec<f32>,  // Fracción de muestras con algo dibujado, por pixel
+}
+
+impl Outline {
+    pub fn new(color: u32, strength: f32) -> Self {
+        Outline {
+            enabled: true,
+            color: Color::from_hex(color),
+            strength: strength.clamp(0.0, 1.0),
+            coverage: Vec::new(),
+        }
+    }
+}
+
+impl PostEffect for Outline {
+    fn name(&self) -> &str {
+        "outline"
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    fn apply(&mut self, frame: &mut Framebuffer, depth: &SceneDepth, _capture: Option<&mut FrameCapture>) {
+        let (width, height) = (frame.width, frame.height);
+        if width == 0 || height == 0 || self.strength <= 0.0 {
+            return;
+        }
+        self.coverage.resize(width * height, 0.0);
+        let samples = (depth.factor * depth.factor) as f32;
+        self.coverage.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+            for (x, covered) in row.iter_mut().enumerate() {
+                *covered = depth.samples(x, y).filter(|value| value.is_finite()).count() as f32 / samples;
+            }
+        });
+
+        let coverage = &self.coverage;
+        let (color, strength) = (self.color, self.strength);
+        frame.buffer.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+            let (up, down) = (y.saturating_sub(1), (y + 1).min(height - 1));
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let (left, right) = (x.saturating_sub(1), (x + 1).min(width - 1));
+                let center = coverage[y * width + x];
+                let edge = [up * width + x, down * width + x, y * width + left, y * width + right].iter()
+                    .map(|&neighbor| (coverage[neighbor] - center).abs())
+                    .fold(0.0, f32::max);
+                if edge > 0.0 {
+                    *pixel = Color::from_hex(*pixel).lerp(&color, edge * strength).to_hex();
+                }
+            }
+        });
+    }
+}
+
+// Temporal anti-aliasing: every frame is rendered a different fraction of a
+// pixel off (Halton 2, 3) and blended into a running history of the past
+// ones. The history follows every object by its motion vector, and the
+// camera through the depth buffer where there is none (the sky, sprites). It
+// is clamped to the colors around the pixel this frame so anything that
+// showed up leaves no ghost behind.
+pub struct TemporalAA {
+    pub enabled: bool,
+    pub blend: f32, // Peso del frame nuevo, más bajo = más suave pero más estela
+    frame: usize,
+    width: usize,
+    height: usize,
+    history: Vec<[f32; 3]>,
+    previous_camera: Option<Mat4>,
+}
+
+impl TemporalAA {
+    pub fn new(blend: f32) -> Self {
+        TemporalAA {
+            enabled: true,
+            blend: blend.clamp(0.01, 1.0),
+            frame: 0,
+            width: 0,
+            height: 0,
+            history: Vec::new(),
+            previous_camera: None,
+        }
+    }
+}
+
+impl PostEffect for TemporalAA {
+    fn name(&self) -> &str {
+        "taa"
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    // The history is stale once frames went by without it
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.previous_camera = None;
+    }
+
+    fn jitter(&self) -> Vec2 {
+        let index = self.frame % JITTER_SAMPLES + 1;
+        Vec2::new(halton(index, 2) - 0.5, halton(index, 3) - 0.5)
+    }
+
+    fn uses_motion(&self) -> bool {
+        true
+    }
+
+    fn apply(&mut self, frame: &mut Framebuffer, depth: &SceneDepth, _capture: Option<&mut FrameCapture>) {
+        let (width, height) = (frame.width, frame.height);
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.frame += 1;
+        let current: Vec<[f32; 3]> = frame.buffer.par_iter().map(|&pixel| unpack(pixel)).collect();
+
+        // This frame's pixels to where they were on the last one
+        let reprojection = self.previous_camera
+            .filter(|_| self.width == width && self.height == height)
+            .and_then(|previous| depth.camera.try_inverse().map(|inverse| previous * inverse));
+        self.previous_camera = Some(depth.camera);
+        let Some(reprojection) = reprojection else {
+            (self.width, self.height) = (width, height);
+            self.history = current;
+            return;
+        };
+
+        let history = &self.history;
+        let blend = self.blend;
+        let resolved: Vec<[f32; 3]> = (0..width * height).into_par_iter().map(|index| {
+            let (x, y) = (index % width, index / width);
+            let color = current[index];
+            let (past_x, past_y) = match depth.motion(x, y) {
+                Some(moved) => (x as f32 - moved.x, y as f32 - moved.y),
+                None => {
+                    let nearest = depth.samples(x, y).fold(f32::INFINITY, f32::min);
+                    let z = if nearest.is_finite() { nearest } else { depth.far_depth };
+                    let past = reprojection * Vec4::new(x as f32 + 0.5, y as f32 + 0.5, z, 1.0);
+                    if past.w <= 0.0 {
+                        return color;
+                    }
+                    (past.x / past.w - 0.5, past.y / past.w - 0.5)
+                }
+            };
+            if past_x < 0.0 || past_y < 0.0 || past_x > (width - 1) as f32 || past_y > (height - 1) as f32 {
+                return color;
+            }
+
+            let (low, high) = neighborhood(&current, width, height, x, y);
+            let past = sample_bilinear(history, width, height, past_x, past_y);
+            [0, 1, 2].map(|channel| {
+                let past = past[channel].clamp(low[channel], high[channel]);
+                past + (color[channel] - past) * blend
+            })
+        }).collect();
+
+        frame.buffer.par_iter_mut().zip(&resolved).for_each(|(pixel, color)| *pixel = pack(*color));
+        self.history = resolved;
+    }
+}
+
+// Smears what moved along its path on screen, as a shutter left open for
+// `shutter` of a frame would: each pixel averages `samples` taps of the frame
+// along its motion vector, centered on it. Only pixels with a motion vector
+// blur, so the sky, the sprites and the asteroids stay sharp.
+pub struct MotionBlur {
+    pub enabled: bool,
+    pub shutter: f32, // Fracción del frame con el obturador abierto, 1 = todo el movimiento
+    samples: usize,
+}
+
+impl MotionBlur {
+    pub fn new(shutter: f32, samples: usize) -> Self {
+        MotionBlur {
+            enabled: true,
+            shutter: shutter.max(0.0),
+            samples: samples.max(2),
+        }
+    }
+}
+
+impl PostEffect for MotionBlur {
+    fn name(&self) -> &str {
+        "motion_blur"
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    fn uses_motion(&self) -> bool {
+        true
+    }
+
+    fn apply(&mut self, frame: &mut Framebuffer, depth: &SceneDepth, _capture: Option<&mut FrameCapture>) {
+        let (width, height) = (frame.width, frame.height);
+        if width == 0 || height == 0 || depth.motion.is_none() {
+            return;
+        }
+        let current: Vec<[f32; 3]> = frame.buffer.par_iter().map(|&pixel| unpack(pixel)).collect();
+        let (shutter, samples) = (self.shutter, self.samples);
+        let (last_x, last_y) = ((width - 1) as f32, (height - 1) as f32);
+        frame.buffer.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let Some(moved) = depth.motion(x, y) else {
+                    continue;
+                };
+                let streak = moved * shutter;
+                if streak.magnitude() < 0.5 {
+                    continue;
+                }
+                let mut sum = [0.0; 3];
+                for tap in 0..samples {
+                    let offset = tap as f32 / (samples - 1) as f32 - 0.5;
+                    let (tap_x, tap_y) = ((x as f32 - streak.x * offset).clamp(0.0, last_x), (y as f32 - streak.y * offset).clamp(0.0, last_y));
+                    let color = sample_bilinear(&current, width, height, tap_x, tap_y);
+                    for channel in 0..3 {
+                        sum[channel] += color[channel];
+                    }
+                }
+                *pixel = pack(sum.map(|channel| channel / samples as f32));
+            }
+        });
+    }
+}
+
+// Darkest and brightest of each channel in the 3x3 pixels around (x, y)
+fn neighborhood(colors: &[[f32; 3]], width: usize, height: usize, x: usize, y: usize) -> ([f32; 3], [f32; 3]) {
+    let mut low = [f32::INFINITY; 3];
+    let mut high = [f32::NEG_INFINITY; 3];
+    for neighbor_y in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+        for neighbor_x in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+            let color = colors[neighbor_y * width + neighbor_x];
+            for channel in 0..3 {
+                low[channel] = low[channel].min(color[channel]);
+                high[channel] = high[channel].max(color[channel]);
+            }
+        }
+    }
+    (low, high)
+}
+
+// `x` and `y` in pixel centers, inside the image
+fn sample_bilinear(colors: &[[f32; 3]], width: usize, height: usize, x: f32, y: f32) -> [f32; 3] {
+    let (x0, y0) = (x as usize, y as usize);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+    let row = |y: usize| {
+        let (left, right) = (colors[y * width + x0], colors[y * width + x1]);
+        [0, 1, 2].map(|channel| left[channel] + (right[channel] - left[channel]) * fx)
+    };
+    let (top, bottom) = (row(y0), row(y1));
+    [0, 1, 2].map(|channel| top[channel] + (bottom[channel] - top[channel]) * fy)
+}
+
+// Element `index` (from 1) of the van der Corput sequence in `base`
+fn halton(mut index: usize, base: usize) -> f32 {
+    let mut result = 0.0;
+    let mut fraction = 1.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+// Box filter of a supersampled frame down to the window's buffer, each pixel
+// the average of its factor x factor samples in linear light. Only the color
+// is resolved, the HUD drawn afterwards doesn't read depth.
+pub fn resolve_supersampled(source: &Framebuffer, target: &mut Framebuffer) {
+    let factor = (source.width / target.width).max(1);
+    if factor == 1 {
+        target.buffer.copy_from_slice(&source.buffer);
+        return;
+    }
+
+    let source_width = source.width;
+    let samples = (factor * factor) as f32;
+    target.buffer.par_chunks_mut(target.width).enumerate().for_each(|(y, row)| {
+        for (x, pixel) in row.iter_mut().enumerate() {
+            let mut sum = [0.0; 3];
+            for sample_y in y * factor..(y + 1) * factor {
+                let start = sample_y * source_width + x * factor;
+                for &sample in &source.buffer[start..start + factor] {
+                    let color = unpack(sample);
+                    for channel in 0..3 {
+                        sum[channel] += color[channel];
+                    }
+                }
+            }
+            *pixel = pack(sum.map(|value| value / samples));
+        }
+    });
+}
+
+// The two low resolution texels around a full resolution pixel and the
+// blend between them, along one axis
+pub fn texel_span(pixel: usize, length: usize, downsample: usize) -> (usize, usize, f32) {
+    let position = ((pixel as f32 + 0.5) / downsample as f32 - 0.5).max(0.0);
+    let first = (position as usize).min(length - 1);
+    (first, (first + 1).min(length - 1), position - first as f32)
+}
+
+// Normalized half of a Gaussian reaching out to `radius` (three sigmas)
+fn gaussian_kernel(radius: f32) -> Vec<f32> {
+    let half_width = radius.ceil().max(1.0) as usize;
+    let sigma = (radius / 3.0).max(0.5);
+    let weights: Vec<f32> = (0..=half_width)
+        .map(|offset| (-((offset * offset) as f32) / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+    weights.iter().map(|weight| weight / total).collect()
+}
+
+fn convolve(kernel: &[f32], sample: impl Fn(isize) -> [f32; 3]) -> [f32; 3] {
+    let mut sum = sample(0).map(|value| value * kernel[0]);
+    for (offset, weight) in kernel.iter().enumerate().skip(1) {
+        let before = sample(-(offset as isize));
+        let after = sample(offset as isize);
+        for channel in 0..3 {
+            sum[channel] += (before[channel] + after[channel]) * weight;
+        }
+    }
+    sum
+}
+
+fn clamp_index(index: isize, length: usize) -> usize {
+    index.clamp(0, length as isize - 1) as usize
+}
+
+// The glow is thresholded, blurred and added in linear light
+fn unpack(color: u32) -> [f32; 3] {
+    Color::from_hex(color).to_linear()
+}
+
+fn pack(color: [f32; 3]) -> u32 {
+    Color::from_linear(color[0], color[1], color[2]).to_hex()
+}
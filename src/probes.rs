@@ -0,0 +1,164 @@
+// probes.rs
+
+use std::f32::consts::PI;
+use std::ops::{Add, Mul};
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+use crate::shaders::SunlightFalloff;
+use crate::skybox::Skybox;
+
+const SKY_SAMPLES: usize = 1024;    // Direcciones repartidas en la esfera al integrar el cielo
+const SKY_TEXELS: f32 = 64.0;       // Texeles por vuelta del mapa al muestrearlo, borroso a propósito
+const DISTANCE_TOLERANCE: f32 = 0.1; // Cambio relativo de la distancia al sol que rehace una sonda
+const AXES: [Vec3; 6] = [
+    Vec3::new(1.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0),
+    Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0),
+    Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0),
+];
+
+// Ambient light as the irradiance reaching a surface that faces each of the
+// six axes (+x, -x, +y, -y, +z, -z). A normal blends the three faces it
+// leans toward by the square of each component.
+#[derive(Clone, Copy, Debug)]
+pub struct AmbientCube {
+    faces: [Color; 6],
+}
+
+impl AmbientCube {
+    pub fn uniform(color: Color) -> Self {
+        AmbientCube { faces: [color; 6] }
+    }
+
+    // `normal` in world space, unit length
+    pub fn irradiance(&self, normal: &Vec3) -> Color {
+        let face = |axis: usize| if normal[axis] >= 0.0 { self.faces[axis * 2] } else { self.faces[axis * 2 + 1] };
+        face(0) * (normal.x * normal.x) + face(1) * (normal.y * normal.y) + face(2) * (normal.z * normal.z)
+    }
+
+    // Cosine-weighted light over each face's hemisphere from evenly spread
+    // directions: each one stands for 4π/n steradians, Lambert divides by π
+    fn integrate(samples: usize, radiance: impl Fn(&Vec3) -> Color) -> Self {
+        let golden_angle = PI * (3.0 - 5.0f32.sqrt());
+        let mut faces = [Color::black(); 6];
+        for index in 0..samples {
+            let y = 1.0 - 2.0 * (index as f32 + 0.5) / samples as f32;
+            let ring = (1.0 - y * y).sqrt();
+            let (sin, cos) = (golden_angle * index as f32).sin_cos();
+            let direction = Vec3::new(cos * ring, y, sin * ring);
+            let light = radiance(&direction);
+            for (face, axis) in faces.iter_mut().zip(AXES) {
+                *face = *face + light * (direction.dot(&axis) * 4.0 / samples as f32);
+            }
+        }
+        AmbientCube { faces }
+    }
+}
+
+impl Add for AmbientCube {
+    type Output = AmbientCube;
+
+    fn add(self, other: AmbientCube) -> AmbientCube {
+        AmbientCube { faces: std::array::from_fn(|face| self.faces[face] + other.faces[face]) }
+    }
+}
+
+impl Mul<f32> for AmbientCube {
+    type Output = AmbientCube;
+
+    fn mul(self, scalar: f32) -> AmbientCube {
+        AmbientCube { faces: self.faces.map(|face| face * scalar) }
+    }
+}
+
+// The sun's share of one probe and where the sun was when it was taken
+struct LightProbe {
+    position: Vec3,
+    sun: AmbientCube,
+    sun_direction: Vec3, // Cero hasta el primer cálculo
+    sun_distance: f32,
+}
+
+// Ambient light probes on the bodies and the station. The sky is at
+// infinity, so its part is the same for every probe and is integrated once
+// from the sky map. The sun's part is its light scattered in from its side
+// of the sky, wrapped around so night sides get a little of it too; it is
+// only taken again when the sun has turned more than `update_angle` as seen
+// from the probe, or its distance changed by a tenth. Objects blend the
+// probes around them by inverse square distance.
+pub struct LightProbes {
+    pub enabled: bool,
+    pub strength: f32,   // Multiplica toda la luz de las sondas
+    pub sun_scatter: f32, // Parte de la luz del sol que llega dispersa
+    update_cos: f32,
+    sky: AmbientCube,
+    probes: Vec<LightProbe>,
+}
+
+impl LightProbes {
+    pub fn new(strength: f32, sun_scatter: f32, update_angle: f32, skybox: &Skybox) -> Self {
+        LightProbes {
+            enabled: true,
+            strength,
+            sun_scatter,
+            update_cos: update_angle.to_radians().cos(),
+            sky: AmbientCube::integrate(SKY_SAMPLES, |direction| skybox.radiance(direction, SKY_TEXELS)),
+            probes: Vec::new(),
+        }
+    }
+
+    // Moves the probes to `positions`, one per anchor in the same order
+    // every frame, and retakes the sun for the ones it moved too much for
+    pub fn update(&mut self, positions: &[Vec3], sun_position: &Vec3, sun_color: Color, sunlight: &SunlightFalloff) {
+        self.probes.truncate(positions.len());
+        while self.probes.len() < positions.len() {
+            self.probes.push(LightProbe {
+                position: positions[self.probes.len()],
+                sun: AmbientCube::uniform(Color::black()),
+                sun_direction: Vec3::zeros(),
+                sun_distance: 0.0,
+            });
+        }
+
+        for (probe, position) in self.probes.iter_mut().zip(positions) {
+            probe.position = *position;
+            let to_sun = sun_position - position;
+            let distance = to_sun.magnitude();
+            if distance < 1e-4 {
+                continue;
+            }
+            let direction = to_sun / distance;
+            let turned = probe.sun_direction.dot(&direction) < self.update_cos;
+            let moved = (distance / probe.sun_distance.max(1e-4) - 1.0).abs() > DISTANCE_TOLERANCE;
+            if !turned && !moved {
+                continue;
+            }
+            let light = sun_color * (sunlight.at(distance) * self.sun_scatter);
+            probe.sun = AmbientCube { faces: AXES.map(|axis| light * ((1.0 + axis.dot(&direction)) * 0.5)) };
+            probe.sun_direction = direction;
+            probe.sun_distance = distance;
+        }
+    }
+
+    // Ambient at `position` on top of the background's `base`, None when
+    // there is no light at all
+    pub fn ambient(&self, position: &Vec3, base: Option<AmbientCube>) -> Option<AmbientCube> {
+        if !self.enabled || self.strength <= 0.0 {
+            return base;
+        }
+        let mut sun = AmbientCube::uniform(Color::black());
+        let mut total = 0.0;
+        for probe in &self.probes {
+            let distance_squared = (probe.position - position).magnitude_squared();
+            if distance_squared < 1e-8 {
+                sun = probe.sun;
+                total = 1.0;
+                break;
+            }
+            sun = sun + probe.sun * (1.0 / distance_squared);
+            total += 1.0 / distance_squared;
+        }
+        let sun = if total > 0.0 { sun * (1.0 / total) } else { sun };
+        let probed = (self.sky + sun) * self.strength;
+        Some(base.map_or(probed, |base| base + probed))
+    }
+}
@@ -0,0 +1,86 @@
+// quality.rs
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum QualityTier {
+    Low,
+    Medium,
+    High, // Todo el detalle de la escena
+}
+
+impl QualityTier {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "low" => Some(QualityTier::Low),
+            "medium" => Some(QualityTier::Medium),
+            "high" => Some(QualityTier::High),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            QualityTier::Low => "low",
+            QualityTier::Medium => "medium",
+            QualityTier::High => "high",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            QualityTier::Low => QualityTier::Medium,
+            QualityTier::Medium => QualityTier::High,
+            QualityTier::High => QualityTier::Low,
+        }
+    }
+
+    pub fn settings(&self) -> QualitySettings {
+        match self {
+            QualityTier::Low => QualitySettings {
+                tier: *self,
+                max_octaves: 2,
+                scattering: 0.4,
+                particles: 0.25,
+                lod_bias: 1.5,
+                mesh_error: 1.5,
+                bloom: false,
+                ssaa_factor: 1,
+            },
+            QualityTier::Medium => QualitySettings {
+                tier: *self,
+                max_octaves: 4,
+                scattering: 0.7,
+                particles: 0.6,
+                lod_bias: 0.5,
+                mesh_error: 0.75,
+                bloom: true,
+                ssaa_factor: 1,
+            },
+            QualityTier::High => QualitySettings {
+                tier: *self,
+                max_octaves: 8,
+                scattering: 1.0,
+                particles: 1.0,
+                lod_bias: 0.0,
+                mesh_error: 0.35,
+                bloom: true,
+                ssaa_factor: 2,
+            },
+        }
+    }
+}
+
+// Everything a tier trades for speed, in one place. The shaders and the
+// nebulae read it from the uniforms, the planets' noise and the asteroid belt
+// are rebuilt with it, and bloom and supersampling are switched by it when a
+// tier is picked.
+#[derive(Clone, Copy, Debug)]
+pub struct QualitySettings {
+    pub tier: QualityTier,
+    pub max_octaves: i32,   // Tope de octavas del ruido de los planetas
+    pub scattering: f32,    // Fracción de los pasos de las nebulosas
+    pub particles: f32,     // Fracción de las rocas del cinturón
+    pub lod_bias: f32,      // Se suma al nivel de mipmap, más alto = texturas más suaves
+    pub mesh_error: f32,    // Pixeles que el contorno de una esfera puede apartarse del real
+    pub bloom: bool,
+    pub ssaa_factor: usize,
+}
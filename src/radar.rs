@@ -0,0 +1,159 @@
+// radar.rs
+
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+use crate::framebuffer::Framebuffer;
+
+const RADAR_RADIUS: usize = 60; // Pixeles
+const RADAR_MARGIN: usize = 12;
+const RANGE_RINGS: usize = 3;
+const SWEEP_SPEED: f32 = 0.04; // Radianes por frame
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum BlipKind {
+    Star,
+    Planet,
+    Ship,
+    Station,
+}
+
+impl BlipKind {
+    fn color(&self) -> u32 {
+        match self {
+            BlipKind::Star => 0xFFCC44,
+            BlipKind::Planet => 0x66CCFF,
+            BlipKind::Ship => 0x66FF66,
+            BlipKind::Station => 0xFF66FF,
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            BlipKind::Star | BlipKind::Station => 2,
+            _ => 1,
+        }
+    }
+}
+
+// Top-down sensor display in the top right corner: the player ship is at
+// the center facing up, bodies within `range` show as blips that light up
+// when the sweep passes over them and fade until the next turn
+pub struct Radar {
+    pub range: f32, // Unidades de mundo hasta el borde
+    pub visible: bool,
+    sweep_angle: f32,
+}
+
+impl Radar {
+    pub fn new(range: f32) -> Self {
+        Radar {
+            range,
+            visible: true,
+            sweep_angle: 0.0,
+        }
+    }
+
+    pub fn zoom(&mut self, factor: f32) {
+        self.range = (self.range * factor).clamp(2.0, 200.0);
+    }
+
+    // With reduced motion the sweep stands still and every blip stays lit
+    pub fn update(&mut self, motion_amount: f32) {
+        self.sweep_angle = (self.sweep_angle + SWEEP_SPEED * motion_amount) % (2.0 * PI);
+    }
+
+    pub fn render(&self, framebuffer: &mut Framebuffer, center: &Vec3, heading: f32, blips: &[(Vec3, BlipKind)], sweeping: bool) {
+        if !self.visible {
+            return;
+        }
+
+        let cx = framebuffer.width.saturating_sub(RADAR_RADIUS + RADAR_MARGIN) as i32;
+        let cy = (RADAR_RADIUS + RADAR_MARGIN) as i32;
+        let radius = RADAR_RADIUS as i32;
+
+        // Darken the disc so the blips read over bright planets
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                if dx * dx + dy * dy <= radius * radius {
+                    dim_pixel(framebuffer, cx + dx, cy + dy);
+                }
+            }
+        }
+
+        // Range rings, the outer one is the sensor radius
+        framebuffer.set_current_color(0x1F5F3F);
+        for ring in 1..=RANGE_RINGS {
+            let ring_radius = RADAR_RADIUS as f32 * ring as f32 / RANGE_RINGS as f32;
+            let steps = (ring_radius * 2.0 * PI) as usize;
+            for step in 0..steps {
+                let angle = step as f32 / steps as f32 * 2.0 * PI;
+                plot(framebuffer, cx as f32 + angle.cos() * ring_radius, cy as f32 + angle.sin() * ring_radius);
+            }
+        }
+
+        if sweeping {
+            framebuffer.set_current_color(0x2FBF6F);
+            for step in 0..RADAR_RADIUS {
+                let length = step as f32;
+                plot(framebuffer, cx as f32 + self.sweep_angle.cos() * length, cy as f32 + self.sweep_angle.sin() * length);
+            }
+        }
+
+        // Rotate into the ship's frame so its nose (+z) points up, the ship's
+        // right is its local -x
+        let (sin, cos) = heading.sin_cos();
+        for (position, kind) in blips {
+            let offset = position - center;
+            let local_x = offset.x * cos - offset.z * sin;
+            let forward = offset.x * sin + offset.z * cos;
+            let distance = (local_x * local_x + forward * forward).sqrt();
+            if distance > self.range {
+                continue;
+            }
+
+            let scale = RADAR_RADIUS as f32 / self.range;
+            let x = cx as f32 - local_x * scale;
+            let y = cy as f32 - forward * scale;
+
+            // Brightest right after the sweep passed, down to a third a turn later
+            let brightness = if sweeping {
+                let angle = (-forward).atan2(-local_x);
+                let behind = (self.sweep_angle - angle).rem_euclid(2.0 * PI);
+                1.0 - behind / (2.0 * PI) * 0.65
+            } else {
+                1.0
+            };
+            framebuffer.set_current_color(scale_color(kind.color(), brightness));
+
+            let size = kind.size() as i32;
+            for dy in -size..=size {
+                for dx in -size..=size {
+                    plot(framebuffer, x + dx as f32, y + dy as f32);
+                }
+            }
+        }
+
+        // The player at the center
+        framebuffer.set_current_color(0xFFFFFF);
+        plot(framebuffer, cx as f32, cy as f32 - 1.0);
+        plot(framebuffer, cx as f32 - 1.0, cy as f32 + 1.0);
+        plot(framebuffer, cx as f32 + 1.0, cy as f32 + 1.0);
+    }
+}
+
+fn plot(framebuffer: &mut Framebuffer, x: f32, y: f32) {
+    if x >= 0.0 && y >= 0.0 {
+        framebuffer.point_overlay(x as usize, y as usize);
+    }
+}
+
+fn dim_pixel(framebuffer: &mut Framebuffer, x: i32, y: i32) {
+    if x >= 0 && y >= 0 {
+        framebuffer.darken(x as usize, y as usize, 0.3);
+    }
+}
+
+fn scale_color(color: u32, factor: f32) -> u32 {
+    let channel = |shift: u32| ((((color >> shift) & 0xFF) as f32 * factor) as u32).min(0xFF) << shift;
+    channel(16) | channel(8) | channel(0)
+}
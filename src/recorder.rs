@@ -0,0 +1,82 @@
+// recorder.rs
+
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::framebuffer::save_buffer_image;
+
+// "Flight recorder": keeps the last frames and the simulation state so a
+// rendering glitch can be dumped to disk right after it happens
+pub struct FlightRecorder {
+    frames: VecDeque<Vec<u32>>,
+    capacity: usize,
+    width: usize,
+    height: usize,
+    frame_number: u64,
+    state: String,
+}
+
+impl FlightRecorder {
+    pub fn new(capacity: usize, width: usize, height: usize) -> Self {
+        FlightRecorder {
+            frames: VecDeque::with_capacity(capacity),
+            capacity,
+            width,
+            height,
+            frame_number: 0,
+            state: String::new(),
+        }
+    }
+
+    // After the window changes size, older frames no longer fit the dump
+    pub fn resize(&mut self, width: usize, height: usize) {
+        if (width, height) != (self.width, self.height) {
+            self.frames.clear();
+            self.width = width;
+            self.height = height;
+        }
+    }
+
+    pub fn record(&mut self, buffer: &[u32], state: String) {
+        // Reuse the oldest frame's allocation once the ring is full
+        let mut frame = if self.frames.len() >= self.capacity {
+            self.frames.pop_front().unwrap_or_default()
+        } else {
+            Vec::with_capacity(buffer.len())
+        };
+        frame.clear();
+        frame.extend_from_slice(buffer);
+
+        self.frames.push_back(frame);
+        self.frame_number += 1;
+        self.state = state;
+    }
+
+    // Writes every buffered frame plus the last simulation state into a new folder
+    pub fn dump(&self, reason: &str) -> Result<PathBuf, String> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let dir = PathBuf::from(format!("captures/flight_{}", timestamp));
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+        let first_frame = self.frame_number - self.frames.len() as u64;
+        for (i, frame) in self.frames.iter().enumerate() {
+            let path = dir.join(format!("frame_{:06}.png", first_frame + i as u64));
+            save_buffer_image(&path, frame, self.width, self.height).map_err(|e| e.to_string())?;
+        }
+
+        let report = format!(
+            "reason: {}\nframes: {}..{}\n{}\n",
+            reason,
+            first_frame,
+            self.frame_number,
+            self.state,
+        );
+        fs::write(dir.join("state.txt"), report).map_err(|e| e.to_string())?;
+
+        Ok(dir)
+    }
+}
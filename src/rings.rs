@@ -0,0 +1,38 @@
+// rings.rs
+
+use nalgebra_glm::{Vec2, Vec3};
+use std::f32::consts::PI;
+use crate::obj::IndexedMesh;
+use crate::planet::{Rings, SPHERE_RADIUS};
+use crate::vertex::Vertex;
+
+const RING_SEGMENTS: u32 = 128;
+
+// Flat annulus in the planet's equatorial plane (y = 0), in the same model
+// space as the planet sphere so it shares the planet's model matrix. Texture
+// coordinates are (radial 0..1 from inner to outer edge, angle 0..1).
+pub fn ring_mesh(rings: &Rings) -> IndexedMesh {
+    let inner = rings.inner_radius * SPHERE_RADIUS;
+    let outer = rings.outer_radius * SPHERE_RADIUS;
+    let normal = Vec3::new(0.0, 1.0, 0.0);
+
+    let mut vertices = Vec::with_capacity(RING_SEGMENTS as usize * 2);
+    for segment in 0..RING_SEGMENTS {
+        let turn = segment as f32 / RING_SEGMENTS as f32;
+        let (sin, cos) = (turn * 2.0 * PI).sin_cos();
+        vertices.push(Vertex::new(Vec3::new(cos * inner, 0.0, sin * inner), normal, Vec2::new(0.0, turn)));
+        vertices.push(Vertex::new(Vec3::new(cos * outer, 0.0, sin * outer), normal, Vec2::new(1.0, turn)));
+    }
+
+    // Two triangles per segment, wrapping around to the first pair
+    let mut indices = Vec::with_capacity(RING_SEGMENTS as usize * 6);
+    for segment in 0..RING_SEGMENTS {
+        let inner_a = segment * 2;
+        let outer_a = inner_a + 1;
+        let inner_b = (segment + 1) % RING_SEGMENTS * 2;
+        let outer_b = inner_b + 1;
+        indices.extend_from_slice(&[inner_a, outer_b, outer_a, inner_a, inner_b, outer_b]);
+    }
+
+    IndexedMesh { vertices, indices }
+}
@@ -0,0 +1,1528 @@
+// scene.rs
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+use nalgebra_glm::Vec3;
+use fastnoise_lite::{FastNoiseLite, FractalType, NoiseType};
+use crate::asteroids::AsteroidBelt;
+use crate::camera::CameraConstraints;
+use crate::orientation::{from_euler, heading};
+use crate::escorts::{Escort, EscortWing};
+use crate::material::{BlendMode, Material, MaterialLibrary, Pbr, Specular};
+use crate::nebula::Nebula;
+use crate::planet::{Atmosphere, CloudLayer, Planet, Rings, SPHERE_RADIUS};
+use crate::rings::ring_mesh;
+use crate::skybox::{Skybox, DEFAULT_PARALLAX_LAYERS};
+use crate::probes::LightProbes;
+use crate::shaders::{find_shader, numbered_shader, Shader, ShadingMode, SunlightFalloff, PlanetshineSettings};
+use crate::station::Station;
+use crate::texture::{Filter, Sampler, WrapMode};
+use crate::touchpad::Touchpad;
+use crate::postprocess::{Bloom, MotionBlur, Outline, PostChain, TemporalAA};
+use crate::exporter::{ExportFormat, StateExporter};
+use crate::depth_range::DepthRange;
+use crate::background::{Background, BackgroundMode};
+use crate::flight::{FlightModel, Gravity};
+use crate::color::Color;
+use crate::comet::{nucleus_mesh, Comet};
+use crate::sphere::{SphereLod, DEFAULT_SUBDIVISIONS};
+use crate::solar_wind::{Magnetosphere, SolarWind};
+use crate::mining::MiningLaser;
+use crate::trade::{Contract, Trade};
+use crate::galaxy::Galaxy;
+use crate::particles::Emitter;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct PlanetConfig {
+    pub name: String,
+    pub radius: f32,
+    #[serde(alias = "orbit_radius")]
+    pub semi_major_axis: f32,
+    #[serde(default)]
+    pub eccentricity: f32,
+    #[serde(default)]
+    pub inclination: f32, // Grados
+    #[serde(default)]
+    pub argument_of_periapsis: f32, // Grados
+    #[serde(default)]
+    pub longitude_of_node: f32, // Grados
+    pub orbit_speed: f32,
+    pub rotation_speed: f32,
+    pub axial_tilt: Option<f32>, // Grados, None toma la de los anillos
+    pub color: u32,
+    pub shader: ShaderRef,
+    pub shading: Option<String>, // "per-fragment", "gouraud" o "flat"
+    pub cull_back_faces: Option<bool>,
+    pub mass: Option<f32>, // Masas terrestres
+    pub temperature: Option<f32>, // Kelvin en la vista térmica, None = equilibrio con la luz del sol
+    pub resonance: Option<ResonanceConfig>,
+    pub rings: Option<RingsConfig>,
+    pub atmosphere: Option<AtmosphereConfig>,
+    pub clouds: Option<CloudsConfig>, // Capa de nubes que gira por su cuenta
+    pub tail: Option<TailConfig>, // Lo vuelve un cometa
+    pub magnetosphere: Option<MagnetosphereConfig>, // Campo que desvía el viento solar
+    pub subdivisions: Option<u32>, // Celdas por arista de la esfera, None = las de todos los planetas
+    pub parent: Option<String>, // Nombre del cuerpo al que orbita, debe aparecer antes
+    pub noise: Option<NoiseConfig>, // None usa el ruido por defecto
+    pub relief: Option<ReliefConfig>, // Montañas y cráteres con forma, necesita noise
+    pub texture: Option<String>,    // Ruta de la imagen, la leen los shaders texturizados
+    pub normal_map: Option<String>,
+    pub specular: Option<SpecularConfig>, // Brillo del sol reflejado, None sin él
+    pub pbr: Option<PbrConfig>,     // Metálico/rugosidad, reemplaza a specular
+    pub filter: Option<String>,     // "nearest", "bilinear" o "trilinear" (por defecto)
+    pub wrap: Option<String>,       // "repeat" (por defecto) o "clamp"
+}
+
+// Terrain relief, see sphere.rs: the planet's noise pushes the surface in
+// and out, so mountains and craters stand out against the sky
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ReliefConfig {
+    pub height: f32, // Fracción del radio con el ruido en 1
+    #[serde(default = "default_relief_scale")]
+    pub scale: f32,  // Multiplica la posición del modelo al leer el ruido, 10 como los cráteres del shader rocoso
+}
+
+fn default_relief_scale() -> f32 {
+    10.0
+}
+
+// FastNoiseLite settings for a shader, names follow the library's enums
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct NoiseConfig {
+    #[serde(default = "default_noise_seed")]
+    pub seed: i32,
+    #[serde(rename = "type", default = "default_noise_type")]
+    pub noise_type: String,
+    pub frequency: Option<f32>,
+    pub fractal: Option<String>,
+    pub octaves: Option<i32>,
+    pub lacunarity: Option<f32>,
+    pub gain: Option<f32>,
+}
+
+fn default_noise_seed() -> i32 {
+    1337
+}
+
+fn default_noise_type() -> String {
+    "open-simplex2".to_string()
+}
+
+impl NoiseConfig {
+    // `max_octaves` caps the fractal octaves for the quality tier
+    pub fn build(&self, max_octaves: i32) -> Result<FastNoiseLite, String> {
+        let noise_type = match self.noise_type.to_lowercase().as_str() {
+            "open-simplex2" => NoiseType::OpenSimplex2,
+            "open-simplex2s" => NoiseType::OpenSimplex2S,
+            "cellular" => NoiseType::Cellular,
+            "perlin" => NoiseType::Perlin,
+            "value-cubic" => NoiseType::ValueCubic,
+            "value" => NoiseType::Value,
+            other => return Err(format!("unknown noise type '{}'", other)),
+        };
+
+        let mut noise = FastNoiseLite::with_seed(self.seed);
+        noise.set_noise_type(Some(noise_type));
+        noise.set_frequency(self.frequency);
+        if let Some(fractal) = &self.fractal {
+            let fractal_type = match fractal.to_lowercase().as_str() {
+                "none" => FractalType::None,
+                "fbm" => FractalType::FBm,
+                "ridged" => FractalType::Ridged,
+                "ping-pong" => FractalType::PingPong,
+                other => return Err(format!("unknown fractal type '{}'", other)),
+            };
+            noise.set_fractal_type(Some(fractal_type));
+        }
+        let octaves = self.octaves.unwrap_or(noise.octaves).min(max_octaves);
+        noise.set_fractal_octaves(Some(octaves));
+        noise.set_fractal_lacunarity(self.lacunarity);
+        noise.set_fractal_gain(self.gain);
+        Ok(noise)
+    }
+}
+
+// One entry of the ship roster
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ShipConfig {
+    pub name: String,
+    pub model: String,
+    #[serde(default = "default_ship_scale")]
+    pub scale: f32,
+    #[serde(default = "default_ship_thrust")]
+    pub thrust: f32,       // Velocidad máxima, unidades por frame
+    #[serde(default = "default_ship_acceleration")]
+    pub acceleration: f32, // Velocidad ganada por frame con el acelerador a fondo
+    #[serde(default = "default_ship_damping")]
+    pub damping: f32,      // Fracción de la velocidad perdida por frame
+    #[serde(default = "default_ship_turn_rate")]
+    pub turn_rate: f32,    // Radianes por frame en cabeceo y guiñada
+    #[serde(default = "default_ship_roll_rate")]
+    pub roll_rate: f32,
+    #[serde(default = "default_ship_max_bank")]
+    pub max_bank: f32,     // Alabeo al girar, radianes
+    #[serde(default = "default_ship_shader")]
+    pub shader: ShaderRef,
+    pub shading: Option<String>,
+    #[serde(default)]
+    pub cull_back_faces: bool, // Desactivar para mallas abiertas como el tie-fighter
+    pub texture: Option<String>,
+    pub normal_map: Option<String>,
+    pub pbr: Option<PbrConfig>,
+    pub filter: Option<String>,
+    pub wrap: Option<String>,
+}
+
+fn default_ship_scale() -> f32 {
+    0.5
+}
+
+fn default_ship_thrust() -> f32 {
+    0.1
+}
+
+fn default_ship_acceleration() -> f32 {
+    0.006
+}
+
+fn default_ship_damping() -> f32 {
+    0.02
+}
+
+fn default_ship_turn_rate() -> f32 {
+    0.05
+}
+
+fn default_ship_roll_rate() -> f32 {
+    0.06
+}
+
+fn default_ship_max_bank() -> f32 {
+    0.5
+}
+
+fn default_ship_shader() -> ShaderRef {
+    ShaderRef::Name("spaceship".to_string())
+}
+
+fn default_ships() -> Vec<ShipConfig> {
+    vec![ShipConfig {
+        name: "TIE".to_string(),
+        model: "assets/model/tie-fighter.obj".to_string(),
+        scale: default_ship_scale(),
+        thrust: default_ship_thrust(),
+        acceleration: default_ship_acceleration(),
+        damping: default_ship_damping(),
+        turn_rate: default_ship_turn_rate(),
+        roll_rate: default_ship_roll_rate(),
+        max_bank: default_ship_max_bank(),
+        shader: default_ship_shader(),
+        shading: None,
+        cull_back_faces: false,
+        texture: None,
+        normal_map: None,
+        pbr: None,
+        filter: None,
+        wrap: None,
+    }]
+}
+
+impl ShipConfig {
+    pub fn material(&self, materials: &mut MaterialLibrary) -> Result<Material, String> {
+        let mut material = materials.material(self.texture.as_deref(), self.normal_map.as_deref())
+            .map_err(|e| format!("{}: {}", self.name, e))?;
+        material.sampler = sampler(&self.filter, &self.wrap).map_err(|e| format!("{}: {}", self.name, e))?;
+        material.pbr = self.pbr.as_ref().map(PbrConfig::pbr);
+        Ok(material)
+    }
+
+    pub fn flight_model(&self) -> FlightModel {
+        FlightModel {
+            max_speed: self.thrust,
+            acceleration: self.acceleration,
+            damping: self.damping,
+            turn_rate: self.turn_rate,
+            roll_rate: self.roll_rate,
+            max_bank: self.max_bank,
+        }
+    }
+
+    pub fn shading_mode(&self) -> Result<Option<ShadingMode>, String> {
+        self.shading.as_deref().map(|name| {
+            ShadingMode::from_name(name).ok_or_else(|| format!("{}: unknown shading mode '{}'", self.name, name))
+        }).transpose()
+    }
+}
+
+// A shader by its registered name, or by the number scene files used
+// before shaders had names
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum ShaderRef {
+    Index(u32),
+    Name(String),
+}
+
+impl ShaderRef {
+    pub fn resolve(&self) -> Result<Shader, String> {
+        match self {
+            ShaderRef::Index(index) => Ok(numbered_shader(*index)),
+            ShaderRef::Name(name) => find_shader(name).ok_or_else(|| format!("unknown shader '{}'", name)),
+        }
+    }
+}
+
+fn sampler(filter: &Option<String>, wrap: &Option<String>) -> Result<Sampler, String> {
+    let mut sampler = Sampler::default();
+    if let Some(name) = filter {
+        sampler.filter = Filter::from_name(name).ok_or_else(|| format!("unknown texture filter '{}'", name))?;
+    }
+    if let Some(name) = wrap {
+        sampler.wrap = WrapMode::from_name(name).ok_or_else(|| format!("unknown wrap mode '{}'", name))?;
+    }
+    Ok(sampler)
+}
+
+// Where the player starts and with which ship of the roster
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct SpaceshipConfig {
+    pub ship: Option<String>, // None usa la primera nave
+    pub position: [f32; 3],
+    pub rotation: [f32; 3], // Radianes
+}
+
+impl Default for SpaceshipConfig {
+    fn default() -> Self {
+        SpaceshipConfig {
+            ship: None,
+            position: [5.5, 1.5, 0.0],
+            rotation: [0.0, 0.0, 0.0],
+        }
+    }
+}
+
+// AI wingman flying in formation with the player, see escorts.rs
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct EscortConfig {
+    pub ship: String,
+    pub offset: [f32; 3], // Marco local del jugador, +z es hacia adelante
+}
+
+// Docking target, see station.rs and docking.rs
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct StationConfig {
+    pub name: String,
+    pub position: [f32; 3],
+    #[serde(default)]
+    pub heading: f32, // Grados, el puerto mira hacia +z girado en Y
+    #[serde(default = "default_ship_scale")]
+    pub scale: f32,
+    #[serde(default = "default_ship_shader")]
+    pub shader: ShaderRef,
+    #[serde(default = "default_approach_range")]
+    pub approach_range: f32, // Distancia al puerto a la que aparece el HUD de atraque
+}
+
+fn default_approach_range() -> f32 {
+    15.0
+}
+
+// Background behind everything: one equirectangular image or six cube faces
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SkyConfig {
+    pub texture: Option<String>,
+    pub faces: Option<Vec<String>>, // +x, -x, +y, -y, +z, -z
+    pub catalog: Option<String>,    // CSV de estrellas reales en vez de las aleatorias
+    #[serde(default = "default_sky_brightness")]
+    pub brightness: f32,
+    #[serde(default = "default_sky_stars")]
+    pub stars: bool, // Estrellas puntuales encima de la imagen
+    #[serde(default = "default_parallax_layers")]
+    pub parallax_layers: usize, // Capas de estrellas cercanas, de 0 a 3
+    #[serde(default = "default_parallax")]
+    pub parallax: f32,          // Cuánto se desplazan al moverse la cámara, 0 = nada
+}
+
+fn default_sky_brightness() -> f32 {
+    1.0
+}
+
+fn default_sky_stars() -> bool {
+    true
+}
+
+fn default_parallax_layers() -> usize {
+    DEFAULT_PARALLAX_LAYERS
+}
+
+fn default_parallax() -> f32 {
+    1.0
+}
+
+impl SkyConfig {
+    pub fn map_paths(&self) -> Result<Vec<String>, String> {
+        match (&self.texture, &self.faces) {
+            (Some(texture), None) => Ok(vec![texture.clone()]),
+            (None, Some(faces)) => Ok(faces.clone()),
+            (None, None) => Ok(Vec::new()),
+            (Some(_), Some(_)) => Err("sky: use either texture or faces, not both".to_string()),
+        }
+    }
+}
+
+// Scroll and pinch gestures, see touchpad.rs
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct TouchpadConfig {
+    pub enabled: bool,
+    pub orbit_sensitivity: f32,
+    pub pinch_sensitivity: f32,
+    pub wheel_sensitivity: f32,
+}
+
+impl Default for TouchpadConfig {
+    fn default() -> Self {
+        TouchpadConfig {
+            enabled: false,
+            orbit_sensitivity: 0.01,
+            pinch_sensitivity: 0.5,
+            wheel_sensitivity: 0.5,
+        }
+    }
+}
+
+impl TouchpadConfig {
+    pub fn touchpad(&self) -> Touchpad {
+        Touchpad {
+            enabled: self.enabled,
+            orbit_sensitivity: self.orbit_sensitivity,
+            pinch_sensitivity: self.pinch_sensitivity,
+            wheel_sensitivity: self.wheel_sensitivity,
+        }
+    }
+}
+
+// Glow around bright pixels, see postprocess.rs
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct BloomConfig {
+    pub enabled: bool,
+    pub threshold: f32, // Luminancia lineal 0..1
+    pub strength: f32,
+    pub radius: f32,    // Pixeles
+}
+
+impl Default for BloomConfig {
+    fn default() -> Self {
+        BloomConfig {
+            enabled: true,
+            threshold: 0.5,
+            strength: 1.2,
+            radius: 64.0,
+        }
+    }
+}
+
+impl BloomConfig {
+    pub fn bloom(&self, downsample: usize) -> Bloom {
+        let mut bloom = Bloom::new(self.threshold, self.strength, self.radius, downsample);
+        bloom.enabled = self.enabled;
+        bloom
+    }
+}
+
+// Costly passes run at a reduced resolution: each value is how many scene
+// pixels per side one sample covers, 1 is every pixel
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct PassScaleConfig {
+    pub atmospheres: usize, // Capas de aire de los planetas, ver render en main.rs
+    pub nebulae: usize,     // Rayos de las nebulosas, ver nebula.rs
+    pub bloom: usize,       // Umbral y desenfoque del bloom
+}
+
+impl Default for PassScaleConfig {
+    fn default() -> Self {
+        PassScaleConfig {
+            atmospheres: 2,
+            nebulae: 2,
+            bloom: 4,
+        }
+    }
+}
+
+// Full-screen effects over the finished scene, see postprocess.rs. `chain`
+// lists them by name in the order they run, effects left out don't run.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct PostConfig {
+    pub chain: Vec<String>,
+    pub outline: OutlineConfig,
+    pub taa: TemporalConfig,
+    pub motion_blur: MotionBlurConfig,
+}
+
+impl Default for PostConfig {
+    fn default() -> Self {
+        PostConfig {
+            chain: vec!["bloom".to_string()],
+            outline: OutlineConfig::default(),
+            taa: TemporalConfig::default(),
+            motion_blur: MotionBlurConfig::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct OutlineConfig {
+    pub color: u32,    // sRGB hex
+    pub strength: f32, // 0..1
+}
+
+impl Default for OutlineConfig {
+    fn default() -> Self {
+        OutlineConfig {
+            color: 0x000000,
+            strength: 0.8,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct TemporalConfig {
+    pub blend: f32, // Peso del frame nuevo en la historia, 0..1
+}
+
+impl Default for TemporalConfig {
+    fn default() -> Self {
+        TemporalConfig { blend: 0.1 }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct MotionBlurConfig {
+    pub shutter: f32,   // Fracción del movimiento del frame que se borronea
+    pub samples: usize, // Muestras a lo largo del vector de movimiento
+}
+
+impl Default for MotionBlurConfig {
+    fn default() -> Self {
+        MotionBlurConfig { shutter: 0.5, samples: 8 }
+    }
+}
+
+// Clear color behind the scene, see background.rs. Colors are sRGB hex.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct BackgroundConfig {
+    pub mode: String, // "black", "solid" o "gradient"
+    pub color: u32,   // Con "solid"
+    pub top: u32,     // Con "gradient"
+    pub bottom: u32,
+    pub ambient: f32, // Cuánto ilumina el fondo las caras nocturnas
+}
+
+impl Default for BackgroundConfig {
+    fn default() -> Self {
+        BackgroundConfig {
+            mode: "solid".to_string(),
+            color: 0x333355,
+            top: 0x1A1A33,
+            bottom: 0x333355,
+            ambient: 0.0,
+        }
+    }
+}
+
+impl BackgroundConfig {
+    pub fn background(&self) -> Result<Background, String> {
+        let mode = BackgroundMode::from_name(&self.mode)
+            .ok_or_else(|| format!("background: unknown mode '{}'", self.mode))?;
+        Ok(Background {
+            mode,
+            color: Color::from_hex(self.color),
+            top: Color::from_hex(self.top),
+            bottom: Color::from_hex(self.bottom),
+            ambient: self.ambient.max(0.0),
+        })
+    }
+}
+
+// Body and ship state written for external analysis, see exporter.rs
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct ExportConfig {
+    pub format: String,     // "csv" o "json"
+    pub interval_days: f64, // Días simulados entre muestras
+    pub directory: String,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        ExportConfig {
+            format: "csv".to_string(),
+            interval_days: 1.0,
+            directory: "exports".to_string(),
+        }
+    }
+}
+
+impl ExportConfig {
+    pub fn exporter(&self) -> Result<StateExporter, String> {
+        let format = ExportFormat::from_name(&self.format)
+            .ok_or_else(|| format!("export: unknown format '{}'", self.format))?;
+        Ok(StateExporter::new(format, self.interval_days, &self.directory))
+    }
+}
+
+// How planets dim away from the sun, see shaders::SunlightFalloff
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct SunlightConfig {
+    pub enabled: bool,
+    pub reference_distance: f32, // Distancia al sol con luz normal (la Tierra)
+    pub floor: f32,              // Luz mínima, para que los planetas lejanos se vean
+    pub ceiling: f32,            // Luz máxima cerca del sol
+}
+
+impl Default for SunlightConfig {
+    fn default() -> Self {
+        SunlightConfig {
+            enabled: true,
+            reference_distance: 8.0,
+            floor: 0.2,
+            ceiling: 2.0,
+        }
+    }
+}
+
+impl SunlightConfig {
+    pub fn falloff(&self) -> SunlightFalloff {
+        SunlightFalloff {
+            enabled: self.enabled,
+            reference_distance: self.reference_distance,
+            floor: self.floor,
+            ceiling: self.ceiling,
+        }
+    }
+}
+
+// Light bounced from a planet onto the bodies right next to it, see
+// shaders::PlanetshineSettings
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct PlanetshineConfig {
+    pub enabled: bool,
+    pub albedo: f32,        // Fracción de la luz del sol que devuelve un planeta
+    pub max_intensity: f32, // Tope, en la escena las lunas están muy pegadas a su planeta
+    pub min_intensity: f32, // Por debajo no se sombrea otra vez
+}
+
+impl Default for PlanetshineConfig {
+    fn default() -> Self {
+        PlanetshineConfig {
+            enabled: true,
+            albedo: 0.3,
+            max_intensity: 0.25,
+            min_intensity: 0.01,
+        }
+    }
+}
+
+impl PlanetshineConfig {
+    pub fn settings(&self) -> PlanetshineSettings {
+        PlanetshineSettings {
+            enabled: self.enabled,
+            albedo: self.albedo,
+            max_intensity: self.max_intensity,
+            min_intensity: self.min_intensity,
+        }
+    }
+}
+
+// Ambient light probes on the bodies and the station, see probes.rs
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct ProbesConfig {
+    pub enabled: bool,
+    pub strength: f32,
+    pub sun_scatter: f32,  // Fracción de la luz del sol que llega dispersa desde su lado del cielo
+    pub update_angle: f32, // Grados que debe girar el sol visto desde una sonda para rehacerla
+}
+
+impl Default for ProbesConfig {
+    fn default() -> Self {
+        ProbesConfig {
+            enabled: true,
+            strength: 1.0,
+            sun_scatter: 0.02,
+            update_angle: 5.0,
+        }
+    }
+}
+
+impl ProbesConfig {
+    // The sky part is integrated here, once
+    pub fn light_probes(&self, skybox: &Skybox) -> LightProbes {
+        let mut probes = LightProbes::new(self.strength.max(0.0), self.sun_scatter.max(0.0), self.update_angle.max(0.1), skybox);
+        probes.enabled = self.enabled;
+        probes
+    }
+}
+
+// Sun and planets pulling on the player's ship, see flight::Gravity
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct GravityConfig {
+    pub enabled: bool,
+    pub strength: f32,
+    pub planet_strength: f32, // Con masas reales los planetas casi no se notan
+}
+
+impl Default for GravityConfig {
+    fn default() -> Self {
+        GravityConfig {
+            enabled: false,
+            strength: 1.0,
+            planet_strength: 100.0,
+        }
+    }
+}
+
+impl GravityConfig {
+    pub fn gravity(&self) -> Gravity {
+        Gravity {
+            enabled: self.enabled,
+            strength: self.strength,
+            planet_strength: self.planet_strength,
+        }
+    }
+}
+
+// Projection near and far planes, see depth_range.rs
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct DepthConfig {
+    pub near: f32,
+    pub far: f32,
+    pub auto_fit: bool,   // Ajusta near/far cada frame a lo visible, near queda como mínimo
+    pub reversed_z: bool,
+    pub infinite_far: bool, // Nada queda detrás del far, far solo limita las nebulosas
+}
+
+impl Default for DepthConfig {
+    fn default() -> Self {
+        DepthConfig {
+            near: 0.1,
+            far: 1000.0,
+            auto_fit: false,
+            reversed_z: false,
+            infinite_far: false,
+        }
+    }
+}
+
+impl DepthConfig {
+    pub fn depth_range(&self) -> DepthRange {
+        DepthRange::new(self.near, self.far, self.auto_fit, self.reversed_z, self.infinite_far)
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct CameraConfig {
+    pub eye: [f32; 3],
+    pub center: [f32; 3],
+    pub up: [f32; 3],
+    pub min_pitch: f32, // Grados sobre el plano del objetivo
+    pub max_pitch: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub azimuth: Option<[f32; 2]>, // Grados, -180..180, None gira libremente
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        let constraints = CameraConstraints::default();
+        CameraConfig {
+            eye: [0.0, 10.0, 30.0],
+            center: [0.0, 0.0, 0.0],
+            up: [0.0, 1.0, 0.0],
+            min_pitch: constraints.min_pitch.to_degrees(),
+            max_pitch: constraints.max_pitch.to_degrees(),
+            min_distance: constraints.min_distance,
+            max_distance: constraints.max_distance,
+            azimuth: None,
+        }
+    }
+}
+
+impl CameraConfig {
+    pub fn constraints(&self) -> Result<CameraConstraints, String> {
+        if self.min_pitch > self.max_pitch || self.min_distance > self.max_distance {
+            return Err("camera: a minimum is above its maximum".to_string());
+        }
+        if self.min_pitch <= -90.0 || self.max_pitch >= 90.0 {
+            return Err("camera: pitch limits must stay inside (-90, 90) to avoid flipping over the poles".to_string());
+        }
+        if self.min_distance <= 0.0 {
+            return Err("camera: min_distance must be positive".to_string());
+        }
+        Ok(CameraConstraints {
+            min_pitch: self.min_pitch.to_radians(),
+            max_pitch: self.max_pitch.to_radians(),
+            min_distance: self.min_distance,
+            max_distance: self.max_distance,
+            azimuth: self.azimuth.map(|[min, max]| (min.min(max).to_radians(), min.max(max).to_radians())),
+        })
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RingsConfig {
+    pub inner_radius: f32, // Radios del planeta
+    pub outer_radius: f32,
+    #[serde(default = "default_ring_opacity")]
+    pub opacity: f32,
+    #[serde(default)]
+    pub tilt: f32, // Grados, inclina al planeta si no tiene axial_tilt
+}
+
+fn default_ring_opacity() -> f32 {
+    0.6
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SpecularConfig {
+    pub color: u32,
+    #[serde(default = "default_shininess")]
+    pub shininess: f32,
+}
+
+fn default_shininess() -> f32 {
+    32.0
+}
+
+impl SpecularConfig {
+    pub fn specular(&self) -> Specular {
+        Specular { color: Color::from_hex(self.color), shininess: self.shininess }
+    }
+}
+
+// Physically based material, see Pbr
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct PbrConfig {
+    pub albedo: u32,   // Tinte del color del shader
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: u32,
+}
+
+impl Default for PbrConfig {
+    fn default() -> Self {
+        PbrConfig { albedo: 0xFFFFFF, metallic: 0.0, roughness: 0.5, emissive: 0x000000 }
+    }
+}
+
+impl PbrConfig {
+    pub fn pbr(&self) -> Pbr {
+        Pbr {
+            albedo: Color::from_hex(self.albedo),
+            metallic: self.metallic.clamp(0.0, 1.0),
+            roughness: self.roughness.clamp(0.0, 1.0),
+            emissive: Color::from_hex(self.emissive),
+        }
+    }
+}
+
+// Translucent shell of air drawn over the planet
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct AtmosphereConfig {
+    pub thickness: f32, // Fracción del radio del planeta
+    pub color: u32,
+    pub density: f32,   // Cobertura en el borde, 0..1
+    pub falloff: f32,   // Exponente del brillo hacia el borde, más alto = borde más fino
+    pub sunset: Option<u32>, // Color del borde cerca del terminador, None = el mismo
+    pub blend: String,  // "additive" (brilla sobre el planeta) o "alpha" (lo tapa, como nubes espesas)
+}
+
+impl Default for AtmosphereConfig {
+    fn default() -> Self {
+        AtmosphereConfig {
+            thickness: 0.05,
+            color: 0x6FA8FF,
+            density: 0.7,
+            falloff: 3.0,
+            sunset: None,
+            blend: "additive".to_string(),
+        }
+    }
+}
+
+// Cloud layer drawn as its own sphere, see CloudLayer
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct CloudsConfig {
+    pub height: f32,         // Fracción del radio sobre la superficie
+    pub rotation_speed: f32, // Giro por paso, como el del planeta
+    pub color: u32,
+    pub coverage: f32,       // 0..1
+    pub opacity: f32,        // 0..1
+    pub zoom: f32,
+}
+
+impl Default for CloudsConfig {
+    fn default() -> Self {
+        CloudsConfig {
+            height: 0.02,
+            rotation_speed: 0.05,
+            color: 0xFFFFFF,
+            coverage: 0.5,
+            opacity: 0.9,
+            zoom: 450.0,
+        }
+    }
+}
+
+// Dust and ion tails of a comet, see comet.rs
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct TailConfig {
+    pub active_distance: f32, // Distancia al sol con las colas a pleno
+    pub length: f32,          // Largo de la cola de iones a pleno, unidades
+    pub coma: f32,            // Radio de la coma a pleno, unidades
+    pub rate: f32,            // Partículas de polvo por frame a pleno
+    pub dust_color: u32,
+    pub ion_color: u32,
+}
+
+impl Default for TailConfig {
+    fn default() -> Self {
+        TailConfig {
+            active_distance: 10.0,
+            length: 12.0,
+            coma: 1.5,
+            rate: 6.0,
+            dust_color: 0xFFE8C0,
+            ion_color: 0x6FA8FF,
+        }
+    }
+}
+
+const ION_LIFETIME: f32 = 60.0;  // Frames
+const DUST_LIFETIME: f32 = 150.0;
+
+impl TailConfig {
+    // Tails for the body at `index` of the planet list
+    pub fn comet(&self, index: usize) -> Comet {
+        let mut ion = Emitter::new(self.rate * 0.5, self.length / ION_LIFETIME, 0.03, ION_LIFETIME, 0.12, self.ion_color, self.ion_color);
+        ion.growth = 2.0;
+        ion.intensity = 0.5;
+        let mut dust = Emitter::new(self.rate, self.length * 0.25 / DUST_LIFETIME, 0.3, DUST_LIFETIME, 0.15, self.dust_color, 0x806040);
+        dust.growth = 3.0;
+        dust.intensity = 0.35;
+        let mut comet = Comet::new(index, self.active_distance, dust, ion);
+        comet.coma_size = self.coma;
+        comet
+    }
+}
+
+// Magnetic field of a planet, see solar_wind.rs
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct MagnetosphereConfig {
+    pub standoff: f32, // Radios del planeta hasta la magnetopausa del lado del sol
+    pub tilt: f32,     // Grados, inclinación del dipolo respecto al eje Y
+    pub color: u32,    // De las líneas de campo
+}
+
+impl Default for MagnetosphereConfig {
+    fn default() -> Self {
+        MagnetosphereConfig {
+            standoff: 4.0,
+            tilt: 0.0,
+            color: 0x60C0FF,
+        }
+    }
+}
+
+// Particle streams out of the sun, see solar_wind.rs
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct SolarWindConfig {
+    pub enabled: bool,
+    pub rate: f32,        // Partículas por frame en todas direcciones
+    pub stream_rate: f32, // Partículas por frame hacia cada planeta con magnetosphere
+    pub speed: f32,       // Unidades por frame
+    pub reach: f32,       // Distancia al sol a la que se pierden
+    pub color: u32,
+}
+
+impl Default for SolarWindConfig {
+    fn default() -> Self {
+        SolarWindConfig {
+            enabled: false,
+            rate: 2.0,
+            stream_rate: 3.0,
+            speed: 0.15,
+            reach: 40.0,
+            color: 0xFFE6A0,
+        }
+    }
+}
+
+// The ship's mining laser, see mining.rs
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct MiningConfig {
+    pub range: f32, // Alcance del rayo, unidades
+    pub rate: f32,  // Toneladas por frame
+    pub color: u32,
+}
+
+impl Default for MiningConfig {
+    fn default() -> Self {
+        MiningConfig {
+            range: 3.0,
+            rate: 1.0,
+            color: 0xFF7A30,
+        }
+    }
+}
+
+// Station trade and the scenario's deliveries, see trade.rs
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct TradeConfig {
+    pub ore_price: f32, // Créditos por tonelada de mineral vendida en la estación
+    pub repeat: bool,   // Al terminar los contratos vuelven a empezar
+    pub contracts: Vec<ContractConfig>,
+}
+
+impl Default for TradeConfig {
+    fn default() -> Self {
+        TradeConfig {
+            ore_price: 2.0,
+            repeat: false,
+            contracts: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ContractConfig {
+    pub cargo: String,
+    pub tons: f32,
+    pub destination: String, // Planeta donde se entrega
+    #[serde(default = "default_delivery_zone")]
+    pub zone: f32,           // Radio de la zona de entrega, en radios del planeta
+    pub reward: f32,         // Créditos
+}
+
+fn default_delivery_zone() -> f32 {
+    4.0
+}
+
+// Mean-motion resonance: orbit_speed = speed of `with` * ratio[0] / ratio[1],
+// e.g. Neptune:Pluto is a 2:3 lock for Pluto
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ResonanceConfig {
+    pub with: String,
+    pub ratio: [u32; 2],
+}
+
+// Volumetric fog ellipsoid, see nebula.rs
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct NebulaConfig {
+    pub center: [f32; 3],
+    pub radii: [f32; 3],
+    pub core_color: u32,
+    pub edge_color: u32,
+    pub density: f32,
+    #[serde(default = "default_noise_scale")]
+    pub noise_scale: f32,
+    #[serde(default)]
+    pub seed: i32,
+    pub steps: Option<usize>,
+}
+
+impl NebulaConfig {
+    pub fn build(&self) -> Nebula {
+        let mut nebula = Nebula::new(
+            Vec3::from(self.center),
+            Vec3::from(self.radii),
+            self.core_color,
+            self.edge_color,
+            self.density,
+            self.noise_scale,
+            self.seed,
+        );
+        if let Some(steps) = self.steps {
+            nebula.steps = steps.max(1);
+        }
+        nebula
+    }
+}
+
+fn default_noise_scale() -> f32 {
+    0.3
+}
+
+// Procedural belt of rocks, see asteroids.rs
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct AsteroidBeltConfig {
+    pub count: usize,
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    pub thickness: f32,
+    pub min_size: f32,
+    pub max_size: f32,
+    pub orbit_speed: f32, // En el borde interior
+    #[serde(default)]
+    pub seed: u64,
+}
+
+// Seeded star systems around this one, see galaxy.rs
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct GalaxyConfig {
+    pub seed: u64,
+    pub systems: usize, // Contando este
+    pub radius: f32,    // Años luz
+}
+
+impl Default for GalaxyConfig {
+    fn default() -> Self {
+        GalaxyConfig {
+            seed: 1792,
+            systems: 24,
+            radius: 50.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SceneConfig {
+    #[serde(default = "default_barycenter_scale")]
+    pub barycenter_scale: f32, // Exagera el bamboleo del sol
+    #[serde(default = "default_days_per_step")]
+    pub days_per_step: f32,    // Días simulados que avanza un paso de orbit_speed
+    pub planets: Vec<PlanetConfig>,
+    #[serde(default)]
+    pub nebulae: Vec<NebulaConfig>,
+    pub asteroid_belt: Option<AsteroidBeltConfig>,
+    #[serde(default = "default_ships")]
+    pub ships: Vec<ShipConfig>,         // Solo se lee al arrancar
+    #[serde(default)]
+    pub spaceship: SpaceshipConfig,     // Solo se lee al arrancar
+    #[serde(default)]
+    pub escorts: Vec<EscortConfig>,     // Solo se lee al arrancar
+    pub station: Option<StationConfig>, // Solo se lee al arrancar
+    pub sky: Option<SkyConfig>,         // Solo se lee al arrancar
+    #[serde(default)]
+    pub camera: CameraConfig,           // Solo se lee al arrancar
+    #[serde(default)]
+    pub touchpad: TouchpadConfig,       // Solo se lee al arrancar
+    #[serde(default)]
+    pub bloom: BloomConfig,
+    #[serde(default)]
+    pub post: PostConfig,
+    #[serde(default)]
+    pub pass_scale: PassScaleConfig,
+    #[serde(default)]
+    pub export: ExportConfig,           // Solo se lee al arrancar
+    #[serde(default)]
+    pub depth: DepthConfig,
+    #[serde(default)]
+    pub sunlight: SunlightConfig,
+    #[serde(default)]
+    pub planetshine: PlanetshineConfig,
+    #[serde(default)]
+    pub probes: ProbesConfig,
+    #[serde(default)]
+    pub background: BackgroundConfig,
+    #[serde(default)]
+    pub gravity: GravityConfig,
+    #[serde(default)]
+    pub solar_wind: SolarWindConfig,
+    #[serde(default)]
+    pub mining: MiningConfig,
+    #[serde(default)]
+    pub trade: TradeConfig,
+    #[serde(default)]
+    pub galaxy: GalaxyConfig,           // Solo se lee al arrancar
+}
+
+fn default_barycenter_scale() -> f32 {
+    1.0
+}
+
+// Earth's orbit_speed of 0.02 going round in 365.25 days
+fn default_days_per_step() -> f32 {
+    365.25 * 0.02 / (2.0 * std::f32::consts::PI)
+}
+
+impl SceneConfig {
+    // The format is picked from the extension, .json or TOML otherwise
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let is_json = Path::new(path).extension().is_some_and(|extension| extension == "json");
+        if is_json {
+            serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))
+        } else {
+            toml::from_str(&text).map_err(|e| format!("{}: {}", path, e))
+        }
+    }
+
+    // Same format rule as `load`
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let is_json = Path::new(path).extension().is_some_and(|extension| extension == "json");
+        let text = if is_json {
+            serde_json::to_string_pretty(self).map_err(|e| format!("{}: {}", path, e))?
+        } else {
+            toml::to_string_pretty(self).map_err(|e| format!("{}: {}", path, e))?
+        };
+        fs::write(path, text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    // Every image the planets and ships use, to preload them in parallel
+    pub fn texture_paths(&self) -> BTreeSet<&str> {
+        self.planets.iter().map(|planet| &planet.texture)
+            .chain(self.ships.iter().map(|ship| &ship.texture))
+            .filter_map(|path| path.as_deref())
+            .collect()
+    }
+
+    pub fn normal_map_paths(&self) -> BTreeSet<&str> {
+        self.planets.iter().map(|planet| &planet.normal_map)
+            .chain(self.ships.iter().map(|ship| &ship.normal_map))
+            .filter_map(|path| path.as_deref())
+            .collect()
+    }
+
+    pub fn build_planets(&self, materials: &mut MaterialLibrary, max_octaves: i32) -> Result<Vec<Planet>, String> {
+        self.planets.iter().enumerate().map(|(index, config)| {
+            let orbit_speed = match &config.resonance {
+                Some(resonance) => self.resonant_speed(config, resonance)?,
+                None => config.orbit_speed,
+            };
+
+            let mut planet = Planet::new(
+                &config.name,
+                config.radius,
+                config.semi_major_axis,
+                orbit_speed,
+                config.rotation_speed,
+                config.color,
+                config.shader.resolve().map_err(|error| format!("{}: {}", config.name, error))?,
+            );
+            planet.eccentricity = config.eccentricity;
+            planet.inclination = config.inclination.to_radians();
+            planet.argument_of_periapsis = config.argument_of_periapsis.to_radians();
+            planet.longitude_of_node = config.longitude_of_node.to_radians();
+            planet.rings = config.rings.as_ref().map(|rings| Rings {
+                inner_radius: rings.inner_radius,
+                outer_radius: rings.outer_radius,
+                opacity: rings.opacity,
+            });
+            // Rings lie on the equator: without a tilt of its own the planet leans with theirs
+            planet.axial_tilt = config.axial_tilt
+                .or(config.rings.as_ref().map(|rings| rings.tilt))
+                .unwrap_or(0.0)
+                .to_radians();
+            planet.ring_mesh = planet.rings.as_ref().map(ring_mesh);
+            if let Some(atmosphere) = &config.atmosphere {
+                planet.atmosphere = Some(Atmosphere {
+                    thickness: atmosphere.thickness,
+                    color: atmosphere.color,
+                    density: atmosphere.density.clamp(0.0, 1.0),
+                    falloff: atmosphere.falloff.max(0.1),
+                    sunset: atmosphere.sunset,
+                    blend: BlendMode::from_name(&atmosphere.blend)
+                        .ok_or_else(|| format!("{}: unknown blend mode '{}'", config.name, atmosphere.blend))?,
+                });
+            }
+            planet.clouds = config.clouds.as_ref().map(|clouds| CloudLayer {
+                height: clouds.height.max(0.0),
+                rotation_speed: clouds.rotation_speed,
+                spin: 0.0,
+                color: clouds.color,
+                coverage: clouds.coverage.clamp(0.0, 1.0),
+                opacity: clouds.opacity.clamp(0.0, 1.0),
+                zoom: clouds.zoom,
+            });
+            planet.mesh = config.tail.as_ref().map(|_| nucleus_mesh(index as u64));
+            planet.sphere = config.subdivisions.filter(|_| planet.mesh.is_none()).map(SphereLod::new);
+            if let Some(parent) = &config.parent {
+                planet.parent = Some(self.parent_index(index, parent)?);
+            }
+            if let Some(mass) = config.mass {
+                planet.mass = mass;
+            }
+            if let Some(cull_back_faces) = config.cull_back_faces {
+                planet.cull_back_faces = cull_back_faces;
+            }
+            if let Some(noise) = &config.noise {
+                let noise = noise.build(max_octaves).map_err(|e| format!("{}: {}", config.name, e))?;
+                planet.noise = Arc::new(noise);
+            }
+            if let Some(relief) = config.relief.as_ref().filter(|_| planet.mesh.is_none()) {
+                if config.noise.is_none() {
+                    return Err(format!("{}: relief needs a noise", config.name));
+                }
+                let noise = Arc::clone(&planet.noise);
+                let sphere = planet.sphere.take().unwrap_or_else(|| SphereLod::new(DEFAULT_SUBDIVISIONS));
+                planet.sphere = Some(sphere.with_relief(|direction| {
+                    let point = direction * SPHERE_RADIUS * relief.scale;
+                    noise.get_noise_3d(point.x, point.y, point.z) * relief.height
+                }));
+            }
+            if let Some(name) = &config.shading {
+                planet.shading_mode = Some(ShadingMode::from_name(name)
+                    .ok_or_else(|| format!("{}: unknown shading mode '{}'", config.name, name))?);
+            }
+            planet.material = materials.material(config.texture.as_deref(), config.normal_map.as_deref())
+                .map_err(|e| format!("{}: {}", config.name, e))?;
+            planet.material.sampler = sampler(&config.filter, &config.wrap)
+                .map_err(|e| format!("{}: {}", config.name, e))?;
+            planet.material.thermal.temperature = config.temperature;
+            planet.material.specular = config.specular.as_ref().map_or_else(Specular::default, SpecularConfig::specular);
+            planet.material.pbr = config.pbr.as_ref().map(PbrConfig::pbr);
+            Ok(planet)
+        }).collect()
+    }
+
+    // Roster index of the ship the player starts with
+    pub fn starting_ship(&self) -> Result<usize, String> {
+        if self.ships.is_empty() {
+            return Err("the ship roster is empty".to_string());
+        }
+        match &self.spaceship.ship {
+            Some(name) => self.ships.iter()
+                .position(|ship| &ship.name == name)
+                .ok_or_else(|| format!("spaceship: ship '{}' not in the roster", name)),
+            None => Ok(0),
+        }
+    }
+
+    // Wingmen start on their slots around the player's starting position
+    pub fn build_escorts(&self) -> Result<EscortWing, String> {
+        let leader_position = Vec3::from(self.spaceship.position);
+        let leader_heading = heading(&from_euler(&Vec3::from(self.spaceship.rotation)));
+        let escorts = self.escorts.iter().enumerate()
+            .map(|(index, config)| self.build_escort(config, &leader_position, leader_heading)
+                .map_err(|e| format!("escort {}: {}", index, e)))
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(EscortWing::new(escorts))
+    }
+
+    // One wingman on its slot around the leader, its ship from the roster
+    pub fn build_escort(&self, config: &EscortConfig, leader_position: &Vec3, leader_heading: f32) -> Result<Escort, String> {
+        let ship = self.ships.iter()
+            .position(|ship| ship.name == config.ship)
+            .ok_or_else(|| format!("ship '{}' not in the roster", config.ship))?;
+        let offset = Vec3::from(config.offset);
+        Ok(Escort {
+            position: EscortWing::slot_position(leader_position, leader_heading, &offset),
+            velocity: Vec3::new(0.0, 0.0, 0.0),
+            heading: leader_heading,
+            offset,
+            ship,
+        })
+    }
+
+    pub fn build_station(&self) -> Result<Option<Station>, String> {
+        self.station.as_ref().map(|config| Ok(Station::new(
+            config.name.clone(),
+            Vec3::from(config.position),
+            config.heading.to_radians(),
+            config.scale,
+            config.shader.resolve().map_err(|error| format!("{}: {}", config.name, error))?,
+        ))).transpose()
+    }
+
+    // `density` is the share of the configured rocks the quality tier keeps
+    pub fn build_asteroid_belt(&self, density: f32) -> Option<AsteroidBelt> {
+        self.asteroid_belt.as_ref().map(|config| AsteroidBelt::new(
+            (config.count as f32 * density.clamp(0.0, 1.0)).round() as usize,
+            config.inner_radius,
+            config.outer_radius,
+            config.thickness,
+            (config.min_size, config.max_size),
+            config.orbit_speed,
+            config.seed,
+        ))
+    }
+
+    pub fn build_comets(&self) -> Vec<Comet> {
+        self.planets.iter()
+            .enumerate()
+            .filter_map(|(index, config)| config.tail.as_ref().map(|tail| tail.comet(index)))
+            .collect()
+    }
+
+    // Wind settings with the field of every planet that has one
+    pub fn build_solar_wind(&self, capacity: usize) -> SolarWind {
+        let mut wind = SolarWind::new(capacity, 0x501A);
+        wind.enabled = self.solar_wind.enabled;
+        wind.rate = self.solar_wind.rate;
+        wind.stream_rate = self.solar_wind.stream_rate;
+        wind.speed = self.solar_wind.speed;
+        wind.reach = self.solar_wind.reach;
+        wind.color = self.solar_wind.color;
+        wind.magnetospheres = self.planets.iter()
+            .enumerate()
+            .filter_map(|(index, config)| config.magnetosphere.as_ref().map(|field| Magnetosphere {
+                body: index,
+                standoff: field.standoff,
+                tilt: field.tilt.to_radians(),
+                color: field.color,
+            }))
+            .collect();
+        wind
+    }
+
+    pub fn build_mining_laser(&self) -> MiningLaser {
+        MiningLaser::new(self.mining.range, self.mining.rate, self.mining.color)
+    }
+
+    pub fn build_trade(&self) -> Result<Trade, String> {
+        let contracts = self.trade.contracts.iter()
+            .map(|contract| {
+                let destination = self.planets.iter()
+                    .position(|planet| planet.name == contract.destination)
+                    .ok_or_else(|| format!("trade: destination '{}' not found", contract.destination))?;
+                Ok(Contract {
+                    cargo: contract.cargo.clone(),
+                    tons: contract.tons,
+                    destination,
+                    zone: contract.zone,
+                    reward: contract.reward,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Trade::new(self.trade.ore_price, self.trade.repeat, contracts))
+    }
+
+    // The built-in effects with this file's settings and its order. Effects
+    // registered by other code stay in the chain, they only need naming.
+    pub fn configure_post_chain(&self, chain: &mut PostChain) {
+        chain.register(Box::new(self.bloom.bloom(self.pass_scale.bloom)));
+        let outline = &self.post.outline;
+        chain.register(Box::new(Outline::new(outline.color, outline.strength)));
+        chain.register(Box::new(TemporalAA::new(self.post.taa.blend)));
+        let motion_blur = &self.post.motion_blur;
+        chain.register(Box::new(MotionBlur::new(motion_blur.shutter, motion_blur.samples)));
+        chain.set_order(&self.post.chain);
+    }
+
+    // The galaxy around this system, named after its star
+    pub fn build_galaxy(&self) -> Galaxy {
+        let home = self.planets.iter()
+            .find(|planet| planet.semi_major_axis == 0.0)
+            .map_or("Sol", |planet| planet.name.as_str());
+        Galaxy::new(self.galaxy.seed, self.galaxy.systems, self.galaxy.radius, home)
+    }
+
+    pub fn build_nebulae(&self) -> Vec<Nebula> {
+        self.nebulae.iter().map(NebulaConfig::build).collect()
+    }
+
+    // Parents must be listed first so update_hierarchy can resolve them in order
+    fn parent_index(&self, index: usize, parent: &str) -> Result<usize, String> {
+        let config = &self.planets[index];
+        self.planets[..index].iter()
+            .position(|other| other.name == parent)
+            .ok_or_else(|| format!("{}: parent '{}' not found before it", config.name, parent))
+    }
+
+    fn resonant_speed(&self, config: &PlanetConfig, resonance: &ResonanceConfig) -> Result<f32, String> {
+        let partner = self.planets.iter()
+            .find(|other| other.name == resonance.with)
+            .ok_or_else(|| format!("{}: resonance partner '{}' not found", config.name, resonance.with))?;
+        if partner.resonance.is_some() {
+            return Err(format!("{}: resonance partner '{}' is itself resonant", config.name, resonance.with));
+        }
+        if resonance.ratio[1] == 0 {
+            return Err(format!("{}: resonance ratio cannot divide by zero", config.name));
+        }
+        Ok(partner.orbit_speed * resonance.ratio[0] as f32 / resonance.ratio[1] as f32)
+    }
+
+    // Replaces the planets keeping the orbit progress of the ones that still exist
+    pub fn apply(&self, planets: &mut Vec<Planet>, materials: &mut MaterialLibrary, max_octaves: i32) -> Result<(), String> {
+        let mut new_planets = self.build_planets(materials, max_octaves)?;
+        for planet in new_planets.iter_mut() {
+            if let Some(old) = planets.iter_mut().find(|old| old.name == planet.name) {
+                planet.current_angle = old.current_angle;
+                planet.spin = old.spin;
+                if let (Some(clouds), Some(old_clouds)) = (&mut planet.clouds, old.clouds) {
+                    clouds.spin = old_clouds.spin;
+                }
+                if old.shader.name() == planet.shader.name() {
+                    planet.surface_cache = old.surface_cache.take();
+                }
+            }
+        }
+        *planets = new_planets;
+        Ok(())
+    }
+
+    // Noise of every planet built again for a new octave cap, the rest of
+    // each planet is kept
+    pub fn rebuild_noise(&self, planets: &mut [Planet], max_octaves: i32) -> Result<(), String> {
+        for config in &self.planets {
+            let Some(noise) = &config.noise else {
+                continue;
+            };
+            if let Some(planet) = planets.iter_mut().find(|planet| planet.name == config.name) {
+                let noise = noise.build(max_octaves).map_err(|e| format!("{}: {}", config.name, e))?;
+                planet.noise = Arc::new(noise);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Polls a file's modification time to detect edits
+pub struct FileWatcher {
+    path: String,
+    last_modified: Option<SystemTime>,
+}
+
+impl FileWatcher {
+    pub fn new(path: &str) -> Self {
+        FileWatcher {
+            path: path.to_string(),
+            last_modified: modified_time(path),
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn changed(&mut self) -> bool {
+        let modified = modified_time(&self.path);
+        if modified != self.last_modified {
+            self.last_modified = modified;
+            modified.is_some()
+        } else {
+            false
+        }
+    }
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
@@ -0,0 +1,267 @@
+// session.rs
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use minifb::Key;
+use nalgebra::Quaternion;
+use nalgebra_glm::Vec3;
+use serde::{Deserialize, Serialize};
+use crate::font::{draw_text, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::framebuffer::Framebuffer;
+use crate::input::Input;
+use crate::orientation::Orientation;
+use crate::planet::Planet;
+use crate::Spaceship;
+
+const TEXT_SCALE: usize = 2;
+const ROW_HEIGHT: usize = (GLYPH_HEIGHT + 3) * TEXT_SCALE;
+const PANEL_PADDING: usize = 12;
+const PANEL_DIM: f32 = 0.25;
+const TITLE_COLOR: u32 = 0xFFFFFF;
+const SLOT_COLOR: u32 = 0xDDDDDD;
+const SELECTED_COLOR: u32 = 0x66FF66;
+const EMPTY_COLOR: u32 = 0x9AA8B8;
+const HINT_COLOR: u32 = 0x9AA8B8;
+
+// Where a body is along its orbit and how far it has turned
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BodyState {
+    pub name: String,
+    pub angle: f32, // Anomalía media
+    pub spin: f32,
+}
+
+// The flown ship: where it is and how it moves, plus the scorch on its hull
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ShipState {
+    pub name: String,
+    pub position: [f32; 3],
+    pub rotation: [f32; 4], // Cuaternión, i j k w
+    pub velocity: [f32; 3],
+    pub bank: f32,
+    pub damage: Vec<f32>,   // Texeles del mapa de quemaduras
+}
+
+impl ShipState {
+    pub fn capture(ship: &Spaceship) -> Self {
+        let rotation = ship.rotation.quaternion().coords;
+        ShipState {
+            name: ship.name.clone(),
+            position: ship.position.into(),
+            rotation: rotation.into(),
+            velocity: ship.flight.velocity.into(),
+            bank: ship.flight.bank,
+            damage: ship.damage.texels().to_vec(),
+        }
+    }
+
+    // The turn the ship was in is not kept, it comes back flying straight
+    pub fn apply(&self, ship: &mut Spaceship) {
+        let [i, j, k, w] = self.rotation;
+        ship.position = Vec3::from(self.position);
+        ship.rotation = Orientation::from_quaternion(Quaternion::new(w, i, j, k));
+        ship.flight = Default::default();
+        ship.flight.velocity = Vec3::from(self.velocity);
+        ship.flight.bank = self.bank;
+        std::sync::Arc::make_mut(&mut ship.damage).restore(&self.damage);
+    }
+}
+
+// The station's contracts: credits earned, the next one to load and the one on board
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TradeState {
+    pub credits: f32,
+    pub next: usize,
+    pub hold: Option<usize>,
+}
+
+// Everything needed to pick a game back up: simulated time, where every body
+// is, the ship, the view with its bookmarks and how far the missions went.
+// Bodies and ships are matched by name, so a session still loads after the
+// scene file changes; what is missing from it is left as it is.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Session {
+    pub saved_at: u64,             // Segundos Unix
+    #[serde(default)]
+    pub system: usize,             // Índice en la galaxia, 0 el de la escena
+    pub elapsed_days: f64,
+    pub rate: f32,                 // Segundos simulados por segundo real
+    pub bodies: Vec<BodyState>,
+    pub ship: ShipState,
+    pub camera: [[f32; 3]; 3],     // Ojo, centro y arriba
+    pub bookmarks: Vec<Option<[[f32; 3]; 2]>>, // Ojo y centro
+    pub cargo: f32,                // Toneladas de mineral
+    pub trade: TradeState,
+}
+
+impl Session {
+    pub fn capture_bodies(planets: &[Planet]) -> Vec<BodyState> {
+        planets.iter()
+            .map(|planet| BodyState { name: planet.name.clone(), angle: planet.current_angle, spin: planet.spin })
+            .collect()
+    }
+
+    // Puts every body saved back on its orbit, returns how many were not found
+    pub fn restore_bodies(&self, planets: &mut [Planet]) -> usize {
+        let mut missing = 0;
+        for body in &self.bodies {
+            match planets.iter_mut().find(|planet| planet.name == body.name) {
+                Some(planet) => {
+                    planet.current_angle = body.angle;
+                    planet.spin = body.spin;
+                }
+                None => missing += 1,
+            }
+        }
+        missing
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(|e| format!("{}: {}", directory.display(), e))?;
+        }
+        let text = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // One line for the load menu: game time, ship, credits and how long ago
+    fn summary(&self, now: u64) -> String {
+        let minutes = now.saturating_sub(self.saved_at) / 60;
+        let ago = match minutes {
+            0 => "NOW".to_string(),
+            1..=59 => format!("{} MIN AGO", minutes),
+            60..=1439 => format!("{} H AGO", minutes / 60),
+            _ => format!("{} D AGO", minutes / 1440),
+        };
+        format!("T+{:.0} D  {}  {:.0} CR  {}", self.elapsed_days, self.ship.name, self.trade.credits, ago)
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub enum MenuCommand {
+    Save(usize),
+    Load(usize),
+}
+
+// Modal list of the save slots. While it is open it takes the arrows, Enter
+// and S for itself: Up and Down pick a slot, Enter loads it and S saves the
+// session into it. Backspace or the chord that opened it closes it.
+pub struct SessionMenu {
+    pub open: bool,
+    directory: PathBuf,
+    selected: usize,
+    slots: Vec<Option<String>>, // Resumen de cada ranura, None vacía
+}
+
+impl SessionMenu {
+    pub fn new(directory: &str, slots: usize) -> Self {
+        SessionMenu {
+            open: false,
+            directory: PathBuf::from(directory),
+            selected: 0,
+            slots: vec![None; slots.max(1)],
+        }
+    }
+
+    pub fn path(&self, slot: usize) -> PathBuf {
+        self.directory.join(format!("slot_{}.json", slot + 1))
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        if self.open {
+            self.refresh();
+        }
+    }
+
+    // Reads the summaries again, after opening or saving
+    pub fn refresh(&mut self) {
+        let now = unix_now();
+        for slot in 0..self.slots.len() {
+            let path = self.path(slot);
+            self.slots[slot] = path.exists().then(|| match Session::load(&path) {
+                Ok(session) => session.summary(now),
+                Err(_) => "UNREADABLE".to_string(),
+            });
+        }
+    }
+
+    pub fn update(&mut self, input: &Input) -> Option<MenuCommand> {
+        if !self.open {
+            return None;
+        }
+        if input.is_pressed(Key::Backspace) {
+            self.open = false;
+            return None;
+        }
+        if input.is_pressed(Key::Up) {
+            self.selected = (self.selected + self.slots.len() - 1) % self.slots.len();
+        }
+        if input.is_pressed(Key::Down) {
+            self.selected = (self.selected + 1) % self.slots.len();
+        }
+        if input.is_pressed(Key::S) {
+            return Some(MenuCommand::Save(self.selected));
+        }
+        if input.is_pressed(Key::Enter) && self.slots[self.selected].is_some() {
+            return Some(MenuCommand::Load(self.selected));
+        }
+        None
+    }
+
+    // A dimmed panel in the middle of the screen with one row per slot
+    pub fn render(&self, framebuffer: &mut Framebuffer) {
+        if !self.open {
+            return;
+        }
+        let rows: Vec<(String, u32)> = self.slots.iter().enumerate()
+            .map(|(slot, summary)| {
+                let marker = if slot == self.selected { ">" } else { " " };
+                let color = match (slot == self.selected, summary) {
+                    (true, _) => SELECTED_COLOR,
+                    (false, Some(_)) => SLOT_COLOR,
+                    (false, None) => EMPTY_COLOR,
+                };
+                (format!("{} {}  {}", marker, slot + 1, summary.as_deref().unwrap_or("EMPTY")), color)
+            })
+            .collect();
+        let title = "SESSIONS";
+        let hint = "UP/DOWN  ENTER LOAD  S SAVE  BACKSPACE CLOSE";
+        let columns = rows.iter().map(|(text, _)| text.chars().count())
+            .chain([title.len(), hint.len()])
+            .max()
+            .unwrap_or(0);
+
+        let width = columns * (GLYPH_WIDTH + 1) * TEXT_SCALE + 2 * PANEL_PADDING;
+        let height = (rows.len() + 3) * ROW_HEIGHT + 2 * PANEL_PADDING;
+        let left = framebuffer.width.saturating_sub(width) / 2;
+        let top = framebuffer.height.saturating_sub(height) / 2;
+        for y in top..top + height {
+            for x in left..left + width {
+                framebuffer.darken(x, y, PANEL_DIM);
+            }
+        }
+
+        let x = left + PANEL_PADDING;
+        let mut y = top + PANEL_PADDING;
+        draw_text(framebuffer, x, y, title, TITLE_COLOR, TEXT_SCALE);
+        y += ROW_HEIGHT * 2;
+        for (text, color) in &rows {
+            draw_text(framebuffer, x, y, text, *color, TEXT_SCALE);
+            y += ROW_HEIGHT;
+        }
+        draw_text(framebuffer, x, y, hint, HINT_COLOR, TEXT_SCALE);
+    }
+}
@@ -0,0 +1,42 @@
+// settings.rs
+
+// Accessibility options consulted by the shaders, effects and simulation.
+// Any effect that shakes the camera, streaks the stars, strobes or flares
+// must check these before running.
+#[derive(Clone, Copy, Debug)]
+pub struct AccessibilitySettings {
+    pub reduced_motion: bool,      // No camera shake, star streaks or pulsating surfaces
+    pub photosensitive_safe: bool, // No strobing, flicker or lens flares
+    pub max_time_scale: f32,       // Simulation speed cap while reduced motion is on
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        AccessibilitySettings {
+            reduced_motion: false,
+            photosensitive_safe: false,
+            max_time_scale: 2.0,
+        }
+    }
+}
+
+impl AccessibilitySettings {
+    // Simulation speed actually applied after the accessibility cap
+    pub fn effective_time_scale(&self, requested: f32) -> f32 {
+        if self.reduced_motion {
+            requested.min(self.max_time_scale)
+        } else {
+            requested
+        }
+    }
+
+    // Multiplier for animated motion (pulsing, shaking, streaking)
+    pub fn motion_amount(&self) -> f32 {
+        if self.reduced_motion { 0.0 } else { 1.0 }
+    }
+
+    // Multiplier for flashing effects (flicker, strobes, flares)
+    pub fn flash_amount(&self) -> f32 {
+        if self.photosensitive_safe { 0.0 } else { 1.0 }
+    }
+}
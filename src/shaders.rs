@@ -1,15 +1,56 @@
 // shaders.rs
 
-use nalgebra_glm::{Vec3, Vec4, Mat3, mat4_to_mat3, dot, cross};
+use nalgebra_glm::{Vec2, Vec3, Vec4, Mat3, mat4_to_mat3, dot, cross};
 use crate::vertex::Vertex;
 use crate::Uniforms;
-use crate::fragment::Fragment;
+use crate::fragment::{Fragment, FragmentBuffer};
+use crate::material::Pbr;
+use crate::probes::AmbientCube;
 use crate::color::Color;
 use rand::Rng;
 use rand::SeedableRng;
 use rand::rngs::StdRng;
-use crate::texture::{Texture, with_texture};
-use crate::normal_map::{NormalMap, with_normal_map};
+use crate::planet::{Rings, SPHERE_RADIUS};
+use crate::surface_cache::uv_from_direction;
+use crate::thermal::{false_color, ViewMode};
+use crate::tuning::Param;
+use std::f32::consts::PI;
+use std::sync::Arc;
+use once_cell::sync::Lazy;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShadingMode {
+	PerFragment, // El shader se evalúa en cada fragmento
+	Gouraud,     // El shader se evalúa en los vértices y el color se interpola
+	Flat,        // Una sola normal por cara, ignora las normales de los vértices
+}
+
+impl ShadingMode {
+	pub fn next(self) -> Self {
+		match self {
+			ShadingMode::PerFragment => ShadingMode::Gouraud,
+			ShadingMode::Gouraud => ShadingMode::Flat,
+			ShadingMode::Flat => ShadingMode::PerFragment,
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name.to_lowercase().as_str() {
+			"per-fragment" => Some(ShadingMode::PerFragment),
+			"gouraud" => Some(ShadingMode::Gouraud),
+			"flat" => Some(ShadingMode::Flat),
+			_ => None,
+		}
+	}
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			ShadingMode::PerFragment => "per-fragment",
+			ShadingMode::Gouraud => "Gouraud",
+			ShadingMode::Flat => "flat",
+		}
+	}
+}
 
 pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
 	// Transform position
@@ -19,7 +60,8 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
 		vertex.position.z,
 		1.0
 	);
-	let transformed = uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix * position;
+	let world = uniforms.model_matrix * position;
+	let transformed = uniforms.projection_matrix * uniforms.view_matrix * world;
 
 	// Perform perspective division
 	let w = transformed.w;
@@ -39,57 +81,116 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
 
 	let transformed_normal = normal_matrix * vertex.normal;
 
+	// Tangents lie on the surface, they follow the model matrix itself
+	let tangent = model_mat3 * vertex.tangent.xyz();
+	let transformed_tangent = Vec4::new(tangent.x, tangent.y, tangent.z, vertex.tangent.w);
+
 	// Create a new Vertex with transformed attributes
 	Vertex {
 		position: vertex.position,
 		normal: vertex.normal,
 		tex_coords: vertex.tex_coords,
+		tangent: vertex.tangent,
 		color: vertex.color,
 		transformed_position: Vec3::new(screen_position.x, screen_position.y, screen_position.z),
 		transformed_normal,
+		transformed_tangent,
+		world_position: Vec3::new(world.x, world.y, world.z),
+		clip_position: transformed,
 	}
 }
 
-pub fn textured_fragment_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Color {
-    let base_color = with_texture(&|texture: &Texture| {
-        texture.sample(fragment.tex_coords.x, fragment.tex_coords.y)
-    });
-    
-    base_color
+// Evaluates the fragment shader once at a transformed vertex (Gouraud shading)
+pub fn shade_vertex(vertex: &Vertex, uniforms: &Uniforms, shader: &dyn PlanetShader) -> Color {
+	let normal = vertex.transformed_normal.normalize();
+	let light_dir = (uniforms.light_position - vertex.world_position).normalize();
+	let intensity = dot(&normal, &light_dir).max(0.0);
+
+	let fragment = Fragment {
+		view_direction: (uniforms.camera_position - vertex.world_position).normalize(),
+		tangent: vertex.transformed_tangent,
+		..Fragment::new(
+			Vec2::new(vertex.transformed_position.x, vertex.transformed_position.y),
+			vertex.color,
+			vertex.transformed_position.z,
+			normal,
+			intensity,
+			vertex.position,
+			vertex.tex_coords,
+		)
+	};
+
+	fragment_shader(&fragment, uniforms, shader)
 }
 
-pub fn calculate_lighting(fragment: &Fragment) -> f32 {
+// Objects without a texture keep their vertex color. `lod` picks the mipmap,
+// see Texture::sample_lod.
+pub fn textured_fragment_shader(fragment: &Fragment, uniforms: &Uniforms, lod: f32) -> Color {
+    match &uniforms.material.texture {
+        Some(texture) => texture.sample_lod(fragment.tex_coords.x, fragment.tex_coords.y, &uniforms.material.sampler, lod),
+        None => fragment.color,
+    }
+}
+
+// Mip level for a texture wrapped once around a planet sphere, from how
+// many texels land on one pixel at this fragment's distance. Grazing angles
+// stretch the footprint, so they pick a smaller level too. The quality tier
+// adds its bias on top.
+fn sphere_texture_lod(fragment: &Fragment, uniforms: &Uniforms, texture_width: u32) -> f32 {
+	let position = fragment.vertex_position;
+	let world = uniforms.model_matrix * Vec4::new(position.x, position.y, position.z, 1.0);
+	let world_position = Vec3::new(world.x, world.y, world.z);
+	let to_camera = uniforms.camera_position - world_position;
+	let distance = to_camera.magnitude().max(1e-3);
+
+	let radius = SPHERE_RADIUS * mat4_to_mat3(&uniforms.model_matrix).column(0).magnitude();
+	let texels_per_unit = texture_width as f32 / (2.0 * PI * radius);
+	let pixels_per_unit = uniforms.viewport_matrix[(1, 1)].abs() * uniforms.projection_matrix[(1, 1)] / distance;
+	let facing = dot(&fragment.normal, &(to_camera / distance)).max(0.1);
+
+	(texels_per_unit / (pixels_per_unit * facing)).max(1.0).log2() + uniforms.quality.lod_bias
+}
+
+pub fn calculate_lighting(fragment: &Fragment, uniforms: &Uniforms) -> f32 {
     // Sample the normal map and transform to world space
-    let normal_from_map = with_normal_map(|normal_map: &NormalMap| {
-        normal_map.sample(fragment.tex_coords.x, fragment.tex_coords.y)
-    });
+    let Some(normal_map) = &uniforms.material.normal_map else {
+        return fragment.intensity;
+    };
+    let normal_from_map = normal_map.sample(fragment.tex_coords.x, fragment.tex_coords.y, &uniforms.material.sampler);
     
     // Combine the normal from the map with the surface normal
     let modified_normal = (fragment.normal + normal_from_map).normalize();
     
     // Calculate lighting with the modified normal
-    let light_dir = Vec3::new(0.0, 0.0, 1.0);
-    dot(&modified_normal, &light_dir).max(0.0)
+    dot(&modified_normal, &world_light_direction(uniforms)).max(0.0)
 }
 
 
-pub fn calculate_tangent_lighting(fragment: &Fragment) -> f32 {
+pub fn calculate_tangent_lighting(fragment: &Fragment, uniforms: &Uniforms) -> f32 {
     // Sample the normal map (comes in tangent space)
-    let tangent_normal = with_normal_map(|normal_map: &NormalMap| {
-        normal_map.sample(fragment.tex_coords.x, fragment.tex_coords.y)
-    });
+    let Some(normal_map) = &uniforms.material.normal_map else {
+        return fragment.intensity;
+    };
+    let tangent_normal = normal_map.sample(fragment.tex_coords.x, fragment.tex_coords.y, &uniforms.material.sampler);
     
     // Calculate TBN matrix
     let normal = fragment.normal.normalize();
     
-    // Calculate tangent and bitangent
-    // This is a simple way to get tangent vectors - ideally these would come from the mesh data
-    let tangent = if normal.y.abs() < 0.999 {
-        cross(&Vec3::new(0.0, 1.0, 0.0), &normal).normalize()
+    // Tangent and bitangent from the mesh's texture coordinates, meshes
+    // without them get a tangent made up from world up
+    let mesh_tangent = fragment.tangent.xyz();
+    let (tangent, bitangent) = if mesh_tangent.magnitude_squared() > 1e-12 {
+        let tangent = (mesh_tangent - normal * dot(&normal, &mesh_tangent)).normalize();
+        let handedness = if fragment.tangent.w < 0.0 { -1.0 } else { 1.0 };
+        (tangent, cross(&normal, &tangent) * handedness)
     } else {
-        cross(&Vec3::new(0.0, 0.0, 1.0), &normal).normalize()
+        let tangent = if normal.y.abs() < 0.999 {
+            cross(&Vec3::new(0.0, 1.0, 0.0), &normal).normalize()
+        } else {
+            cross(&Vec3::new(0.0, 0.0, 1.0), &normal).normalize()
+        };
+        (tangent, cross(&normal, &tangent).normalize())
     };
-    let bitangent = cross(&normal, &tangent).normalize();
     
     // Create TBN matrix to transform from tangent space to world space
     let tbn = Mat3::new(
@@ -102,27 +203,454 @@ pub fn calculate_tangent_lighting(fragment: &Fragment) -> f32 {
     let world_normal = (tbn * tangent_normal).normalize();
     
     // Calculate lighting with the transformed normal
-    let light_dir = Vec3::new(0.0, 0.0, 1.0);
-    dot(&world_normal, &light_dir).max(0.0)
+    dot(&world_normal, &world_light_direction(uniforms)).max(0.0)
+}
+
+pub fn fragment_shader(fragment: &Fragment, uniforms: &Uniforms, shader: &dyn PlanetShader) -> Color {
+	let light = LightTerms::at(fragment, uniforms, shader);
+	shade_lit(fragment, uniforms, shader, &light)
+}
+
+// The lighting every shader gets on top of its own color at one fragment:
+// how much the rings and the distance to the sun dim it, the material's
+// highlight and the light from the background and the probes
+pub struct LightTerms {
+	pub dimming: f32,
+	pub highlight: f32,
+	pub ambient: Color,
+}
+
+impl LightTerms {
+	fn at(fragment: &Fragment, uniforms: &Uniforms, shader: &dyn PlanetShader) -> Self {
+		let sunlight = sunlight_factor(uniforms, shader);
+		LightTerms {
+			dimming: ring_light(uniforms, shader)
+				.map_or(sunlight, |(direction, rings)| ring_shadow(&fragment.vertex_position, &direction, &rings) * sunlight),
+			highlight: highlight_shininess(uniforms, shader)
+				.map_or(0.0, |shininess| highlight(&fragment.normal, &fragment.view_direction, &world_light_direction(uniforms), shininess)),
+			ambient: ambient_light(uniforms, shader).map_or(Color::black(), |ambient| ambient.irradiance(&fragment.normal)),
+		}
+	}
+}
+
+// LightTerms for every fragment of a triangle, each term a loop over the
+// buffer's arrays with what is the same for the whole object worked out once
+pub fn light_fragments(fragments: &mut FragmentBuffer, uniforms: &Uniforms, shader: &dyn PlanetShader) {
+	let count = fragments.positions.len();
+	let sunlight = sunlight_factor(uniforms, shader);
+	match ring_light(uniforms, shader) {
+		Some((direction, rings)) => fragments.dimming.extend(fragments.vertex_positions.iter()
+			.map(|position| ring_shadow(position, &direction, &rings) * sunlight)),
+		None => fragments.dimming.resize(count, sunlight),
+	}
+
+	match highlight_shininess(uniforms, shader) {
+		Some(shininess) => {
+			let light_direction = world_light_direction(uniforms);
+			fragments.highlights.extend(fragments.normals.iter()
+				.zip(&fragments.view_directions)
+				.map(|(normal, view_direction)| highlight(normal, view_direction, &light_direction, shininess)));
+		}
+		None => fragments.highlights.resize(count, 0.0),
+	}
+
+	match ambient_light(uniforms, shader) {
+		Some(ambient) => fragments.ambient.extend(fragments.normals.iter().map(|normal| ambient.irradiance(normal))),
+		None => fragments.ambient.resize(count, Color::black()),
+	}
+}
+
+// Everything but the sun itself gets dimmer away from it
+fn sunlight_factor(uniforms: &Uniforms, shader: &dyn PlanetShader) -> f32 {
+	if shader.is_star() {
+		1.0
+	} else {
+		uniforms.sunlight.at(object_light_distance(uniforms))
+	}
+}
+
+// Ringed planets receive the shadow of their rings
+fn ring_light(uniforms: &Uniforms, shader: &dyn PlanetShader) -> Option<(Vec3, Rings)> {
+	match uniforms.rings {
+		Some(rings) if shader.name() != RING_SHADER => Some((object_light_direction(uniforms), rings)),
+		_ => None,
+	}
+}
+
+// A physically based material lights the surface itself, otherwise the
+// material's highlight goes on top
+fn highlight_shininess(uniforms: &Uniforms, shader: &dyn PlanetShader) -> Option<f32> {
+	let specular = uniforms.material.specular;
+	let plain = uniforms.material.pbr.is_none() && !specular.color.is_black() && !shader.is_star();
+	plain.then_some(specular.shininess)
+}
+
+// The background's glow and the light probes', by the way the surface faces
+fn ambient_light(uniforms: &Uniforms, shader: &dyn PlanetShader) -> Option<AmbientCube> {
+	uniforms.ambient.filter(|_| takes_ambient(uniforms, shader))
+}
+
+fn takes_ambient(uniforms: &Uniforms, shader: &dyn PlanetShader) -> bool {
+	uniforms.ambient.is_some() && (shader.uses_lighting() || uniforms.material.pbr.is_some())
+}
+
+// The shader's own color with the lighting terms applied
+pub fn shade_lit(fragment: &Fragment, uniforms: &Uniforms, shader: &dyn PlanetShader, light: &LightTerms) -> Color {
+	if uniforms.view_mode == ViewMode::Thermal {
+		return thermal_shader(fragment, uniforms, shader);
+	}
+	let color = match uniforms.material.pbr {
+		Some(pbr) if !shader.is_star() => cook_torrance(fragment, uniforms, shader, &pbr),
+		_ if light.highlight > 0.0 => shader.shade(fragment, uniforms) + uniforms.material.specular.color * light.highlight,
+		_ => shader.shade(fragment, uniforms),
+	};
+	let color = color * light.dimming;
+
+	// Light reflected by a nearby planet, it carries its own falloff
+	let color = match uniforms.planetshine {
+		Some(shine) if shader.uses_lighting() => color + planetshine_light(fragment, uniforms, shader, &shine),
+		_ => color,
+	};
+
+	let color = if takes_ambient(uniforms, shader) {
+		let relit = Fragment { intensity: 1.0, ..*fragment };
+		color + shader.shade(&relit, uniforms).blend_multiply(&light.ambient)
+	} else {
+		color
+	};
+
+	// Light of its own, the same near the sun or far from it
+	match uniforms.material.pbr {
+		Some(pbr) if !pbr.emissive.is_black() => color + pbr.emissive,
+		_ => color,
+	}
+}
+
+// Cook-Torrance with a GGX distribution, Smith-Schlick shadowing and
+// Schlick's Fresnel. The shader's color at full light is the albedo: shaders
+// that use fragment.intensity give their unlit color, the spaceship its bare
+// hull. Metals have no diffuse and tint their highlight with the albedo;
+// everything else reflects 4% head on, more at grazing angles.
+fn cook_torrance(fragment: &Fragment, uniforms: &Uniforms, shader: &dyn PlanetShader, pbr: &Pbr) -> Color {
+	let relit = Fragment { intensity: 1.0, ..*fragment };
+	let albedo = shader.shade(&relit, uniforms).blend_multiply(&pbr.albedo);
+	let base = albedo.to_linear();
+	let f0 = base.map(|channel| DIELECTRIC_REFLECTANCE + (channel - DIELECTRIC_REFLECTANCE) * pbr.metallic);
+
+	// fragment.intensity is N·L, already zero on the night side
+	let n_dot_l = fragment.intensity.clamp(0.0, 1.0);
+	let light_direction = world_light_direction(uniforms);
+	let view_direction = fragment.view_direction;
+	// Baked surfaces have no view direction, they only keep the diffuse part
+	let (specular, fresnel) = if view_direction == Vec3::zeros() || n_dot_l <= 0.0 {
+		(0.0, f0.map(|_| 0.0))
+	} else {
+		let normal = fragment.normal;
+		let half_vector = (light_direction + view_direction).normalize();
+		let n_dot_v = dot(&normal, &view_direction).max(1e-4);
+		let n_dot_h = dot(&normal, &half_vector).max(0.0);
+		let v_dot_h = dot(&view_direction, &half_vector).max(0.0);
+
+		let alpha = pbr.roughness.max(MIN_ROUGHNESS).powi(2);
+		let alpha2 = alpha * alpha;
+		let denominator = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+		let distribution = alpha2 / (PI * denominator * denominator);
+		let k = (pbr.roughness + 1.0).powi(2) / 8.0;
+		let geometry = n_dot_v / (n_dot_v * (1.0 - k) + k) * n_dot_l / (n_dot_l * (1.0 - k) + k);
+		// π cancels the 1/π the diffuse term leaves out, so white matte stays N·L
+		let specular = PI * distribution * geometry / (4.0 * n_dot_v);
+		(specular, f0.map(|reflectance| reflectance + (1.0 - reflectance) * (1.0 - v_dot_h).powi(5)))
+	};
+
+	let channel = |index: usize| {
+		let diffuse = (1.0 - fresnel[index]) * (1.0 - pbr.metallic) * base[index] * n_dot_l;
+		diffuse + fresnel[index] * specular
+	};
+	Color::from_linear(channel(0), channel(1), channel(2)).with_alpha(albedo.alpha())
+}
+
+// Blinn-Phong: how much of the sun a fragment mirrors towards the eye, from
+// the normal against the vector halfway between the light and the view.
+// Nothing on the night side or without a view direction, as when baking.
+pub fn blinn_phong(fragment: &Fragment, uniforms: &Uniforms, shininess: f32) -> f32 {
+	highlight(&fragment.normal, &fragment.view_direction, &world_light_direction(uniforms), shininess)
+}
+
+fn highlight(normal: &Vec3, view_direction: &Vec3, light_direction: &Vec3, shininess: f32) -> f32 {
+	if *view_direction == Vec3::zeros() || dot(normal, light_direction) <= 0.0 {
+		return 0.0;
+	}
+	let half_vector = (light_direction + view_direction).normalize();
+	dot(normal, &half_vector).max(0.0).powf(shininess)
+}
+
+// Thermal view: the temperature of the body, warmer on its day side and
+// with its surface pattern as small variations, through a false-color palette.
+// Engines add their heat towards the tail.
+fn thermal_shader(fragment: &Fragment, uniforms: &Uniforms, shader: &dyn PlanetShader) -> Color {
+	let thermal = &uniforms.material.thermal;
+	let body = thermal.body_temperature(shader.is_star(), object_light_distance(uniforms), uniforms.sunlight.reference_distance);
+	let day = if shader.uses_lighting() { fragment.intensity.clamp(0.0, 1.0) } else { 1.0 };
+	let relit = Fragment { intensity: 1.0, ..*fragment };
+	let surface_color = shader.shade(&relit, uniforms);
+	let [r, g, b] = surface_color.to_linear();
+	let detail = (0.2126 * r + 0.7152 * g + 0.0722 * b).clamp(0.0, 1.0);
+	let surface = body * (NIGHT_SIDE_WARMTH + (1.0 - NIGHT_SIDE_WARMTH) * day) * (0.9 + 0.2 * detail);
+	false_color(surface + thermal.engine_temperature(fragment.vertex_position.z)).with_alpha(surface_color.alpha())
+}
+
+// The surface shaded again as if lit from the other body, tinted per channel
+// by its reflected light. Only shaders that use fragment.intensity can be relit.
+fn planetshine_light(fragment: &Fragment, uniforms: &Uniforms, shader: &dyn PlanetShader, shine: &Planetshine) -> Color {
+	let facing = dot(&fragment.normal, &(shine.position - object_center(uniforms)).normalize());
+	if facing <= 0.0 {
+		return Color::black();
+	}
+	let relit = Fragment { intensity: facing, ..*fragment };
+	shader.shade(&relit, uniforms).blend_multiply(&shine.color)
+}
+
+// Sunlight received by an object, 1/d² relative to what it gets at
+// `reference_distance` and kept between `floor` and `ceiling` so the outer
+// planets stay visible and the inner ones don't burn out
+#[derive(Clone, Copy, Debug)]
+pub struct SunlightFalloff {
+	pub enabled: bool,
+	pub reference_distance: f32,
+	pub floor: f32,
+	pub ceiling: f32,
+}
+
+impl SunlightFalloff {
+	pub fn at(&self, distance: f32) -> f32 {
+		if !self.enabled {
+			return 1.0;
+		}
+		let ratio = self.reference_distance / distance.max(1e-3);
+		(ratio * ratio).clamp(self.floor, self.ceiling.max(self.floor))
+	}
+}
+
+// Sunlight reflected by a nearby body onto the one being drawn: Earth's glow
+// on the Moon's night side or Jupiter's warm tint on Io
+#[derive(Clone, Copy, Debug)]
+pub struct Planetshine {
+	pub position: Vec3, // Centro del cuerpo que refleja
+	pub color: Color,   // Luz que llega, ya escalada
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PlanetshineSettings {
+	pub enabled: bool,
+	pub albedo: f32,
+	pub max_intensity: f32,
+	pub min_intensity: f32,
+}
+
+impl PlanetshineSettings {
+	// Brightest of `sources` (center, radius, color) as seen from `receiver`.
+	// Each is a Lambertian sphere: the sunlight it gets, times its albedo,
+	// (R/d)² and the phase function of the lit part that faces the receiver.
+	pub fn brightest(
+		&self,
+		receiver: Vec3,
+		sources: impl Iterator<Item = (Vec3, f32, u32)>,
+		sun_position: Vec3,
+		sunlight: &SunlightFalloff,
+	) -> Option<Planetshine> {
+		if !self.enabled {
+			return None;
+		}
+		sources
+			.filter_map(|(position, radius, color)| {
+				let to_receiver = receiver - position;
+				let to_sun = sun_position - position;
+				let distance = to_receiver.magnitude();
+				if distance <= radius || to_sun.magnitude() < 1e-3 {
+					return None;
+				}
+				let phase_angle = dot(&(to_receiver / distance), &to_sun.normalize()).clamp(-1.0, 1.0).acos();
+				let phase = (phase_angle.sin() + (PI - phase_angle) * phase_angle.cos()) / PI;
+				let ratio = radius / distance;
+				let intensity = sunlight.at(to_sun.magnitude()) * self.albedo * (2.0 / 3.0) * ratio * ratio * phase;
+				(intensity >= self.min_intensity).then_some((position, color, intensity))
+			})
+			.max_by(|a, b| a.2.total_cmp(&b.2))
+			.map(|(position, color, intensity)| Planetshine {
+				position,
+				color: Color::from_hex(color) * intensity.min(self.max_intensity),
+			})
+	}
+}
+
+// Distance from the object's center to the light
+pub fn object_light_distance(uniforms: &Uniforms) -> f32 {
+	(uniforms.light_position - object_center(uniforms)).magnitude()
+}
+
+// Translation of the model matrix
+fn object_center(uniforms: &Uniforms) -> Vec3 {
+	Vec3::new(uniforms.model_matrix[(0, 3)], uniforms.model_matrix[(1, 3)], uniforms.model_matrix[(2, 3)])
+}
+
+// Direction from the object's center towards the light, in world space
+pub fn world_light_direction(uniforms: &Uniforms) -> Vec3 {
+	(uniforms.light_position - object_center(uniforms)).normalize()
 }
 
-pub fn fragment_shader(fragment: &Fragment, uniforms: &Uniforms, current_shader: u32) -> Color {
+// Direction towards the light in the object's model space
+pub fn object_light_direction(uniforms: &Uniforms) -> Vec3 {
+	let world_direction = world_light_direction(uniforms);
+	// The model matrix is a rotation times a uniform scale, so its transpose
+	// undoes the rotation and the scale goes away when normalizing
+	(mat4_to_mat3(&uniforms.model_matrix).transpose() * world_direction).normalize()
+}
+
+// Light factor for a surface point (model space) whose path to the light may
+// cross the ring annulus in the equatorial plane (y = 0)
+pub fn ring_shadow(position: &Vec3, light_direction: &Vec3, rings: &Rings) -> f32 {
+	if light_direction.y.abs() < 1e-4 {
+		return 1.0;
+	}
+	let t = -position.y / light_direction.y;
+	if t <= 0.0 {
+		return 1.0;
+	}
+
+	let hit = position + light_direction * t;
+	let distance = (hit.x * hit.x + hit.z * hit.z).sqrt() / SPHERE_RADIUS;
+	if distance >= rings.inner_radius && distance <= rings.outer_radius {
+		1.0 - rings.opacity
+	} else {
+		1.0
+	}
+}
+
+// Whether a point (model space) lies in the planet's umbra: a cone behind the
+// planet along the light direction narrowing by `cone_slope` per unit length
+pub fn in_planet_shadow(position: &Vec3, light_direction: &Vec3, cone_slope: f32) -> bool {
+	let along = -dot(position, light_direction);
+	if along <= 0.0 {
+		return false;
+	}
+	let radial = (position + light_direction * along).magnitude();
+	radial < SPHERE_RADIUS - along * cone_slope
+}
+
+const NIGHT_SIDE_WARMTH: f32 = 0.7; // Temperatura de la cara nocturna respecto a la diurna
+const DIELECTRIC_REFLECTANCE: f32 = 0.04; // Lo que refleja de frente lo que no es metal
+const MIN_ROUGHNESS: f32 = 0.05; // Un espejo perfecto daría un brillo de un solo píxel
+
+pub const SUN_SHADER: &str = "sun";
+pub const RING_SHADER: &str = "ring";
+pub const ASTEROID_SHADER: &str = "asteroid";
+pub const ATMOSPHERE_SHADER: &str = "atmosphere";
+pub const CLOUD_SHADER: &str = "cloud-layer";
+pub const DEFAULT_SHADER: &str = "vertex-color";
+
+// A surface shader: the color of one fragment of whatever is drawn with it.
+// Bodies, ships and the station hold one and the scene file picks it by name.
+// A new one is a function and a line in BUILTIN_SHADERS.
+pub trait PlanetShader: Send + Sync {
+	fn name(&self) -> &str;
+	fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color;
+
+	// Whether `shade` already multiplies by fragment.intensity, which lets
+	// planetshine and ambient light relight the surface
+	fn uses_lighting(&self) -> bool {
+		false
+	}
+
+	// Shines on its own: no sunlight falloff and the hottest body in the thermal view
+	fn is_star(&self) -> bool {
+		false
+	}
+}
+
+pub type Shader = Arc<dyn PlanetShader>;
+
+type ShadeFn = fn(&Fragment, &Uniforms) -> Color;
+
+struct BuiltinShader {
+	name: &'static str,
+	shade: ShadeFn,
+	lit: bool,
+}
 
-	// Call the appropriate shader based on the current_shader value
-	match current_shader {
-		0 => lava_planet_shader(fragment, uniforms),
-		1 => gas_planet_color(fragment, uniforms),
-		2 => sun_shader(fragment, uniforms),
-		3 => rocky_planet_shader(fragment, uniforms),
-		4 => gas_giant_shader(fragment, uniforms),
-		5 => ice_planet_shader(fragment, uniforms),
-		6 => wave_shader(fragment, uniforms),
-		7 => moon_shader(fragment, uniforms),
-        8 => atmospheric_shader(fragment, uniforms),
-        9 => dynamic_surface_shader(fragment, uniforms),
-        10 => earth_clouds(fragment, uniforms),
-        _ => default_shader(fragment, uniforms),
+impl PlanetShader for BuiltinShader {
+	fn name(&self) -> &str {
+		self.name
 	}
+
+	fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+		(self.shade)(fragment, uniforms)
+	}
+
+	fn uses_lighting(&self) -> bool {
+		self.lit
+	}
+
+	fn is_star(&self) -> bool {
+		self.name == SUN_SHADER
+	}
+}
+
+// The built-in shaders, in the order of the numbers scene files used before
+// shaders had names: `shader = 3` is still "rocky"
+const BUILTIN_SHADERS: [(&str, ShadeFn, bool); 18] = [
+	("lava", lava_planet_shader, true),
+	("gas", gas_planet_color, true),
+	(SUN_SHADER, sun_shader, false),
+	("rocky", rocky_planet_shader, true),
+	("gas-giant", gas_giant_shader, true),
+	("ice", ice_planet_shader, true),
+	("waves", wave_shader, true),
+	("moon", moon_shader, true),
+	("atmospheric", atmospheric_shader, true),
+	("dynamic", dynamic_surface_shader, true),
+	("clouds", earth_clouds, true),
+	("spaceship", spaceship_shader, false),
+	(RING_SHADER, ring_shader, false),
+	(ASTEROID_SHADER, asteroid_shader, false),
+	("earth-texture", earth_texture_shader, false),
+	(ATMOSPHERE_SHADER, atmosphere_shell_shader, false),
+	(CLOUD_SHADER, cloud_layer_shader, false),
+	(DEFAULT_SHADER, default_shader, false),
+];
+
+static SHADERS: Lazy<Vec<Shader>> = Lazy::new(|| {
+	BUILTIN_SHADERS.iter()
+		.map(|&(name, shade, lit)| Arc::new(BuiltinShader { name, shade, lit }) as Shader)
+		.collect()
+});
+
+pub fn find_shader(name: &str) -> Option<Shader> {
+	SHADERS.iter().find(|shader| shader.name() == name).cloned()
+}
+
+// One of the built-in shaders, which are always there
+pub fn builtin_shader(name: &str) -> Shader {
+	find_shader(name).unwrap_or_else(|| panic!("no built-in shader named {}", name))
+}
+
+// A shader by its old number, the vertex color one past the built-in list
+pub fn numbered_shader(index: u32) -> Shader {
+	let name = BUILTIN_SHADERS.get(index as usize).map_or(DEFAULT_SHADER, |&(name, _, _)| name);
+	builtin_shader(name)
+}
+
+// Ring density at the fragment's radius: noise bands, a Cassini-like gap and
+// soft inner and outer edges, scaled by the ring opacity. It is the alpha of
+// the ring's color.
+fn ring_coverage(fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+	let Some(rings) = uniforms.rings else { return 0.0 };
+	let radial = fragment.tex_coords.x.clamp(0.0, 1.0);
+
+	let bands = uniforms.noise.get_noise_2d(radial * 400.0, 0.0) * 0.5 + 0.5;
+	let gap = ((radial - 0.62).abs() / 0.03).min(1.0);
+	let edges = (radial / 0.05).min(1.0) * ((1.0 - radial) / 0.05).min(1.0);
+
+	(rings.opacity * (0.4 + 0.6 * bands) * gap * edges).clamp(0.0, 1.0)
 }
 
 fn default_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Color {
@@ -130,9 +658,17 @@ fn default_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Color {
 }
 
 fn earth_texture_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-    // let intensity = calculate_lighting(fragment);
-    let intensity = calculate_tangent_lighting(fragment);
-    let texture_color = textured_fragment_shader(fragment, uniforms);
+    // The sphere's texture coordinates are only exact at its vertices, so the
+    // planet maps the texture equirectangularly from its model-space position
+    let (u, v) = uv_from_direction(&fragment.vertex_position.normalize());
+    let fragment = Fragment { tex_coords: Vec2::new(u, v), ..*fragment };
+
+    let lod = uniforms.material.texture.as_ref()
+        .map_or(0.0, |texture| sphere_texture_lod(&fragment, uniforms, texture.width()));
+
+    // let intensity = calculate_lighting(&fragment, uniforms);
+    let intensity = calculate_tangent_lighting(&fragment, uniforms);
+    let texture_color = textured_fragment_shader(&fragment, uniforms, lod);
     texture_color * intensity
 }
 
@@ -147,11 +683,12 @@ fn atmospheric_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let cloud_color = Color::new(255, 255, 255); // Blanco para nubes
 
     let blend_factor = (noise_value + 1.0) / 2.0; // Escalar a rango [0, 1]
-    base_color.lerp(&cloud_color, blend_factor)
+    base_color.lerp(&cloud_color, blend_factor) * fragment.intensity
 }
 
 fn earth_clouds(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-    let zoom = 80.0;
+    let params = &uniforms.shader_params;
+    let zoom = params.number(Param::CloudZoom);
     let x = fragment.vertex_position.x;
     let y = fragment.vertex_position.y;
     let t = uniforms.time as f32 * 0.1;
@@ -163,10 +700,11 @@ fn earth_clouds(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let desert_color = Color::new(210, 180, 140);
     let snow_color = Color::new(255, 250, 250);
 
-    let snow_threshold = 0.7;
-    let land_threshold = 0.4;
-    let desert_threshold = 0.3;
+    let snow_threshold = params.number(Param::CloudSnow);
+    let land_threshold = params.number(Param::CloudLand);
+    let desert_threshold = params.number(Param::CloudDesert);
 
+    let is_ocean = y.abs() <= snow_threshold && surface_noise <= desert_threshold;
     let base_color = if y.abs() > snow_threshold {
         snow_color
     } else if surface_noise > land_threshold {
@@ -177,20 +715,51 @@ fn earth_clouds(fragment: &Fragment, uniforms: &Uniforms) -> Color {
         ocean_color
     };
 
-    let cloud_zoom = 100.0;
+    let cloud_zoom = params.number(Param::CloudCoverZoom);
     let cloud_noise = uniforms.noise.get_noise_2d(x * cloud_zoom + t * 0.5, y * cloud_zoom + t * 0.5);
 
     let cloud_color = Color::new(255, 255, 255);
     let sky_gradient = Color::new(135, 206, 250);
 
     let cloud_intensity = cloud_noise.clamp(0.4, 0.7) - 0.4;
-    let final_color = if cloud_noise > 0.6 {
+    let final_color = if cloud_noise > params.number(Param::CloudCover) {
         base_color.lerp(&cloud_color, cloud_intensity * 0.5)
     } else {
         base_color.lerp(&sky_gradient, 0.1)
     };
 
-    final_color * fragment.intensity
+    // Open water mirrors the sun, clouds over it hide the glint
+    let glint = if is_ocean && cloud_noise <= params.number(Param::CloudCover) {
+        blinn_phong(fragment, uniforms, params.number(Param::CloudGlintShininess)) * params.number(Param::CloudGlint)
+    } else {
+        0.0
+    };
+
+    let land = if is_ocean || y.abs() > snow_threshold { 0.0 } else { 1.0 };
+    final_color * fragment.intensity + Color::new(255, 250, 235) * glint + city_lights(fragment, uniforms, land)
+}
+
+// Lights of the cities on the side away from the sun, fading in across the
+// terminator. Towns cluster inside noise regions and only on land; the poles
+// stay dark.
+fn city_lights(fragment: &Fragment, uniforms: &Uniforms, land: f32) -> Color {
+    let params = &uniforms.shader_params;
+    let terminator = params.number(Param::CityTerminator);
+    let sun = dot(&fragment.normal, &world_light_direction(uniforms));
+    let night = ((terminator - sun) / (2.0 * terminator)).clamp(0.0, 1.0);
+    let direction = fragment.vertex_position.normalize();
+    let inhabited = ((0.8 - direction.y.abs()) * 10.0).clamp(0.0, 1.0);
+    if night * land * inhabited <= 0.0 {
+        return Color::black();
+    }
+
+    let zoom = params.number(Param::CityZoom);
+    let point = direction * SPHERE_RADIUS * zoom;
+    let regions = uniforms.noise.get_noise_3d(point.x, point.y, point.z);
+    let towns = uniforms.noise.get_noise_3d(point.x * 6.0 + 500.0, point.y * 6.0, point.z * 6.0);
+    let density = ((regions - 0.1) * 2.5).clamp(0.0, 1.0) * ((towns - 0.2) * 2.0).clamp(0.0, 1.0);
+
+    params.color(Param::CityColor) * (params.number(Param::CityLights) * night * land * inhabited * density)
 }
 
 
@@ -205,7 +774,7 @@ fn dynamic_surface_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let water_color = Color::new(30, 144, 255); // Azul para agua
 
     let blend_factor = (noise_value + 1.0) / 2.0;
-    land_color.lerp(&water_color, blend_factor)
+    land_color.lerp(&water_color, blend_factor) * fragment.intensity
 }
 
 
@@ -242,7 +811,7 @@ fn moon_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let t = uniforms.time as f32 * 0.1;
 
     // Añadimos un efecto pulsante a los cráteres
-    let pulsate = (t * 0.5).sin() * 0.05;
+    let pulsate = (t * 0.5).sin() * 0.05 * uniforms.accessibility.motion_amount();
 
     // Ruido para la textura de la superficie
     let surface_noise = uniforms.noise.get_noise_2d(x * zoom + t, y * zoom + t);
@@ -267,7 +836,9 @@ fn moon_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 
 fn gas_planet_color(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     // Utiliza la posición del fragmento y el tiempo para generar un "seed" para el ruido.
-    let seed = uniforms.time as f32 * fragment.vertex_position.y * fragment.vertex_position.x;
+    // Sin el tiempo el patrón queda fijo y no parpadea (modo fotosensible)
+    let flicker_time = uniforms.time as f32 * uniforms.accessibility.flash_amount();
+    let seed = flicker_time * fragment.vertex_position.y * fragment.vertex_position.x;
     
     // Crea un generador de números aleatorios basado en el seed.
     let mut rng = StdRng::seed_from_u64(seed.abs() as u64);
@@ -303,33 +874,29 @@ fn gas_planet_color(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let glow_factor = (1.0 - (fragment.vertex_position.y / 10.0).max(0.0).min(1.0)).max(0.0); // Basado en altura
     let final_glow = glow_color * glow_factor * 0.1; // Brillo sutil
 
-    // Devuelve el color final combinado
-    final_color + final_glow
+    // Devuelve el color final combinado, iluminado por el sol
+    (final_color + final_glow) * fragment.intensity
 }
 
 
 fn lava_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 	// Base colors for the lava effect
-	let bright_color = Color::new(255, 240, 0); // Bright orange (lava-like)
-	let dark_color = Color::new(130, 20, 0);   // Darker red-orange
-
-	// Get fragment position
-	let position = Vec3::new(
-		fragment.vertex_position.x,
-		fragment.vertex_position.y,
-		fragment.depth
-	);
+	let bright_color = uniforms.shader_params.color(Param::LavaBright); // Bright orange (lava-like)
+	let dark_color = uniforms.shader_params.color(Param::LavaDark);     // Darker red-orange
+
+	// Get fragment position, in model space so the pattern doesn't depend on the depth range
+	let position = fragment.vertex_position;
 
 	// Base frequency and amplitude for the pulsating effect
-	let base_frequency = 0.2;
-	let pulsate_amplitude = 0.5;
+	let base_frequency = uniforms.shader_params.number(Param::LavaFrequency);
+	let pulsate_amplitude = uniforms.shader_params.number(Param::LavaPulse);
 	let t = uniforms.time as f32 * 0.01;
 
 	// Pulsate on the z-axis to change spot size
-	let pulsate = (t * base_frequency).sin() * pulsate_amplitude;
+	let pulsate = (t * base_frequency).sin() * pulsate_amplitude * uniforms.accessibility.motion_amount();
 
 	// Apply noise to coordinates with subtle pulsating on z-axis
-	let zoom = 1000.0; // Constant zoom factor
+	let zoom = uniforms.shader_params.number(Param::LavaZoom);
 	let noise_value1 = uniforms.noise.get_noise_3d(
 		position.x * zoom,
 		position.y * zoom,
@@ -376,7 +943,8 @@ fn sun_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 	let final_glow = glow_color * glow_factor * 0.1;
 
     let final_color = base_color.lerp(&noise_color, noise_value.clamp(0.0, 1.0));
-    final_color + final_glow * fragment.intensity
+    // The sun is the light source, it is not lit by itself
+    final_color + final_glow
 }
 
 fn rocky_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -405,11 +973,11 @@ fn gas_giant_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let position = fragment.vertex_position;
 
     // Base colors for gas giant bands
-    let base_color = Color::new(70, 130, 180); // Azul
-    let band_color = Color::new(255, 255, 255); // Blanco para las bandas
+    let base_color = uniforms.shader_params.color(Param::GiantBase); // Azul
+    let band_color = uniforms.shader_params.color(Param::GiantBand); // Blanco para las bandas
 
     // Generate horizontal bands using sine waves
-    let band_factor = (position.y * 10.0).sin().abs();
+    let band_factor = (position.y * uniforms.shader_params.number(Param::GiantBands)).sin().abs();
 
     // Turbulence effect
     let turbulence = uniforms.noise.get_noise_3d(position.x * 5.0, position.y * 5.0, uniforms.time as f32 * 0.01).abs();
@@ -422,7 +990,7 @@ fn gas_giant_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let glow_factor = (1.0 - position.magnitude() / 10.0).clamp(0.0, 1.0);
     let final_glow = glow_color * glow_factor * 0.1;
 
-    gas_color + final_glow
+    (gas_color + final_glow) * fragment.intensity
 }
 
 
@@ -445,6 +1013,152 @@ fn ice_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 	let glow_factor = (1.0 - position.magnitude() / 10.0).clamp(0.0, 1.0);
 	let final_glow = glow_color * glow_factor * 0.1;
 
-	ice_planet_color + final_glow
+	(ice_planet_color + final_glow) * fragment.intensity
+}
+
+
+// Hull plating for the spaceship: panel lines on a grid picked by the dominant
+// axis of the model-space normal, noise scratches, a metallic highlight
+// towards the sun and emissive cockpit window and engine vent
+fn spaceship_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+	let position = fragment.vertex_position;
+	let model_rotation = mat4_to_mat3(&uniforms.model_matrix);
+	let model_normal = (model_rotation.transpose() * fragment.normal).normalize();
+
+	// Cockpit window in front of the central pod, engine vent behind it
+	let in_pod = position.x.abs() < 1.0 && position.y.abs() < 1.0;
+	if in_pod && position.z < -0.85 {
+		let rim = ((position.x * position.x + position.y * position.y).sqrt() * 6.0).fract();
+		return if rim < 0.15 { Color::new(40, 45, 55) } else { Color::new(110, 200, 255) };
+	}
+	if in_pod && position.z > 0.85 {
+		return Color::new(255, 140, 60);
+	}
+
+	// The two coordinates running along the surface
+	let abs_normal = model_normal.abs();
+	let (u, v) = if abs_normal.x >= abs_normal.y && abs_normal.x >= abs_normal.z {
+		(position.y, position.z)
+	} else if abs_normal.y >= abs_normal.z {
+		(position.x, position.z)
+	} else {
+		(position.x, position.y)
+	};
+
+	// Wings are dark solar panels with a finer grid
+	let is_wing = position.x.abs() > 1.5;
+	let (base_color, panel_size) = if is_wing {
+		(Color::new(45, 50, 62), 0.18)
+	} else {
+		(Color::new(140, 146, 155), 0.35)
+	};
+
+	let line_width = 0.06;
+	let on_line = (u / panel_size).rem_euclid(1.0) < line_width || (v / panel_size).rem_euclid(1.0) < line_width;
+
+	// Thin streaks: noise stretched along one axis
+	let scratch = uniforms.noise.get_noise_3d(position.x * 40.0, position.y * 3.0, position.z * 40.0);
+	let mut hull = if on_line { base_color * 0.45 } else { base_color };
+	if scratch > 0.55 && !on_line {
+		hull = hull.lerp(&Color::new(200, 205, 215), (scratch - 0.55) * 2.0);
+	}
+
+	// Accumulated scorch marks darken the plating and kill the shine
+	let scorch = uniforms.damage.as_ref().map_or(0.0, |damage| damage.sample(&position));
+	hull = hull.lerp(&Color::new(14, 10, 8), scorch);
+
+	// A physically based material lights the bare hull in fragment_shader
+	if uniforms.material.pbr.is_some() {
+		return hull;
+	}
+
+	// Blinn-Phong highlight against the sun, seen from the camera
+	let world = uniforms.model_matrix * Vec4::new(position.x, position.y, position.z, 1.0);
+	let world_position = Vec3::new(world.x, world.y, world.z);
+
+	let light_direction = (uniforms.light_position - world_position).normalize();
+	let view_direction = (uniforms.camera_position - world_position).normalize();
+	let half_vector = (light_direction + view_direction).normalize();
+	let diffuse = dot(&fragment.normal, &light_direction).max(0.0);
+	let shininess = if is_wing { 64.0 } else { 24.0 };
+	let specular = dot(&fragment.normal, &half_vector).max(0.0).powf(shininess) * diffuse.ceil();
+
+	hull * (0.15 + diffuse * 0.85) + Color::new(255, 245, 230) * specular * 0.8 * (1.0 - scorch)
+}
+
+// Ring particles: bands tinted from the inner to the outer edge, lit by how
+// directly the sun hits the ring plane and dark inside the planet's umbra
+fn ring_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+	let radial = fragment.tex_coords.x.clamp(0.0, 1.0);
+	let inner_color = Color::new(120, 105, 85);
+	let outer_color = Color::new(225, 210, 180);
+	let bands = uniforms.noise.get_noise_2d(radial * 150.0, 7.0) * 0.5 + 0.5;
+	let base_color = inner_color.lerp(&outer_color, radial * 0.6 + bands * 0.4);
+
+	let coverage = ring_coverage(fragment, uniforms);
+
+	let light_direction = object_light_direction(uniforms);
+	if in_planet_shadow(&fragment.vertex_position, &light_direction, 0.01) {
+		return (base_color * 0.08).with_alpha(coverage);
+	}
+
+	// Both faces scatter light, the sunlit side a bit more
+	let lighting = 0.25 + 0.75 * light_direction.y.abs().sqrt();
+	(base_color * lighting).with_alpha(coverage)
 }
 
+// Air seen from outside: almost clear looking straight down, thick along the
+// limb, lit by the sun with a terminator that wraps a little into the night
+fn atmosphere_shell_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+	let Some(atmosphere) = uniforms.atmosphere else {
+		return Color::black().with_alpha(0.0);
+	};
+	let normal = fragment.normal.normalize();
+	let facing = (mat4_to_mat3(&uniforms.view_matrix) * normal).normalize().z.clamp(0.0, 1.0);
+	let limb = (1.0 - facing).powf(atmosphere.falloff);
+	let sun = dot(&normal, &world_light_direction(uniforms));
+	let lit = ((sun + 0.25) / 0.6).clamp(0.0, 1.0);
+	// Light grazing the terminator crosses the most air and reddens
+	let color = match atmosphere.sunset {
+		Some(sunset) => Color::from_hex(sunset).lerp(&Color::from_hex(atmosphere.color), ((sun + 0.05) / 0.4).clamp(0.0, 1.0)),
+		None => Color::from_hex(atmosphere.color),
+	};
+	(color * lit).with_alpha(atmosphere.density * (0.15 + 0.85 * limb) * lit)
+}
+
+// Cloud shell of uniforms.clouds: broad banks with finer detail on top, slowly
+// changing shape while the shell turns. Coverage moves the noise threshold and
+// the edges fade out; clouds on the night side go dark but still hide what's
+// under them.
+fn cloud_layer_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+	let Some(clouds) = uniforms.clouds else {
+		return Color::black().with_alpha(0.0);
+	};
+	let drift = uniforms.time as f32 * 0.05 * uniforms.accessibility.motion_amount();
+	let point = fragment.vertex_position.normalize() * SPHERE_RADIUS * clouds.zoom;
+	let banks = uniforms.noise.get_noise_3d(point.x + drift, point.y, point.z);
+	let detail = uniforms.noise.get_noise_3d(point.x * 3.0 - drift, point.y * 3.0 + 200.0, point.z * 3.0);
+	let density = banks * 0.7 + detail * 0.3;
+	let threshold = 1.0 - 2.0 * clouds.coverage;
+	let alpha = ((density - threshold) * 4.0).clamp(0.0, 1.0) * clouds.opacity;
+	if alpha <= 0.0 {
+		return Color::black().with_alpha(0.0);
+	}
+
+	let sun = dot(&fragment.normal.normalize(), &world_light_direction(uniforms));
+	let lit = ((sun + 0.1) / 0.4).clamp(0.0, 1.0);
+	(Color::from_hex(clouds.color) * lit).with_alpha(alpha)
+}
+
+// Dusty rock lit by the sun, the world position comes from the instance's model matrix
+fn asteroid_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+	let position = fragment.vertex_position;
+	let grain = uniforms.noise.get_noise_3d(position.x * 8.0, position.y * 8.0, position.z * 8.0) * 0.5 + 0.5;
+	let base_color = Color::new(95, 85, 75).lerp(&Color::new(150, 138, 120), grain);
+
+	let world = uniforms.model_matrix * Vec4::new(position.x, position.y, position.z, 1.0);
+	let light_direction = (uniforms.light_position - Vec3::new(world.x, world.y, world.z)).normalize();
+	let diffuse = dot(&fragment.normal, &light_direction).max(0.0);
+
+	base_color * (0.1 + diffuse * 0.9)
+}
@@ -0,0 +1,176 @@
+// simplify.rs
+
+use std::collections::{HashMap, HashSet};
+use nalgebra_glm::{Mat3, Vec2, Vec3};
+use crate::obj::IndexedMesh;
+use crate::vertex::Vertex;
+
+const LOD_RESOLUTIONS: [f32; 3] = [32.0, 16.0, 8.0]; // Celdas a lo ancho del modelo en cada nivel
+const MIN_REDUCTION: f32 = 0.8; // Un nivel con más de este tanto de triángulos no vale la pena
+const MIN_TRIANGLES: usize = 8;
+const QUADRIC_PULL: f32 = 1e-3; // Cuánto tira hacia el promedio de la celda donde la superficie es plana
+
+// Error of the planes around the vertices of one cell, (A, b, c) so that a
+// point x is Σ w·(n·x + d)² = xᵀAx + 2b·x + c away from them
+#[derive(Clone, Copy)]
+struct Quadric {
+    a: Mat3,
+    b: Vec3,
+    sum: Vec3, // Suma de las posiciones, para el promedio
+    count: f32,
+}
+
+impl Default for Quadric {
+    fn default() -> Self {
+        Quadric { a: Mat3::zeros(), b: Vec3::zeros(), sum: Vec3::zeros(), count: 0.0 }
+    }
+}
+
+impl Quadric {
+    // The point closest to every plane, pulled a little towards the average
+    // so it stays put along flat faces and edges, where the planes alone
+    // don't pin it down. It can't leave the cell.
+    fn optimal(&self, low: &Vec3, high: &Vec3) -> Vec3 {
+        let mean = self.sum / self.count.max(1.0);
+        let pull = QUADRIC_PULL * (self.a.trace() + 1e-12);
+        let system = self.a + Mat3::identity() * pull;
+        match system.try_inverse() {
+            Some(inverse) => {
+                let point = inverse * (mean * pull - self.b);
+                Vec3::new(point.x.clamp(low.x, high.x), point.y.clamp(low.y, high.y), point.z.clamp(low.z, high.z))
+            }
+            None => mean,
+        }
+    }
+}
+
+// Vertex clustering: the model's box is cut into cubes `cell` wide and every
+// vertex in a cube moves to one point, the one nearest the planes of the
+// triangles around them (quadric error metrics). Triangles left with two
+// corners in the same cube disappear. Vertices of a cube whose normals point
+// into different octants stay apart, sharing the point, so hard edges keep
+// their shading without opening cracks. A vertex moves at most a cube's
+// diagonal.
+pub fn simplify(mesh: &IndexedMesh, cell: f32) -> IndexedMesh {
+    let origin = mesh.vertices.iter().fold(Vec3::repeat(f32::MAX), |low, vertex| low.inf(&vertex.position));
+    let cell_of = |position: &Vec3| {
+        let index = (position - origin) / cell;
+        [index.x.floor() as i32, index.y.floor() as i32, index.z.floor() as i32]
+    };
+    let octant = |normal: &Vec3| (normal.x < 0.0) as u8 | ((normal.y < 0.0) as u8) << 1 | ((normal.z < 0.0) as u8) << 2;
+
+    let cells: Vec<[i32; 3]> = mesh.vertices.iter().map(|vertex| cell_of(&vertex.position)).collect();
+    let mut quadrics: HashMap<[i32; 3], Quadric> = HashMap::new();
+    for (vertex, key) in mesh.vertices.iter().zip(&cells) {
+        let quadric = quadrics.entry(*key).or_default();
+        quadric.sum += vertex.position;
+        quadric.count += 1.0;
+    }
+    for triangle in mesh.indices.chunks_exact(3) {
+        let corners = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+        let [p1, p2, p3] = corners.map(|index| mesh.vertices[index].position);
+        // The cross product's length is twice the area, bigger faces weigh more
+        let cross = (p2 - p1).cross(&(p3 - p1));
+        let area = cross.magnitude();
+        if area < 1e-12 {
+            continue;
+        }
+        let normal = cross / area;
+        let distance = -normal.dot(&p1);
+        for index in corners {
+            let quadric = quadrics.entry(cells[index]).or_default();
+            quadric.a += normal * normal.transpose() * area;
+            quadric.b += normal * distance * area;
+        }
+    }
+
+    // One output vertex per cube and octant, with the averaged attributes
+    let mut slots: HashMap<([i32; 3], u8), u32> = HashMap::new();
+    let mut sums: Vec<(Vec3, Vec2, f32)> = Vec::new();
+    let mut remap = Vec::with_capacity(mesh.vertices.len());
+    let mut vertices = Vec::new();
+    for (vertex, key) in mesh.vertices.iter().zip(&cells) {
+        let slot = *slots.entry((*key, octant(&vertex.normal))).or_insert_with(|| {
+            let low = origin + Vec3::new(key[0] as f32, key[1] as f32, key[2] as f32) * cell;
+            let position = quadrics[key].optimal(&low, &(low + Vec3::repeat(cell)));
+            vertices.push(Vertex::new(position, vertex.normal, vertex.tex_coords));
+            sums.push((Vec3::zeros(), Vec2::zeros(), 0.0));
+            (vertices.len() - 1) as u32
+        });
+        let sum = &mut sums[slot as usize];
+        sum.0 += vertex.normal;
+        sum.1 += vertex.tex_coords;
+        sum.2 += 1.0;
+        remap.push(slot);
+    }
+    for (vertex, (normal, tex_coords, count)) in vertices.iter_mut().zip(sums) {
+        if normal.magnitude() > 1e-6 {
+            vertex.normal = normal.normalize();
+        }
+        vertex.tex_coords = tex_coords / count;
+    }
+
+    // The same triangle can come out of several, it is kept once per winding
+    let mut seen = HashSet::new();
+    let mut indices = Vec::new();
+    for triangle in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+        if cells[a] == cells[b] || cells[b] == cells[c] || cells[a] == cells[c] {
+            continue;
+        }
+        let corners = [remap[a], remap[b], remap[c]];
+        let first = (0..3).min_by_key(|&corner| corners[corner]).unwrap_or(0);
+        let key = [corners[first], corners[(first + 1) % 3], corners[(first + 2) % 3]];
+        if seen.insert(key) {
+            indices.extend_from_slice(&corners);
+        }
+    }
+
+    let mut simplified = IndexedMesh { vertices, indices };
+    simplified.generate_tangents();
+    simplified
+}
+
+// A model at a few levels of detail: itself and coarser clusterings of it.
+// The level drawn is the coarsest whose vertices moved at most `error`
+// pixels, like SphereLod does for the planets.
+pub struct MeshLod {
+    levels: Vec<(f32, IndexedMesh)>, // Cuánto se movieron los vértices en unidades del modelo y malla, la original primero
+}
+
+impl MeshLod {
+    pub fn new(mesh: IndexedMesh) -> Self {
+        let low = mesh.vertices.iter().fold(Vec3::repeat(f32::MAX), |low, vertex| low.inf(&vertex.position));
+        let high = mesh.vertices.iter().fold(Vec3::repeat(f32::MIN), |high, vertex| high.sup(&vertex.position));
+        let size = (high - low).max();
+        let mut levels = vec![(0.0, mesh)];
+        if size <= 0.0 {
+            return MeshLod { levels };
+        }
+        for resolution in LOD_RESOLUTIONS {
+            let cell = size / resolution;
+            let finer = &levels[levels.len() - 1].1;
+            let simplified = simplify(&levels[0].1, cell);
+            if simplified.indices.len() / 3 < MIN_TRIANGLES {
+                break;
+            }
+            if simplified.indices.len() as f32 <= finer.indices.len() as f32 * MIN_REDUCTION {
+                levels.push((cell * 3.0f32.sqrt(), simplified));
+            }
+        }
+        MeshLod { levels }
+    }
+
+    // The model as loaded
+    pub fn full(&self) -> &IndexedMesh {
+        &self.levels[0].1
+    }
+
+    // `pixels_per_unit` of the model where it is drawn, `error` in pixels
+    pub fn select(&self, pixels_per_unit: f32, error: f32) -> &IndexedMesh {
+        self.levels.iter()
+            .rev()
+            .find(|(moved, _)| moved * pixels_per_unit <= error)
+            .map_or(self.full(), |(_, mesh)| mesh)
+    }
+}
@@ -1,109 +1,509 @@
 // skybox.rs
 
-use nalgebra_glm::{Vec3, Vec4};
+use nalgebra_glm::{Mat4, Vec3, Vec4};
 use rand::prelude::*;
+use rayon::prelude::*;
 use std::f32::consts::PI;
+use std::fs;
+use crate::color::Color;
+use crate::culling::Frustum;
+use crate::surface_cache::uv_from_direction;
+use crate::texture::{Filter, Sampler, Texture, WrapMode};
 use crate::{Framebuffer, Uniforms};
 
 pub struct Star {
     position: Vec3,
     brightness: f32,
     size: u8,
+    color: Color,
+    twinkle_phase: f32,
+    twinkle_speed: f32, // Radianes por frame
 }
 
-pub struct Skybox {
+const STAR_DISTANCE: f32 = 100.0;
+// Same sky and twinkling on every run, so replays and recordings match
+const STAR_SEED: u64 = 0x5EED_57A2;
+// Tilt between the celestial equator and the ecliptic, the scene's XZ plane
+const OBLIQUITY: f32 = 23.44 * PI / 180.0;
+
+// Exposed brightness above which a star gets a glow sprite, and spikes
+const GLOW_THRESHOLD: f32 = 0.995;
+const SPIKE_THRESHOLD: f32 = 0.999;
+// How far twinkling moves the brightness either way
+const TWINKLE_AMOUNT: f32 = 0.25;
+// Cells per side of each cube face when grouping stars by direction
+const BUCKET_GRID: usize = 8;
+
+// Closer shells of faint stars in front of the far sky, nearest first:
+// (radius in STAR_DISTANCE, share of the camera's translation the shell lags
+// behind, star count). Only the angle shift/radius shows on screen.
+const PARALLAX_LAYERS: [(f32, f32, usize); 3] = [(0.5, 0.02, 3000), (0.7, 0.012, 5000), (0.85, 0.006, 8000)];
+pub const DEFAULT_PARALLAX_LAYERS: usize = 2;
+const LAYER_BRIGHTNESS: f32 = 0.35; // Las capas cercanas son estrellas tenues
+// A shell never lags more than this share of its radius, far from the sun the
+// stars stop sliding instead of leaving their shell
+const MAX_PARALLAX_SHIFT: f32 = 0.3;
+
+// Stars whose directions fall in one cell of a cube around the camera, kept
+// as a contiguous run of `Skybox::stars` inside a sphere relative to the eye
+struct StarBucket {
+    start: usize,
+    end: usize,
+    center: Vec3,
+    radius: f32,
+}
+
+// Background image at infinite distance, looked up by view direction
+pub enum SkyMap {
+    Equirectangular(Texture),
+    CubeMap(Vec<Texture>), // +x, -x, +y, -y, +z, -z
+}
+
+impl SkyMap {
+    // One image, or six cube faces in the order above
+    pub fn load(paths: &[String]) -> Result<Self, String> {
+        let load = |path: &String| Texture::new(path).map_err(|e| format!("{}: {}", path, e));
+        match paths.len() {
+            1 => Ok(SkyMap::Equirectangular(load(&paths[0])?)),
+            6 => Ok(SkyMap::CubeMap(paths.iter().map(load).collect::<Result<_, _>>()?)),
+            count => Err(format!("sky map needs 1 or 6 images, got {}", count)),
+        }
+    }
+
+    // `lod` as in Texture::sample_lod, for the image's own width
+    fn sample(&self, direction: &Vec3, lod: f32) -> Color {
+        match self {
+            SkyMap::Equirectangular(texture) => {
+                let (u, v) = uv_from_direction(direction);
+                let sampler = Sampler { filter: Filter::Bilinear, wrap: WrapMode::Repeat };
+                texture.sample_lod(u, v, &sampler, lod)
+            }
+            SkyMap::CubeMap(faces) => {
+                // Major axis picks the face, the other two are its (u, v)
+                let (face, s, t) = cube_face(direction);
+                let sampler = Sampler { filter: Filter::Bilinear, wrap: WrapMode::Clamp };
+                faces[face].sample_lod((s + 1.0) * 0.5, (t + 1.0) * 0.5, &sampler, lod)
+            }
+        }
+    }
+
+    // Level-0 texels across the full turn of the sky
+    fn texels_per_turn(&self) -> f32 {
+        match self {
+            SkyMap::Equirectangular(texture) => texture.width() as f32,
+            SkyMap::CubeMap(faces) => faces[0].width() as f32 * 4.0,
+        }
+    }
+}
+
+// One shell of stars around the camera. The far sky moves with the camera,
+// the closer shells trail part of its translation and slide against it.
+struct StarLayer {
     stars: Vec<Star>,
+    buckets: Vec<StarBucket>,
+    radius: f32,
+    parallax: f32, // Parte del desplazamiento de la cámara que se queda atrás
+}
+
+impl StarLayer {
+    fn new(mut stars: Vec<Star>, radius: f32, parallax: f32) -> Self {
+        let buckets = bucket_stars(&mut stars);
+        StarLayer { stars, buckets, radius, parallax }
+    }
+
+    // Center of the shell for a camera position
+    fn center(&self, camera_position: &Vec3, strength: f32) -> Vec3 {
+        let lag = camera_position * self.parallax * strength;
+        let max_lag = self.radius * MAX_PARALLAX_SHIFT;
+        let lag = if lag.magnitude() > max_lag { lag.normalize() * max_lag } else { lag };
+        camera_position - lag
+    }
+}
+
+pub struct Skybox {
+    layers: Vec<StarLayer>, // El cielo lejano primero, luego de la capa más lejana a la más cercana
+    pub exposure: f32,            // Multiplica el brillo de las estrellas
+    pub diffraction_spikes: bool, // Cruces de 4 puntas en las más brillantes
+    pub map: Option<SkyMap>,
+    pub map_brightness: f32,
+    pub show_stars: bool,         // Puntos encima del mapa
+    pub parallax: f32,            // Multiplica el desfase de las capas cercanas, 0 las deja fijas
+    pub pixel_scale: f32,         // Pixeles del framebuffer por pixel de pantalla, >1 con supersampling
 }
 
 impl Skybox {
-    pub fn new(star_count: usize) -> Self {
-        let mut rng = rand::thread_rng();
-        let mut stars = Vec::with_capacity(star_count);
-
-        for _ in 0..star_count {
-            // Generate random spherical coordinates
-            let theta = rng.gen::<f32>() * 2.0 * PI;  // Azimuth angle
-            let phi = rng.gen::<f32>() * PI;          // Polar angle
-            let radius = 100.0;  // Fixed radius for all stars
-
-            // Convert spherical to Cartesian coordinates
-            let x = radius * phi.sin() * theta.cos();
-            let y = radius * phi.cos();
-            let z = radius * phi.sin() * theta.sin();
-
-            // Random brightness between 0.0 and 1.0
-            let brightness = rng.gen::<f32>();
-            let size : u8 = rng.gen_range(1..=3);
-
-            stars.push(Star {
-                position: Vec3::new(x, y, z),
-                brightness,
-                size,
-            });
+    // Random white stars, or the stars of a catalog file at their real
+    // positions, brightness and color when one is given. Up to three faint
+    // parallax layers go in front of them.
+    pub fn new(star_count: usize, catalog: Option<&str>, parallax_layers: usize) -> Result<Self, String> {
+        let stars = match catalog {
+            Some(path) => load_catalog(path)?,
+            None => random_stars(star_count, STAR_SEED),
+        };
+        let mut layers = vec![StarLayer::new(stars, STAR_DISTANCE, 0.0)];
+        let count = parallax_layers.min(PARALLAX_LAYERS.len());
+        for (index, (radius, parallax, star_count)) in PARALLAX_LAYERS[..count].iter().enumerate().rev() {
+            let radius = radius * STAR_DISTANCE;
+            layers.push(StarLayer::new(faint_stars(*star_count, STAR_SEED + 1 + index as u64, radius), radius, *parallax));
         }
 
-        Skybox { stars }
+        Ok(Skybox {
+            layers,
+            exposure: 1.0,
+            diffraction_spikes: true,
+            pixel_scale: 1.0,
+            map: None,
+            map_brightness: 1.0,
+            show_stars: true,
+            parallax: 1.0,
+        })
+    }
+
+    // Light of the sky map along `direction`, blurred to about `texels`
+    // across the whole turn; black without a map. For the light probes.
+    pub fn radiance(&self, direction: &Vec3, texels: f32) -> Color {
+        let Some(map) = &self.map else {
+            return Color::black();
+        };
+        let lod = (map.texels_per_turn() / texels).max(1.0).log2();
+        map.sample(direction, lod) * self.map_brightness
     }
 
     pub fn render(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, camera_position: Vec3) {
-        // let mut rng = rand::thread_rng();
-
-        for star in &self.stars {
-            // Calculate star position relative to camera
-            let position = star.position + camera_position;
-
-            // Project the star position to screen space
-            let pos_vec4 = Vec4::new(position.x, position.y, position.z, 1.0);
-            let projected = uniforms.projection_matrix * uniforms.view_matrix * pos_vec4;
-
-            // Perform perspective division
-            if projected.w <= 0.0 { continue; }
-            let ndc = projected / projected.w;
-
-            // Apply viewport transform
-            let screen_pos = uniforms.viewport_matrix * Vec4::new(ndc.x, ndc.y, ndc.z, 1.0);
-
-            // Check if star is in front of camera and within screen bounds
-            if screen_pos.z < 0.0 { continue; }
-
-            let x = screen_pos.x as usize;
-            let y = screen_pos.y as usize;
-
-            if x < framebuffer.width && y < framebuffer.height {
-                // Random chance for star to blink
-                /*
-                let blink_chance = rng.gen::<f32>();
-                let blink_amount = if blink_chance < 0.3 {
-                    (rng.gen::<f32>() - 0.5) * 0.2 // This gives us -0.1 to 0.1 variation
-                } else {
-                    0.0
-                };
-                let adjusted_brightness = (star.brightness + blink_amount).clamp(0.0, 1.0);
-                */
-                let intensity = (star.brightness * 255.0) as u8;
-                let color = (intensity as u32) << 16 | (intensity as u32) << 8 | intensity as u32;
-
-                framebuffer.set_current_color(color);
-                // framebuffer.point(x, y, 1000.0);  // depth is high so things render in front
-
-                match star.size {
-                    1 => framebuffer.point(x, y, 1000.0),
-                    2 => {
-                        framebuffer.point(x, y, 1000.0);
-                        framebuffer.point(x + 1, y, 1000.0);
-                        framebuffer.point(x, y + 1, 1000.0);
-                        framebuffer.point(x + 1, y + 1, 1000.0);
-                    }
-                    3 => {
-                        framebuffer.point(x, y, 1000.0);
-                        framebuffer.point(x - 1, y, 1000.0);
-                        framebuffer.point(x + 1, y, 1000.0);
-                        framebuffer.point(x, y - 1, 1000.0);
-                        framebuffer.point(x, y + 1, 1000.0);
+        self.render_map(framebuffer, uniforms);
+        if self.show_stars {
+            self.render_stars(framebuffer, uniforms, camera_position);
+        }
+    }
+
+    // Fills every pixel with the sky map seen along its view ray. Only the
+    // camera's rotation is used, so the map never gets closer.
+    fn render_map(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms) {
+        let Some(map) = &self.map else {
+            return;
+        };
+
+        let view = uniforms.view_matrix;
+        let right = Vec3::new(view[(0, 0)], view[(0, 1)], view[(0, 2)]);
+        let up = Vec3::new(view[(1, 0)], view[(1, 1)], view[(1, 2)]);
+        let back = Vec3::new(view[(2, 0)], view[(2, 1)], view[(2, 2)]);
+        let scale_x = 1.0 / uniforms.projection_matrix[(0, 0)];
+        let scale_y = 1.0 / uniforms.projection_matrix[(1, 1)];
+
+        // The map is soft next to the point stars, so it is shaded once per
+        // 2x2 block. Angle covered by one block against the angle of one texel.
+        let block_angle = 4.0 * scale_y.atan() / framebuffer.height as f32;
+        let lod = (block_angle * map.texels_per_turn() / (2.0 * PI)).max(1.0).log2();
+
+        let width = framebuffer.width;
+        let height = framebuffer.height;
+        let brightness = self.map_brightness;
+        // Same rule as Framebuffer::point_background, pixels with depth
+        // already belong to the scene
+        let rows = framebuffer.buffer.par_chunks_mut(width * 2).zip(framebuffer.zbuffer.par_chunks(width * 2));
+        rows.enumerate().for_each(|(block_y, (rows, depths))| {
+            let ndc_y = 1.0 - (block_y as f32 * 2.0 + 1.0) / height as f32 * 2.0;
+            for block_x in 0..width.div_ceil(2) {
+                let ndc_x = (block_x as f32 * 2.0 + 1.0) / width as f32 * 2.0 - 1.0;
+                let direction = (right * ndc_x * scale_x + up * ndc_y * scale_y - back).normalize();
+                let color = (map.sample(&direction, lod) * brightness).to_hex();
+                for (row, row_depths) in rows.chunks_mut(width).zip(depths.chunks(width)) {
+                    let block = row.iter_mut().zip(row_depths).skip(block_x * 2).take(2);
+                    for (pixel, depth) in block {
+                        if *depth == f32::INFINITY {
+                            *pixel = color;
+                        }
                     }
-                    _ => {}
                 }
             }
+        });
+    }
+
+    // Layers are drawn far to near. Only the buckets inside the view are
+    // projected, in parallel, then the splats are written one after another
+    // in bucket order.
+    fn render_stars(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, camera_position: Vec3) {
+        let view_projection = uniforms.projection_matrix * uniforms.view_matrix;
+        let frustum = Frustum::from_matrix(&view_projection, uniforms.depth_range.reversed);
+        for layer in &self.layers {
+            self.render_layer(framebuffer, uniforms, &view_projection, &frustum, layer, layer.center(&camera_position, self.parallax));
+        }
+    }
+
+    fn render_layer(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, view_projection: &Mat4, frustum: &Frustum, layer: &StarLayer, center: Vec3) {
+        // Twinkling is a slow flicker, photosensitive mode holds it still
+        let time = uniforms.time as f32;
+        let twinkle_amount = TWINKLE_AMOUNT * uniforms.accessibility.flash_amount();
+        let (width, height) = (framebuffer.width as f32, framebuffer.height as f32);
+
+        let visible: Vec<&StarBucket> = layer.buckets.iter()
+            .filter(|bucket| frustum.contains_sphere_sides(&(bucket.center + center), bucket.radius))
+            .collect();
+        let projected: Vec<(f32, f32, &Star)> = visible
+            .par_iter()
+            .flat_map_iter(|bucket| layer.stars[bucket.start..bucket.end].iter())
+            .filter_map(|star| {
+                // Stars keep their distance to the shell's center
+                let position = star.position + center;
+                let clip = view_projection * Vec4::new(position.x, position.y, position.z, 1.0);
+                if clip.w <= 0.0 {
+                    return None;
+                }
+                let ndc = clip / clip.w;
+                let screen_pos = uniforms.viewport_matrix * Vec4::new(ndc.x, ndc.y, ndc.z, 1.0);
+
+                // In front of the camera and within screen bounds
+                let (x, y) = (screen_pos.x, screen_pos.y);
+                if screen_pos.z < 0.0 || x < 0.0 || y < 0.0 || x >= width || y >= height {
+                    return None;
+                }
+                Some((x, y, star))
+            })
+            .collect();
+
+        for (x, y, star) in projected {
+            // Twinkling only dims and brightens the point, glows keep to the
+            // steady brightness so they don't pop on and off
+            let exposed = star.brightness * self.exposure;
+            let twinkle = 1.0 + twinkle_amount * (time * star.twinkle_speed + star.twinkle_phase).sin();
+            splat(framebuffer, x, y, star.size, star.color * (exposed * twinkle).min(1.0), self.pixel_scale);
+
+            if exposed > GLOW_THRESHOLD {
+                self.render_glow(framebuffer, x as usize, y as usize, exposed);
+            }
         }
     }
-}
\ No newline at end of file
+
+    // Additive halo around a bright star plus optional horizontal and
+    // vertical diffraction spikes, both growing with the exposed brightness.
+    // Sizes are in screen pixels, scaled up when supersampling.
+    fn render_glow(&self, framebuffer: &mut Framebuffer, x: usize, y: usize, exposed: f32) {
+        let strength = ((exposed - GLOW_THRESHOLD) / (1.0 - GLOW_THRESHOLD)).min(2.0);
+        let radius = (2.0 + 2.0 * strength) * self.pixel_scale;
+        let reach = radius.ceil() as i32;
+
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                let distance = ((dx * dx + dy * dy) as f32).sqrt();
+                if distance == 0.0 || distance > radius {
+                    continue;
+                }
+                let falloff = (1.0 - distance / radius).powi(2);
+                add_star_light(framebuffer, x as i32 + dx, y as i32 + dy, falloff * 0.6);
+            }
+        }
+
+        if !self.diffraction_spikes || exposed <= SPIKE_THRESHOLD {
+            return;
+        }
+        let length = ((4.0 + 6.0 * strength) * self.pixel_scale) as i32;
+        let thickness = self.pixel_scale.round().max(1.0) as i32;
+        for step in (reach + 1)..=length {
+            let falloff = 0.5 * (1.0 - step as f32 / (length + 1) as f32);
+            for across in (0..thickness).map(|offset| offset - thickness / 2) {
+                for (dx, dy) in [(step, across), (-step, across), (across, step), (across, -step)] {
+                    add_star_light(framebuffer, x as i32 + dx, y as i32 + dy, falloff);
+                }
+            }
+        }
+    }
+}
+
+// Sorts the stars by the cube cell their direction points into and returns
+// the bounding sphere of every non-empty cell
+fn bucket_stars(stars: &mut [Star]) -> Vec<StarBucket> {
+    let cell = |star: &Star| {
+        let (face, s, t) = cube_face(&star.position);
+        let to_cell = |value: f32| (((value + 1.0) * 0.5 * BUCKET_GRID as f32) as usize).min(BUCKET_GRID - 1);
+        (face * BUCKET_GRID + to_cell(t)) * BUCKET_GRID + to_cell(s)
+    };
+    stars.sort_by_key(cell);
+
+    let mut buckets = Vec::new();
+    let mut start = 0;
+    while start < stars.len() {
+        let index = cell(&stars[start]);
+        let end = start + stars[start..].iter().take_while(|star| cell(star) == index).count();
+        let run = &stars[start..end];
+        let center = run.iter().map(|star| star.position).sum::<Vec3>() / run.len() as f32;
+        let radius = run.iter().map(|star| (star.position - center).magnitude()).fold(0.0, f32::max);
+        buckets.push(StarBucket { start, end, center, radius });
+        start = end;
+    }
+    buckets
+}
+
+// Cube face of a direction, same order as SkyMap::CubeMap, and the position
+// on it in -1..1
+fn cube_face(direction: &Vec3) -> (usize, f32, f32) {
+    let (x, y, z) = (direction.x, direction.y, direction.z);
+    let (face, s, t, major) = if x.abs() >= y.abs() && x.abs() >= z.abs() {
+        if x > 0.0 { (0, -z, -y, x) } else { (1, z, -y, -x) }
+    } else if y.abs() >= z.abs() {
+        if y > 0.0 { (2, x, z, y) } else { (3, x, -z, -y) }
+    } else if z > 0.0 {
+        (4, x, -y, z)
+    } else {
+        (5, -x, -y, -z)
+    };
+    (face, s / major, t / major)
+}
+
+fn random_stars(star_count: usize, seed: u64) -> Vec<Star> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut stars = Vec::with_capacity(star_count);
+
+    for _ in 0..star_count {
+        // Generate random spherical coordinates
+        let theta = rng.gen::<f32>() * 2.0 * PI;  // Azimuth angle
+        let phi = rng.gen::<f32>() * PI;          // Polar angle
+        let radius = STAR_DISTANCE;  // Fixed radius for all stars
+
+        // Convert spherical to Cartesian coordinates
+        let x = radius * phi.sin() * theta.cos();
+        let y = radius * phi.cos();
+        let z = radius * phi.sin() * theta.sin();
+
+        // Random brightness between 0.0 and 1.0
+        let brightness = rng.gen::<f32>();
+        let size : u8 = rng.gen_range(1..=3);
+
+        stars.push(Star {
+            position: Vec3::new(x, y, z),
+            brightness,
+            size,
+            color: color_from_temperature(random_temperature(&mut rng)),
+            twinkle_phase: rng.gen::<f32>() * 2.0 * PI,
+            twinkle_speed: rng.gen_range(0.03..0.12),
+        });
+    }
+
+    stars
+}
+
+// Small dim stars on a shell of `radius`, for a parallax layer
+fn faint_stars(star_count: usize, seed: u64, radius: f32) -> Vec<Star> {
+    let mut stars = random_stars(star_count, seed);
+    for star in &mut stars {
+        star.position *= radius / STAR_DISTANCE;
+        star.brightness *= LAYER_BRIGHTNESS;
+        star.size = 1;
+    }
+    stars
+}
+
+// CSV with a `name,ra,dec,mag,bv` header, angles in degrees. Lines starting
+// with # are comments.
+fn load_catalog(path: &str) -> Result<Vec<Star>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut rng = StdRng::seed_from_u64(STAR_SEED);
+    let mut stars = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("name,") {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if fields.len() != 5 {
+            return Err(format!("{}:{}: expected name,ra,dec,mag,bv", path, index + 1));
+        }
+        let value = |field: usize| fields[field].parse::<f32>()
+            .map_err(|e| format!("{}:{}: '{}': {}", path, index + 1, fields[field], e));
+        let (right_ascension, declination, magnitude, color_index) = (value(1)?, value(2)?, value(3)?, value(4)?);
+
+        stars.push(Star {
+            position: celestial_direction(right_ascension, declination) * STAR_DISTANCE,
+            // Square root of the flux ratio, magnitude 1 is full brightness
+            // and the brightest few go past it into glows and spikes
+            brightness: 10f32.powf(-0.2 * (magnitude - 1.0)),
+            size: if magnitude < 0.5 { 3 } else if magnitude < 2.0 { 2 } else { 1 },
+            color: color_from_index(color_index),
+            twinkle_phase: rng.gen::<f32>() * 2.0 * PI,
+            twinkle_speed: rng.gen_range(0.03..0.12),
+        });
+    }
+
+    Ok(stars)
+}
+
+// Equatorial coordinates to a unit vector in the scene, whose XZ plane is
+// the ecliptic with its north pole along +y
+fn celestial_direction(right_ascension: f32, declination: f32) -> Vec3 {
+    let (ra, dec) = (right_ascension.to_radians(), declination.to_radians());
+    let equatorial = Vec3::new(dec.cos() * ra.cos(), dec.cos() * ra.sin(), dec.sin());
+    let (sin, cos) = OBLIQUITY.sin_cos();
+    let ecliptic_y = equatorial.y * cos + equatorial.z * sin;
+    let ecliptic_z = -equatorial.y * sin + equatorial.z * cos;
+    Vec3::new(equatorial.x, ecliptic_z, -ecliptic_y)
+}
+
+// B-V color index to temperature (Ballesteros)
+fn color_from_index(color_index: f32) -> Color {
+    color_from_temperature(4600.0 * (1.0 / (0.92 * color_index + 1.7) + 1.0 / (0.92 * color_index + 0.62)))
+}
+
+// Rough mix of spectral classes among naked-eye stars, hot ones are
+// overrepresented because they are brighter
+fn random_temperature(rng: &mut impl Rng) -> f32 {
+    let (low, high) = match rng.gen::<f32>() {
+        roll if roll < 0.10 => (10_000.0, 30_000.0), // O, B: azules
+        roll if roll < 0.35 => (7_500.0, 10_000.0),  // A: blancas
+        roll if roll < 0.55 => (6_000.0, 7_500.0),   // F
+        roll if roll < 0.75 => (5_200.0, 6_000.0),   // G: amarillas
+        roll if roll < 0.92 => (3_700.0, 5_200.0),   // K: naranjas
+        _ => (2_400.0, 3_700.0),                     // M: rojas
+    };
+    rng.gen_range(low..high)
+}
+
+// Blackbody color fit for a temperature in kelvin
+fn color_from_temperature(temperature: f32) -> Color {
+    let t = temperature / 100.0;
+    let red = if t <= 66.0 { 255.0 } else { 329.7 * (t - 60.0).powf(-0.1332) };
+    let green = if t <= 66.0 { 99.47 * t.ln() - 161.12 } else { 288.12 * (t - 60.0).powf(-0.0755) };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.52 * (t - 10.0).ln() - 305.04
+    };
+    Color::from_float(red / 255.0, green / 255.0, blue / 255.0)
+}
+
+// Gaussian footprint around the exact projected position, so stars slide
+// smoothly across pixels. Carries about as much light as the old squares.
+// `scale` pixels make one screen pixel, the star keeps its size on screen.
+fn splat(framebuffer: &mut Framebuffer, x: f32, y: f32, size: u8, color: Color, scale: f32) {
+    let sigma = (0.3 + 0.2 * size as f32) * scale;
+    let energy = (size * size) as f32 * scale * scale;
+    let reach = (sigma * 2.5).ceil() as i32;
+    let (cx, cy) = (x.floor() as i32, y.floor() as i32);
+
+    let mut weights = Vec::with_capacity(((2 * reach + 1) * (2 * reach + 1)) as usize);
+    for py in (cy - reach)..=(cy + reach) {
+        for px in (cx - reach)..=(cx + reach) {
+            let dx = px as f32 + 0.5 - x;
+            let dy = py as f32 + 0.5 - y;
+            weights.push((px, py, (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp()));
+        }
+    }
+    let total: f32 = weights.iter().map(|(_, _, weight)| weight).sum();
+
+    for (px, py, weight) in weights {
+        let amount = (weight / total * energy).min(1.0);
+        if px >= 0 && py >= 0 && amount > 0.01 {
+            framebuffer.point_background_additive(px as usize, py as usize, (color * amount).to_hex());
+        }
+    }
+}
+
+fn add_star_light(framebuffer: &mut Framebuffer, x: i32, y: i32, amount: f32) {
+    if x < 0 || y < 0 {
+        return;
+    }
+    // Slightly blue-white so glows read as starlight
+    let color = Color::new(230, 235, 255) * amount;
+    framebuffer.point_background_additive(x as usize, y as usize, color.to_hex());
+}
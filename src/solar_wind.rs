@@ -0,0 +1,209 @@
+// solar_wind.rs
+
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use nalgebra_glm::Vec3;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::line::{line, polyline};
+use crate::planet::Planet;
+use crate::Uniforms;
+
+const STREAM_WIDTH: f32 = 2.5;     // Ancho del chorro hacia cada planeta, en radios de su magnetopausa
+const STREAK_FRAMES: f32 = 4.0;    // Largo de cada trazo, en frames de recorrido
+const DAY_COMPRESSION: f32 = 0.35; // El viento aplasta el campo del lado del sol
+const NIGHT_STRETCH: f32 = 1.5;    // y lo estira en una cola del otro lado
+const SHELLS: [f32; 3] = [0.5, 0.75, 1.0]; // Líneas de campo, en fracción de la magnetopausa
+const AZIMUTHS: usize = 8;
+const LINE_SEGMENTS: usize = 24;
+const FIELD_BRIGHTNESS: f32 = 0.35;
+
+// A planet's magnetic field, a dipole along its axis tilted by `tilt`
+#[derive(Clone, Copy, Debug)]
+pub struct Magnetosphere {
+    pub body: usize,    // Índice en planets
+    pub standoff: f32,  // Distancia a la magnetopausa del lado del sol, en radios del planeta
+    pub tilt: f32,      // Radianes, respecto al eje Y
+    pub color: u32,
+}
+
+impl Magnetosphere {
+    // Field lines as polylines: dipole loops from pole to pole, squeezed on
+    // the day side and drawn out into a tail on the night side
+    fn field_lines(&self, center: &Vec3, radius: f32, sun_position: &Vec3) -> Vec<Vec<Vec3>> {
+        let axis = Vec3::new(0.0, self.tilt.cos(), self.tilt.sin());
+        let to_sun = sun_position - center;
+        let sunward = (to_sun - axis * to_sun.dot(&axis)).try_normalize(1e-6).unwrap_or(Vec3::x());
+        let side = axis.cross(&sunward);
+
+        let mut lines = Vec::with_capacity(SHELLS.len() * AZIMUTHS);
+        for shell in SHELLS {
+            // Equatorial reach before the squeeze, so the outer shell meets the magnetopause
+            let reach = shell * self.standoff / (1.0 - DAY_COMPRESSION);
+            if reach <= 1.0 {
+                continue;
+            }
+            // Colatitude where the line leaves the surface: reach * sin² = 1
+            let foot = (1.0 / reach).sqrt().asin();
+            for azimuth in 0..AZIMUTHS {
+                let (sin_azimuth, cos_azimuth) = (azimuth as f32 / AZIMUTHS as f32 * 2.0 * PI).sin_cos();
+                let outward = sunward * cos_azimuth + side * sin_azimuth;
+                let points = (0..=LINE_SEGMENTS).map(|segment| {
+                    let colatitude = foot + (PI - 2.0 * foot) * segment as f32 / LINE_SEGMENTS as f32;
+                    let (sin, cos) = colatitude.sin_cos();
+                    let dipole = reach * sin * sin;
+                    let facing = cos_azimuth * sin;
+                    let squeeze = if facing > 0.0 { 1.0 - DAY_COMPRESSION * facing } else { 1.0 - NIGHT_STRETCH * facing };
+                    // Full squeeze at the top of the loop, none at the feet so they stay on the surface
+                    let height = ((dipole - 1.0) / (reach - 1.0)).clamp(0.0, 1.0);
+                    let distance = dipole * (1.0 + (squeeze - 1.0) * height);
+                    center + (outward * sin + axis * cos) * distance * radius
+                }).collect();
+                lines.push(points);
+            }
+        }
+        lines
+    }
+}
+
+struct Gust {
+    position: Vec3,
+    velocity: Vec3,
+    age: f32,      // Frames
+    lifetime: f32,
+}
+
+// Charged particles streaming out of the sun. Most of them are aimed at the
+// planets with a magnetic field, so the way each field turns the flow aside
+// shows: a particle reaching the magnetopause slides along it instead of
+// going through and carries on past the planet. The rest blow out evenly in
+// every direction.
+pub struct SolarWind {
+    pub enabled: bool,
+    pub rate: f32,         // Partículas por frame en todas direcciones
+    pub stream_rate: f32,  // Partículas por frame hacia cada planeta con campo
+    pub speed: f32,        // Unidades por frame
+    pub reach: f32,        // Distancia al sol a la que se pierden las que no van a un planeta
+    pub color: u32,
+    pub magnetospheres: Vec<Magnetosphere>,
+    gusts: VecDeque<Gust>,
+    capacity: usize,
+    carry: f32,
+    rng: StdRng,
+}
+
+impl SolarWind {
+    pub fn new(capacity: usize, seed: u64) -> Self {
+        SolarWind {
+            enabled: false,
+            rate: 2.0,
+            stream_rate: 3.0,
+            speed: 0.15,
+            reach: 40.0,
+            color: 0xFFE6A0,
+            magnetospheres: Vec::new(),
+            gusts: VecDeque::with_capacity(capacity),
+            capacity,
+            carry: 0.0,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    fn spawn(&mut self, gust: Gust) {
+        if self.gusts.len() >= self.capacity {
+            self.gusts.pop_front();
+        }
+        self.gusts.push_back(gust);
+    }
+
+    // Magnetopause radius of a magnetosphere this frame, world units
+    fn magnetopause(&self, magnetosphere: &Magnetosphere, planets: &[Planet], model_radius: f32) -> Option<(Vec3, f32)> {
+        planets.get(magnetosphere.body)
+            .map(|planet| (planet.get_position(), planet.radius * model_radius * magnetosphere.standoff))
+    }
+
+    // New particles for this frame and every one moved a frame, turned aside
+    // by the fields it meets. `density` scales the count for the quality tier.
+    pub fn update(&mut self, planets: &[Planet], sun_position: &Vec3, model_radius: f32, density: f32) {
+        if !self.enabled {
+            self.gusts.clear();
+            return;
+        }
+
+        let magnetopauses: Vec<(Vec3, f32)> = self.magnetospheres.iter()
+            .filter_map(|magnetosphere| self.magnetopause(magnetosphere, planets, model_radius))
+            .collect();
+
+        // Streams aimed at a disc a few magnetopauses wide around each planet
+        self.carry += (self.rate + self.stream_rate * magnetopauses.len() as f32) * density.max(0.0);
+        let count = self.carry.floor();
+        self.carry -= count;
+        let stream_share = self.stream_rate * magnetopauses.len() as f32 / (self.rate + self.stream_rate * magnetopauses.len() as f32).max(1e-6);
+        for _ in 0..count as usize {
+            let (target, lifetime) = if !magnetopauses.is_empty() && self.rng.gen_range(0.0..1.0) < stream_share {
+                let (center, radius) = magnetopauses[self.rng.gen_range(0..magnetopauses.len())];
+                let toward = (center - sun_position).try_normalize(1e-6).unwrap_or(Vec3::x());
+                let across = toward.cross(&Vec3::y()).try_normalize(1e-6).unwrap_or(Vec3::z());
+                let up = across.cross(&toward);
+                let angle = self.rng.gen_range(0.0..2.0 * PI);
+                let spread = self.rng.gen_range(0.0f32..1.0).sqrt() * radius * STREAM_WIDTH;
+                let target = center + (across * angle.cos() + up * angle.sin()) * spread;
+                let distance = (target - sun_position).magnitude();
+                (target, distance * 2.0 / self.speed.max(1e-6))
+            } else {
+                let direction = Vec3::new(self.rng.gen_range(-1.0..=1.0), self.rng.gen_range(-0.3..=0.3), self.rng.gen_range(-1.0..=1.0));
+                (sun_position + direction, self.reach / self.speed.max(1e-6))
+            };
+            let direction = (target - sun_position).try_normalize(1e-6).unwrap_or(Vec3::x());
+            let speed = self.speed * self.rng.gen_range(0.8..=1.2);
+            let lag = self.rng.gen_range(0.0..1.0);
+            self.spawn(Gust { position: sun_position + direction * speed * lag, velocity: direction * speed, age: lag, lifetime });
+        }
+
+        for gust in self.gusts.iter_mut() {
+            gust.position += gust.velocity;
+            gust.age += 1.0;
+            // Slide along the magnetopause instead of crossing it, same speed
+            for (center, radius) in &magnetopauses {
+                let offset = gust.position - center;
+                let distance = offset.magnitude();
+                if distance >= *radius || distance < 1e-6 {
+                    continue;
+                }
+                let normal = offset / distance;
+                gust.position = center + normal * *radius;
+                let inward = gust.velocity.dot(&normal);
+                if inward < 0.0 {
+                    let speed = gust.velocity.magnitude();
+                    gust.velocity = (gust.velocity - normal * inward).try_normalize(1e-6).unwrap_or(normal) * speed;
+                }
+            }
+        }
+        self.gusts.retain(|gust| gust.age < gust.lifetime);
+    }
+
+    // After the opaque geometry, with the particles: the wind as short
+    // streaks along their motion and each field as its lines
+    pub fn render(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, planets: &[Planet], sun_position: &Vec3, model_radius: f32) {
+        if !self.enabled {
+            return;
+        }
+        let color = Color::from_hex(self.color);
+        for gust in &self.gusts {
+            let fade = 1.0 - (gust.age / gust.lifetime).clamp(0.0, 1.0);
+            let tail = gust.position - gust.velocity * STREAK_FRAMES;
+            line(framebuffer, uniforms, &tail, &gust.position, color * fade);
+        }
+        for magnetosphere in &self.magnetospheres {
+            let Some(planet) = planets.get(magnetosphere.body) else {
+                continue;
+            };
+            let field_color = Color::from_hex(magnetosphere.color) * FIELD_BRIGHTNESS;
+            for points in magnetosphere.field_lines(&planet.get_position(), planet.radius * model_radius, sun_position) {
+                polyline(framebuffer, uniforms, &points, field_color);
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+// sphere.rs
+
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+use nalgebra_glm::{Vec2, Vec3};
+use crate::obj::IndexedMesh;
+use crate::planet::SPHERE_RADIUS;
+use crate::surface_cache::uv_from_direction;
+use crate::vertex::Vertex;
+
+pub const DEFAULT_SUBDIVISIONS: u32 = 10; // ~1200 triángulos
+const MAX_SUBDIVISIONS: u32 = 64;
+const POLE: f32 = 0.9999;                 // |y| de un vértice que cae en el polo
+const LOD_LEVELS: usize = 4;              // Mallas por esfera, cada una con la mitad de celdas
+const MIN_LOD_SUBDIVISIONS: u32 = 2;
+const SLOPE_STEP: f32 = 1e-3;             // Radianes a cada lado al medir la pendiente del relieve
+
+// Cube faces as (outward, right, up) with right × up = outward, so the
+// triangles of every face wind the same way
+const FACES: [(Vec3, Vec3, Vec3); 6] = [
+    (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
+    (Vec3::new(-1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 0.0)),
+    (Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 0.0)),
+    (Vec3::new(0.0, -1.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
+    (Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+    (Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0)),
+];
+
+// Planet sphere of radius SPHERE_RADIUS built from a cube whose faces are
+// split into `subdivisions` × `subdivisions` cells and pushed out onto the
+// sphere. The grid is warped with a tangent so the cells come out about the
+// same size instead of bunching up at the cube's corners. Texture
+// coordinates are equirectangular, the same as uv_from_direction; triangles
+// across the seam or touching a pole get vertices of their own so they don't
+// interpolate the wrong way around.
+pub fn sphere_mesh(subdivisions: u32) -> IndexedMesh {
+    let cells = subdivisions.clamp(1, MAX_SUBDIVISIONS);
+    let row = cells + 1;
+    let mut vertices = Vec::with_capacity((6 * row * row) as usize);
+    let mut indices = Vec::with_capacity((6 * cells * cells * 6) as usize);
+
+    for (outward, right, up) in FACES {
+        let base = vertices.len() as u32;
+        for j in 0..=cells {
+            for i in 0..=cells {
+                let warp = |step: u32| (FRAC_PI_4 * (2.0 * step as f32 / cells as f32 - 1.0)).tan();
+                let direction = (outward + right * warp(i) + up * warp(j)).normalize();
+                let (u, v) = uv_from_direction(&direction);
+                vertices.push(Vertex::new(direction * SPHERE_RADIUS, direction, Vec2::new(u, v)));
+            }
+        }
+        for j in 0..cells {
+            for i in 0..cells {
+                let a = base + j * row + i;
+                let b = a + 1;
+                let c = b + row;
+                let d = a + row;
+                indices.extend_from_slice(&[a, b, c, a, c, d]);
+            }
+        }
+    }
+
+    for triangle in indices.chunks_mut(3) {
+        fix_texture_wrap(&mut vertices, triangle);
+    }
+    let mut mesh = IndexedMesh { vertices, indices };
+    mesh.generate_tangents();
+    mesh
+}
+
+// A triangle spanning the seam has some corners near u = 1 and some near
+// u = 0; the low ones are moved past 1 so it covers the thin strip between.
+// A corner on a pole has no longitude, it takes the one of the other two.
+fn fix_texture_wrap(vertices: &mut Vec<Vertex>, triangle: &mut [u32]) {
+    let corners: Vec<Vertex> = triangle.iter().map(|&index| vertices[index as usize].clone()).collect();
+    let on_pole = |vertex: &Vertex| vertex.normal.y.abs() > POLE;
+    let longitudes: Vec<f32> = corners.iter().filter(|vertex| !on_pole(vertex)).map(|vertex| vertex.tex_coords.x).collect();
+    let highest = longitudes.iter().copied().fold(f32::MIN, f32::max);
+    let lowest = longitudes.iter().copied().fold(f32::MAX, f32::min);
+    let wraps = highest - lowest > 0.5;
+    let unwrap = |u: f32| if wraps && u < 0.5 { u + 1.0 } else { u };
+    let pole_longitude = longitudes.iter().map(|&u| unwrap(u)).sum::<f32>() / longitudes.len().max(1) as f32;
+
+    for (corner, index) in corners.iter().zip(triangle.iter_mut()) {
+        let u = if on_pole(corner) { pole_longitude } else { unwrap(corner.tex_coords.x) };
+        if u != corner.tex_coords.x {
+            let mut vertex = corner.clone();
+            vertex.tex_coords.x = u;
+            *index = vertices.len() as u32;
+            vertices.push(vertex);
+        }
+    }
+}
+
+// Terrain relief: every vertex pushed along its direction to `1 + height`
+// times the radius, `height` taken at the vertex's direction on the unit
+// sphere. Normals come from the slope of the height around each vertex rather
+// than from the triangles, so the copies along the cube's edges and the
+// texture seam still agree on theirs. Returns the farthest vertex in radii.
+fn displace(mesh: &mut IndexedMesh, height: &impl Fn(&Vec3) -> f32) -> f32 {
+    let mut extent: f32 = 1.0;
+    for vertex in mesh.vertices.iter_mut() {
+        let direction = vertex.position.normalize();
+        let reference = if direction.y.abs() > POLE { Vec3::x() } else { Vec3::y() };
+        let tangent = reference.cross(&direction).normalize();
+        let bitangent = direction.cross(&tangent);
+        let slope = |axis: &Vec3| {
+            let ahead = height(&(direction + axis * SLOPE_STEP).normalize());
+            let behind = height(&(direction - axis * SLOPE_STEP).normalize());
+            (ahead - behind) / (2.0 * SLOPE_STEP)
+        };
+
+        // With p = r·d the tangents are r·t + r'·d; their cross product
+        // points along r·d - r'₁·t₁ - r'₂·t₂
+        let radius = 1.0 + height(&direction);
+        vertex.position = direction * SPHERE_RADIUS * radius;
+        vertex.normal = (direction * radius - tangent * slope(&tangent) - bitangent * slope(&bitangent)).normalize();
+        extent = extent.max(radius);
+    }
+    extent
+}
+
+// A sphere at a few levels of detail, each with half the cells per edge of
+// the one before. A cell spans about π/2 / cells radians, so its flat face
+// sits r·θ²/8 inside the true outline; the level drawn is the coarsest that
+// keeps that under `error` pixels at the radius the sphere covers on screen.
+pub struct SphereLod {
+    levels: Vec<(u32, IndexedMesh)>, // Celdas por arista y malla, la más fina primero
+    pub extent: f32,                 // Radios hasta el vértice más lejano, más de 1 con relieve
+}
+
+impl SphereLod {
+    pub fn new(subdivisions: u32) -> Self {
+        let mut cells = subdivisions.clamp(1, MAX_SUBDIVISIONS);
+        let mut levels = vec![(cells, sphere_mesh(cells))];
+        while levels.len() < LOD_LEVELS && cells / 2 >= MIN_LOD_SUBDIVISIONS {
+            cells /= 2;
+            levels.push((cells, sphere_mesh(cells)));
+        }
+        SphereLod { levels, extent: 1.0 }
+    }
+
+    // Every level with the same relief, see `displace`
+    pub fn with_relief(mut self, height: impl Fn(&Vec3) -> f32) -> Self {
+        for (_, mesh) in self.levels.iter_mut() {
+            self.extent = self.extent.max(displace(mesh, &height));
+            mesh.generate_tangents();
+        }
+        self
+    }
+
+    // `screen_radius` and `error` in pixels
+    pub fn select(&self, screen_radius: f32, error: f32) -> &IndexedMesh {
+        let needed = FRAC_PI_2 * (screen_radius / (8.0 * error.max(1e-3))).sqrt();
+        self.levels.iter()
+            .rev()
+            .find(|(cells, _)| *cells as f32 >= needed)
+            .map_or(&self.levels[0].1, |(_, mesh)| mesh)
+    }
+}
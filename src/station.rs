@@ -0,0 +1,121 @@
+// station.rs
+
+use nalgebra_glm::{Mat4, Vec2, Vec3};
+use std::f32::consts::PI;
+use crate::obj::IndexedMesh;
+use crate::shaders::Shader;
+use crate::simplify::MeshLod;
+use crate::vertex::Vertex;
+
+const HUB_RADIUS: f32 = 1.5;
+const HUB_LENGTH: f32 = 2.5; // Medio largo, a lo largo de z
+const PORT_RADIUS: f32 = 0.5;
+const PORT_LENGTH: f32 = 0.5;
+const SEGMENTS: u32 = 16;
+
+// Orbital station parked in the scene, its docking port sticks out of the
+// hub along the model's +z axis
+pub struct Station {
+    pub name: String,
+    pub position: Vec3,
+    pub heading: f32, // Giro en Y, radianes
+    pub scale: f32,
+    pub shader: Shader,
+    pub mesh: MeshLod,
+}
+
+impl Station {
+    pub fn new(name: String, position: Vec3, heading: f32, scale: f32, shader: Shader) -> Self {
+        Station {
+            name,
+            position,
+            heading,
+            scale,
+            shader,
+            mesh: MeshLod::new(station_mesh()),
+        }
+    }
+
+    pub fn get_model_matrix(&self) -> Mat4 {
+        nalgebra_glm::translation(&self.position)
+            * nalgebra_glm::rotation(self.heading, &Vec3::y_axis())
+            * nalgebra_glm::scaling(&Vec3::new(self.scale, self.scale, self.scale))
+    }
+
+    // World position of the mouth of the docking port
+    pub fn port_position(&self) -> Vec3 {
+        self.position + self.port_axis() * (HUB_LENGTH + PORT_LENGTH) * self.scale
+    }
+
+    // Direction a ship has to come in from, pointing out of the port
+    pub fn port_axis(&self) -> Vec3 {
+        Vec3::new(self.heading.sin(), 0.0, self.heading.cos())
+    }
+
+    pub fn bounding_radius(&self) -> f32 {
+        5.0 * self.scale
+    }
+}
+
+// Capped hub, a narrower docking collar on its +z end and a solar panel on
+// each side. Sized for the spaceship shader: the hub is too wide to count as
+// its pod, the collar glows like an engine and the panels sit past |x| = 1.5.
+fn station_mesh() -> IndexedMesh {
+    let mut mesh = IndexedMesh { vertices: Vec::new(), indices: Vec::new() };
+    cylinder(&mut mesh, HUB_RADIUS, -HUB_LENGTH, HUB_LENGTH);
+    cylinder(&mut mesh, PORT_RADIUS, HUB_LENGTH, HUB_LENGTH + PORT_LENGTH);
+    for side in [-1.0, 1.0] {
+        cuboid(&mut mesh, Vec3::new(side * 3.4, 0.0, 0.0), Vec3::new(1.8, 0.05, 1.0));
+    }
+    mesh
+}
+
+fn cylinder(mesh: &mut IndexedMesh, radius: f32, z0: f32, z1: f32) {
+    for segment in 0..SEGMENTS {
+        let a0 = segment as f32 / SEGMENTS as f32 * 2.0 * PI;
+        let a1 = (segment + 1) as f32 / SEGMENTS as f32 * 2.0 * PI;
+        let side = |angle: f32, z: f32| Vec3::new(angle.cos() * radius, angle.sin() * radius, z);
+        let middle = (a0 + a1) * 0.5;
+        let outward = Vec3::new(middle.cos(), middle.sin(), 0.0);
+        quad(mesh, [side(a0, z0), side(a1, z0), side(a1, z1), side(a0, z1)], outward);
+
+        // End caps as triangle fans
+        let back = Vec3::new(0.0, 0.0, z0);
+        let front = Vec3::new(0.0, 0.0, z1);
+        triangle(mesh, [front, side(a0, z1), side(a1, z1)], Vec3::new(0.0, 0.0, 1.0));
+        triangle(mesh, [back, side(a1, z0), side(a0, z0)], Vec3::new(0.0, 0.0, -1.0));
+    }
+}
+
+fn cuboid(mesh: &mut IndexedMesh, center: Vec3, half: Vec3) {
+    let corner = |x: f32, y: f32, z: f32| center + Vec3::new(x * half.x, y * half.y, z * half.z);
+    let faces = [
+        (Vec3::new(1.0, 0.0, 0.0), [corner(1.0, -1.0, -1.0), corner(1.0, 1.0, -1.0), corner(1.0, 1.0, 1.0), corner(1.0, -1.0, 1.0)]),
+        (Vec3::new(-1.0, 0.0, 0.0), [corner(-1.0, -1.0, -1.0), corner(-1.0, 1.0, -1.0), corner(-1.0, 1.0, 1.0), corner(-1.0, -1.0, 1.0)]),
+        (Vec3::new(0.0, 1.0, 0.0), [corner(-1.0, 1.0, -1.0), corner(1.0, 1.0, -1.0), corner(1.0, 1.0, 1.0), corner(-1.0, 1.0, 1.0)]),
+        (Vec3::new(0.0, -1.0, 0.0), [corner(-1.0, -1.0, -1.0), corner(1.0, -1.0, -1.0), corner(1.0, -1.0, 1.0), corner(-1.0, -1.0, 1.0)]),
+        (Vec3::new(0.0, 0.0, 1.0), [corner(-1.0, -1.0, 1.0), corner(1.0, -1.0, 1.0), corner(1.0, 1.0, 1.0), corner(-1.0, 1.0, 1.0)]),
+        (Vec3::new(0.0, 0.0, -1.0), [corner(-1.0, -1.0, -1.0), corner(1.0, -1.0, -1.0), corner(1.0, 1.0, -1.0), corner(-1.0, 1.0, -1.0)]),
+    ];
+    for (normal, corners) in faces {
+        quad(mesh, corners, normal);
+    }
+}
+
+fn quad(mesh: &mut IndexedMesh, corners: [Vec3; 4], normal: Vec3) {
+    triangle(mesh, [corners[0], corners[1], corners[2]], normal);
+    triangle(mesh, [corners[0], corners[2], corners[3]], normal);
+}
+
+// Flat-normal triangle, flipped if needed so it winds counter-clockwise
+// seen from the side `normal` points to
+fn triangle(mesh: &mut IndexedMesh, mut corners: [Vec3; 3], normal: Vec3) {
+    if (corners[1] - corners[0]).cross(&(corners[2] - corners[0])).dot(&normal) < 0.0 {
+        corners.swap(1, 2);
+    }
+    let base = mesh.vertices.len() as u32;
+    for corner in corners {
+        mesh.vertices.push(Vertex::new(corner, normal, Vec2::new(0.0, 0.0)));
+    }
+    mesh.indices.extend_from_slice(&[base, base + 1, base + 2]);
+}
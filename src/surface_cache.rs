@@ -0,0 +1,117 @@
+// surface_cache.rs
+
+use nalgebra_glm::{Vec2, Vec3};
+use rayon::prelude::*;
+use std::f32::consts::PI;
+use crate::color::Color;
+use crate::fragment::Fragment;
+use crate::shaders::{fragment_shader, PlanetShader};
+use crate::tuning::ShaderParams;
+use crate::planet::SPHERE_RADIUS;
+use crate::Uniforms;
+
+// Texture-space shading cache: the planet shader is evaluated once per texel
+// of an equirectangular surface texture and the rasterizer samples it
+pub struct SurfaceCache {
+    width: usize,
+    height: usize,
+    data: Vec<Color>,
+    lit: bool,
+    baked_time: Option<u32>,
+    baked_params: ShaderParams, // Cambiarlos rehace la textura en el acto
+    frames_since_bake: u32,
+    refresh_interval: u32,
+}
+
+impl SurfaceCache {
+    pub fn new(width: usize, height: usize, refresh_interval: u32) -> Self {
+        SurfaceCache {
+            width,
+            height,
+            data: vec![Color::black(); width * height],
+            lit: false,
+            baked_time: None,
+            baked_params: ShaderParams::default(),
+            frames_since_bake: 0,
+            refresh_interval,
+        }
+    }
+
+    // Rebakes only when the shader time changed and enough frames went by,
+    // or right away when the shader parameters were tuned
+    pub fn update(&mut self, uniforms: &Uniforms, shader: &dyn PlanetShader) {
+        self.frames_since_bake += 1;
+
+        let stale = match self.baked_time {
+            None => true,
+            Some(time) => (time != uniforms.time && self.frames_since_bake >= self.refresh_interval)
+                || self.baked_params != uniforms.shader_params,
+        };
+
+        if stale {
+            self.bake(uniforms, shader);
+        }
+    }
+
+    fn bake(&mut self, uniforms: &Uniforms, shader: &dyn PlanetShader) {
+        let width = self.width;
+        let height = self.height;
+
+        self.data.par_iter_mut().enumerate().for_each(|(index, texel)| {
+            let u = ((index % width) as f32 + 0.5) / width as f32;
+            let v = ((index / width) as f32 + 0.5) / height as f32;
+            let direction = direction_from_uv(u, v);
+
+            // Bake unlit, lighting is applied when sampling
+            let fragment = Fragment::new(
+                Vec2::new(0.0, 0.0),
+                Color::new(100, 100, 100),
+                0.0,
+                direction,
+                1.0,
+                direction * SPHERE_RADIUS,
+                Vec2::new(u, v),
+            );
+            *texel = fragment_shader(&fragment, uniforms, shader);
+        });
+
+        self.lit = shader.uses_lighting();
+        self.baked_time = Some(uniforms.time);
+        self.baked_params = uniforms.shader_params;
+        self.frames_since_bake = 0;
+    }
+
+    pub fn shade(&self, fragment: &Fragment) -> Color {
+        let direction = fragment.vertex_position.normalize();
+        let (u, v) = uv_from_direction(&direction);
+
+        let x = ((u * self.width as f32) as usize).min(self.width - 1);
+        let y = ((v * self.height as f32) as usize).min(self.height - 1);
+        let color = self.data[y * self.width + x];
+
+        if self.lit {
+            color * fragment.intensity
+        } else {
+            color
+        }
+    }
+}
+
+pub fn direction_from_uv(u: f32, v: f32) -> Vec3 {
+    let longitude = u * 2.0 * PI - PI;
+    let latitude = PI / 2.0 - v * PI;
+    Vec3::new(
+        latitude.cos() * longitude.cos(),
+        latitude.sin(),
+        latitude.cos() * longitude.sin(),
+    )
+}
+
+pub fn uv_from_direction(direction: &Vec3) -> (f32, f32) {
+    let longitude = direction.z.atan2(direction.x);
+    let latitude = direction.y.clamp(-1.0, 1.0).asin();
+    (
+        (longitude + PI) / (2.0 * PI),
+        (PI / 2.0 - latitude) / PI,
+    )
+}
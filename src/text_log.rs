@@ -0,0 +1,59 @@
+// text_log.rs
+
+use std::collections::VecDeque;
+use crate::framebuffer::Framebuffer;
+use crate::font::{draw_text, GLYPH_HEIGHT};
+
+// Lines kept for crash reports, only the last `visible_lines` are drawn
+const HISTORY_LENGTH: usize = 100;
+
+// Accessible text channel: every state change is printed to stdout and kept
+// in a small on-screen console so the scene can be followed without visuals
+pub struct TextLog {
+    entries: VecDeque<String>,
+    visible_lines: usize,
+    pub visible: bool,
+}
+
+impl TextLog {
+    pub fn new(visible_lines: usize) -> Self {
+        TextLog {
+            entries: VecDeque::with_capacity(HISTORY_LENGTH),
+            visible_lines,
+            visible: true,
+        }
+    }
+
+    pub fn log(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        println!("[log] {}", message);
+
+        self.entries.push_back(message);
+        while self.entries.len() > HISTORY_LENGTH {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &String> {
+        self.entries.iter()
+    }
+
+    pub fn toggle_visible(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn render(&self, framebuffer: &mut Framebuffer) {
+        if !self.visible {
+            return;
+        }
+
+        let scale = 2;
+        let line_height = (GLYPH_HEIGHT + 2) * scale;
+        let shown = self.entries.len().min(self.visible_lines);
+        let top = framebuffer.height.saturating_sub(shown * line_height + 4);
+
+        for (i, entry) in self.entries.iter().skip(self.entries.len() - shown).enumerate() {
+            draw_text(framebuffer, 4, top + i * line_height, entry, 0xDDDDDD, scale);
+        }
+    }
+}
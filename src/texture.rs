@@ -1,18 +1,125 @@
 // texture.rs
 
-use std::sync::Arc;
-use once_cell::sync::OnceCell;
 use crate::color::Color;
 
-static TEXTURE: OnceCell<Arc<Texture>> = OnceCell::new();
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Filter {
+    Nearest,
+    Bilinear, // Mezcla los 4 texeles vecinos, evita el parpadeo al rotar
+    #[default]
+    Trilinear, // Bilineal en los dos mipmaps más cercanos, evita el brillo a distancia
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum WrapMode {
+    #[default]
+    Repeat,
+    Clamp, // Repite el texel del borde
+}
+
+impl Filter {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "nearest" => Some(Filter::Nearest),
+            "bilinear" => Some(Filter::Bilinear),
+            "trilinear" => Some(Filter::Trilinear),
+            _ => None,
+        }
+    }
+}
+
+impl WrapMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "repeat" => Some(WrapMode::Repeat),
+            "clamp" => Some(WrapMode::Clamp),
+            _ => None,
+        }
+    }
+
+    fn apply(&self, index: i64, size: u32) -> usize {
+        match self {
+            WrapMode::Repeat => index.rem_euclid(size as i64) as usize,
+            WrapMode::Clamp => index.clamp(0, size as i64 - 1) as usize,
+        }
+    }
+}
+
+// How a material reads its images
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sampler {
+    pub filter: Filter,
+    pub wrap: WrapMode,
+}
+
+impl Sampler {
+    // Texel indices and the bilinear weights for (u, v) in a width x height
+    // image, shared by textures and normal maps. Nearest returns the same
+    // texel four times with all the weight on the first one.
+    pub fn footprint(&self, u: f32, v: f32, width: u32, height: u32) -> ([usize; 4], f32, f32) {
+        let x = u * width as f32;
+        let y = v * height as f32;
+        let index = |tx: i64, ty: i64| self.wrap.apply(ty, height) * width as usize + self.wrap.apply(tx, width);
 
+        match self.filter {
+            Filter::Nearest => {
+                let texel = index(x.floor() as i64, y.floor() as i64);
+                ([texel; 4], 0.0, 0.0)
+            }
+            Filter::Bilinear | Filter::Trilinear => {
+                // Texel centers sit at +0.5
+                let x = x - 0.5;
+                let y = y - 0.5;
+                let (x0, y0) = (x.floor() as i64, y.floor() as i64);
+                (
+                    [index(x0, y0), index(x0 + 1, y0), index(x0, y0 + 1), index(x0 + 1, y0 + 1)],
+                    x - x0 as f32,
+                    y - y0 as f32,
+                )
+            }
+        }
+    }
+}
+
+// One level of the mip chain
 #[derive(Clone, Debug)]
-pub struct Texture {
+struct MipLevel {
     width: u32,
     height: u32,
     data: Vec<Color>,
 }
 
+impl MipLevel {
+    fn sample(&self, u: f32, v: f32, sampler: &Sampler) -> Color {
+        let (texels, tx, ty) = sampler.footprint(u, v, self.width, self.height);
+        let top = self.data[texels[0]].lerp(&self.data[texels[1]], tx);
+        let bottom = self.data[texels[2]].lerp(&self.data[texels[3]], tx);
+        top.lerp(&bottom, ty)
+    }
+
+    // Half size, each texel the average of a 2x2 block (edges reuse the last row/column).
+    // Averaged in linear light, so distant levels don't darken.
+    fn downsample(&self) -> MipLevel {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let texel = |x: u32, y: u32| self.data[(y.min(self.height - 1) * self.width + x.min(self.width - 1)) as usize];
+
+        let data = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                (texel(2 * x, 2 * y) + texel(2 * x + 1, 2 * y) + texel(2 * x, 2 * y + 1) + texel(2 * x + 1, 2 * y + 1)) * 0.25
+            })
+            .collect();
+
+        MipLevel { width, height, data }
+    }
+}
+
+// Image plus its mip chain down to 1x1, built once when loading
+#[derive(Clone, Debug)]
+pub struct Texture {
+    levels: Vec<MipLevel>,
+}
+
 impl Texture {
     pub fn new(path: &str) -> Result<Self, image::ImageError> {
         let img = image::open(path)?.to_rgba8();
@@ -21,34 +128,35 @@ impl Texture {
             .map(|p| Color::new(p[0], p[1], p[2]))
             .collect();
 
-        Ok(Texture {
-            width,
-            height,
-            data,
-        })
+        let mut levels = vec![MipLevel { width, height, data }];
+        while let Some(last) = levels.last().filter(|level| level.width > 1 || level.height > 1) {
+            levels.push(last.downsample());
+        }
+
+        Ok(Texture { levels })
     }
 
-    pub fn sample(&self, u: f32, v: f32) -> Color {
-        let u = u.fract().abs();
-        let v = v.fract().abs();
-        
-        let x = (u * (self.width as f32)) as u32;
-        let y = (v * (self.height as f32)) as u32;
-        
-        let index = (y * self.width + x) as usize;
-        self.data[index]
+    pub fn width(&self) -> u32 {
+        self.levels[0].width
     }
-}
 
-pub fn init_texture(path: &str) -> Result<(), image::ImageError> {
-    let texture = Texture::new(path)?;
-    TEXTURE.set(Arc::new(texture))
-        .expect("Texture already initialized");
-    Ok(())
-}
+    pub fn sample(&self, u: f32, v: f32, sampler: &Sampler) -> Color {
+        self.sample_lod(u, v, sampler, 0.0)
+    }
 
-pub fn with_texture(f: impl FnOnce(&Texture) -> Color) -> Color {
-    let texture = TEXTURE.get()
-        .expect("Texture not initialized");
-    f(texture)
-}
\ No newline at end of file
+    // `lod` is log2 of how many level-0 texels fall in one pixel, 0 = full size.
+    // Trilinear blends the two closest levels, the other filters snap to one.
+    pub fn sample_lod(&self, u: f32, v: f32, sampler: &Sampler, lod: f32) -> Color {
+        let lod = lod.clamp(0.0, (self.levels.len() - 1) as f32);
+        if sampler.filter != Filter::Trilinear {
+            return self.levels[lod.round() as usize].sample(u, v, sampler);
+        }
+        let lower = lod.floor() as usize;
+        let upper = (lower + 1).min(self.levels.len() - 1);
+        let color = self.levels[lower].sample(u, v, sampler);
+        if upper == lower {
+            return color;
+        }
+        color.lerp(&self.levels[upper].sample(u, v, sampler), lod - lower as f32)
+    }
+}
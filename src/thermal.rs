@@ -0,0 +1,81 @@
+// thermal.rs
+
+use crate::color::Color;
+
+const STAR_TEMPERATURE: f32 = 5772.0;  // Kelvin, sin temperatura propia en la escena
+const EARTH_TEMPERATURE: f32 = 288.0;  // A la distancia de referencia de la luz del sol
+const ENGINE_TEMPERATURE: f32 = 1800.0; // Lo que suman los motores a fondo
+const COLDEST: f32 = 40.0;             // Extremos de la escala de colores
+const HOTTEST: f32 = 6000.0;
+
+// "Ironbow" palette of thermal cameras, cold to hot
+const GRADIENT: [(f32, u32); 6] = [
+    (0.0, 0x000010),
+    (0.25, 0x2A0A7A),
+    (0.45, 0x9C1C8C),
+    (0.62, 0xE0402A),
+    (0.8, 0xF8A020),
+    (1.0, 0xFFFFE8),
+];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ViewMode {
+    Visible,
+    Thermal, // Colores falsos según la temperatura de cada cuerpo
+}
+
+impl ViewMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ViewMode::Visible => "visible",
+            ViewMode::Thermal => "thermal",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            ViewMode::Visible => ViewMode::Thermal,
+            ViewMode::Thermal => ViewMode::Visible,
+        }
+    }
+}
+
+// How warm a material reads in the thermal view. Bodies without a
+// temperature of their own sit at the equilibrium for their distance to the
+// sun, so the outer planets come out colder.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Thermal {
+    pub temperature: Option<f32>, // Kelvin, None = lo que le da el sol
+    pub engine_heat: f32,         // 0..1, motores en la cola (-Z del modelo)
+    pub engine_length: f32,       // Media longitud del modelo sin escalar, hasta la tobera
+}
+
+impl Thermal {
+    // Temperature of the body as a whole, `star` for the light source itself
+    pub fn body_temperature(&self, star: bool, light_distance: f32, reference_distance: f32) -> f32 {
+        match self.temperature {
+            Some(kelvin) => kelvin,
+            None if star => STAR_TEMPERATURE,
+            None => EARTH_TEMPERATURE * (reference_distance / light_distance.max(1e-3)).sqrt(),
+        }
+    }
+
+    // Heat added at a model-space depth along the ship, strongest at the tail
+    pub fn engine_temperature(&self, z: f32) -> f32 {
+        if self.engine_heat <= 0.0 || self.engine_length <= 0.0 {
+            return 0.0;
+        }
+        let tail = ((-z / self.engine_length - 0.3) / 0.7).clamp(0.0, 1.0);
+        ENGINE_TEMPERATURE * self.engine_heat * tail * tail
+    }
+}
+
+// False color for a temperature, on a log scale so both the sun and the
+// outer planets fit
+pub fn false_color(kelvin: f32) -> Color {
+    let t = ((kelvin.max(COLDEST) / COLDEST).ln() / (HOTTEST / COLDEST).ln()).clamp(0.0, 1.0);
+    let upper = GRADIENT.iter().position(|(stop, _)| *stop >= t).unwrap_or(GRADIENT.len() - 1).max(1);
+    let (start, from) = GRADIENT[upper - 1];
+    let (end, to) = GRADIENT[upper];
+    Color::from_hex(from).lerp(&Color::from_hex(to), (t - start) / (end - start))
+}
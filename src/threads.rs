@@ -0,0 +1,58 @@
+// threads.rs
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+static SINGLE_THREADED: AtomicBool = AtomicBool::new(false);
+
+// How many threads the program works on. `--threads <n>` sizes rayon's pool,
+// which every parallel pass of the frame runs on; without it there is one
+// thread per core. `--deterministic` runs everything on a single thread and
+// at a fixed time step, so the same scene gives the same frames on
+// every run: rayon's splits and the order of the results no longer depend on
+// which worker is free, and background work (asset loading, building a
+// system after a jump) finishes before the frame that started it goes on.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ThreadSettings {
+    pub threads: Option<usize>, // None, uno por núcleo
+    pub deterministic: bool,
+}
+
+impl ThreadSettings {
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let threads = match args.iter().position(|arg| arg == "--threads") {
+            Some(index) => Some(args.get(index + 1)
+                .and_then(|count| count.parse().ok())
+                .filter(|count: &usize| *count > 0)
+                .ok_or("--threads takes a positive number")?),
+            None => None,
+        };
+        let deterministic = args.iter().any(|arg| arg == "--deterministic");
+        if deterministic && threads.is_some_and(|count| count != 1) {
+            return Err("--deterministic runs on a single thread, it can't take --threads".to_string());
+        }
+        Ok(ThreadSettings { threads, deterministic })
+    }
+
+    // Before anything runs in parallel: rayon's pool can only be set up once
+    pub fn apply(&self) -> Result<(), String> {
+        let threads = if self.deterministic { Some(1) } else { self.threads };
+        if let Some(threads) = threads {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build_global()
+                .map_err(|e| e.to_string())?;
+        }
+        SINGLE_THREADED.store(self.deterministic, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+// Work that would run in the background, done right away in deterministic mode
+pub fn spawn(job: impl FnOnce() + Send + 'static) {
+    if SINGLE_THREADED.load(Ordering::Relaxed) {
+        job();
+    } else {
+        thread::spawn(job);
+    }
+}
@@ -0,0 +1,27 @@
+// touchpad.rs
+
+use crate::camera::Camera;
+
+// Turns scroll deltas into camera motion. Precise touchpads report a
+// two-finger drag as 2D scroll and, on several platforms, a pinch as
+// vertical scroll with Ctrl held.
+pub struct Touchpad {
+    pub enabled: bool,          // false: la rueda solo hace zoom, como con un mouse
+    pub orbit_sensitivity: f32, // Radianes por unidad de scroll
+    pub pinch_sensitivity: f32, // Distancia de zoom por unidad de scroll
+    pub wheel_sensitivity: f32, // Zoom con la rueda cuando está desactivado
+}
+
+impl Touchpad {
+    pub fn apply(&self, camera: &mut Camera, scroll: (f32, f32), ctrl: bool) {
+        let (dx, dy) = scroll;
+        if !self.enabled {
+            camera.zoom(dy * self.wheel_sensitivity);
+        } else if ctrl {
+            // Spreading the fingers scrolls up, which zooms in
+            camera.zoom(dy * self.pinch_sensitivity);
+        } else {
+            camera.orbit(-dx * self.orbit_sensitivity, -dy * self.orbit_sensitivity);
+        }
+    }
+}
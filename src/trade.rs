@@ -0,0 +1,148 @@
+// trade.rs
+
+use std::f32::consts::PI;
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+use crate::font::{draw_text, GLYPH_WIDTH};
+use crate::framebuffer::Framebuffer;
+use crate::line::line;
+use crate::planet::Planet;
+use crate::Uniforms;
+
+const ZONE_SEGMENTS: usize = 64;   // Tramos del círculo de la zona, la mitad se dibujan
+const ZONE_COLOR: u32 = 0x66FF66;
+const ZONE_BRIGHTNESS: f32 = 0.6;
+const TEXT_SCALE: usize = 2;
+const READOUT_TOP: usize = 180;    // Pixeles, debajo de las líneas de minería
+const READOUT_MARGIN: usize = 12;
+const TEXT_COLOR: u32 = 0x66FF66;
+const IDLE_COLOR: u32 = 0x9AA8B8;
+
+// A delivery: `tons` of `cargo` picked up at the station and dropped inside
+// `zone` radii of the destination planet for `reward` credits
+#[derive(Clone, Debug)]
+pub struct Contract {
+    pub cargo: String,
+    pub tons: f32,
+    pub destination: usize, // Índice en planets
+    pub zone: f32,          // Radios del planeta
+    pub reward: f32,        // Créditos
+}
+
+// The station's trade: docked, the ship sells the ore it mined and loads the
+// next contract of the scenario; flying into the contract's zone delivers
+// it. Contracts go in order and, with `repeat`, start over after the last.
+pub struct Trade {
+    pub ore_price: f32, // Créditos por tonelada
+    pub repeat: bool,
+    pub contracts: Vec<Contract>,
+    pub credits: f32,
+    next: usize,          // Próximo contrato a cargar
+    hold: Option<usize>,  // Contrato a bordo
+}
+
+impl Trade {
+    pub fn new(ore_price: f32, repeat: bool, contracts: Vec<Contract>) -> Self {
+        Trade {
+            ore_price,
+            repeat,
+            contracts,
+            credits: 0.0,
+            next: 0,
+            hold: None,
+        }
+    }
+
+    // The next contract and the one on board, to save a session
+    pub fn progress(&self) -> (usize, Option<usize>) {
+        (self.next, self.hold)
+    }
+
+    // Picks up where a saved session left off, contracts the scene no longer has are dropped
+    pub fn resume(&mut self, next: usize, hold: Option<usize>) {
+        self.next = next.min(self.contracts.len());
+        self.hold = hold.filter(|&contract| contract < self.contracts.len());
+    }
+
+    // Center and radius of the delivery zone of the contract on board
+    fn zone<'a>(&'a self, planets: &'a [Planet], model_radius: f32) -> Option<(&'a Contract, &'a Planet, f32)> {
+        let contract = self.contracts.get(self.hold?)?;
+        let planet = planets.get(contract.destination)?;
+        Some((contract, planet, planet.radius * model_radius * contract.zone))
+    }
+
+    // One frame of trade, `ore` is the mining hold and is emptied when sold.
+    // Returns what happened for the log.
+    pub fn update(&mut self, docked: bool, ore: &mut f32, ship: &Vec3, planets: &[Planet], model_radius: f32) -> Vec<String> {
+        let mut messages = Vec::new();
+        if docked && *ore > 0.0 && self.ore_price > 0.0 {
+            let earned = *ore * self.ore_price;
+            self.credits += earned;
+            messages.push(format!("Sold {:.0} t of ore for {:.0} credits", *ore, earned));
+            *ore = 0.0;
+        }
+
+        if docked && self.hold.is_none() {
+            if self.next >= self.contracts.len() && self.repeat {
+                self.next = 0;
+            }
+            if let Some(contract) = self.contracts.get(self.next) {
+                let destination = planets.get(contract.destination).map_or("?", |planet| planet.name.as_str());
+                messages.push(format!("Loaded {:.0} t of {} for {}", contract.tons, contract.cargo, destination));
+                self.hold = Some(self.next);
+                self.next += 1;
+            }
+        }
+
+        let delivered = self.zone(planets, model_radius)
+            .filter(|(_, planet, radius)| (ship - planet.get_position()).magnitude() < *radius)
+            .map(|(contract, planet, _)| (format!("Delivered {} to {}, {:.0} credits", contract.cargo, planet.name, contract.reward), contract.reward));
+        if let Some((message, reward)) = delivered {
+            self.credits += reward;
+            self.hold = None;
+            messages.push(message);
+            if self.next >= self.contracts.len() && !self.repeat {
+                messages.push(format!("All contracts done, {:.0} credits", self.credits));
+            }
+        }
+        messages
+    }
+
+    // The delivery zone as a dashed circle around the destination, level
+    // with the ecliptic, while a contract is on board
+    pub fn render_zone(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, planets: &[Planet], model_radius: f32) {
+        let Some((_, planet, radius)) = self.zone(planets, model_radius) else {
+            return;
+        };
+        let center = planet.get_position();
+        let color = Color::from_hex(ZONE_COLOR) * ZONE_BRIGHTNESS;
+        let point = |segment: usize| {
+            let (sin, cos) = (segment as f32 / ZONE_SEGMENTS as f32 * 2.0 * PI).sin_cos();
+            center + Vec3::new(cos, 0.0, sin) * radius
+        };
+        for segment in (0..ZONE_SEGMENTS).step_by(2) {
+            line(framebuffer, uniforms, &point(segment), &point(segment + 1), color);
+        }
+    }
+
+    // Credits and the contract on board with the distance left to its zone,
+    // or the next one waiting at the station, under the mining readouts
+    pub fn render(&self, framebuffer: &mut Framebuffer, ship: &Vec3, planets: &[Planet], model_radius: f32) {
+        if self.contracts.is_empty() && self.credits <= 0.0 {
+            return;
+        }
+        let mut lines = vec![(format!("CREDITS {:.0}", self.credits), TEXT_COLOR)];
+        if let Some((contract, planet, radius)) = self.zone(planets, model_radius) {
+            let remaining = ((ship - planet.get_position()).magnitude() - radius).max(0.0);
+            lines.push((format!("HOLD {:.0} T {} > {}", contract.tons, contract.cargo, planet.name), TEXT_COLOR));
+            lines.push((format!("ZONE {:.1}", remaining), TEXT_COLOR));
+        } else if let Some(contract) = self.contracts.get(self.next).or_else(|| self.contracts.first().filter(|_| self.repeat)) {
+            lines.push((format!("DOCK TO LOAD {:.0} T {}", contract.tons, contract.cargo), IDLE_COLOR));
+        }
+        for (i, (text, color)) in lines.iter().enumerate() {
+            let width = text.chars().count() * (GLYPH_WIDTH + 1) * TEXT_SCALE;
+            let x = framebuffer.width.saturating_sub(width + READOUT_MARGIN);
+            draw_text(framebuffer, x, READOUT_TOP + i * 14, text, *color, TEXT_SCALE);
+        }
+    }
+}
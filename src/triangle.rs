@@ -1,75 +1,206 @@
 // triangle.rs
 
-use nalgebra_glm::{Vec3, dot, Vec2};
-use crate::fragment::Fragment;
+use nalgebra_glm::{Vec3, Vec4, Mat4, dot, Vec2};
+use crate::fragment::FragmentBuffer;
 use crate::vertex::Vertex;
 use crate::color::Color;
+use crate::shaders::ShadingMode;
+
+// Triangles are clipped to this multiple of the screen in x/y (guard band),
+// beyond it the rasterizer would only walk huge off-screen bounding boxes
+const GUARD_BAND: f32 = 1.25;
+
+// Clip planes in homogeneous space as (x, y, z, w) weights: a vertex is inside
+// when dot(plane, clip_position) >= 0. The near plane comes from the depth range.
+const CLIP_PLANES: [[f32; 4]; 4] = [
+    [1.0, 0.0, 0.0, GUARD_BAND],   // left
+    [-1.0, 0.0, 0.0, GUARD_BAND],  // right
+    [0.0, 1.0, 0.0, GUARD_BAND],   // bottom
+    [0.0, -1.0, 0.0, GUARD_BAND],  // top
+];
+
+// The polygons clipping goes through and the triangles it leaves, kept from
+// triangle to triangle so clipping doesn't allocate
+#[derive(Default)]
+pub struct Clipper {
+    polygon: Vec<Vertex>,
+    clipped: Vec<Vertex>,
+    triangles: Vec<[Vertex; 3]>,
+}
+
+impl Clipper {
+    pub fn clear(&mut self) {
+        self.polygon.clear();
+        self.clipped.clear();
+        self.triangles.clear();
+    }
+
+    // Clips a triangle against the near plane and the guard band, returning the
+    // resulting fan of triangles with screen positions recomputed
+    pub fn clip(&mut self, v1: &Vertex, v2: &Vertex, v3: &Vertex, viewport_matrix: &Mat4, near_plane: &Vec4) -> &[[Vertex; 3]] {
+        self.triangles.clear();
+        let guard = |i: usize| Vec4::new(CLIP_PLANES[i][0], CLIP_PLANES[i][1], CLIP_PLANES[i][2], CLIP_PLANES[i][3]);
+        let planes = [*near_plane, guard(0), guard(1), guard(2), guard(3)];
+        let distances = |v: &Vertex| planes.map(|plane| dot(&plane, &v.clip_position));
+
+        // Fast paths: fully inside or fully outside a single plane
+        let (d1, d2, d3) = (distances(v1), distances(v2), distances(v3));
+        if (0..planes.len()).all(|i| d1[i] >= 0.0 && d2[i] >= 0.0 && d3[i] >= 0.0) {
+            self.triangles.push([v1.clone(), v2.clone(), v3.clone()]);
+            return &self.triangles;
+        }
+        if (0..planes.len()).any(|i| d1[i] < 0.0 && d2[i] < 0.0 && d3[i] < 0.0) {
+            return &self.triangles;
+        }
+
+        // Sutherland-Hodgman against each plane
+        self.polygon.clear();
+        self.polygon.extend([v1.clone(), v2.clone(), v3.clone()]);
+        for plane in planes.iter() {
+            self.clipped.clear();
+            let polygon = &self.polygon;
+
+            for i in 0..polygon.len() {
+                let current = &polygon[i];
+                let next = &polygon[(i + 1) % polygon.len()];
+                let d_current = dot(plane, &current.clip_position);
+                let d_next = dot(plane, &next.clip_position);
+
+                if d_current >= 0.0 {
+                    self.clipped.push(current.clone());
+                }
+                if (d_current >= 0.0) != (d_next >= 0.0) {
+                    let t = d_current / (d_current - d_next);
+                    self.clipped.push(lerp_vertex(current, next, t, viewport_matrix));
+                }
+            }
 
-pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
-    let mut fragments = Vec::new();
+            std::mem::swap(&mut self.polygon, &mut self.clipped);
+            if self.polygon.len() < 3 {
+                return &self.triangles;
+            }
+        }
+
+        let polygon = &self.polygon;
+        self.triangles.extend((1..polygon.len() - 1).map(|i| [polygon[0].clone(), polygon[i].clone(), polygon[i + 1].clone()]));
+        &self.triangles
+    }
+}
+
+// Front faces are counter-clockwise in world space, which after the viewport's
+// y flip gives a positive screen-space area
+pub fn is_back_facing(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> bool {
+    edge_function(&v1.transformed_position, &v2.transformed_position, &v3.transformed_position) <= 0.0
+}
+
+fn lerp_vertex(a: &Vertex, b: &Vertex, t: f32, viewport_matrix: &Mat4) -> Vertex {
+    let clip_position = a.clip_position + (b.clip_position - a.clip_position) * t;
+    let ndc = clip_position / clip_position.w;
+    let screen = viewport_matrix * Vec4::new(ndc.x, ndc.y, ndc.z, 1.0);
+
+    Vertex {
+        position: a.position.lerp(&b.position, t),
+        normal: a.normal.lerp(&b.normal, t),
+        tex_coords: a.tex_coords.lerp(&b.tex_coords, t),
+        tangent: a.tangent.lerp(&b.tangent, t),
+        color: a.color.lerp(&b.color, t),
+        transformed_position: Vec3::new(screen.x, screen.y, screen.z),
+        transformed_normal: a.transformed_normal.lerp(&b.transformed_normal, t),
+        transformed_tangent: a.transformed_tangent.lerp(&b.transformed_tangent, t),
+        world_position: a.world_position.lerp(&b.world_position, t),
+        clip_position,
+    }
+}
+
+// `light_position` is the Sun in world space, lighting is N·L towards it,
+// and `camera_position` is the eye the view directions point to.
+// Fills `fragments` with the covered pixels of a `screen` (width, height)
+// wide target, replacing what it held.
+pub fn triangle(
+    v1: &Vertex,
+    v2: &Vertex,
+    v3: &Vertex,
+    shading_mode: ShadingMode,
+    (light_position, camera_position): (&Vec3, &Vec3),
+    screen: (usize, usize),
+    fragments: &mut FragmentBuffer,
+) {
+    fragments.clear();
     let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
     let (t1, t2, t3) = (v1.tex_coords, v2.tex_coords, v3.tex_coords);
 
-    let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
-
-    let light_dir = Vec3::new(0.0, 0.0, 1.0);
+    let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c, screen);
 
     let triangle_area = edge_function(&a, &b, &c);
 
-    // Iterate over each pixel in the bounding box
+    // Coverage first: the pixels inside the triangle and their barycentric weights
     for y in min_y..=max_y {
         for x in min_x..=max_x {
             let point = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
-
-            // Calculate barycentric coordinates
             let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
 
-            // Check if the point is inside the triangle
             if w1 >= 0.0 && w1 <= 1.0 && 
             w2 >= 0.0 && w2 <= 1.0 &&
             w3 >= 0.0 && w3 <= 1.0 {
+                fragments.positions.push(Vec2::new(x as f32, y as f32));
+                fragments.weights.push(Vec3::new(w1, w2, w3));
+            }
+        }
+    }
 
-                // Interpolate normal
-                let normal = v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3;
-                let normal = normal.normalize();
-
-                // Calculate lighting intensity
-                let intensity = dot(&normal, &light_dir).max(0.0);
-
-                // Create a gray color (unchanged)
-                let color = Color::new(100, 100, 100); // Medium gray
-
-                // Interpolate depth
-                let depth = a.z * w1 + b.z * w2 + c.z * w3;
+    // Then every attribute in its own pass over the weights
+    let weights = &fragments.weights;
+    fragments.depths.extend(weights.iter().map(|w| a.z * w.x + b.z * w.y + c.z * w.z));
 
-                // Positions of the original vertex
-                let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
+    // Positions of the original vertex
+    fragments.vertex_positions.extend(weights.iter().map(|w| v1.position * w.x + v2.position * w.y + v3.position * w.z));
 
-                // Interpolate texture coordinates
-                let tex_u = t1.x * w1 + t2.x * w2 + t3.x * w3;
-                let tex_v = t1.y * w1 + t2.y * w2 + t3.y * w3;
+    fragments.tex_coords.extend(weights.iter().map(|w| t1 * w.x + t2 * w.y + t3 * w.z));
 
-                fragments.push(Fragment::new(
-                    Vec2::new(x as f32, y as f32),
-                    color,
-                    depth,
-                    normal,
-                    intensity,
-                    vertex_position,
-                    Vec2::new(tex_u, tex_v),
-                ));
-            }
+    // Flat shading uses the face normal from the world-space winding (CCW = front)
+    match shading_mode {
+        ShadingMode::Flat => {
+            let face_normal = (v2.world_position - v1.world_position)
+                .cross(&(v3.world_position - v1.world_position))
+                .normalize();
+            fragments.normals.resize(weights.len(), face_normal);
         }
+        _ => fragments.normals.extend(weights.iter().map(|w| {
+            (v1.transformed_normal * w.x + v2.transformed_normal * w.y + v3.transformed_normal * w.z).normalize()
+        })),
     }
 
-    fragments
+    // Tangents for normal maps, renormalized by the shader that reads them
+    fragments.tangents.extend(weights.iter().map(|w| v1.transformed_tangent * w.x + v2.transformed_tangent * w.y + v3.transformed_tangent * w.z));
+
+    // Lighting intensity towards the light at each point
+    fragments.intensities.extend(weights.iter().zip(&fragments.normals).map(|(w, normal)| {
+        let world_position = v1.world_position * w.x + v2.world_position * w.y + v3.world_position * w.z;
+        let light_dir = (light_position - world_position).normalize();
+        dot(normal, &light_dir).max(0.0)
+    }));
+
+    // Direction towards the eye, for highlights
+    fragments.view_directions.extend(weights.iter().map(|w| {
+        let world_position = v1.world_position * w.x + v2.world_position * w.y + v3.world_position * w.z;
+        (camera_position - world_position).normalize()
+    }));
+
+    // Gouraud interpolates the colors shaded at the vertices,
+    // otherwise use a gray color (unchanged)
+    match shading_mode {
+        ShadingMode::Gouraud => fragments.colors.extend(weights.iter().map(|w| v1.color * w.x + v2.color * w.y + v3.color * w.z)),
+        _ => fragments.colors.resize(weights.len(), Color::new(100, 100, 100)), // Medium gray
+    }
 }
 
-fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
-    let min_x = v1.x.min(v2.x).min(v3.x).floor() as i32;
-    let min_y = v1.y.min(v2.y).min(v3.y).floor() as i32;
-    let max_x = v1.x.max(v2.x).max(v3.x).ceil() as i32;
-    let max_y = v1.y.max(v2.y).max(v3.y).ceil() as i32;
+// Clamped to the screen: the guard band lets clipped triangles reach past
+// its edges, and those pixels are never drawn
+fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3, (width, height): (usize, usize)) -> (i32, i32, i32, i32) {
+    let min_x = (v1.x.min(v2.x).min(v3.x).floor() as i32).max(0);
+    let min_y = (v1.y.min(v2.y).min(v3.y).floor() as i32).max(0);
+    let max_x = (v1.x.max(v2.x).max(v3.x).ceil() as i32).min(width as i32 - 1);
+    let max_y = (v1.y.max(v2.y).max(v3.y).ceil() as i32).min(height as i32 - 1);
 
     (min_x, min_y, max_x, max_y)
 }
@@ -0,0 +1,289 @@
+// tuning.rs
+
+use std::collections::BTreeMap;
+use std::fs;
+use minifb::Key;
+use serde::Deserialize;
+use crate::color::Color;
+use crate::font::{draw_text, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::framebuffer::Framebuffer;
+use crate::input::Input;
+
+const TEXT_SCALE: usize = 2;
+const ROW_HEIGHT: usize = (GLYPH_HEIGHT + 3) * TEXT_SCALE;
+const PANEL_PADDING: usize = 12;
+const PANEL_DIM: f32 = 0.25;
+const TITLE_COLOR: u32 = 0xFFFFFF;
+const ROW_COLOR: u32 = 0xDDDDDD;
+const CHANGED_COLOR: u32 = 0xFFCC66;
+const SELECTED_COLOR: u32 = 0x66FF66;
+const HINT_COLOR: u32 = 0x9AA8B8;
+const FINE_STEP: f32 = 0.1; // Con Shift cada paso es una décima
+
+// A value a shader reads instead of a constant
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum ParamValue {
+    Number(f32),
+    Color([u8; 3]), // sRGB, como Color::new
+}
+
+// Every tunable value, in the order of PARAMS
+#[derive(Clone, Copy, Debug)]
+pub enum Param {
+    LavaFrequency,
+    LavaPulse,
+    LavaZoom,
+    LavaBright,
+    LavaDark,
+    CloudZoom,
+    CloudCoverZoom,
+    CloudSnow,
+    CloudLand,
+    CloudDesert,
+    CloudCover,
+    CloudGlint,
+    CloudGlintShininess,
+    CityLights,
+    CityColor,
+    CityZoom,
+    CityTerminator,
+    GiantBands,
+    GiantBase,
+    GiantBand,
+}
+
+struct ParamSpec {
+    shader: &'static str,
+    name: &'static str,
+    default: ParamValue,
+    range: (f32, f32), // Los colores van de 0 a 255
+    step: f32,         // Por frame con la tecla apretada
+}
+
+const fn number(shader: &'static str, name: &'static str, default: f32, range: (f32, f32), step: f32) -> ParamSpec {
+    ParamSpec { shader, name, default: ParamValue::Number(default), range, step }
+}
+
+const fn color(shader: &'static str, name: &'static str, default: [u8; 3]) -> ParamSpec {
+    ParamSpec { shader, name, default: ParamValue::Color(default), range: (0.0, 255.0), step: 1.0 }
+}
+
+const PARAM_COUNT: usize = 20;
+const PARAMS: [ParamSpec; PARAM_COUNT] = [
+    number("lava", "frequency", 0.2, (0.0, 2.0), 0.01),
+    number("lava", "pulse", 0.5, (0.0, 2.0), 0.01),
+    number("lava", "zoom", 1000.0, (10.0, 5000.0), 10.0),
+    color("lava", "bright", [255, 240, 0]),
+    color("lava", "dark", [130, 20, 0]),
+    number("clouds", "zoom", 80.0, (1.0, 500.0), 1.0),
+    number("clouds", "cloud_zoom", 100.0, (1.0, 500.0), 1.0),
+    number("clouds", "snow", 0.7, (0.0, 1.0), 0.01),
+    number("clouds", "land", 0.4, (-1.0, 1.0), 0.01),
+    number("clouds", "desert", 0.3, (-1.0, 1.0), 0.01),
+    number("clouds", "cover", 0.6, (-1.0, 1.0), 0.01),
+    number("clouds", "glint", 0.8, (0.0, 4.0), 0.01),
+    number("clouds", "glint_shininess", 96.0, (1.0, 512.0), 1.0),
+    number("clouds", "city_lights", 1.0, (0.0, 4.0), 0.01),
+    color("clouds", "city_color", [255, 196, 120]),
+    number("clouds", "city_zoom", 600.0, (10.0, 5000.0), 10.0),
+    number("clouds", "terminator", 0.15, (0.01, 1.0), 0.01),
+    number("gas-giant", "bands", 10.0, (0.0, 50.0), 0.1),
+    color("gas-giant", "base", [70, 130, 180]),
+    color("gas-giant", "band", [255, 255, 255]),
+];
+
+// The values every shader reads this frame, copied into the uniforms
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShaderParams {
+    values: [ParamValue; PARAM_COUNT],
+}
+
+impl Default for ShaderParams {
+    fn default() -> Self {
+        ShaderParams { values: PARAMS.map(|spec| spec.default) }
+    }
+}
+
+impl ShaderParams {
+    pub fn number(&self, param: Param) -> f32 {
+        match self.values[param as usize] {
+            ParamValue::Number(value) => value,
+            ParamValue::Color(_) => 0.0,
+        }
+    }
+
+    pub fn color(&self, param: Param) -> Color {
+        match self.values[param as usize] {
+            ParamValue::Color([r, g, b]) => Color::new(r, g, b),
+            ParamValue::Number(_) => Color::black(),
+        }
+    }
+
+    // Parameters missing from the file keep their defaults
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let table: BTreeMap<String, BTreeMap<String, ParamValue>> = toml::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+
+        let mut params = ShaderParams::default();
+        for (shader, values) in &table {
+            for (name, value) in values {
+                let index = PARAMS.iter()
+                    .position(|spec| spec.shader == shader && spec.name == name)
+                    .ok_or_else(|| format!("{}: unknown parameter {}.{}", path, shader, name))?;
+                let spec = &PARAMS[index];
+                params.values[index] = match (spec.default, *value) {
+                    (ParamValue::Number(_), ParamValue::Number(value)) => ParamValue::Number(value.clamp(spec.range.0, spec.range.1)),
+                    (ParamValue::Color(_), ParamValue::Color(value)) => ParamValue::Color(value),
+                    _ => return Err(format!("{}: {}.{} is the wrong kind of value", path, shader, name)),
+                };
+            }
+        }
+        Ok(params)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let mut table: BTreeMap<&str, BTreeMap<&str, toml::Value>> = BTreeMap::new();
+        for (spec, value) in PARAMS.iter().zip(self.values) {
+            let value = match value {
+                // Through the shortest decimal, so 0.2 isn't written as 0.20000000298023224
+                ParamValue::Number(value) => toml::Value::Float(value.to_string().parse().unwrap_or(value as f64)),
+                ParamValue::Color(channels) => toml::Value::Array(channels.iter().map(|&byte| toml::Value::Integer(byte as i64)).collect()),
+            };
+            table.entry(spec.shader).or_default().insert(spec.name, value);
+        }
+        let text = toml::to_string(&table).map_err(|e| e.to_string())?;
+        let text = format!("# Parámetros de los shaders, Ctrl+U los ajusta mientras corre el programa\n\n{}", text);
+        fs::write(path, text).map_err(|e| format!("{}: {}", path, e))
+    }
+}
+
+pub enum TuningCommand {
+    Save,
+    Load,
+}
+
+// Panel listing every parameter: the arrows pick one and nudge it, colors
+// one channel at a time
+pub struct TuningPanel {
+    pub open: bool,
+    selected: usize,
+    channel: usize, // Canal que mueven las flechas en los colores, 0 = rojo
+}
+
+impl TuningPanel {
+    pub fn new() -> Self {
+        TuningPanel { open: false, selected: 0, channel: 0 }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    pub fn update(&mut self, input: &Input, params: &mut ShaderParams) -> Option<TuningCommand> {
+        if !self.open {
+            return None;
+        }
+        if input.is_pressed(Key::Backspace) {
+            self.open = false;
+            return None;
+        }
+        if input.is_pressed(Key::Up) {
+            self.selected = (self.selected + PARAM_COUNT - 1) % PARAM_COUNT;
+        }
+        if input.is_pressed(Key::Down) {
+            self.selected = (self.selected + 1) % PARAM_COUNT;
+        }
+        if input.is_pressed(Key::C) {
+            self.channel = (self.channel + 1) % 3;
+        }
+        if input.is_pressed(Key::R) {
+            params.values[self.selected] = PARAMS[self.selected].default;
+        }
+
+        let direction = match (input.is_down(Key::Left), input.is_down(Key::Right)) {
+            (true, false) => -1.0,
+            (false, true) => 1.0,
+            _ => 0.0,
+        };
+        if direction != 0.0 {
+            let spec = &PARAMS[self.selected];
+            let fine = input.is_down(Key::LeftShift) || input.is_down(Key::RightShift);
+            match &mut params.values[self.selected] {
+                ParamValue::Number(value) => {
+                    let step = if fine { spec.step * FINE_STEP } else { spec.step };
+                    *value = (*value + direction * step).clamp(spec.range.0, spec.range.1);
+                }
+                // Colors move a whole byte either way, Shift doesn't make it finer
+                ParamValue::Color(channels) => {
+                    let channel = &mut channels[self.channel];
+                    *channel = (*channel as f32 + direction).clamp(spec.range.0, spec.range.1) as u8;
+                }
+            }
+        }
+
+        if input.is_pressed(Key::S) {
+            return Some(TuningCommand::Save);
+        }
+        if input.is_pressed(Key::L) {
+            return Some(TuningCommand::Load);
+        }
+        None
+    }
+
+    pub fn render(&self, framebuffer: &mut Framebuffer, params: &ShaderParams) {
+        if !self.open {
+            return;
+        }
+        let rows: Vec<(String, u32)> = PARAMS.iter().zip(params.values).enumerate()
+            .map(|(index, (spec, value))| {
+                let selected = index == self.selected;
+                let text = match value {
+                    ParamValue::Number(value) => format!("{:.3}", value),
+                    ParamValue::Color(channels) => channels.iter().enumerate()
+                        .map(|(channel, byte)| {
+                            let marker = if selected && channel == self.channel { "*" } else { "" };
+                            format!("{}{}", marker, byte)
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                };
+                let color = if selected {
+                    SELECTED_COLOR
+                } else if value != spec.default {
+                    CHANGED_COLOR
+                } else {
+                    ROW_COLOR
+                };
+                let marker = if selected { ">" } else { " " };
+                (format!("{} {} {}  {}", marker, spec.shader, spec.name, text).to_uppercase(), color)
+            })
+            .collect();
+        let title = "SHADER PARAMETERS";
+        let hint = "LEFT/RIGHT CHANGE  SHIFT FINE  C CHANNEL  R RESET  S SAVE  L LOAD";
+        let columns = rows.iter().map(|(text, _)| text.chars().count())
+            .chain([title.len(), hint.len()])
+            .max()
+            .unwrap_or(0);
+
+        let width = columns * (GLYPH_WIDTH + 1) * TEXT_SCALE + 2 * PANEL_PADDING;
+        let height = (rows.len() + 3) * ROW_HEIGHT + 2 * PANEL_PADDING;
+        let left = framebuffer.width.saturating_sub(width) / 2;
+        let top = framebuffer.height.saturating_sub(height) / 2;
+        for y in top..top + height {
+            for x in left..left + width {
+                framebuffer.darken(x, y, PANEL_DIM);
+            }
+        }
+
+        let x = left + PANEL_PADDING;
+        let mut y = top + PANEL_PADDING;
+        draw_text(framebuffer, x, y, title, TITLE_COLOR, TEXT_SCALE);
+        y += ROW_HEIGHT * 2;
+        for (text, color) in &rows {
+            draw_text(framebuffer, x, y, text, *color, TEXT_SCALE);
+            y += ROW_HEIGHT;
+        }
+        draw_text(framebuffer, x, y, hint, HINT_COLOR, TEXT_SCALE);
+    }
+}
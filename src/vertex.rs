@@ -1,6 +1,6 @@
 // vertex.rs
 
-use nalgebra_glm::{Vec2, Vec3};
+use nalgebra_glm::{Vec2, Vec3, Vec4};
 use crate::color::Color;
 
 #[derive(Clone, Debug)]
@@ -8,9 +8,13 @@ pub struct Vertex {
   pub position: Vec3,
   pub normal: Vec3,
   pub tex_coords: Vec2,
+  pub tangent: Vec4, // Hacia donde crece u en el modelo, w el signo de la bitangente; cero sin textura
   pub color: Color,
   pub transformed_position: Vec3,
   pub transformed_normal: Vec3,
+  pub transformed_tangent: Vec4, // En el mundo
+  pub world_position: Vec3,
+  pub clip_position: Vec4,
 }
 
 impl Vertex {
@@ -19,9 +23,13 @@ impl Vertex {
 			position,
 			normal,
 			tex_coords,
+			tangent: Vec4::zeros(),
 			color: Color::black(),
 			transformed_position: position,
 			transformed_normal: normal,
+			transformed_tangent: Vec4::zeros(),
+			world_position: position,
+			clip_position: Vec4::new(position.x, position.y, position.z, 1.0),
 		}
 	}
 
@@ -30,9 +38,13 @@ impl Vertex {
 			position,
 			normal: Vec3::new(0.0, 0.0, 0.0),
 			tex_coords: Vec2::new(0.0, 0.0),
+			tangent: Vec4::zeros(),
 			color,
 			transformed_position: Vec3::new(0.0, 0.0, 0.0),
 			transformed_normal: Vec3::new(0.0, 0.0, 0.0),
+			transformed_tangent: Vec4::zeros(),
+			world_position: position,
+			clip_position: Vec4::new(position.x, position.y, position.z, 1.0),
 		}
 	}
 
@@ -48,9 +60,13 @@ impl Default for Vertex {
 			position: Vec3::new(0.0, 0.0, 0.0),
 			normal: Vec3::new(0.0, 1.0, 0.0),
 			tex_coords: Vec2::new(0.0, 0.0),
+			tangent: Vec4::zeros(),
 			color: Color::black(),
 			transformed_position: Vec3::new(0.0, 0.0, 0.0),
 			transformed_normal: Vec3::new(0.0, 1.0, 0.0),
+			transformed_tangent: Vec4::zeros(),
+			world_position: Vec3::new(0.0, 0.0, 0.0),
+			clip_position: Vec4::new(0.0, 0.0, 0.0, 1.0),
 		}
 	}
 }
\ No newline at end of file